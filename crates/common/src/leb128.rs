@@ -0,0 +1,31 @@
+// Unsigned LEB128 as used for indices and sub-opcodes in the binary format.
+
+/// Decode a `u32` from the start of `bytes`, returning it with the number of
+/// bytes read. `None` if the encoding is truncated or does not fit a `u32`.
+pub fn read_u32(bytes: &[u8]) -> Option<(u32, usize)> {
+    let mut value: u32 = 0;
+    for (i, byte) in bytes.iter().enumerate().take(5) {
+        let low = (byte & 0x7F) as u32;
+        // the fifth byte may only carry the top four bits
+        if i == 4 && low > 0x0F {
+            return None;
+        }
+        value |= low << (7 * i);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+pub fn write_u32(mut value: u32, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
@@ -1,5 +1,9 @@
 
+pub mod leb128;
+pub mod metadata;
 pub mod opcodes;
+pub mod printing;
+pub mod rng;
 
 
 pub fn add(left: u64, right: u64) -> u64 {
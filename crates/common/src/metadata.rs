@@ -0,0 +1,753 @@
+// Static facts about every opcode in `opcodes`: the immediates that follow
+// it, what it does to the operand stack, whether it can trap and which
+// proposal introduced it.
+
+use crate::opcodes::{OpCode, Prefix};
+
+
+/// Value types as far as fixed stack signatures need them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValKind {
+    I32,
+    I64,
+    F32,
+    F64,
+    V128,
+    FuncRef,
+    ExternRef,
+}
+
+/// Immediates following an opcode, in encoding order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Immediate {
+    BlockType,
+    LabelIndex,
+    // label vector and default label of `br_table`
+    LabelTable,
+    FuncIndex,
+    TypeIndex,
+    TagIndex,
+    TableIndex,
+    LocalIndex,
+    GlobalIndex,
+    MemoryIndex,
+    DataIndex,
+    ElemIndex,
+    FieldIndex,
+    MemArg,
+    Lane,
+    ConstI32,
+    ConstI64,
+    ConstF32,
+    ConstF64,
+    ConstV128,
+    // the 16 lane indices of `i8x16.shuffle`
+    Shuffle,
+    HeapType,
+    // the result types of `select t`
+    ValTypes,
+    // number of operands of `array.new_fixed`
+    Count,
+    // nullability of both types of `br_on_cast`
+    CastFlags,
+    // a zero byte kept for future use
+    Reserved,
+    // the catch clauses of `try_table`, each a kind, a tag unless it is a
+    // `catch_all` and a label
+    CatchTable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StackSignature {
+    /// Always pops `params` and pushes `results`.
+    Fixed { params: &'static [ValKind], results: &'static [ValKind] },
+    /// Depends on the immediates, the operands or the module, e.g. `call`,
+    /// `local.get` or `drop`.
+    Dependent,
+    /// Never falls through, the stack after it is polymorphic.
+    Polymorphic,
+}
+
+/// The proposal an instruction comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Proposal {
+    Mvp,
+    SignExtension,
+    SaturatingFloatToInt,
+    BulkMemory,
+    ReferenceTypes,
+    Simd,
+    RelaxedSimd,
+    Threads,
+    Gc,
+    ExceptionHandling,
+    TailCall,
+    FunctionReferences,
+}
+
+impl Proposal {
+    /// Name of the flag enabling the proposal in `wasmparser::WasmFeatures`
+    /// and in most engines, `None` for the MVP.
+    pub fn feature(&self) -> Option<&'static str> {
+        match self {
+            Proposal::Mvp                  => None,
+            Proposal::SignExtension        => Some("sign_extension"),
+            Proposal::SaturatingFloatToInt => Some("saturating_float_to_int"),
+            Proposal::BulkMemory           => Some("bulk_memory"),
+            Proposal::ReferenceTypes       => Some("reference_types"),
+            Proposal::Simd                 => Some("simd"),
+            Proposal::RelaxedSimd          => Some("relaxed_simd"),
+            Proposal::Threads              => Some("threads"),
+            Proposal::Gc                   => Some("gc"),
+            Proposal::ExceptionHandling    => Some("exceptions"),
+            Proposal::TailCall             => Some("tail_call"),
+            Proposal::FunctionReferences   => Some("function_references"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpInfo {
+    pub opcode: OpCode,
+    pub immediates: &'static [Immediate],
+    pub signature: StackSignature,
+    pub can_trap: bool,
+    pub proposal: Proposal,
+}
+
+impl OpCode {
+    /// Metadata of a known opcode, `None` for `Unknown` and unnamed
+    /// prefixed opcodes.
+    pub fn info(&self) -> Option<&'static OpInfo> {
+        let key = self.to_u32();
+        OPCODES
+            .binary_search_by_key(&key, |info| info.opcode.to_u32())
+            .ok()
+            .map(|index| &OPCODES[index])
+    }
+}
+
+const fn op(
+    opcode: OpCode,
+    immediates: &'static [Immediate],
+    signature: StackSignature,
+    can_trap: bool,
+    proposal: Proposal,
+) -> OpInfo {
+    OpInfo { opcode, immediates, signature, can_trap, proposal }
+}
+
+const fn fixed(params: &'static [ValKind], results: &'static [ValKind]) -> StackSignature {
+    StackSignature::Fixed { params, results }
+}
+
+use Immediate::*;
+use Proposal::*;
+use StackSignature::{Dependent, Polymorphic};
+use ValKind::*;
+
+/// Every known opcode, sorted by `OpCode::to_u32`. Calls can trap because
+/// the stack can run out.
+pub static OPCODES: &[OpInfo] = &[
+    // single-byte opcodes
+    op(OpCode::Unreachable, &[], Polymorphic, true, Mvp),
+    op(OpCode::Nop, &[], fixed(&[], &[]), false, Mvp),
+    op(OpCode::Block, &[BlockType], Dependent, false, Mvp),
+    op(OpCode::Loop, &[BlockType], Dependent, false, Mvp),
+    op(OpCode::If, &[BlockType], Dependent, false, Mvp),
+    op(OpCode::Else_, &[], Dependent, false, Mvp),
+    op(OpCode::Throw, &[TagIndex], Polymorphic, true, ExceptionHandling),
+    op(OpCode::ThrowRef, &[], Polymorphic, true, ExceptionHandling),
+    op(OpCode::End_, &[], Dependent, false, Mvp),
+    op(OpCode::Br, &[LabelIndex], Polymorphic, false, Mvp),
+    op(OpCode::BrIf, &[LabelIndex], Dependent, false, Mvp),
+    op(OpCode::BrTable, &[LabelTable], Polymorphic, false, Mvp),
+    op(OpCode::Return, &[], Polymorphic, false, Mvp),
+    op(OpCode::Call, &[FuncIndex], Dependent, true, Mvp),
+    op(OpCode::CallIndirect, &[TypeIndex, TableIndex], Dependent, true, Mvp),
+    op(OpCode::ReturnCall, &[FuncIndex], Polymorphic, true, TailCall),
+    op(OpCode::ReturnCallIndirect, &[TypeIndex, TableIndex], Polymorphic, true, TailCall),
+    op(OpCode::CallRef, &[TypeIndex], Dependent, true, FunctionReferences),
+    op(OpCode::ReturnCallRef, &[TypeIndex], Polymorphic, true, TailCall),
+    op(OpCode::Drop, &[], Dependent, false, Mvp),
+    op(OpCode::Select, &[], Dependent, false, Mvp),
+    op(OpCode::SelectT, &[ValTypes], Dependent, false, ReferenceTypes),
+    op(OpCode::TryTable, &[BlockType, CatchTable], Dependent, false, ExceptionHandling),
+    op(OpCode::LocalGet, &[LocalIndex], Dependent, false, Mvp),
+    op(OpCode::LocalSet, &[LocalIndex], Dependent, false, Mvp),
+    op(OpCode::LocalTee, &[LocalIndex], Dependent, false, Mvp),
+    op(OpCode::GlobalGet, &[GlobalIndex], Dependent, false, Mvp),
+    op(OpCode::GlobalSet, &[GlobalIndex], Dependent, false, Mvp),
+    op(OpCode::TableGet, &[TableIndex], Dependent, true, ReferenceTypes),
+    op(OpCode::TableSet, &[TableIndex], Dependent, true, ReferenceTypes),
+    op(OpCode::I32Load, &[MemArg], fixed(&[I32], &[I32]), true, Mvp),
+    op(OpCode::I64Load, &[MemArg], fixed(&[I32], &[I64]), true, Mvp),
+    op(OpCode::F32Load, &[MemArg], fixed(&[I32], &[F32]), true, Mvp),
+    op(OpCode::F64Load, &[MemArg], fixed(&[I32], &[F64]), true, Mvp),
+    op(OpCode::I32Load8S, &[MemArg], fixed(&[I32], &[I32]), true, Mvp),
+    op(OpCode::I32Load8U, &[MemArg], fixed(&[I32], &[I32]), true, Mvp),
+    op(OpCode::I32Load16S, &[MemArg], fixed(&[I32], &[I32]), true, Mvp),
+    op(OpCode::I32Load16U, &[MemArg], fixed(&[I32], &[I32]), true, Mvp),
+    op(OpCode::I64Load8S, &[MemArg], fixed(&[I32], &[I64]), true, Mvp),
+    op(OpCode::I64Load8U, &[MemArg], fixed(&[I32], &[I64]), true, Mvp),
+    op(OpCode::I64Load16S, &[MemArg], fixed(&[I32], &[I64]), true, Mvp),
+    op(OpCode::I64Load16U, &[MemArg], fixed(&[I32], &[I64]), true, Mvp),
+    op(OpCode::I64Load32S, &[MemArg], fixed(&[I32], &[I64]), true, Mvp),
+    op(OpCode::I64Load32U, &[MemArg], fixed(&[I32], &[I64]), true, Mvp),
+    op(OpCode::I32Store, &[MemArg], fixed(&[I32, I32], &[]), true, Mvp),
+    op(OpCode::I64Store, &[MemArg], fixed(&[I32, I64], &[]), true, Mvp),
+    op(OpCode::F32Store, &[MemArg], fixed(&[I32, F32], &[]), true, Mvp),
+    op(OpCode::F64Store, &[MemArg], fixed(&[I32, F64], &[]), true, Mvp),
+    op(OpCode::I32Store8, &[MemArg], fixed(&[I32, I32], &[]), true, Mvp),
+    op(OpCode::I32Store16, &[MemArg], fixed(&[I32, I32], &[]), true, Mvp),
+    op(OpCode::I64Store8, &[MemArg], fixed(&[I32, I64], &[]), true, Mvp),
+    op(OpCode::I64Store16, &[MemArg], fixed(&[I32, I64], &[]), true, Mvp),
+    op(OpCode::I64Store32, &[MemArg], fixed(&[I32, I64], &[]), true, Mvp),
+    op(OpCode::MemorySize, &[MemoryIndex], fixed(&[], &[I32]), false, Mvp),
+    op(OpCode::MemoryGrow, &[MemoryIndex], fixed(&[I32], &[I32]), false, Mvp),
+    op(OpCode::I32Const, &[ConstI32], fixed(&[], &[I32]), false, Mvp),
+    op(OpCode::I64Const, &[ConstI64], fixed(&[], &[I64]), false, Mvp),
+    op(OpCode::F32Const, &[ConstF32], fixed(&[], &[F32]), false, Mvp),
+    op(OpCode::F64Const, &[ConstF64], fixed(&[], &[F64]), false, Mvp),
+    op(OpCode::I32Eqz, &[], fixed(&[I32], &[I32]), false, Mvp),
+    op(OpCode::I32Eq, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32Ne, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32LtS, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32LtU, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32GtS, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32GtU, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32LeS, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32LeU, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32GeS, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32GeU, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I64Eqz, &[], fixed(&[I64], &[I32]), false, Mvp),
+    op(OpCode::I64Eq, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64Ne, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64LtS, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64LtU, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64GtS, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64GtU, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64LeS, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64LeU, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64GeS, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::I64GeU, &[], fixed(&[I64, I64], &[I32]), false, Mvp),
+    op(OpCode::F32Eq, &[], fixed(&[F32, F32], &[I32]), false, Mvp),
+    op(OpCode::F32Ne, &[], fixed(&[F32, F32], &[I32]), false, Mvp),
+    op(OpCode::F32Lt, &[], fixed(&[F32, F32], &[I32]), false, Mvp),
+    op(OpCode::F32Gt, &[], fixed(&[F32, F32], &[I32]), false, Mvp),
+    op(OpCode::F32Le, &[], fixed(&[F32, F32], &[I32]), false, Mvp),
+    op(OpCode::F32Ge, &[], fixed(&[F32, F32], &[I32]), false, Mvp),
+    op(OpCode::F64Eq, &[], fixed(&[F64, F64], &[I32]), false, Mvp),
+    op(OpCode::F64Ne, &[], fixed(&[F64, F64], &[I32]), false, Mvp),
+    op(OpCode::F64Lt, &[], fixed(&[F64, F64], &[I32]), false, Mvp),
+    op(OpCode::F64Gt, &[], fixed(&[F64, F64], &[I32]), false, Mvp),
+    op(OpCode::F64Le, &[], fixed(&[F64, F64], &[I32]), false, Mvp),
+    op(OpCode::F64Ge, &[], fixed(&[F64, F64], &[I32]), false, Mvp),
+    op(OpCode::I32Clz, &[], fixed(&[I32], &[I32]), false, Mvp),
+    op(OpCode::I32Ctz, &[], fixed(&[I32], &[I32]), false, Mvp),
+    op(OpCode::I32Popcnt, &[], fixed(&[I32], &[I32]), false, Mvp),
+    op(OpCode::I32Add, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32Sub, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32Mul, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32DivS, &[], fixed(&[I32, I32], &[I32]), true, Mvp),
+    op(OpCode::I32DivU, &[], fixed(&[I32, I32], &[I32]), true, Mvp),
+    op(OpCode::I32RemS, &[], fixed(&[I32, I32], &[I32]), true, Mvp),
+    op(OpCode::I32RemU, &[], fixed(&[I32, I32], &[I32]), true, Mvp),
+    op(OpCode::I32And, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32Or, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32Xor, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32Shl, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32ShrS, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32ShrU, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32Rotl, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I32Rotr, &[], fixed(&[I32, I32], &[I32]), false, Mvp),
+    op(OpCode::I64Clz, &[], fixed(&[I64], &[I64]), false, Mvp),
+    op(OpCode::I64Ctz, &[], fixed(&[I64], &[I64]), false, Mvp),
+    op(OpCode::I64Popcnt, &[], fixed(&[I64], &[I64]), false, Mvp),
+    op(OpCode::I64Add, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64Sub, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64Mul, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64DivS, &[], fixed(&[I64, I64], &[I64]), true, Mvp),
+    op(OpCode::I64DivU, &[], fixed(&[I64, I64], &[I64]), true, Mvp),
+    op(OpCode::I64RemS, &[], fixed(&[I64, I64], &[I64]), true, Mvp),
+    op(OpCode::I64RemU, &[], fixed(&[I64, I64], &[I64]), true, Mvp),
+    op(OpCode::I64And, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64Or, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64Xor, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64Shl, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64ShrS, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64ShrU, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64Rotl, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::I64Rotr, &[], fixed(&[I64, I64], &[I64]), false, Mvp),
+    op(OpCode::F32Abs, &[], fixed(&[F32], &[F32]), false, Mvp),
+    op(OpCode::F32Neg, &[], fixed(&[F32], &[F32]), false, Mvp),
+    op(OpCode::F32Ceil, &[], fixed(&[F32], &[F32]), false, Mvp),
+    op(OpCode::F32Floor, &[], fixed(&[F32], &[F32]), false, Mvp),
+    op(OpCode::F32Trunc, &[], fixed(&[F32], &[F32]), false, Mvp),
+    op(OpCode::F32Nearest, &[], fixed(&[F32], &[F32]), false, Mvp),
+    op(OpCode::F32Sqrt, &[], fixed(&[F32], &[F32]), false, Mvp),
+    op(OpCode::F32Add, &[], fixed(&[F32, F32], &[F32]), false, Mvp),
+    op(OpCode::F32Sub, &[], fixed(&[F32, F32], &[F32]), false, Mvp),
+    op(OpCode::F32Mul, &[], fixed(&[F32, F32], &[F32]), false, Mvp),
+    op(OpCode::F32Div, &[], fixed(&[F32, F32], &[F32]), false, Mvp),
+    op(OpCode::F32Min, &[], fixed(&[F32, F32], &[F32]), false, Mvp),
+    op(OpCode::F32Max, &[], fixed(&[F32, F32], &[F32]), false, Mvp),
+    op(OpCode::F32Copysign, &[], fixed(&[F32, F32], &[F32]), false, Mvp),
+    op(OpCode::F64Abs, &[], fixed(&[F64], &[F64]), false, Mvp),
+    op(OpCode::F64Neg, &[], fixed(&[F64], &[F64]), false, Mvp),
+    op(OpCode::F64Ceil, &[], fixed(&[F64], &[F64]), false, Mvp),
+    op(OpCode::F64Floor, &[], fixed(&[F64], &[F64]), false, Mvp),
+    op(OpCode::F64Trunc, &[], fixed(&[F64], &[F64]), false, Mvp),
+    op(OpCode::F64Nearest, &[], fixed(&[F64], &[F64]), false, Mvp),
+    op(OpCode::F64Sqrt, &[], fixed(&[F64], &[F64]), false, Mvp),
+    op(OpCode::F64Add, &[], fixed(&[F64, F64], &[F64]), false, Mvp),
+    op(OpCode::F64Sub, &[], fixed(&[F64, F64], &[F64]), false, Mvp),
+    op(OpCode::F64Mul, &[], fixed(&[F64, F64], &[F64]), false, Mvp),
+    op(OpCode::F64Div, &[], fixed(&[F64, F64], &[F64]), false, Mvp),
+    op(OpCode::F64Min, &[], fixed(&[F64, F64], &[F64]), false, Mvp),
+    op(OpCode::F64Max, &[], fixed(&[F64, F64], &[F64]), false, Mvp),
+    op(OpCode::F64Copysign, &[], fixed(&[F64, F64], &[F64]), false, Mvp),
+    op(OpCode::I32WrapI64, &[], fixed(&[I64], &[I32]), false, Mvp),
+    op(OpCode::I32TruncF32S, &[], fixed(&[F32], &[I32]), true, Mvp),
+    op(OpCode::I32TruncF32U, &[], fixed(&[F32], &[I32]), true, Mvp),
+    op(OpCode::I32TruncF64S, &[], fixed(&[F64], &[I32]), true, Mvp),
+    op(OpCode::I32TruncF64U, &[], fixed(&[F64], &[I32]), true, Mvp),
+    op(OpCode::I64ExtendI32S, &[], fixed(&[I32], &[I64]), false, Mvp),
+    op(OpCode::I64ExtendI32U, &[], fixed(&[I32], &[I64]), false, Mvp),
+    op(OpCode::I64TruncF32S, &[], fixed(&[F32], &[I64]), true, Mvp),
+    op(OpCode::I64TruncF32U, &[], fixed(&[F32], &[I64]), true, Mvp),
+    op(OpCode::I64TruncF64S, &[], fixed(&[F64], &[I64]), true, Mvp),
+    op(OpCode::I64TruncF64U, &[], fixed(&[F64], &[I64]), true, Mvp),
+    op(OpCode::F32ConvertI32S, &[], fixed(&[I32], &[F32]), false, Mvp),
+    op(OpCode::F32ConvertI32U, &[], fixed(&[I32], &[F32]), false, Mvp),
+    op(OpCode::F32ConvertI64S, &[], fixed(&[I64], &[F32]), false, Mvp),
+    op(OpCode::F32ConvertI64U, &[], fixed(&[I64], &[F32]), false, Mvp),
+    op(OpCode::F32DemoteF64, &[], fixed(&[F64], &[F32]), false, Mvp),
+    op(OpCode::F64ConvertI32S, &[], fixed(&[I32], &[F64]), false, Mvp),
+    op(OpCode::F64ConvertI32U, &[], fixed(&[I32], &[F64]), false, Mvp),
+    op(OpCode::F64ConvertI64S, &[], fixed(&[I64], &[F64]), false, Mvp),
+    op(OpCode::F64ConvertI64U, &[], fixed(&[I64], &[F64]), false, Mvp),
+    op(OpCode::F64PromoteF32, &[], fixed(&[F32], &[F64]), false, Mvp),
+    op(OpCode::I32ReinterpretF32, &[], fixed(&[F32], &[I32]), false, Mvp),
+    op(OpCode::I64ReinterpretF64, &[], fixed(&[F64], &[I64]), false, Mvp),
+    op(OpCode::F32ReinterpretI32, &[], fixed(&[I32], &[F32]), false, Mvp),
+    op(OpCode::F64ReinterpretI64, &[], fixed(&[I64], &[F64]), false, Mvp),
+    op(OpCode::I32Extend8S, &[], fixed(&[I32], &[I32]), false, SignExtension),
+    op(OpCode::I32Extend16S, &[], fixed(&[I32], &[I32]), false, SignExtension),
+    op(OpCode::I64Extend8S, &[], fixed(&[I64], &[I64]), false, SignExtension),
+    op(OpCode::I64Extend16S, &[], fixed(&[I64], &[I64]), false, SignExtension),
+    op(OpCode::I64Extend32S, &[], fixed(&[I64], &[I64]), false, SignExtension),
+    op(OpCode::RefNull, &[HeapType], Dependent, false, ReferenceTypes),
+    op(OpCode::RefIsNull, &[], Dependent, false, ReferenceTypes),
+    op(OpCode::RefFunc, &[FuncIndex], fixed(&[], &[FuncRef]), false, ReferenceTypes),
+    op(OpCode::RefEq, &[], Dependent, false, Gc),
+    op(OpCode::RefAsNonNull, &[], Dependent, true, FunctionReferences),
+    op(OpCode::BrOnNull, &[LabelIndex], Dependent, false, FunctionReferences),
+    op(OpCode::BrOnNonNull, &[LabelIndex], Dependent, false, FunctionReferences),
+    // 0xFB: GC
+    op(OpCode::Prefixed(Prefix::Gc, 0x00), &[TypeIndex], Dependent, false, Gc), // struct.new
+    op(OpCode::Prefixed(Prefix::Gc, 0x01), &[TypeIndex], Dependent, false, Gc), // struct.new_default
+    op(OpCode::Prefixed(Prefix::Gc, 0x02), &[TypeIndex, FieldIndex], Dependent, true, Gc), // struct.get
+    op(OpCode::Prefixed(Prefix::Gc, 0x03), &[TypeIndex, FieldIndex], Dependent, true, Gc), // struct.get_s
+    op(OpCode::Prefixed(Prefix::Gc, 0x04), &[TypeIndex, FieldIndex], Dependent, true, Gc), // struct.get_u
+    op(OpCode::Prefixed(Prefix::Gc, 0x05), &[TypeIndex, FieldIndex], Dependent, true, Gc), // struct.set
+    op(OpCode::Prefixed(Prefix::Gc, 0x06), &[TypeIndex], Dependent, false, Gc), // array.new
+    op(OpCode::Prefixed(Prefix::Gc, 0x07), &[TypeIndex], Dependent, false, Gc), // array.new_default
+    op(OpCode::Prefixed(Prefix::Gc, 0x08), &[TypeIndex, Count], Dependent, false, Gc), // array.new_fixed
+    op(OpCode::Prefixed(Prefix::Gc, 0x09), &[TypeIndex, DataIndex], Dependent, true, Gc), // array.new_data
+    op(OpCode::Prefixed(Prefix::Gc, 0x0A), &[TypeIndex, ElemIndex], Dependent, true, Gc), // array.new_elem
+    op(OpCode::Prefixed(Prefix::Gc, 0x0B), &[TypeIndex], Dependent, true, Gc), // array.get
+    op(OpCode::Prefixed(Prefix::Gc, 0x0C), &[TypeIndex], Dependent, true, Gc), // array.get_s
+    op(OpCode::Prefixed(Prefix::Gc, 0x0D), &[TypeIndex], Dependent, true, Gc), // array.get_u
+    op(OpCode::Prefixed(Prefix::Gc, 0x0E), &[TypeIndex], Dependent, true, Gc), // array.set
+    op(OpCode::Prefixed(Prefix::Gc, 0x0F), &[], Dependent, true, Gc), // array.len
+    op(OpCode::Prefixed(Prefix::Gc, 0x10), &[TypeIndex], Dependent, true, Gc), // array.fill
+    op(OpCode::Prefixed(Prefix::Gc, 0x11), &[TypeIndex, TypeIndex], Dependent, true, Gc), // array.copy
+    op(OpCode::Prefixed(Prefix::Gc, 0x12), &[TypeIndex, DataIndex], Dependent, true, Gc), // array.init_data
+    op(OpCode::Prefixed(Prefix::Gc, 0x13), &[TypeIndex, ElemIndex], Dependent, true, Gc), // array.init_elem
+    op(OpCode::Prefixed(Prefix::Gc, 0x14), &[HeapType], Dependent, false, Gc), // ref.test
+    op(OpCode::Prefixed(Prefix::Gc, 0x15), &[HeapType], Dependent, false, Gc), // ref.test_null
+    op(OpCode::Prefixed(Prefix::Gc, 0x16), &[HeapType], Dependent, true, Gc), // ref.cast
+    op(OpCode::Prefixed(Prefix::Gc, 0x17), &[HeapType], Dependent, true, Gc), // ref.cast_null
+    op(OpCode::Prefixed(Prefix::Gc, 0x18), &[CastFlags, LabelIndex, HeapType, HeapType], Dependent, false, Gc), // br_on_cast
+    op(OpCode::Prefixed(Prefix::Gc, 0x19), &[CastFlags, LabelIndex, HeapType, HeapType], Dependent, false, Gc), // br_on_cast_fail
+    op(OpCode::Prefixed(Prefix::Gc, 0x1A), &[], Dependent, false, Gc), // any.convert_extern
+    op(OpCode::Prefixed(Prefix::Gc, 0x1B), &[], Dependent, false, Gc), // extern.convert_any
+    op(OpCode::Prefixed(Prefix::Gc, 0x1C), &[], Dependent, false, Gc), // ref.i31
+    op(OpCode::Prefixed(Prefix::Gc, 0x1D), &[], Dependent, true, Gc), // i31.get_s
+    op(OpCode::Prefixed(Prefix::Gc, 0x1E), &[], Dependent, true, Gc), // i31.get_u
+    // 0xFC: saturating truncation, bulk memory and table instructions
+    op(OpCode::Prefixed(Prefix::Misc, 0x00), &[], fixed(&[F32], &[I32]), false, SaturatingFloatToInt), // i32.trunc_sat_f32_s
+    op(OpCode::Prefixed(Prefix::Misc, 0x01), &[], fixed(&[F32], &[I32]), false, SaturatingFloatToInt), // i32.trunc_sat_f32_u
+    op(OpCode::Prefixed(Prefix::Misc, 0x02), &[], fixed(&[F64], &[I32]), false, SaturatingFloatToInt), // i32.trunc_sat_f64_s
+    op(OpCode::Prefixed(Prefix::Misc, 0x03), &[], fixed(&[F64], &[I32]), false, SaturatingFloatToInt), // i32.trunc_sat_f64_u
+    op(OpCode::Prefixed(Prefix::Misc, 0x04), &[], fixed(&[F32], &[I64]), false, SaturatingFloatToInt), // i64.trunc_sat_f32_s
+    op(OpCode::Prefixed(Prefix::Misc, 0x05), &[], fixed(&[F32], &[I64]), false, SaturatingFloatToInt), // i64.trunc_sat_f32_u
+    op(OpCode::Prefixed(Prefix::Misc, 0x06), &[], fixed(&[F64], &[I64]), false, SaturatingFloatToInt), // i64.trunc_sat_f64_s
+    op(OpCode::Prefixed(Prefix::Misc, 0x07), &[], fixed(&[F64], &[I64]), false, SaturatingFloatToInt), // i64.trunc_sat_f64_u
+    op(OpCode::Prefixed(Prefix::Misc, 0x08), &[DataIndex, MemoryIndex], fixed(&[I32, I32, I32], &[]), true, BulkMemory), // memory.init
+    op(OpCode::Prefixed(Prefix::Misc, 0x09), &[DataIndex], fixed(&[], &[]), false, BulkMemory), // data.drop
+    op(OpCode::Prefixed(Prefix::Misc, 0x0A), &[MemoryIndex, MemoryIndex], fixed(&[I32, I32, I32], &[]), true, BulkMemory), // memory.copy
+    op(OpCode::Prefixed(Prefix::Misc, 0x0B), &[MemoryIndex], fixed(&[I32, I32, I32], &[]), true, BulkMemory), // memory.fill
+    op(OpCode::Prefixed(Prefix::Misc, 0x0C), &[ElemIndex, TableIndex], fixed(&[I32, I32, I32], &[]), true, BulkMemory), // table.init
+    op(OpCode::Prefixed(Prefix::Misc, 0x0D), &[ElemIndex], fixed(&[], &[]), false, BulkMemory), // elem.drop
+    op(OpCode::Prefixed(Prefix::Misc, 0x0E), &[TableIndex, TableIndex], fixed(&[I32, I32, I32], &[]), true, BulkMemory), // table.copy
+    op(OpCode::Prefixed(Prefix::Misc, 0x0F), &[TableIndex], Dependent, false, ReferenceTypes), // table.grow
+    op(OpCode::Prefixed(Prefix::Misc, 0x10), &[TableIndex], fixed(&[], &[I32]), false, ReferenceTypes), // table.size
+    op(OpCode::Prefixed(Prefix::Misc, 0x11), &[TableIndex], Dependent, true, ReferenceTypes), // table.fill
+    // 0xFD: SIMD
+    op(OpCode::Prefixed(Prefix::Simd, 0x00), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load
+    op(OpCode::Prefixed(Prefix::Simd, 0x01), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load8x8_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x02), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load8x8_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x03), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load16x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x04), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load16x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x05), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load32x2_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x06), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load32x2_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x07), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load8_splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x08), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load16_splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x09), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load32_splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x0A), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load64_splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x0B), &[MemArg], fixed(&[I32, V128], &[]), true, Simd), // v128.store
+    op(OpCode::Prefixed(Prefix::Simd, 0x0C), &[ConstV128], fixed(&[], &[V128]), false, Simd), // v128.const
+    op(OpCode::Prefixed(Prefix::Simd, 0x0D), &[Shuffle], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.shuffle
+    op(OpCode::Prefixed(Prefix::Simd, 0x0E), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.swizzle
+    op(OpCode::Prefixed(Prefix::Simd, 0x0F), &[], fixed(&[I32], &[V128]), false, Simd), // i8x16.splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x10), &[], fixed(&[I32], &[V128]), false, Simd), // i16x8.splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x11), &[], fixed(&[I32], &[V128]), false, Simd), // i32x4.splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x12), &[], fixed(&[I64], &[V128]), false, Simd), // i64x2.splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x13), &[], fixed(&[F32], &[V128]), false, Simd), // f32x4.splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x14), &[], fixed(&[F64], &[V128]), false, Simd), // f64x2.splat
+    op(OpCode::Prefixed(Prefix::Simd, 0x15), &[Lane], fixed(&[V128], &[I32]), false, Simd), // i8x16.extract_lane_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x16), &[Lane], fixed(&[V128], &[I32]), false, Simd), // i8x16.extract_lane_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x17), &[Lane], fixed(&[V128, I32], &[V128]), false, Simd), // i8x16.replace_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x18), &[Lane], fixed(&[V128], &[I32]), false, Simd), // i16x8.extract_lane_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x19), &[Lane], fixed(&[V128], &[I32]), false, Simd), // i16x8.extract_lane_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x1A), &[Lane], fixed(&[V128, I32], &[V128]), false, Simd), // i16x8.replace_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x1B), &[Lane], fixed(&[V128], &[I32]), false, Simd), // i32x4.extract_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x1C), &[Lane], fixed(&[V128, I32], &[V128]), false, Simd), // i32x4.replace_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x1D), &[Lane], fixed(&[V128], &[I64]), false, Simd), // i64x2.extract_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x1E), &[Lane], fixed(&[V128, I64], &[V128]), false, Simd), // i64x2.replace_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x1F), &[Lane], fixed(&[V128], &[F32]), false, Simd), // f32x4.extract_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x20), &[Lane], fixed(&[V128, F32], &[V128]), false, Simd), // f32x4.replace_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x21), &[Lane], fixed(&[V128], &[F64]), false, Simd), // f64x2.extract_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x22), &[Lane], fixed(&[V128, F64], &[V128]), false, Simd), // f64x2.replace_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x23), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.eq
+    op(OpCode::Prefixed(Prefix::Simd, 0x24), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.ne
+    op(OpCode::Prefixed(Prefix::Simd, 0x25), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.lt_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x26), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.lt_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x27), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.gt_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x28), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.gt_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x29), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.le_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x2A), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.le_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x2B), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.ge_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x2C), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.ge_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x2D), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.eq
+    op(OpCode::Prefixed(Prefix::Simd, 0x2E), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.ne
+    op(OpCode::Prefixed(Prefix::Simd, 0x2F), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.lt_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x30), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.lt_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x31), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.gt_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x32), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.gt_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x33), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.le_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x34), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.le_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x35), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.ge_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x36), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.ge_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x37), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.eq
+    op(OpCode::Prefixed(Prefix::Simd, 0x38), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.ne
+    op(OpCode::Prefixed(Prefix::Simd, 0x39), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.lt_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x3A), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.lt_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x3B), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.gt_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x3C), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.gt_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x3D), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.le_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x3E), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.le_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x3F), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.ge_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x40), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.ge_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x41), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.eq
+    op(OpCode::Prefixed(Prefix::Simd, 0x42), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.ne
+    op(OpCode::Prefixed(Prefix::Simd, 0x43), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.lt
+    op(OpCode::Prefixed(Prefix::Simd, 0x44), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.gt
+    op(OpCode::Prefixed(Prefix::Simd, 0x45), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.le
+    op(OpCode::Prefixed(Prefix::Simd, 0x46), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.ge
+    op(OpCode::Prefixed(Prefix::Simd, 0x47), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.eq
+    op(OpCode::Prefixed(Prefix::Simd, 0x48), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.ne
+    op(OpCode::Prefixed(Prefix::Simd, 0x49), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.lt
+    op(OpCode::Prefixed(Prefix::Simd, 0x4A), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.gt
+    op(OpCode::Prefixed(Prefix::Simd, 0x4B), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.le
+    op(OpCode::Prefixed(Prefix::Simd, 0x4C), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.ge
+    op(OpCode::Prefixed(Prefix::Simd, 0x4D), &[], fixed(&[V128], &[V128]), false, Simd), // v128.not
+    op(OpCode::Prefixed(Prefix::Simd, 0x4E), &[], fixed(&[V128, V128], &[V128]), false, Simd), // v128.and
+    op(OpCode::Prefixed(Prefix::Simd, 0x4F), &[], fixed(&[V128, V128], &[V128]), false, Simd), // v128.andnot
+    op(OpCode::Prefixed(Prefix::Simd, 0x50), &[], fixed(&[V128, V128], &[V128]), false, Simd), // v128.or
+    op(OpCode::Prefixed(Prefix::Simd, 0x51), &[], fixed(&[V128, V128], &[V128]), false, Simd), // v128.xor
+    op(OpCode::Prefixed(Prefix::Simd, 0x52), &[], fixed(&[V128, V128, V128], &[V128]), false, Simd), // v128.bitselect
+    op(OpCode::Prefixed(Prefix::Simd, 0x53), &[], fixed(&[V128], &[I32]), false, Simd), // v128.any_true
+    op(OpCode::Prefixed(Prefix::Simd, 0x54), &[MemArg, Lane], fixed(&[I32, V128], &[V128]), true, Simd), // v128.load8_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x55), &[MemArg, Lane], fixed(&[I32, V128], &[V128]), true, Simd), // v128.load16_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x56), &[MemArg, Lane], fixed(&[I32, V128], &[V128]), true, Simd), // v128.load32_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x57), &[MemArg, Lane], fixed(&[I32, V128], &[V128]), true, Simd), // v128.load64_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x58), &[MemArg, Lane], fixed(&[I32, V128], &[]), true, Simd), // v128.store8_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x59), &[MemArg, Lane], fixed(&[I32, V128], &[]), true, Simd), // v128.store16_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x5A), &[MemArg, Lane], fixed(&[I32, V128], &[]), true, Simd), // v128.store32_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x5B), &[MemArg, Lane], fixed(&[I32, V128], &[]), true, Simd), // v128.store64_lane
+    op(OpCode::Prefixed(Prefix::Simd, 0x5C), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load32_zero
+    op(OpCode::Prefixed(Prefix::Simd, 0x5D), &[MemArg], fixed(&[I32], &[V128]), true, Simd), // v128.load64_zero
+    op(OpCode::Prefixed(Prefix::Simd, 0x5E), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.demote_f64x2_zero
+    op(OpCode::Prefixed(Prefix::Simd, 0x5F), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.promote_low_f32x4
+    op(OpCode::Prefixed(Prefix::Simd, 0x60), &[], fixed(&[V128], &[V128]), false, Simd), // i8x16.abs
+    op(OpCode::Prefixed(Prefix::Simd, 0x61), &[], fixed(&[V128], &[V128]), false, Simd), // i8x16.neg
+    op(OpCode::Prefixed(Prefix::Simd, 0x62), &[], fixed(&[V128], &[V128]), false, Simd), // i8x16.popcnt
+    op(OpCode::Prefixed(Prefix::Simd, 0x63), &[], fixed(&[V128], &[I32]), false, Simd), // i8x16.all_true
+    op(OpCode::Prefixed(Prefix::Simd, 0x64), &[], fixed(&[V128], &[I32]), false, Simd), // i8x16.bitmask
+    op(OpCode::Prefixed(Prefix::Simd, 0x65), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.narrow_i16x8_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x66), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.narrow_i16x8_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x67), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.ceil
+    op(OpCode::Prefixed(Prefix::Simd, 0x68), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.floor
+    op(OpCode::Prefixed(Prefix::Simd, 0x69), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.trunc
+    op(OpCode::Prefixed(Prefix::Simd, 0x6A), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.nearest
+    op(OpCode::Prefixed(Prefix::Simd, 0x6B), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i8x16.shl
+    op(OpCode::Prefixed(Prefix::Simd, 0x6C), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i8x16.shr_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x6D), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i8x16.shr_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x6E), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.add
+    op(OpCode::Prefixed(Prefix::Simd, 0x6F), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.add_sat_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x70), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.add_sat_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x71), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.sub
+    op(OpCode::Prefixed(Prefix::Simd, 0x72), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.sub_sat_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x73), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.sub_sat_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x74), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.ceil
+    op(OpCode::Prefixed(Prefix::Simd, 0x75), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.floor
+    op(OpCode::Prefixed(Prefix::Simd, 0x76), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.min_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x77), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.min_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x78), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.max_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x79), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.max_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x7A), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.trunc
+    op(OpCode::Prefixed(Prefix::Simd, 0x7B), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i8x16.avgr_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x7C), &[], fixed(&[V128], &[V128]), false, Simd), // i16x8.extadd_pairwise_i8x16_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x7D), &[], fixed(&[V128], &[V128]), false, Simd), // i16x8.extadd_pairwise_i8x16_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x7E), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.extadd_pairwise_i16x8_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x7F), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.extadd_pairwise_i16x8_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x80), &[], fixed(&[V128], &[V128]), false, Simd), // i16x8.abs
+    op(OpCode::Prefixed(Prefix::Simd, 0x81), &[], fixed(&[V128], &[V128]), false, Simd), // i16x8.neg
+    op(OpCode::Prefixed(Prefix::Simd, 0x82), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.q15mulr_sat_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x83), &[], fixed(&[V128], &[I32]), false, Simd), // i16x8.all_true
+    op(OpCode::Prefixed(Prefix::Simd, 0x84), &[], fixed(&[V128], &[I32]), false, Simd), // i16x8.bitmask
+    op(OpCode::Prefixed(Prefix::Simd, 0x85), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.narrow_i32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x86), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.narrow_i32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x87), &[], fixed(&[V128], &[V128]), false, Simd), // i16x8.extend_low_i8x16_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x88), &[], fixed(&[V128], &[V128]), false, Simd), // i16x8.extend_high_i8x16_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x89), &[], fixed(&[V128], &[V128]), false, Simd), // i16x8.extend_low_i8x16_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x8A), &[], fixed(&[V128], &[V128]), false, Simd), // i16x8.extend_high_i8x16_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x8B), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i16x8.shl
+    op(OpCode::Prefixed(Prefix::Simd, 0x8C), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i16x8.shr_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x8D), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i16x8.shr_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x8E), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.add
+    op(OpCode::Prefixed(Prefix::Simd, 0x8F), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.add_sat_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x90), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.add_sat_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x91), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.sub
+    op(OpCode::Prefixed(Prefix::Simd, 0x92), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.sub_sat_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x93), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.sub_sat_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x94), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.nearest
+    op(OpCode::Prefixed(Prefix::Simd, 0x95), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.mul
+    op(OpCode::Prefixed(Prefix::Simd, 0x96), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.min_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x97), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.min_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x98), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.max_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x99), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.max_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x9B), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.avgr_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x9C), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.extmul_low_i8x16_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x9D), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.extmul_high_i8x16_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x9E), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.extmul_low_i8x16_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x9F), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i16x8.extmul_high_i8x16_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xA0), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.abs
+    op(OpCode::Prefixed(Prefix::Simd, 0xA1), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.neg
+    op(OpCode::Prefixed(Prefix::Simd, 0xA3), &[], fixed(&[V128], &[I32]), false, Simd), // i32x4.all_true
+    op(OpCode::Prefixed(Prefix::Simd, 0xA4), &[], fixed(&[V128], &[I32]), false, Simd), // i32x4.bitmask
+    op(OpCode::Prefixed(Prefix::Simd, 0xA7), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.extend_low_i16x8_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xA8), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.extend_high_i16x8_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xA9), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.extend_low_i16x8_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xAA), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.extend_high_i16x8_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xAB), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i32x4.shl
+    op(OpCode::Prefixed(Prefix::Simd, 0xAC), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i32x4.shr_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xAD), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i32x4.shr_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xAE), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.add
+    op(OpCode::Prefixed(Prefix::Simd, 0xB1), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.sub
+    op(OpCode::Prefixed(Prefix::Simd, 0xB5), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.mul
+    op(OpCode::Prefixed(Prefix::Simd, 0xB6), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.min_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xB7), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.min_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xB8), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.max_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xB9), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.max_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xBA), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.dot_i16x8_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xBC), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.extmul_low_i16x8_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xBD), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.extmul_high_i16x8_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xBE), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.extmul_low_i16x8_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xBF), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i32x4.extmul_high_i16x8_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xC0), &[], fixed(&[V128], &[V128]), false, Simd), // i64x2.abs
+    op(OpCode::Prefixed(Prefix::Simd, 0xC1), &[], fixed(&[V128], &[V128]), false, Simd), // i64x2.neg
+    op(OpCode::Prefixed(Prefix::Simd, 0xC3), &[], fixed(&[V128], &[I32]), false, Simd), // i64x2.all_true
+    op(OpCode::Prefixed(Prefix::Simd, 0xC4), &[], fixed(&[V128], &[I32]), false, Simd), // i64x2.bitmask
+    op(OpCode::Prefixed(Prefix::Simd, 0xC7), &[], fixed(&[V128], &[V128]), false, Simd), // i64x2.extend_low_i32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xC8), &[], fixed(&[V128], &[V128]), false, Simd), // i64x2.extend_high_i32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xC9), &[], fixed(&[V128], &[V128]), false, Simd), // i64x2.extend_low_i32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xCA), &[], fixed(&[V128], &[V128]), false, Simd), // i64x2.extend_high_i32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xCB), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i64x2.shl
+    op(OpCode::Prefixed(Prefix::Simd, 0xCC), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i64x2.shr_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xCD), &[], fixed(&[V128, I32], &[V128]), false, Simd), // i64x2.shr_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xCE), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.add
+    op(OpCode::Prefixed(Prefix::Simd, 0xD1), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.sub
+    op(OpCode::Prefixed(Prefix::Simd, 0xD5), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.mul
+    op(OpCode::Prefixed(Prefix::Simd, 0xD6), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.eq
+    op(OpCode::Prefixed(Prefix::Simd, 0xD7), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.ne
+    op(OpCode::Prefixed(Prefix::Simd, 0xD8), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.lt_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xD9), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.gt_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xDA), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.le_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xDB), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.ge_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xDC), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.extmul_low_i32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xDD), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.extmul_high_i32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xDE), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.extmul_low_i32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xDF), &[], fixed(&[V128, V128], &[V128]), false, Simd), // i64x2.extmul_high_i32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xE0), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.abs
+    op(OpCode::Prefixed(Prefix::Simd, 0xE1), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.neg
+    op(OpCode::Prefixed(Prefix::Simd, 0xE3), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.sqrt
+    op(OpCode::Prefixed(Prefix::Simd, 0xE4), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.add
+    op(OpCode::Prefixed(Prefix::Simd, 0xE5), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.sub
+    op(OpCode::Prefixed(Prefix::Simd, 0xE6), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.mul
+    op(OpCode::Prefixed(Prefix::Simd, 0xE7), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.div
+    op(OpCode::Prefixed(Prefix::Simd, 0xE8), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.min
+    op(OpCode::Prefixed(Prefix::Simd, 0xE9), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.max
+    op(OpCode::Prefixed(Prefix::Simd, 0xEA), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.pmin
+    op(OpCode::Prefixed(Prefix::Simd, 0xEB), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f32x4.pmax
+    op(OpCode::Prefixed(Prefix::Simd, 0xEC), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.abs
+    op(OpCode::Prefixed(Prefix::Simd, 0xED), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.neg
+    op(OpCode::Prefixed(Prefix::Simd, 0xEF), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.sqrt
+    op(OpCode::Prefixed(Prefix::Simd, 0xF0), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.add
+    op(OpCode::Prefixed(Prefix::Simd, 0xF1), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.sub
+    op(OpCode::Prefixed(Prefix::Simd, 0xF2), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.mul
+    op(OpCode::Prefixed(Prefix::Simd, 0xF3), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.div
+    op(OpCode::Prefixed(Prefix::Simd, 0xF4), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.min
+    op(OpCode::Prefixed(Prefix::Simd, 0xF5), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.max
+    op(OpCode::Prefixed(Prefix::Simd, 0xF6), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.pmin
+    op(OpCode::Prefixed(Prefix::Simd, 0xF7), &[], fixed(&[V128, V128], &[V128]), false, Simd), // f64x2.pmax
+    op(OpCode::Prefixed(Prefix::Simd, 0xF8), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.trunc_sat_f32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xF9), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.trunc_sat_f32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xFA), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.convert_i32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xFB), &[], fixed(&[V128], &[V128]), false, Simd), // f32x4.convert_i32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0xFC), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.trunc_sat_f64x2_s_zero
+    op(OpCode::Prefixed(Prefix::Simd, 0xFD), &[], fixed(&[V128], &[V128]), false, Simd), // i32x4.trunc_sat_f64x2_u_zero
+    op(OpCode::Prefixed(Prefix::Simd, 0xFE), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.convert_low_i32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0xFF), &[], fixed(&[V128], &[V128]), false, Simd), // f64x2.convert_low_i32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x100), &[], fixed(&[V128, V128], &[V128]), false, RelaxedSimd), // i8x16.relaxed_swizzle
+    op(OpCode::Prefixed(Prefix::Simd, 0x101), &[], fixed(&[V128], &[V128]), false, RelaxedSimd), // i32x4.relaxed_trunc_f32x4_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x102), &[], fixed(&[V128], &[V128]), false, RelaxedSimd), // i32x4.relaxed_trunc_f32x4_u
+    op(OpCode::Prefixed(Prefix::Simd, 0x103), &[], fixed(&[V128], &[V128]), false, RelaxedSimd), // i32x4.relaxed_trunc_f64x2_s_zero
+    op(OpCode::Prefixed(Prefix::Simd, 0x104), &[], fixed(&[V128], &[V128]), false, RelaxedSimd), // i32x4.relaxed_trunc_f64x2_u_zero
+    op(OpCode::Prefixed(Prefix::Simd, 0x105), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // f32x4.relaxed_madd
+    op(OpCode::Prefixed(Prefix::Simd, 0x106), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // f32x4.relaxed_nmadd
+    op(OpCode::Prefixed(Prefix::Simd, 0x107), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // f64x2.relaxed_madd
+    op(OpCode::Prefixed(Prefix::Simd, 0x108), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // f64x2.relaxed_nmadd
+    op(OpCode::Prefixed(Prefix::Simd, 0x109), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // i8x16.relaxed_laneselect
+    op(OpCode::Prefixed(Prefix::Simd, 0x10A), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // i16x8.relaxed_laneselect
+    op(OpCode::Prefixed(Prefix::Simd, 0x10B), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // i32x4.relaxed_laneselect
+    op(OpCode::Prefixed(Prefix::Simd, 0x10C), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // i64x2.relaxed_laneselect
+    op(OpCode::Prefixed(Prefix::Simd, 0x10D), &[], fixed(&[V128, V128], &[V128]), false, RelaxedSimd), // f32x4.relaxed_min
+    op(OpCode::Prefixed(Prefix::Simd, 0x10E), &[], fixed(&[V128, V128], &[V128]), false, RelaxedSimd), // f32x4.relaxed_max
+    op(OpCode::Prefixed(Prefix::Simd, 0x10F), &[], fixed(&[V128, V128], &[V128]), false, RelaxedSimd), // f64x2.relaxed_min
+    op(OpCode::Prefixed(Prefix::Simd, 0x110), &[], fixed(&[V128, V128], &[V128]), false, RelaxedSimd), // f64x2.relaxed_max
+    op(OpCode::Prefixed(Prefix::Simd, 0x111), &[], fixed(&[V128, V128], &[V128]), false, RelaxedSimd), // i16x8.relaxed_q15mulr_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x112), &[], fixed(&[V128, V128], &[V128]), false, RelaxedSimd), // i16x8.relaxed_dot_i8x16_i7x16_s
+    op(OpCode::Prefixed(Prefix::Simd, 0x113), &[], fixed(&[V128, V128, V128], &[V128]), false, RelaxedSimd), // i32x4.relaxed_dot_i8x16_i7x16_add_s
+    // 0xFE: threads
+    op(OpCode::Prefixed(Prefix::Threads, 0x00), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // memory.atomic.notify
+    op(OpCode::Prefixed(Prefix::Threads, 0x01), &[MemArg], fixed(&[I32, I32, I64], &[I32]), true, Threads), // memory.atomic.wait32
+    op(OpCode::Prefixed(Prefix::Threads, 0x02), &[MemArg], fixed(&[I32, I64, I64], &[I32]), true, Threads), // memory.atomic.wait64
+    op(OpCode::Prefixed(Prefix::Threads, 0x03), &[Reserved], fixed(&[], &[]), false, Threads), // atomic.fence
+    op(OpCode::Prefixed(Prefix::Threads, 0x10), &[MemArg], fixed(&[I32], &[I32]), true, Threads), // i32.atomic.load
+    op(OpCode::Prefixed(Prefix::Threads, 0x11), &[MemArg], fixed(&[I32], &[I64]), true, Threads), // i64.atomic.load
+    op(OpCode::Prefixed(Prefix::Threads, 0x12), &[MemArg], fixed(&[I32], &[I32]), true, Threads), // i32.atomic.load8_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x13), &[MemArg], fixed(&[I32], &[I32]), true, Threads), // i32.atomic.load16_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x14), &[MemArg], fixed(&[I32], &[I64]), true, Threads), // i64.atomic.load8_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x15), &[MemArg], fixed(&[I32], &[I64]), true, Threads), // i64.atomic.load16_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x16), &[MemArg], fixed(&[I32], &[I64]), true, Threads), // i64.atomic.load32_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x17), &[MemArg], fixed(&[I32, I32], &[]), true, Threads), // i32.atomic.store
+    op(OpCode::Prefixed(Prefix::Threads, 0x18), &[MemArg], fixed(&[I32, I64], &[]), true, Threads), // i64.atomic.store
+    op(OpCode::Prefixed(Prefix::Threads, 0x19), &[MemArg], fixed(&[I32, I32], &[]), true, Threads), // i32.atomic.store8
+    op(OpCode::Prefixed(Prefix::Threads, 0x1A), &[MemArg], fixed(&[I32, I32], &[]), true, Threads), // i32.atomic.store16
+    op(OpCode::Prefixed(Prefix::Threads, 0x1B), &[MemArg], fixed(&[I32, I64], &[]), true, Threads), // i64.atomic.store8
+    op(OpCode::Prefixed(Prefix::Threads, 0x1C), &[MemArg], fixed(&[I32, I64], &[]), true, Threads), // i64.atomic.store16
+    op(OpCode::Prefixed(Prefix::Threads, 0x1D), &[MemArg], fixed(&[I32, I64], &[]), true, Threads), // i64.atomic.store32
+    op(OpCode::Prefixed(Prefix::Threads, 0x1E), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw.add
+    op(OpCode::Prefixed(Prefix::Threads, 0x1F), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw.add
+    op(OpCode::Prefixed(Prefix::Threads, 0x20), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw8.add_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x21), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw16.add_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x22), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw8.add_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x23), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw16.add_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x24), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw32.add_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x25), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw.sub
+    op(OpCode::Prefixed(Prefix::Threads, 0x26), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw.sub
+    op(OpCode::Prefixed(Prefix::Threads, 0x27), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw8.sub_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x28), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw16.sub_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x29), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw8.sub_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x2A), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw16.sub_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x2B), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw32.sub_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x2C), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw.and
+    op(OpCode::Prefixed(Prefix::Threads, 0x2D), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw.and
+    op(OpCode::Prefixed(Prefix::Threads, 0x2E), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw8.and_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x2F), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw16.and_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x30), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw8.and_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x31), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw16.and_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x32), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw32.and_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x33), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw.or
+    op(OpCode::Prefixed(Prefix::Threads, 0x34), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw.or
+    op(OpCode::Prefixed(Prefix::Threads, 0x35), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw8.or_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x36), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw16.or_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x37), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw8.or_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x38), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw16.or_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x39), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw32.or_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x3A), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw.xor
+    op(OpCode::Prefixed(Prefix::Threads, 0x3B), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw.xor
+    op(OpCode::Prefixed(Prefix::Threads, 0x3C), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw8.xor_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x3D), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw16.xor_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x3E), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw8.xor_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x3F), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw16.xor_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x40), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw32.xor_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x41), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw.xchg
+    op(OpCode::Prefixed(Prefix::Threads, 0x42), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw.xchg
+    op(OpCode::Prefixed(Prefix::Threads, 0x43), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw8.xchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x44), &[MemArg], fixed(&[I32, I32], &[I32]), true, Threads), // i32.atomic.rmw16.xchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x45), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw8.xchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x46), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw16.xchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x47), &[MemArg], fixed(&[I32, I64], &[I64]), true, Threads), // i64.atomic.rmw32.xchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x48), &[MemArg], fixed(&[I32, I32, I32], &[I32]), true, Threads), // i32.atomic.rmw.cmpxchg
+    op(OpCode::Prefixed(Prefix::Threads, 0x49), &[MemArg], fixed(&[I32, I64, I64], &[I64]), true, Threads), // i64.atomic.rmw.cmpxchg
+    op(OpCode::Prefixed(Prefix::Threads, 0x4A), &[MemArg], fixed(&[I32, I32, I32], &[I32]), true, Threads), // i32.atomic.rmw8.cmpxchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x4B), &[MemArg], fixed(&[I32, I32, I32], &[I32]), true, Threads), // i32.atomic.rmw16.cmpxchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x4C), &[MemArg], fixed(&[I32, I64, I64], &[I64]), true, Threads), // i64.atomic.rmw8.cmpxchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x4D), &[MemArg], fixed(&[I32, I64, I64], &[I64]), true, Threads), // i64.atomic.rmw16.cmpxchg_u
+    op(OpCode::Prefixed(Prefix::Threads, 0x4E), &[MemArg], fixed(&[I32, I64, I64], &[I64]), true, Threads), // i64.atomic.rmw32.cmpxchg_u
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_named_opcode_has_info() {
+        let named = (0x00..=0xFF)
+            .map(OpCode::from_u32)
+            .filter(|op| !matches!(op, OpCode::Unknown(_)))
+            .count();
+        assert_eq!(OPCODES.len(), named + 372);
+        assert!(OPCODES.windows(2).all(|pair| pair[0].opcode.to_u32() < pair[1].opcode.to_u32()));
+        for info in OPCODES {
+            assert_eq!(info.opcode.info(), Some(info));
+            assert!(!info.opcode.name().starts_with("unknown"), "{:?}", info.opcode);
+        }
+
+        let div = OpCode::I64DivU.info().unwrap();
+        assert_eq!(div.signature, fixed(&[ValKind::I64, ValKind::I64], &[ValKind::I64]));
+        assert!(div.can_trap);
+        let copy = OpCode::from_name("memory.copy").unwrap().info().unwrap();
+        assert_eq!((copy.immediates, copy.proposal), (&[MemoryIndex, MemoryIndex][..], BulkMemory));
+        assert_eq!(OpCode::Br.info().unwrap().signature, Polymorphic);
+        let try_table = OpCode::TryTable.info().unwrap();
+        assert_eq!((try_table.immediates, try_table.proposal), (&[BlockType, CatchTable][..], ExceptionHandling));
+        assert_eq!(OpCode::ReturnCallRef.info().unwrap().proposal, TailCall);
+        assert_eq!(OpCode::Unknown(0x27).info(), None);
+    }
+}
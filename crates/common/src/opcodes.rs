@@ -1,3 +1,6 @@
+use crate::leb128;
+
+
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum OpCode {
@@ -7,6 +10,8 @@ pub enum OpCode {
     Loop        = 0x03,
     If          = 0x04,
     Else_       = 0x05,
+    Throw       = 0x08,
+    ThrowRef    = 0x0A,
 
     End_         = 0x0B,
     Br           = 0x0C,
@@ -16,9 +21,15 @@ pub enum OpCode {
     Call         = 0x10,
     CallIndirect = 0x11,
 
-    Drop    = 0x1A,
-    Select  = 0x1B,
-    SelectT = 0x1C,
+    ReturnCall         = 0x12,
+    ReturnCallIndirect = 0x13,
+    CallRef            = 0x14,
+    ReturnCallRef      = 0x15,
+
+    Drop     = 0x1A,
+    Select   = 0x1B,
+    SelectT  = 0x1C,
+    TryTable = 0x1F,
 
     LocalGet  = 0x20,
     LocalSet  = 0x21,
@@ -43,13 +54,212 @@ pub enum OpCode {
     I64Load32S = 0x34,
     I64Load32U = 0x35,
     I32Store   = 0x36,
+    I64Store   = 0x37,
+    F32Store   = 0x38,
+    F64Store   = 0x39,
+    I32Store8  = 0x3A,
+    I32Store16 = 0x3B,
+    I64Store8  = 0x3C,
+    I64Store16 = 0x3D,
+    I64Store32 = 0x3E,
+    MemorySize = 0x3F,
+    MemoryGrow = 0x40,
+
+    I32Const = 0x41,
+    I64Const = 0x42,
+    F32Const = 0x43,
+    F64Const = 0x44,
+
+    I32Eqz = 0x45,
+    I32Eq  = 0x46,
+    I32Ne  = 0x47,
+    I32LtS = 0x48,
+    I32LtU = 0x49,
+    I32GtS = 0x4A,
+    I32GtU = 0x4B,
+    I32LeS = 0x4C,
+    I32LeU = 0x4D,
+    I32GeS = 0x4E,
+    I32GeU = 0x4F,
+
+    I64Eqz = 0x50,
+    I64Eq  = 0x51,
+    I64Ne  = 0x52,
+    I64LtS = 0x53,
+    I64LtU = 0x54,
+    I64GtS = 0x55,
+    I64GtU = 0x56,
+    I64LeS = 0x57,
+    I64LeU = 0x58,
+    I64GeS = 0x59,
+    I64GeU = 0x5A,
+
+    F32Eq = 0x5B,
+    F32Ne = 0x5C,
+    F32Lt = 0x5D,
+    F32Gt = 0x5E,
+    F32Le = 0x5F,
+    F32Ge = 0x60,
+
+    F64Eq = 0x61,
+    F64Ne = 0x62,
+    F64Lt = 0x63,
+    F64Gt = 0x64,
+    F64Le = 0x65,
+    F64Ge = 0x66,
+
+    I32Clz    = 0x67,
+    I32Ctz    = 0x68,
+    I32Popcnt = 0x69,
+    I32Add    = 0x6A,
+    I32Sub    = 0x6B,
+    I32Mul    = 0x6C,
+    I32DivS   = 0x6D,
+    I32DivU   = 0x6E,
+    I32RemS   = 0x6F,
+    I32RemU   = 0x70,
+    I32And    = 0x71,
+    I32Or     = 0x72,
+    I32Xor    = 0x73,
+    I32Shl    = 0x74,
+    I32ShrS   = 0x75,
+    I32ShrU   = 0x76,
+    I32Rotl   = 0x77,
+    I32Rotr   = 0x78,
+
+    I64Clz    = 0x79,
+    I64Ctz    = 0x7A,
+    I64Popcnt = 0x7B,
+    I64Add    = 0x7C,
+    I64Sub    = 0x7D,
+    I64Mul    = 0x7E,
+    I64DivS   = 0x7F,
+    I64DivU   = 0x80,
+    I64RemS   = 0x81,
+    I64RemU   = 0x82,
+    I64And    = 0x83,
+    I64Or     = 0x84,
+    I64Xor    = 0x85,
+    I64Shl    = 0x86,
+    I64ShrS   = 0x87,
+    I64ShrU   = 0x88,
+    I64Rotl   = 0x89,
+    I64Rotr   = 0x8A,
+
+    F32Abs      = 0x8B,
+    F32Neg      = 0x8C,
+    F32Ceil     = 0x8D,
+    F32Floor    = 0x8E,
+    F32Trunc    = 0x8F,
+    F32Nearest  = 0x90,
+    F32Sqrt     = 0x91,
+    F32Add      = 0x92,
+    F32Sub      = 0x93,
+    F32Mul      = 0x94,
+    F32Div      = 0x95,
+    F32Min      = 0x96,
+    F32Max      = 0x97,
+    F32Copysign = 0x98,
+
+    F64Abs      = 0x99,
+    F64Neg      = 0x9A,
+    F64Ceil     = 0x9B,
+    F64Floor    = 0x9C,
+    F64Trunc    = 0x9D,
+    F64Nearest  = 0x9E,
+    F64Sqrt     = 0x9F,
+    F64Add      = 0xA0,
+    F64Sub      = 0xA1,
+    F64Mul      = 0xA2,
+    F64Div      = 0xA3,
+    F64Min      = 0xA4,
+    F64Max      = 0xA5,
+    F64Copysign = 0xA6,
+
+    I32WrapI64        = 0xA7,
+    I32TruncF32S      = 0xA8,
+    I32TruncF32U      = 0xA9,
+    I32TruncF64S      = 0xAA,
+    I32TruncF64U      = 0xAB,
+    I64ExtendI32S     = 0xAC,
+    I64ExtendI32U     = 0xAD,
+    I64TruncF32S      = 0xAE,
+    I64TruncF32U      = 0xAF,
+    I64TruncF64S      = 0xB0,
+    I64TruncF64U      = 0xB1,
+    F32ConvertI32S    = 0xB2,
+    F32ConvertI32U    = 0xB3,
+    F32ConvertI64S    = 0xB4,
+    F32ConvertI64U    = 0xB5,
+    F32DemoteF64      = 0xB6,
+    F64ConvertI32S    = 0xB7,
+    F64ConvertI32U    = 0xB8,
+    F64ConvertI64S    = 0xB9,
+    F64ConvertI64U    = 0xBA,
+    F64PromoteF32     = 0xBB,
+    I32ReinterpretF32 = 0xBC,
+    I64ReinterpretF64 = 0xBD,
+    F32ReinterpretI32 = 0xBE,
+    F64ReinterpretI64 = 0xBF,
+
+    I32Extend8S  = 0xC0,
+    I32Extend16S = 0xC1,
+    I64Extend8S  = 0xC2,
+    I64Extend16S = 0xC3,
+    I64Extend32S = 0xC4,
+
+    RefNull   = 0xD0,
+    RefIsNull = 0xD1,
+    RefFunc   = 0xD2,
+
+    RefEq        = 0xD3,
+    RefAsNonNull = 0xD4,
+    BrOnNull     = 0xD5,
+    BrOnNonNull  = 0xD6,
 
+    // prefix byte followed by a LEB128 sub-opcode
+    Prefixed(Prefix, u32),
     Unknown(u32),
 }
 
+/// Prefix bytes of the instructions added by post-MVP proposals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Prefix {
+    Gc      = 0xFB,
+    Misc    = 0xFC,
+    Simd    = 0xFD,
+    Threads = 0xFE,
+}
+
+impl Prefix {
+    pub const ALL: [Prefix; 4] = [Prefix::Gc, Prefix::Misc, Prefix::Simd, Prefix::Threads];
+
+    pub fn from_u8(byte: u8) -> Option<Self> {
+        Prefix::ALL.into_iter().find(|prefix| *prefix as u8 == byte)
+    }
+
+    // (sub-opcode, name) of every known instruction, sorted by sub-opcode
+    fn names(self) -> &'static [(u32, &'static str)] {
+        match self {
+            Prefix::Gc      => GC_NAMES,
+            Prefix::Misc    => MISC_NAMES,
+            Prefix::Simd    => SIMD_NAMES,
+            Prefix::Threads => THREADS_NAMES,
+        }
+    }
+}
+
+// Sub-opcodes are kept below the prefix byte in the `u32` form of an opcode.
+const SUB_OPCODE_BITS: u32 = 24;
+
 impl OpCode {
 
+    /// Single-byte opcodes map to their value, prefixed ones to
+    /// `prefix << 24 | sub_opcode`.
     pub fn from_u32(value:u32)-> Self {
+        if let Some(prefix) = Prefix::from_u8((value >> SUB_OPCODE_BITS) as u8) {
+            return OpCode::Prefixed(prefix, value & ((1 << SUB_OPCODE_BITS) - 1));
+        }
         match value {
             0x00 => OpCode::Unreachable,
             0x01 => OpCode::Nop,
@@ -57,6 +267,8 @@ impl OpCode {
             0x03 => OpCode::Loop,
             0x04 => OpCode::If,
             0x05 => OpCode::Else_,
+            0x08 => OpCode::Throw,
+            0x0A => OpCode::ThrowRef,
             0x0B => OpCode::End_,
             0x0C => OpCode::Br,
             0x0D => OpCode::BrIf,
@@ -64,9 +276,14 @@ impl OpCode {
             0x0F => OpCode::Return,
             0x10 => OpCode::Call,
             0x11 => OpCode::CallIndirect,
+            0x12 => OpCode::ReturnCall,
+            0x13 => OpCode::ReturnCallIndirect,
+            0x14 => OpCode::CallRef,
+            0x15 => OpCode::ReturnCallRef,
             0x1A => OpCode::Drop,
             0x1B => OpCode::Select,
             0x1C => OpCode::SelectT,
+            0x1F => OpCode::TryTable,
             0x20 => OpCode::LocalGet,
             0x21 => OpCode::LocalSet,
             0x22 => OpCode::LocalTee,
@@ -89,53 +306,1059 @@ impl OpCode {
             0x34 => OpCode::I64Load32S,
             0x35 => OpCode::I64Load32U,
             0x36 => OpCode::I32Store,
+            0x37 => OpCode::I64Store,
+            0x38 => OpCode::F32Store,
+            0x39 => OpCode::F64Store,
+            0x3A => OpCode::I32Store8,
+            0x3B => OpCode::I32Store16,
+            0x3C => OpCode::I64Store8,
+            0x3D => OpCode::I64Store16,
+            0x3E => OpCode::I64Store32,
+            0x3F => OpCode::MemorySize,
+            0x40 => OpCode::MemoryGrow,
+            0x41 => OpCode::I32Const,
+            0x42 => OpCode::I64Const,
+            0x43 => OpCode::F32Const,
+            0x44 => OpCode::F64Const,
+            0x45 => OpCode::I32Eqz,
+            0x46 => OpCode::I32Eq,
+            0x47 => OpCode::I32Ne,
+            0x48 => OpCode::I32LtS,
+            0x49 => OpCode::I32LtU,
+            0x4A => OpCode::I32GtS,
+            0x4B => OpCode::I32GtU,
+            0x4C => OpCode::I32LeS,
+            0x4D => OpCode::I32LeU,
+            0x4E => OpCode::I32GeS,
+            0x4F => OpCode::I32GeU,
+            0x50 => OpCode::I64Eqz,
+            0x51 => OpCode::I64Eq,
+            0x52 => OpCode::I64Ne,
+            0x53 => OpCode::I64LtS,
+            0x54 => OpCode::I64LtU,
+            0x55 => OpCode::I64GtS,
+            0x56 => OpCode::I64GtU,
+            0x57 => OpCode::I64LeS,
+            0x58 => OpCode::I64LeU,
+            0x59 => OpCode::I64GeS,
+            0x5A => OpCode::I64GeU,
+            0x5B => OpCode::F32Eq,
+            0x5C => OpCode::F32Ne,
+            0x5D => OpCode::F32Lt,
+            0x5E => OpCode::F32Gt,
+            0x5F => OpCode::F32Le,
+            0x60 => OpCode::F32Ge,
+            0x61 => OpCode::F64Eq,
+            0x62 => OpCode::F64Ne,
+            0x63 => OpCode::F64Lt,
+            0x64 => OpCode::F64Gt,
+            0x65 => OpCode::F64Le,
+            0x66 => OpCode::F64Ge,
+            0x67 => OpCode::I32Clz,
+            0x68 => OpCode::I32Ctz,
+            0x69 => OpCode::I32Popcnt,
+            0x6A => OpCode::I32Add,
+            0x6B => OpCode::I32Sub,
+            0x6C => OpCode::I32Mul,
+            0x6D => OpCode::I32DivS,
+            0x6E => OpCode::I32DivU,
+            0x6F => OpCode::I32RemS,
+            0x70 => OpCode::I32RemU,
+            0x71 => OpCode::I32And,
+            0x72 => OpCode::I32Or,
+            0x73 => OpCode::I32Xor,
+            0x74 => OpCode::I32Shl,
+            0x75 => OpCode::I32ShrS,
+            0x76 => OpCode::I32ShrU,
+            0x77 => OpCode::I32Rotl,
+            0x78 => OpCode::I32Rotr,
+            0x79 => OpCode::I64Clz,
+            0x7A => OpCode::I64Ctz,
+            0x7B => OpCode::I64Popcnt,
+            0x7C => OpCode::I64Add,
+            0x7D => OpCode::I64Sub,
+            0x7E => OpCode::I64Mul,
+            0x7F => OpCode::I64DivS,
+            0x80 => OpCode::I64DivU,
+            0x81 => OpCode::I64RemS,
+            0x82 => OpCode::I64RemU,
+            0x83 => OpCode::I64And,
+            0x84 => OpCode::I64Or,
+            0x85 => OpCode::I64Xor,
+            0x86 => OpCode::I64Shl,
+            0x87 => OpCode::I64ShrS,
+            0x88 => OpCode::I64ShrU,
+            0x89 => OpCode::I64Rotl,
+            0x8A => OpCode::I64Rotr,
+            0x8B => OpCode::F32Abs,
+            0x8C => OpCode::F32Neg,
+            0x8D => OpCode::F32Ceil,
+            0x8E => OpCode::F32Floor,
+            0x8F => OpCode::F32Trunc,
+            0x90 => OpCode::F32Nearest,
+            0x91 => OpCode::F32Sqrt,
+            0x92 => OpCode::F32Add,
+            0x93 => OpCode::F32Sub,
+            0x94 => OpCode::F32Mul,
+            0x95 => OpCode::F32Div,
+            0x96 => OpCode::F32Min,
+            0x97 => OpCode::F32Max,
+            0x98 => OpCode::F32Copysign,
+            0x99 => OpCode::F64Abs,
+            0x9A => OpCode::F64Neg,
+            0x9B => OpCode::F64Ceil,
+            0x9C => OpCode::F64Floor,
+            0x9D => OpCode::F64Trunc,
+            0x9E => OpCode::F64Nearest,
+            0x9F => OpCode::F64Sqrt,
+            0xA0 => OpCode::F64Add,
+            0xA1 => OpCode::F64Sub,
+            0xA2 => OpCode::F64Mul,
+            0xA3 => OpCode::F64Div,
+            0xA4 => OpCode::F64Min,
+            0xA5 => OpCode::F64Max,
+            0xA6 => OpCode::F64Copysign,
+            0xA7 => OpCode::I32WrapI64,
+            0xA8 => OpCode::I32TruncF32S,
+            0xA9 => OpCode::I32TruncF32U,
+            0xAA => OpCode::I32TruncF64S,
+            0xAB => OpCode::I32TruncF64U,
+            0xAC => OpCode::I64ExtendI32S,
+            0xAD => OpCode::I64ExtendI32U,
+            0xAE => OpCode::I64TruncF32S,
+            0xAF => OpCode::I64TruncF32U,
+            0xB0 => OpCode::I64TruncF64S,
+            0xB1 => OpCode::I64TruncF64U,
+            0xB2 => OpCode::F32ConvertI32S,
+            0xB3 => OpCode::F32ConvertI32U,
+            0xB4 => OpCode::F32ConvertI64S,
+            0xB5 => OpCode::F32ConvertI64U,
+            0xB6 => OpCode::F32DemoteF64,
+            0xB7 => OpCode::F64ConvertI32S,
+            0xB8 => OpCode::F64ConvertI32U,
+            0xB9 => OpCode::F64ConvertI64S,
+            0xBA => OpCode::F64ConvertI64U,
+            0xBB => OpCode::F64PromoteF32,
+            0xBC => OpCode::I32ReinterpretF32,
+            0xBD => OpCode::I64ReinterpretF64,
+            0xBE => OpCode::F32ReinterpretI32,
+            0xBF => OpCode::F64ReinterpretI64,
+            0xC0 => OpCode::I32Extend8S,
+            0xC1 => OpCode::I32Extend16S,
+            0xC2 => OpCode::I64Extend8S,
+            0xC3 => OpCode::I64Extend16S,
+            0xC4 => OpCode::I64Extend32S,
+            0xD0 => OpCode::RefNull,
+            0xD1 => OpCode::RefIsNull,
+            0xD2 => OpCode::RefFunc,
+            0xD3 => OpCode::RefEq,
+            0xD4 => OpCode::RefAsNonNull,
+            0xD5 => OpCode::BrOnNull,
+            0xD6 => OpCode::BrOnNonNull,
 
             _ => OpCode::Unknown(value),
         }
     }
 
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            OpCode::Unreachable       => 0x00,
+            OpCode::Nop               => 0x01,
+            OpCode::Block             => 0x02,
+            OpCode::Loop              => 0x03,
+            OpCode::If                => 0x04,
+            OpCode::Else_             => 0x05,
+            OpCode::Throw             => 0x08,
+            OpCode::ThrowRef          => 0x0A,
+            OpCode::End_              => 0x0B,
+            OpCode::Br                => 0x0C,
+            OpCode::BrIf              => 0x0D,
+            OpCode::BrTable           => 0x0E,
+            OpCode::Return            => 0x0F,
+            OpCode::Call              => 0x10,
+            OpCode::CallIndirect      => 0x11,
+            OpCode::ReturnCall        => 0x12,
+            OpCode::ReturnCallIndirect => 0x13,
+            OpCode::CallRef           => 0x14,
+            OpCode::ReturnCallRef     => 0x15,
+            OpCode::Drop              => 0x1A,
+            OpCode::Select            => 0x1B,
+            OpCode::SelectT           => 0x1C,
+            OpCode::TryTable          => 0x1F,
+            OpCode::LocalGet          => 0x20,
+            OpCode::LocalSet          => 0x21,
+            OpCode::LocalTee          => 0x22,
+            OpCode::GlobalGet         => 0x23,
+            OpCode::GlobalSet         => 0x24,
+            OpCode::TableGet          => 0x25,
+            OpCode::TableSet          => 0x26,
+            OpCode::I32Load           => 0x28,
+            OpCode::I64Load           => 0x29,
+            OpCode::F32Load           => 0x2A,
+            OpCode::F64Load           => 0x2B,
+            OpCode::I32Load8S         => 0x2C,
+            OpCode::I32Load8U         => 0x2D,
+            OpCode::I32Load16S        => 0x2E,
+            OpCode::I32Load16U        => 0x2F,
+            OpCode::I64Load8S         => 0x30,
+            OpCode::I64Load8U         => 0x31,
+            OpCode::I64Load16S        => 0x32,
+            OpCode::I64Load16U        => 0x33,
+            OpCode::I64Load32S        => 0x34,
+            OpCode::I64Load32U        => 0x35,
+            OpCode::I32Store          => 0x36,
+            OpCode::I64Store          => 0x37,
+            OpCode::F32Store          => 0x38,
+            OpCode::F64Store          => 0x39,
+            OpCode::I32Store8         => 0x3A,
+            OpCode::I32Store16        => 0x3B,
+            OpCode::I64Store8         => 0x3C,
+            OpCode::I64Store16        => 0x3D,
+            OpCode::I64Store32        => 0x3E,
+            OpCode::MemorySize        => 0x3F,
+            OpCode::MemoryGrow        => 0x40,
+            OpCode::I32Const          => 0x41,
+            OpCode::I64Const          => 0x42,
+            OpCode::F32Const          => 0x43,
+            OpCode::F64Const          => 0x44,
+            OpCode::I32Eqz            => 0x45,
+            OpCode::I32Eq             => 0x46,
+            OpCode::I32Ne             => 0x47,
+            OpCode::I32LtS            => 0x48,
+            OpCode::I32LtU            => 0x49,
+            OpCode::I32GtS            => 0x4A,
+            OpCode::I32GtU            => 0x4B,
+            OpCode::I32LeS            => 0x4C,
+            OpCode::I32LeU            => 0x4D,
+            OpCode::I32GeS            => 0x4E,
+            OpCode::I32GeU            => 0x4F,
+            OpCode::I64Eqz            => 0x50,
+            OpCode::I64Eq             => 0x51,
+            OpCode::I64Ne             => 0x52,
+            OpCode::I64LtS            => 0x53,
+            OpCode::I64LtU            => 0x54,
+            OpCode::I64GtS            => 0x55,
+            OpCode::I64GtU            => 0x56,
+            OpCode::I64LeS            => 0x57,
+            OpCode::I64LeU            => 0x58,
+            OpCode::I64GeS            => 0x59,
+            OpCode::I64GeU            => 0x5A,
+            OpCode::F32Eq             => 0x5B,
+            OpCode::F32Ne             => 0x5C,
+            OpCode::F32Lt             => 0x5D,
+            OpCode::F32Gt             => 0x5E,
+            OpCode::F32Le             => 0x5F,
+            OpCode::F32Ge             => 0x60,
+            OpCode::F64Eq             => 0x61,
+            OpCode::F64Ne             => 0x62,
+            OpCode::F64Lt             => 0x63,
+            OpCode::F64Gt             => 0x64,
+            OpCode::F64Le             => 0x65,
+            OpCode::F64Ge             => 0x66,
+            OpCode::I32Clz            => 0x67,
+            OpCode::I32Ctz            => 0x68,
+            OpCode::I32Popcnt         => 0x69,
+            OpCode::I32Add            => 0x6A,
+            OpCode::I32Sub            => 0x6B,
+            OpCode::I32Mul            => 0x6C,
+            OpCode::I32DivS           => 0x6D,
+            OpCode::I32DivU           => 0x6E,
+            OpCode::I32RemS           => 0x6F,
+            OpCode::I32RemU           => 0x70,
+            OpCode::I32And            => 0x71,
+            OpCode::I32Or             => 0x72,
+            OpCode::I32Xor            => 0x73,
+            OpCode::I32Shl            => 0x74,
+            OpCode::I32ShrS           => 0x75,
+            OpCode::I32ShrU           => 0x76,
+            OpCode::I32Rotl           => 0x77,
+            OpCode::I32Rotr           => 0x78,
+            OpCode::I64Clz            => 0x79,
+            OpCode::I64Ctz            => 0x7A,
+            OpCode::I64Popcnt         => 0x7B,
+            OpCode::I64Add            => 0x7C,
+            OpCode::I64Sub            => 0x7D,
+            OpCode::I64Mul            => 0x7E,
+            OpCode::I64DivS           => 0x7F,
+            OpCode::I64DivU           => 0x80,
+            OpCode::I64RemS           => 0x81,
+            OpCode::I64RemU           => 0x82,
+            OpCode::I64And            => 0x83,
+            OpCode::I64Or             => 0x84,
+            OpCode::I64Xor            => 0x85,
+            OpCode::I64Shl            => 0x86,
+            OpCode::I64ShrS           => 0x87,
+            OpCode::I64ShrU           => 0x88,
+            OpCode::I64Rotl           => 0x89,
+            OpCode::I64Rotr           => 0x8A,
+            OpCode::F32Abs            => 0x8B,
+            OpCode::F32Neg            => 0x8C,
+            OpCode::F32Ceil           => 0x8D,
+            OpCode::F32Floor          => 0x8E,
+            OpCode::F32Trunc          => 0x8F,
+            OpCode::F32Nearest        => 0x90,
+            OpCode::F32Sqrt           => 0x91,
+            OpCode::F32Add            => 0x92,
+            OpCode::F32Sub            => 0x93,
+            OpCode::F32Mul            => 0x94,
+            OpCode::F32Div            => 0x95,
+            OpCode::F32Min            => 0x96,
+            OpCode::F32Max            => 0x97,
+            OpCode::F32Copysign       => 0x98,
+            OpCode::F64Abs            => 0x99,
+            OpCode::F64Neg            => 0x9A,
+            OpCode::F64Ceil           => 0x9B,
+            OpCode::F64Floor          => 0x9C,
+            OpCode::F64Trunc          => 0x9D,
+            OpCode::F64Nearest        => 0x9E,
+            OpCode::F64Sqrt           => 0x9F,
+            OpCode::F64Add            => 0xA0,
+            OpCode::F64Sub            => 0xA1,
+            OpCode::F64Mul            => 0xA2,
+            OpCode::F64Div            => 0xA3,
+            OpCode::F64Min            => 0xA4,
+            OpCode::F64Max            => 0xA5,
+            OpCode::F64Copysign       => 0xA6,
+            OpCode::I32WrapI64        => 0xA7,
+            OpCode::I32TruncF32S      => 0xA8,
+            OpCode::I32TruncF32U      => 0xA9,
+            OpCode::I32TruncF64S      => 0xAA,
+            OpCode::I32TruncF64U      => 0xAB,
+            OpCode::I64ExtendI32S     => 0xAC,
+            OpCode::I64ExtendI32U     => 0xAD,
+            OpCode::I64TruncF32S      => 0xAE,
+            OpCode::I64TruncF32U      => 0xAF,
+            OpCode::I64TruncF64S      => 0xB0,
+            OpCode::I64TruncF64U      => 0xB1,
+            OpCode::F32ConvertI32S    => 0xB2,
+            OpCode::F32ConvertI32U    => 0xB3,
+            OpCode::F32ConvertI64S    => 0xB4,
+            OpCode::F32ConvertI64U    => 0xB5,
+            OpCode::F32DemoteF64      => 0xB6,
+            OpCode::F64ConvertI32S    => 0xB7,
+            OpCode::F64ConvertI32U    => 0xB8,
+            OpCode::F64ConvertI64S    => 0xB9,
+            OpCode::F64ConvertI64U    => 0xBA,
+            OpCode::F64PromoteF32     => 0xBB,
+            OpCode::I32ReinterpretF32 => 0xBC,
+            OpCode::I64ReinterpretF64 => 0xBD,
+            OpCode::F32ReinterpretI32 => 0xBE,
+            OpCode::F64ReinterpretI64 => 0xBF,
+            OpCode::I32Extend8S       => 0xC0,
+            OpCode::I32Extend16S      => 0xC1,
+            OpCode::I64Extend8S       => 0xC2,
+            OpCode::I64Extend16S      => 0xC3,
+            OpCode::I64Extend32S      => 0xC4,
+            OpCode::RefNull           => 0xD0,
+            OpCode::RefIsNull         => 0xD1,
+            OpCode::RefFunc           => 0xD2,
+            OpCode::RefEq             => 0xD3,
+            OpCode::RefAsNonNull      => 0xD4,
+            OpCode::BrOnNull          => 0xD5,
+            OpCode::BrOnNonNull       => 0xD6,
+            OpCode::Prefixed(prefix, sub) => (*prefix as u32) << SUB_OPCODE_BITS | sub,
+            OpCode::Unknown(value) => *value,
+        }
+    }
+
+    /// Decode the opcode at the start of `bytes`, returning it with the
+    /// number of bytes it takes.
+    pub fn read(bytes: &[u8]) -> Option<(Self, usize)> {
+        let first = *bytes.first()?;
+        match Prefix::from_u8(first) {
+            Some(prefix) => {
+                let (sub, len) = leb128::read_u32(&bytes[1..])?;
+                if sub >> SUB_OPCODE_BITS != 0 {
+                    return None;
+                }
+                Some((OpCode::Prefixed(prefix, sub), 1 + len))
+            }
+            None => Some((OpCode::from_u32(first as u32), 1)),
+        }
+    }
+
+    /// The binary encoding, without immediates.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            OpCode::Prefixed(prefix, sub) => {
+                let mut bytes = vec![*prefix as u8];
+                leb128::write_u32(*sub, &mut bytes);
+                bytes
+            }
+            // only single-byte values come out of `read`
+            _ => vec![self.to_u32() as u8],
+        }
+    }
+
+    // Reverse of `name()`, used when reading the text format.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let prefixed = Prefix::ALL
+            .into_iter()
+            .flat_map(|prefix| prefix.names().iter().map(move |(sub, _)| OpCode::Prefixed(prefix, *sub)));
+        (0x00..=0xFF)
+            .map(OpCode::from_u32)
+            .chain(prefixed)
+            .find(|op| !matches!(op, OpCode::Unknown(_)) && op.name() == name)
+    }
+
     pub fn name(&self) -> String {
         match self {
-            OpCode::Unreachable => "unreachable".to_string(),
-            OpCode::Nop         => "nop".to_string(),
-            OpCode::Block       => "block".to_string(),
-            OpCode::Loop        => "loop".to_string(),
-            OpCode::If          => "if".to_string(),
-            OpCode::Else_       => "else".to_string(),
-            OpCode::End_        => "end".to_string(),
-            OpCode::Br          => "br".to_string(),
-            OpCode::BrIf        => "br_if".to_string(),
-            OpCode::BrTable     => "br_table".to_string(),
-            OpCode::Return      => "return".to_string(),
-            OpCode::Call        => "call".to_string(),
-            OpCode::CallIndirect=> "call_indirect".to_string(),
-            OpCode::Drop        => "drop".to_string(),
-            OpCode::Select      => "select".to_string(),
-            OpCode::SelectT     => "select_t".to_string(),
-            OpCode::LocalGet   => "local.get".to_string(),
-            OpCode::LocalSet   => "local.set".to_string(),
-            OpCode::LocalTee   => "local.tee".to_string(),
-            OpCode::GlobalGet  => "global.get".to_string(),
-            OpCode::GlobalSet  => "global.set".to_string(),
-            OpCode::TableGet   => "table.get".to_string(),
-            OpCode::TableSet   => "table.set".to_string(),
-            OpCode::I32Load    => "i32.load".to_string(),
-            OpCode::I64Load    => "i64.load".to_string(),
-            OpCode::F32Load    => "f32.load".to_string(),
-            OpCode::F64Load    => "f64.load".to_string(),
-            OpCode::I32Load8S  => "i32.load8_s".to_string(),
-            OpCode::I32Load8U  => "i32.load8_u".to_string(),
-            OpCode::I32Load16S => "i32.load16_s".to_string(),
-            OpCode::I32Load16U => "i32.load16_u".to_string(),
-            OpCode::I64Load8S  => "i32.load8_s".to_string(),
-            OpCode::I64Load8U  => "i32.load8_u".to_string(),
-            OpCode::I64Load16S => "i32.load16_s".to_string(),
-            OpCode::I64Load16U => "i32.load16_u".to_string(),
-            OpCode::I64Load32S => "i32.load32_s".to_string(),
-            OpCode::I64Load32U => "i32.load32_u".to_string(),
-            OpCode::I32Store   => "i32.store".to_string(),
+            OpCode::Unreachable       => "unreachable".to_string(),
+            OpCode::Nop               => "nop".to_string(),
+            OpCode::Block             => "block".to_string(),
+            OpCode::Loop              => "loop".to_string(),
+            OpCode::If                => "if".to_string(),
+            OpCode::Else_             => "else".to_string(),
+            OpCode::Throw             => "throw".to_string(),
+            OpCode::ThrowRef          => "throw_ref".to_string(),
+            OpCode::End_              => "end".to_string(),
+            OpCode::Br                => "br".to_string(),
+            OpCode::BrIf              => "br_if".to_string(),
+            OpCode::BrTable           => "br_table".to_string(),
+            OpCode::Return            => "return".to_string(),
+            OpCode::Call              => "call".to_string(),
+            OpCode::CallIndirect      => "call_indirect".to_string(),
+            OpCode::ReturnCall        => "return_call".to_string(),
+            OpCode::ReturnCallIndirect => "return_call_indirect".to_string(),
+            OpCode::CallRef           => "call_ref".to_string(),
+            OpCode::ReturnCallRef     => "return_call_ref".to_string(),
+            OpCode::Drop              => "drop".to_string(),
+            OpCode::Select            => "select".to_string(),
+            OpCode::SelectT           => "select_t".to_string(),
+            OpCode::TryTable          => "try_table".to_string(),
+            OpCode::LocalGet          => "local.get".to_string(),
+            OpCode::LocalSet          => "local.set".to_string(),
+            OpCode::LocalTee          => "local.tee".to_string(),
+            OpCode::GlobalGet         => "global.get".to_string(),
+            OpCode::GlobalSet         => "global.set".to_string(),
+            OpCode::TableGet          => "table.get".to_string(),
+            OpCode::TableSet          => "table.set".to_string(),
+            OpCode::I32Load           => "i32.load".to_string(),
+            OpCode::I64Load           => "i64.load".to_string(),
+            OpCode::F32Load           => "f32.load".to_string(),
+            OpCode::F64Load           => "f64.load".to_string(),
+            OpCode::I32Load8S         => "i32.load8_s".to_string(),
+            OpCode::I32Load8U         => "i32.load8_u".to_string(),
+            OpCode::I32Load16S        => "i32.load16_s".to_string(),
+            OpCode::I32Load16U        => "i32.load16_u".to_string(),
+            OpCode::I64Load8S         => "i64.load8_s".to_string(),
+            OpCode::I64Load8U         => "i64.load8_u".to_string(),
+            OpCode::I64Load16S        => "i64.load16_s".to_string(),
+            OpCode::I64Load16U        => "i64.load16_u".to_string(),
+            OpCode::I64Load32S        => "i64.load32_s".to_string(),
+            OpCode::I64Load32U        => "i64.load32_u".to_string(),
+            OpCode::I32Store          => "i32.store".to_string(),
+            OpCode::I64Store          => "i64.store".to_string(),
+            OpCode::F32Store          => "f32.store".to_string(),
+            OpCode::F64Store          => "f64.store".to_string(),
+            OpCode::I32Store8         => "i32.store8".to_string(),
+            OpCode::I32Store16        => "i32.store16".to_string(),
+            OpCode::I64Store8         => "i64.store8".to_string(),
+            OpCode::I64Store16        => "i64.store16".to_string(),
+            OpCode::I64Store32        => "i64.store32".to_string(),
+            OpCode::MemorySize        => "memory.size".to_string(),
+            OpCode::MemoryGrow        => "memory.grow".to_string(),
+            OpCode::I32Const          => "i32.const".to_string(),
+            OpCode::I64Const          => "i64.const".to_string(),
+            OpCode::F32Const          => "f32.const".to_string(),
+            OpCode::F64Const          => "f64.const".to_string(),
+            OpCode::I32Eqz            => "i32.eqz".to_string(),
+            OpCode::I32Eq             => "i32.eq".to_string(),
+            OpCode::I32Ne             => "i32.ne".to_string(),
+            OpCode::I32LtS            => "i32.lt_s".to_string(),
+            OpCode::I32LtU            => "i32.lt_u".to_string(),
+            OpCode::I32GtS            => "i32.gt_s".to_string(),
+            OpCode::I32GtU            => "i32.gt_u".to_string(),
+            OpCode::I32LeS            => "i32.le_s".to_string(),
+            OpCode::I32LeU            => "i32.le_u".to_string(),
+            OpCode::I32GeS            => "i32.ge_s".to_string(),
+            OpCode::I32GeU            => "i32.ge_u".to_string(),
+            OpCode::I64Eqz            => "i64.eqz".to_string(),
+            OpCode::I64Eq             => "i64.eq".to_string(),
+            OpCode::I64Ne             => "i64.ne".to_string(),
+            OpCode::I64LtS            => "i64.lt_s".to_string(),
+            OpCode::I64LtU            => "i64.lt_u".to_string(),
+            OpCode::I64GtS            => "i64.gt_s".to_string(),
+            OpCode::I64GtU            => "i64.gt_u".to_string(),
+            OpCode::I64LeS            => "i64.le_s".to_string(),
+            OpCode::I64LeU            => "i64.le_u".to_string(),
+            OpCode::I64GeS            => "i64.ge_s".to_string(),
+            OpCode::I64GeU            => "i64.ge_u".to_string(),
+            OpCode::F32Eq             => "f32.eq".to_string(),
+            OpCode::F32Ne             => "f32.ne".to_string(),
+            OpCode::F32Lt             => "f32.lt".to_string(),
+            OpCode::F32Gt             => "f32.gt".to_string(),
+            OpCode::F32Le             => "f32.le".to_string(),
+            OpCode::F32Ge             => "f32.ge".to_string(),
+            OpCode::F64Eq             => "f64.eq".to_string(),
+            OpCode::F64Ne             => "f64.ne".to_string(),
+            OpCode::F64Lt             => "f64.lt".to_string(),
+            OpCode::F64Gt             => "f64.gt".to_string(),
+            OpCode::F64Le             => "f64.le".to_string(),
+            OpCode::F64Ge             => "f64.ge".to_string(),
+            OpCode::I32Clz            => "i32.clz".to_string(),
+            OpCode::I32Ctz            => "i32.ctz".to_string(),
+            OpCode::I32Popcnt         => "i32.popcnt".to_string(),
+            OpCode::I32Add            => "i32.add".to_string(),
+            OpCode::I32Sub            => "i32.sub".to_string(),
+            OpCode::I32Mul            => "i32.mul".to_string(),
+            OpCode::I32DivS           => "i32.div_s".to_string(),
+            OpCode::I32DivU           => "i32.div_u".to_string(),
+            OpCode::I32RemS           => "i32.rem_s".to_string(),
+            OpCode::I32RemU           => "i32.rem_u".to_string(),
+            OpCode::I32And            => "i32.and".to_string(),
+            OpCode::I32Or             => "i32.or".to_string(),
+            OpCode::I32Xor            => "i32.xor".to_string(),
+            OpCode::I32Shl            => "i32.shl".to_string(),
+            OpCode::I32ShrS           => "i32.shr_s".to_string(),
+            OpCode::I32ShrU           => "i32.shr_u".to_string(),
+            OpCode::I32Rotl           => "i32.rotl".to_string(),
+            OpCode::I32Rotr           => "i32.rotr".to_string(),
+            OpCode::I64Clz            => "i64.clz".to_string(),
+            OpCode::I64Ctz            => "i64.ctz".to_string(),
+            OpCode::I64Popcnt         => "i64.popcnt".to_string(),
+            OpCode::I64Add            => "i64.add".to_string(),
+            OpCode::I64Sub            => "i64.sub".to_string(),
+            OpCode::I64Mul            => "i64.mul".to_string(),
+            OpCode::I64DivS           => "i64.div_s".to_string(),
+            OpCode::I64DivU           => "i64.div_u".to_string(),
+            OpCode::I64RemS           => "i64.rem_s".to_string(),
+            OpCode::I64RemU           => "i64.rem_u".to_string(),
+            OpCode::I64And            => "i64.and".to_string(),
+            OpCode::I64Or             => "i64.or".to_string(),
+            OpCode::I64Xor            => "i64.xor".to_string(),
+            OpCode::I64Shl            => "i64.shl".to_string(),
+            OpCode::I64ShrS           => "i64.shr_s".to_string(),
+            OpCode::I64ShrU           => "i64.shr_u".to_string(),
+            OpCode::I64Rotl           => "i64.rotl".to_string(),
+            OpCode::I64Rotr           => "i64.rotr".to_string(),
+            OpCode::F32Abs            => "f32.abs".to_string(),
+            OpCode::F32Neg            => "f32.neg".to_string(),
+            OpCode::F32Ceil           => "f32.ceil".to_string(),
+            OpCode::F32Floor          => "f32.floor".to_string(),
+            OpCode::F32Trunc          => "f32.trunc".to_string(),
+            OpCode::F32Nearest        => "f32.nearest".to_string(),
+            OpCode::F32Sqrt           => "f32.sqrt".to_string(),
+            OpCode::F32Add            => "f32.add".to_string(),
+            OpCode::F32Sub            => "f32.sub".to_string(),
+            OpCode::F32Mul            => "f32.mul".to_string(),
+            OpCode::F32Div            => "f32.div".to_string(),
+            OpCode::F32Min            => "f32.min".to_string(),
+            OpCode::F32Max            => "f32.max".to_string(),
+            OpCode::F32Copysign       => "f32.copysign".to_string(),
+            OpCode::F64Abs            => "f64.abs".to_string(),
+            OpCode::F64Neg            => "f64.neg".to_string(),
+            OpCode::F64Ceil           => "f64.ceil".to_string(),
+            OpCode::F64Floor          => "f64.floor".to_string(),
+            OpCode::F64Trunc          => "f64.trunc".to_string(),
+            OpCode::F64Nearest        => "f64.nearest".to_string(),
+            OpCode::F64Sqrt           => "f64.sqrt".to_string(),
+            OpCode::F64Add            => "f64.add".to_string(),
+            OpCode::F64Sub            => "f64.sub".to_string(),
+            OpCode::F64Mul            => "f64.mul".to_string(),
+            OpCode::F64Div            => "f64.div".to_string(),
+            OpCode::F64Min            => "f64.min".to_string(),
+            OpCode::F64Max            => "f64.max".to_string(),
+            OpCode::F64Copysign       => "f64.copysign".to_string(),
+            OpCode::I32WrapI64        => "i32.wrap_i64".to_string(),
+            OpCode::I32TruncF32S      => "i32.trunc_f32_s".to_string(),
+            OpCode::I32TruncF32U      => "i32.trunc_f32_u".to_string(),
+            OpCode::I32TruncF64S      => "i32.trunc_f64_s".to_string(),
+            OpCode::I32TruncF64U      => "i32.trunc_f64_u".to_string(),
+            OpCode::I64ExtendI32S     => "i64.extend_i32_s".to_string(),
+            OpCode::I64ExtendI32U     => "i64.extend_i32_u".to_string(),
+            OpCode::I64TruncF32S      => "i64.trunc_f32_s".to_string(),
+            OpCode::I64TruncF32U      => "i64.trunc_f32_u".to_string(),
+            OpCode::I64TruncF64S      => "i64.trunc_f64_s".to_string(),
+            OpCode::I64TruncF64U      => "i64.trunc_f64_u".to_string(),
+            OpCode::F32ConvertI32S    => "f32.convert_i32_s".to_string(),
+            OpCode::F32ConvertI32U    => "f32.convert_i32_u".to_string(),
+            OpCode::F32ConvertI64S    => "f32.convert_i64_s".to_string(),
+            OpCode::F32ConvertI64U    => "f32.convert_i64_u".to_string(),
+            OpCode::F32DemoteF64      => "f32.demote_f64".to_string(),
+            OpCode::F64ConvertI32S    => "f64.convert_i32_s".to_string(),
+            OpCode::F64ConvertI32U    => "f64.convert_i32_u".to_string(),
+            OpCode::F64ConvertI64S    => "f64.convert_i64_s".to_string(),
+            OpCode::F64ConvertI64U    => "f64.convert_i64_u".to_string(),
+            OpCode::F64PromoteF32     => "f64.promote_f32".to_string(),
+            OpCode::I32ReinterpretF32 => "i32.reinterpret_f32".to_string(),
+            OpCode::I64ReinterpretF64 => "i64.reinterpret_f64".to_string(),
+            OpCode::F32ReinterpretI32 => "f32.reinterpret_i32".to_string(),
+            OpCode::F64ReinterpretI64 => "f64.reinterpret_i64".to_string(),
+            OpCode::I32Extend8S       => "i32.extend8_s".to_string(),
+            OpCode::I32Extend16S      => "i32.extend16_s".to_string(),
+            OpCode::I64Extend8S       => "i64.extend8_s".to_string(),
+            OpCode::I64Extend16S      => "i64.extend16_s".to_string(),
+            OpCode::I64Extend32S      => "i64.extend32_s".to_string(),
+            OpCode::RefNull           => "ref.null".to_string(),
+            OpCode::RefIsNull         => "ref.is_null".to_string(),
+            OpCode::RefFunc           => "ref.func".to_string(),
+            OpCode::RefEq             => "ref.eq".to_string(),
+            OpCode::RefAsNonNull      => "ref.as_non_null".to_string(),
+            OpCode::BrOnNull          => "br_on_null".to_string(),
+            OpCode::BrOnNonNull       => "br_on_non_null".to_string(),
+            OpCode::Prefixed(prefix, sub) => {
+                let names = prefix.names();
+                match names.binary_search_by_key(sub, |(code, _)| *code) {
+                    Ok(index) => names[index].1.to_string(),
+                    Err(_) => format!("unknown(0x{:02x} {})", *prefix as u8, sub),
+                }
+            }
             OpCode::Unknown(value) => format!("unknown(0x{:04x})", value),
         }
     }
 }
 
+// 0xFB prefix: GC
+const GC_NAMES: &[(u32, &str)] = &[
+    (0x00, "struct.new"),
+    (0x01, "struct.new_default"),
+    (0x02, "struct.get"),
+    (0x03, "struct.get_s"),
+    (0x04, "struct.get_u"),
+    (0x05, "struct.set"),
+    (0x06, "array.new"),
+    (0x07, "array.new_default"),
+    (0x08, "array.new_fixed"),
+    (0x09, "array.new_data"),
+    (0x0A, "array.new_elem"),
+    (0x0B, "array.get"),
+    (0x0C, "array.get_s"),
+    (0x0D, "array.get_u"),
+    (0x0E, "array.set"),
+    (0x0F, "array.len"),
+    (0x10, "array.fill"),
+    (0x11, "array.copy"),
+    (0x12, "array.init_data"),
+    (0x13, "array.init_elem"),
+    // the text format spells the nullable forms with `null` in the
+    // reference type, they get names of their own so that names are unique
+    (0x14, "ref.test"),
+    (0x15, "ref.test_null"),
+    (0x16, "ref.cast"),
+    (0x17, "ref.cast_null"),
+    (0x18, "br_on_cast"),
+    (0x19, "br_on_cast_fail"),
+    (0x1A, "any.convert_extern"),
+    (0x1B, "extern.convert_any"),
+    (0x1C, "ref.i31"),
+    (0x1D, "i31.get_s"),
+    (0x1E, "i31.get_u"),
+];
+
+// 0xFC prefix: saturating truncation and bulk memory
+const MISC_NAMES: &[(u32, &str)] = &[
+    (0x00, "i32.trunc_sat_f32_s"),
+    (0x01, "i32.trunc_sat_f32_u"),
+    (0x02, "i32.trunc_sat_f64_s"),
+    (0x03, "i32.trunc_sat_f64_u"),
+    (0x04, "i64.trunc_sat_f32_s"),
+    (0x05, "i64.trunc_sat_f32_u"),
+    (0x06, "i64.trunc_sat_f64_s"),
+    (0x07, "i64.trunc_sat_f64_u"),
+    (0x08, "memory.init"),
+    (0x09, "data.drop"),
+    (0x0A, "memory.copy"),
+    (0x0B, "memory.fill"),
+    (0x0C, "table.init"),
+    (0x0D, "elem.drop"),
+    (0x0E, "table.copy"),
+    (0x0F, "table.grow"),
+    (0x10, "table.size"),
+    (0x11, "table.fill"),
+];
+
+// 0xFD prefix: fixed-width and relaxed SIMD
+const SIMD_NAMES: &[(u32, &str)] = &[
+    (0x00, "v128.load"),
+    (0x01, "v128.load8x8_s"),
+    (0x02, "v128.load8x8_u"),
+    (0x03, "v128.load16x4_s"),
+    (0x04, "v128.load16x4_u"),
+    (0x05, "v128.load32x2_s"),
+    (0x06, "v128.load32x2_u"),
+    (0x07, "v128.load8_splat"),
+    (0x08, "v128.load16_splat"),
+    (0x09, "v128.load32_splat"),
+    (0x0A, "v128.load64_splat"),
+    (0x0B, "v128.store"),
+    (0x0C, "v128.const"),
+    (0x0D, "i8x16.shuffle"),
+    (0x0E, "i8x16.swizzle"),
+    (0x0F, "i8x16.splat"),
+    (0x10, "i16x8.splat"),
+    (0x11, "i32x4.splat"),
+    (0x12, "i64x2.splat"),
+    (0x13, "f32x4.splat"),
+    (0x14, "f64x2.splat"),
+    (0x15, "i8x16.extract_lane_s"),
+    (0x16, "i8x16.extract_lane_u"),
+    (0x17, "i8x16.replace_lane"),
+    (0x18, "i16x8.extract_lane_s"),
+    (0x19, "i16x8.extract_lane_u"),
+    (0x1A, "i16x8.replace_lane"),
+    (0x1B, "i32x4.extract_lane"),
+    (0x1C, "i32x4.replace_lane"),
+    (0x1D, "i64x2.extract_lane"),
+    (0x1E, "i64x2.replace_lane"),
+    (0x1F, "f32x4.extract_lane"),
+    (0x20, "f32x4.replace_lane"),
+    (0x21, "f64x2.extract_lane"),
+    (0x22, "f64x2.replace_lane"),
+    (0x23, "i8x16.eq"),
+    (0x24, "i8x16.ne"),
+    (0x25, "i8x16.lt_s"),
+    (0x26, "i8x16.lt_u"),
+    (0x27, "i8x16.gt_s"),
+    (0x28, "i8x16.gt_u"),
+    (0x29, "i8x16.le_s"),
+    (0x2A, "i8x16.le_u"),
+    (0x2B, "i8x16.ge_s"),
+    (0x2C, "i8x16.ge_u"),
+    (0x2D, "i16x8.eq"),
+    (0x2E, "i16x8.ne"),
+    (0x2F, "i16x8.lt_s"),
+    (0x30, "i16x8.lt_u"),
+    (0x31, "i16x8.gt_s"),
+    (0x32, "i16x8.gt_u"),
+    (0x33, "i16x8.le_s"),
+    (0x34, "i16x8.le_u"),
+    (0x35, "i16x8.ge_s"),
+    (0x36, "i16x8.ge_u"),
+    (0x37, "i32x4.eq"),
+    (0x38, "i32x4.ne"),
+    (0x39, "i32x4.lt_s"),
+    (0x3A, "i32x4.lt_u"),
+    (0x3B, "i32x4.gt_s"),
+    (0x3C, "i32x4.gt_u"),
+    (0x3D, "i32x4.le_s"),
+    (0x3E, "i32x4.le_u"),
+    (0x3F, "i32x4.ge_s"),
+    (0x40, "i32x4.ge_u"),
+    (0x41, "f32x4.eq"),
+    (0x42, "f32x4.ne"),
+    (0x43, "f32x4.lt"),
+    (0x44, "f32x4.gt"),
+    (0x45, "f32x4.le"),
+    (0x46, "f32x4.ge"),
+    (0x47, "f64x2.eq"),
+    (0x48, "f64x2.ne"),
+    (0x49, "f64x2.lt"),
+    (0x4A, "f64x2.gt"),
+    (0x4B, "f64x2.le"),
+    (0x4C, "f64x2.ge"),
+    (0x4D, "v128.not"),
+    (0x4E, "v128.and"),
+    (0x4F, "v128.andnot"),
+    (0x50, "v128.or"),
+    (0x51, "v128.xor"),
+    (0x52, "v128.bitselect"),
+    (0x53, "v128.any_true"),
+    (0x54, "v128.load8_lane"),
+    (0x55, "v128.load16_lane"),
+    (0x56, "v128.load32_lane"),
+    (0x57, "v128.load64_lane"),
+    (0x58, "v128.store8_lane"),
+    (0x59, "v128.store16_lane"),
+    (0x5A, "v128.store32_lane"),
+    (0x5B, "v128.store64_lane"),
+    (0x5C, "v128.load32_zero"),
+    (0x5D, "v128.load64_zero"),
+    (0x5E, "f32x4.demote_f64x2_zero"),
+    (0x5F, "f64x2.promote_low_f32x4"),
+    (0x60, "i8x16.abs"),
+    (0x61, "i8x16.neg"),
+    (0x62, "i8x16.popcnt"),
+    (0x63, "i8x16.all_true"),
+    (0x64, "i8x16.bitmask"),
+    (0x65, "i8x16.narrow_i16x8_s"),
+    (0x66, "i8x16.narrow_i16x8_u"),
+    (0x67, "f32x4.ceil"),
+    (0x68, "f32x4.floor"),
+    (0x69, "f32x4.trunc"),
+    (0x6A, "f32x4.nearest"),
+    (0x6B, "i8x16.shl"),
+    (0x6C, "i8x16.shr_s"),
+    (0x6D, "i8x16.shr_u"),
+    (0x6E, "i8x16.add"),
+    (0x6F, "i8x16.add_sat_s"),
+    (0x70, "i8x16.add_sat_u"),
+    (0x71, "i8x16.sub"),
+    (0x72, "i8x16.sub_sat_s"),
+    (0x73, "i8x16.sub_sat_u"),
+    (0x74, "f64x2.ceil"),
+    (0x75, "f64x2.floor"),
+    (0x76, "i8x16.min_s"),
+    (0x77, "i8x16.min_u"),
+    (0x78, "i8x16.max_s"),
+    (0x79, "i8x16.max_u"),
+    (0x7A, "f64x2.trunc"),
+    (0x7B, "i8x16.avgr_u"),
+    (0x7C, "i16x8.extadd_pairwise_i8x16_s"),
+    (0x7D, "i16x8.extadd_pairwise_i8x16_u"),
+    (0x7E, "i32x4.extadd_pairwise_i16x8_s"),
+    (0x7F, "i32x4.extadd_pairwise_i16x8_u"),
+    (0x80, "i16x8.abs"),
+    (0x81, "i16x8.neg"),
+    (0x82, "i16x8.q15mulr_sat_s"),
+    (0x83, "i16x8.all_true"),
+    (0x84, "i16x8.bitmask"),
+    (0x85, "i16x8.narrow_i32x4_s"),
+    (0x86, "i16x8.narrow_i32x4_u"),
+    (0x87, "i16x8.extend_low_i8x16_s"),
+    (0x88, "i16x8.extend_high_i8x16_s"),
+    (0x89, "i16x8.extend_low_i8x16_u"),
+    (0x8A, "i16x8.extend_high_i8x16_u"),
+    (0x8B, "i16x8.shl"),
+    (0x8C, "i16x8.shr_s"),
+    (0x8D, "i16x8.shr_u"),
+    (0x8E, "i16x8.add"),
+    (0x8F, "i16x8.add_sat_s"),
+    (0x90, "i16x8.add_sat_u"),
+    (0x91, "i16x8.sub"),
+    (0x92, "i16x8.sub_sat_s"),
+    (0x93, "i16x8.sub_sat_u"),
+    (0x94, "f64x2.nearest"),
+    (0x95, "i16x8.mul"),
+    (0x96, "i16x8.min_s"),
+    (0x97, "i16x8.min_u"),
+    (0x98, "i16x8.max_s"),
+    (0x99, "i16x8.max_u"),
+    (0x9B, "i16x8.avgr_u"),
+    (0x9C, "i16x8.extmul_low_i8x16_s"),
+    (0x9D, "i16x8.extmul_high_i8x16_s"),
+    (0x9E, "i16x8.extmul_low_i8x16_u"),
+    (0x9F, "i16x8.extmul_high_i8x16_u"),
+    (0xA0, "i32x4.abs"),
+    (0xA1, "i32x4.neg"),
+    (0xA3, "i32x4.all_true"),
+    (0xA4, "i32x4.bitmask"),
+    (0xA7, "i32x4.extend_low_i16x8_s"),
+    (0xA8, "i32x4.extend_high_i16x8_s"),
+    (0xA9, "i32x4.extend_low_i16x8_u"),
+    (0xAA, "i32x4.extend_high_i16x8_u"),
+    (0xAB, "i32x4.shl"),
+    (0xAC, "i32x4.shr_s"),
+    (0xAD, "i32x4.shr_u"),
+    (0xAE, "i32x4.add"),
+    (0xB1, "i32x4.sub"),
+    (0xB5, "i32x4.mul"),
+    (0xB6, "i32x4.min_s"),
+    (0xB7, "i32x4.min_u"),
+    (0xB8, "i32x4.max_s"),
+    (0xB9, "i32x4.max_u"),
+    (0xBA, "i32x4.dot_i16x8_s"),
+    (0xBC, "i32x4.extmul_low_i16x8_s"),
+    (0xBD, "i32x4.extmul_high_i16x8_s"),
+    (0xBE, "i32x4.extmul_low_i16x8_u"),
+    (0xBF, "i32x4.extmul_high_i16x8_u"),
+    (0xC0, "i64x2.abs"),
+    (0xC1, "i64x2.neg"),
+    (0xC3, "i64x2.all_true"),
+    (0xC4, "i64x2.bitmask"),
+    (0xC7, "i64x2.extend_low_i32x4_s"),
+    (0xC8, "i64x2.extend_high_i32x4_s"),
+    (0xC9, "i64x2.extend_low_i32x4_u"),
+    (0xCA, "i64x2.extend_high_i32x4_u"),
+    (0xCB, "i64x2.shl"),
+    (0xCC, "i64x2.shr_s"),
+    (0xCD, "i64x2.shr_u"),
+    (0xCE, "i64x2.add"),
+    (0xD1, "i64x2.sub"),
+    (0xD5, "i64x2.mul"),
+    (0xD6, "i64x2.eq"),
+    (0xD7, "i64x2.ne"),
+    (0xD8, "i64x2.lt_s"),
+    (0xD9, "i64x2.gt_s"),
+    (0xDA, "i64x2.le_s"),
+    (0xDB, "i64x2.ge_s"),
+    (0xDC, "i64x2.extmul_low_i32x4_s"),
+    (0xDD, "i64x2.extmul_high_i32x4_s"),
+    (0xDE, "i64x2.extmul_low_i32x4_u"),
+    (0xDF, "i64x2.extmul_high_i32x4_u"),
+    (0xE0, "f32x4.abs"),
+    (0xE1, "f32x4.neg"),
+    (0xE3, "f32x4.sqrt"),
+    (0xE4, "f32x4.add"),
+    (0xE5, "f32x4.sub"),
+    (0xE6, "f32x4.mul"),
+    (0xE7, "f32x4.div"),
+    (0xE8, "f32x4.min"),
+    (0xE9, "f32x4.max"),
+    (0xEA, "f32x4.pmin"),
+    (0xEB, "f32x4.pmax"),
+    (0xEC, "f64x2.abs"),
+    (0xED, "f64x2.neg"),
+    (0xEF, "f64x2.sqrt"),
+    (0xF0, "f64x2.add"),
+    (0xF1, "f64x2.sub"),
+    (0xF2, "f64x2.mul"),
+    (0xF3, "f64x2.div"),
+    (0xF4, "f64x2.min"),
+    (0xF5, "f64x2.max"),
+    (0xF6, "f64x2.pmin"),
+    (0xF7, "f64x2.pmax"),
+    (0xF8, "i32x4.trunc_sat_f32x4_s"),
+    (0xF9, "i32x4.trunc_sat_f32x4_u"),
+    (0xFA, "f32x4.convert_i32x4_s"),
+    (0xFB, "f32x4.convert_i32x4_u"),
+    (0xFC, "i32x4.trunc_sat_f64x2_s_zero"),
+    (0xFD, "i32x4.trunc_sat_f64x2_u_zero"),
+    (0xFE, "f64x2.convert_low_i32x4_s"),
+    (0xFF, "f64x2.convert_low_i32x4_u"),
+    (0x100, "i8x16.relaxed_swizzle"),
+    (0x101, "i32x4.relaxed_trunc_f32x4_s"),
+    (0x102, "i32x4.relaxed_trunc_f32x4_u"),
+    (0x103, "i32x4.relaxed_trunc_f64x2_s_zero"),
+    (0x104, "i32x4.relaxed_trunc_f64x2_u_zero"),
+    (0x105, "f32x4.relaxed_madd"),
+    (0x106, "f32x4.relaxed_nmadd"),
+    (0x107, "f64x2.relaxed_madd"),
+    (0x108, "f64x2.relaxed_nmadd"),
+    (0x109, "i8x16.relaxed_laneselect"),
+    (0x10A, "i16x8.relaxed_laneselect"),
+    (0x10B, "i32x4.relaxed_laneselect"),
+    (0x10C, "i64x2.relaxed_laneselect"),
+    (0x10D, "f32x4.relaxed_min"),
+    (0x10E, "f32x4.relaxed_max"),
+    (0x10F, "f64x2.relaxed_min"),
+    (0x110, "f64x2.relaxed_max"),
+    (0x111, "i16x8.relaxed_q15mulr_s"),
+    (0x112, "i16x8.relaxed_dot_i8x16_i7x16_s"),
+    (0x113, "i32x4.relaxed_dot_i8x16_i7x16_add_s"),
+];
+
+// 0xFE prefix: threads
+const THREADS_NAMES: &[(u32, &str)] = &[
+    (0x00, "memory.atomic.notify"),
+    (0x01, "memory.atomic.wait32"),
+    (0x02, "memory.atomic.wait64"),
+    (0x03, "atomic.fence"),
+    (0x10, "i32.atomic.load"),
+    (0x11, "i64.atomic.load"),
+    (0x12, "i32.atomic.load8_u"),
+    (0x13, "i32.atomic.load16_u"),
+    (0x14, "i64.atomic.load8_u"),
+    (0x15, "i64.atomic.load16_u"),
+    (0x16, "i64.atomic.load32_u"),
+    (0x17, "i32.atomic.store"),
+    (0x18, "i64.atomic.store"),
+    (0x19, "i32.atomic.store8"),
+    (0x1A, "i32.atomic.store16"),
+    (0x1B, "i64.atomic.store8"),
+    (0x1C, "i64.atomic.store16"),
+    (0x1D, "i64.atomic.store32"),
+    (0x1E, "i32.atomic.rmw.add"),
+    (0x1F, "i64.atomic.rmw.add"),
+    (0x20, "i32.atomic.rmw8.add_u"),
+    (0x21, "i32.atomic.rmw16.add_u"),
+    (0x22, "i64.atomic.rmw8.add_u"),
+    (0x23, "i64.atomic.rmw16.add_u"),
+    (0x24, "i64.atomic.rmw32.add_u"),
+    (0x25, "i32.atomic.rmw.sub"),
+    (0x26, "i64.atomic.rmw.sub"),
+    (0x27, "i32.atomic.rmw8.sub_u"),
+    (0x28, "i32.atomic.rmw16.sub_u"),
+    (0x29, "i64.atomic.rmw8.sub_u"),
+    (0x2A, "i64.atomic.rmw16.sub_u"),
+    (0x2B, "i64.atomic.rmw32.sub_u"),
+    (0x2C, "i32.atomic.rmw.and"),
+    (0x2D, "i64.atomic.rmw.and"),
+    (0x2E, "i32.atomic.rmw8.and_u"),
+    (0x2F, "i32.atomic.rmw16.and_u"),
+    (0x30, "i64.atomic.rmw8.and_u"),
+    (0x31, "i64.atomic.rmw16.and_u"),
+    (0x32, "i64.atomic.rmw32.and_u"),
+    (0x33, "i32.atomic.rmw.or"),
+    (0x34, "i64.atomic.rmw.or"),
+    (0x35, "i32.atomic.rmw8.or_u"),
+    (0x36, "i32.atomic.rmw16.or_u"),
+    (0x37, "i64.atomic.rmw8.or_u"),
+    (0x38, "i64.atomic.rmw16.or_u"),
+    (0x39, "i64.atomic.rmw32.or_u"),
+    (0x3A, "i32.atomic.rmw.xor"),
+    (0x3B, "i64.atomic.rmw.xor"),
+    (0x3C, "i32.atomic.rmw8.xor_u"),
+    (0x3D, "i32.atomic.rmw16.xor_u"),
+    (0x3E, "i64.atomic.rmw8.xor_u"),
+    (0x3F, "i64.atomic.rmw16.xor_u"),
+    (0x40, "i64.atomic.rmw32.xor_u"),
+    (0x41, "i32.atomic.rmw.xchg"),
+    (0x42, "i64.atomic.rmw.xchg"),
+    (0x43, "i32.atomic.rmw8.xchg_u"),
+    (0x44, "i32.atomic.rmw16.xchg_u"),
+    (0x45, "i64.atomic.rmw8.xchg_u"),
+    (0x46, "i64.atomic.rmw16.xchg_u"),
+    (0x47, "i64.atomic.rmw32.xchg_u"),
+    (0x48, "i32.atomic.rmw.cmpxchg"),
+    (0x49, "i64.atomic.rmw.cmpxchg"),
+    (0x4A, "i32.atomic.rmw8.cmpxchg_u"),
+    (0x4B, "i32.atomic.rmw16.cmpxchg_u"),
+    (0x4C, "i64.atomic.rmw8.cmpxchg_u"),
+    (0x4D, "i64.atomic.rmw16.cmpxchg_u"),
+    (0x4E, "i64.atomic.rmw32.cmpxchg_u"),
+];
+// Opcodes are stored by their `u32` value, which unlike the variant names
+// never changes.
+#[cfg(feature = "serde")]
+impl serde::Serialize for OpCode {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(self.to_u32())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for OpCode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u32::deserialize(deserializer).map(OpCode::from_u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_byte_opcodes_round_trip() {
+        let known: Vec<OpCode> = (0x00..=0xFF).map(OpCode::from_u32).filter(|op| !matches!(op, OpCode::Unknown(_))).collect();
+        // MVP, sign extension, reference types, exception handling, tail
+        // calls, typed function references and GC
+        assert_eq!(known.len(), 194);
+        for op in known {
+            assert_eq!(OpCode::from_u32(op.to_u32()), op);
+            assert_eq!(OpCode::from_name(&op.name()), Some(op), "{}", op.name());
+        }
+        assert_eq!(OpCode::I64Load32U.name(), "i64.load32_u");
+        assert_eq!(OpCode::from_u32(0xC4), OpCode::I64Extend32S);
+        assert_eq!(OpCode::from_u32(0x1F), OpCode::TryTable);
+        assert_eq!(OpCode::from_name("return_call_indirect"), Some(OpCode::ReturnCallIndirect));
+        assert_eq!(OpCode::BrOnNonNull.name(), "br_on_non_null");
+        assert_eq!(OpCode::from_u32(0x27), OpCode::Unknown(0x27));
+    }
+
+    #[test]
+    fn test_prefixed_opcodes() {
+        let copy = OpCode::from_name("memory.copy").unwrap();
+        assert_eq!(copy, OpCode::Prefixed(Prefix::Misc, 0x0A));
+        assert_eq!(OpCode::from_u32(copy.to_u32()), copy);
+        assert_eq!(copy.to_bytes(), vec![0xFC, 0x0A]);
+
+        // SIMD sub-opcodes above 0x7F take two LEB128 bytes
+        let swizzle = OpCode::from_name("i8x16.relaxed_swizzle").unwrap();
+        assert_eq!(swizzle.to_bytes(), vec![0xFD, 0x80, 0x02]);
+        assert_eq!(OpCode::read(&[0xFD, 0x80, 0x02, 0x0B]), Some((swizzle, 3)));
+        assert_eq!(OpCode::read(&[0x6A, 0x0B]), Some((OpCode::I32Add, 1)));
+        assert_eq!(OpCode::read(&[0xFE, 0x80]), None);
+
+        assert_eq!(OpCode::from_name("struct.new"), Some(OpCode::Prefixed(Prefix::Gc, 0)));
+        assert_eq!(OpCode::from_name("i32.atomic.rmw.cmpxchg"), Some(OpCode::Prefixed(Prefix::Threads, 0x48)));
+        assert_eq!(OpCode::Prefixed(Prefix::Simd, 0x9A).name(), "unknown(0xfd 154)");
+
+        for prefix in Prefix::ALL {
+            for (sub, name) in prefix.names() {
+                assert_eq!(OpCode::from_name(name), Some(OpCode::Prefixed(prefix, *sub)), "{}", name);
+            }
+        }
+        assert_eq!(OpCode::from_name("ref.cast_null"), Some(OpCode::Prefixed(Prefix::Gc, 0x17)));
+    }
+}
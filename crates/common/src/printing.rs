@@ -0,0 +1,9 @@
+/// How instruction sequences are laid out in WebAssembly text format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InstrStyle {
+    /// One instruction per line, blocks closed with `end`.
+    #[default]
+    Flat,
+    /// S-expressions, operands nested inside the instruction consuming them.
+    Folded,
+}
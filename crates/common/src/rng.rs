@@ -0,0 +1,60 @@
+/// Small seeded PRNG (xorshift64*) so that generated programs can be
+/// reproduced from their seed alone.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // the state must never be zero
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+/// Where generators take their random decisions from: a seeded `Rng`, or
+/// the input of a fuzzer so that coverage feedback steers every choice.
+pub trait Choices {
+    /// Uniform in `0..bound`, 0 when `bound` is 0.
+    fn below(&mut self, bound: usize) -> usize;
+
+    /// Index of an entry picked with probability proportional to its weight,
+    /// `None` if all weights are zero.
+    fn weighted(&mut self, weights: &[u32]) -> Option<usize> {
+        let total: usize = weights.iter().map(|&w| w as usize).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target = self.below(total);
+        for (index, &weight) in weights.iter().enumerate() {
+            if target < weight as usize {
+                return Some(index);
+            }
+            target -= weight as usize;
+        }
+        unreachable!()
+    }
+}
+
+impl Choices for Rng {
+    fn below(&mut self, bound: usize) -> usize {
+        if bound == 0 { 0 } else { (self.next_u64() % bound as u64) as usize }
+    }
+}
+
+// Once the input runs out every choice is 0, which the generators treat as
+// the smallest option, so short inputs give small programs.
+#[cfg(feature = "arbitrary")]
+impl Choices for arbitrary::Unstructured<'_> {
+    fn below(&mut self, bound: usize) -> usize {
+        if bound <= 1 {
+            return 0;
+        }
+        self.int_in_range(0..=bound - 1).unwrap_or(0)
+    }
+}
@@ -2,12 +2,21 @@ use crate::instruction::{Instruction, ValType};
 
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Node {
+    #[cfg_attr(feature = "serde", serde(default))]
     pub id: Option<u64>,
     pub instr: Instruction,
+    // operands in stack order; for block and loop this is the body instead,
+    // the branches of an if live in its IfArgs
+    #[cfg_attr(feature = "serde", serde(default))]
     pub sub_instrs: Vec<Node>,
     pub instr_type: InstructionType,
+    #[cfg_attr(feature = "serde", serde(default))]
     pub context: Option<Context>,
+    // how many harvested snippets this one stands for, see `canonical`
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub count: Option<u32>,
 }
 
 impl Node {
@@ -18,6 +27,7 @@ impl Node {
             sub_instrs: Vec::new(),
             instr_type,
             context,
+            count: None,
         }
     }
 }
@@ -26,12 +36,14 @@ impl Node {
 
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InstructionType {
     pub params: Vec<ValType>,
     pub results: Vec<ValType>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Context {
     pub local_variable: Option<LocalVariableContext>,
     pub functype: Option<FunctionTypeContext>,
@@ -40,22 +52,26 @@ pub struct Context {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LocalVariableContext {
     pub local_variable_type: ValType,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionTypeContext {
     pub param_types: Vec<ValType>,
     pub result_types: Vec<ValType>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalVariableContext {
     pub global_variable_type: ValType,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemoryContext {
     pub max: u32,
 }
\ No newline at end of file
@@ -1,49 +1,503 @@
 use mongodb::sync::{Client, Collection};
-use mongodb::bson::{doc, Document};
-use anyhow::Result;
+use mongodb::bson::{doc, Bson, Document};
+use anyhow::{Context as _, Result, anyhow, bail};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use crate::AST::Node;
+use crate::ast_schema::{document_to_json, json_to_document};
+use crate::instruction::ValType;
 
-pub fn load_ASTs() -> Result<Vec<Vec<Document>>> {
-    let client = Client::with_uri_str("mongodb://localhost:27017/")?;
-    let db = client.database("runtime-fuzz");
+pub const DEFAULT_MONGO_URI: &str = "mongodb://localhost:27017/";
+pub const DEFAULT_DATABASE: &str = "runtime-fuzz";
+
+/// A context a snippet can depend on, see `AST::Context`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContextKind {
+    LocalVariable,
+    FuncType,
+    GlobalVariable,
+    Memory,
+}
+
+// Top-level fields a snippet is decoded from, the only ones MongoDB sends.
+pub(crate) const SNIPPET_FIELDS: &[&str] = &["_id", "id", "instr", "sub_instrs", "instr_type", "context", "count"];
+
+/// Which snippets to read and how many. An unset field does not filter.
+#[derive(Debug, Clone, Default)]
+pub struct AstQuery {
+    // binary opcode of the root instruction
+    pub opcodes: Option<Vec<u32>>,
+    pub result_types: Option<Vec<ValType>>,
+    // snippets needing any other context are skipped
+    pub allowed_contexts: Option<Vec<ContextKind>>,
+    // documents fetched per round trip, only used by MongoDB
+    pub batch_size: Option<u32>,
+    // snippets yielded at most, documents that fail to decode don't count
+    pub limit: Option<usize>,
+}
+
+impl AstQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_opcodes(mut self, opcodes: Vec<u32>) -> Self {
+        self.opcodes = Some(opcodes);
+        self
+    }
+
+    pub fn with_result_types(mut self, result_types: Vec<ValType>) -> Self {
+        self.result_types = Some(result_types);
+        self
+    }
+
+    pub fn with_allowed_contexts(mut self, contexts: Vec<ContextKind>) -> Self {
+        self.allowed_contexts = Some(contexts);
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn matches(&self, node: &Node) -> bool {
+        if let Some(opcodes) = &self.opcodes
+            && !opcodes.contains(&node.instr.opcode.to_u32())
+        {
+            return false;
+        }
+        if let Some(result_types) = &self.result_types
+            && &node.instr_type.results != result_types
+        {
+            return false;
+        }
+        match (&self.allowed_contexts, &node.context) {
+            (Some(allowed), Some(context)) => [
+                (context.local_variable.is_some(), ContextKind::LocalVariable),
+                (context.functype.is_some(), ContextKind::FuncType),
+                (context.global_variable.is_some(), ContextKind::GlobalVariable),
+                (context.memory.is_some(), ContextKind::Memory),
+            ]
+            .iter()
+            .all(|(required, kind)| !required || allowed.contains(kind)),
+            _ => true,
+        }
+    }
+
+    /// Drop the snippets that don't match and stop at the limit. Errors are
+    /// passed through so callers can report them and go on.
+    pub(crate) fn apply<'a>(&self, nodes: impl Iterator<Item = Result<Node>> + 'a) -> AstStream<'a> {
+        let query = self.clone();
+        let nodes = nodes.filter(move |node| node.as_ref().map_or(true, |node| query.matches(node)));
+        limit_nodes(nodes, self.limit)
+    }
+}
+
+// Stop after `limit` snippets; errors are passed through and don't count.
+fn limit_nodes<'a>(nodes: impl Iterator<Item = Result<Node>> + 'a, limit: Option<usize>) -> AstStream<'a> {
+    let Some(limit) = limit else {
+        return Box::new(nodes);
+    };
+    Box::new(nodes.scan(limit, |left, node| {
+        if *left == 0 {
+            return None;
+        }
+        if node.is_ok() {
+            *left -= 1;
+        }
+        Some(node)
+    }))
+}
+
+/// Snippets decoded one at a time. A document that cannot be read or decoded
+/// yields an `Err` and the stream continues with the next one.
+pub type AstStream<'a> = Box<dyn Iterator<Item = Result<Node>> + 'a>;
+
+/// Somewhere snippet documents are stored, grouped into named collections.
+pub trait AstSource {
+    fn collection_names(&self) -> Result<Vec<String>>;
+
+    fn has_collection(&self, name: &str) -> Result<bool> {
+        Ok(self.collection_names()?.iter().any(|known| known == name))
+    }
 
-    // 获取所有集合名称，需要调用 run() 方法
-    let collection_names = db.list_collection_names().run()?;
-    let mut all_docs_per_collection: Vec<Vec<Document>> = Vec::new();
+    fn load_collection(&self, name: &str) -> Result<Vec<Document>>;
 
-    for col_name in collection_names {
-        let collection: Collection<Document> = db.collection(&col_name);
+    /// Lazily read the snippets of one collection matching `query`.
+    fn stream_collection(&self, name: &str, query: &AstQuery) -> Result<AstStream<'_>>;
 
+    /// Add `docs` to collection `name`, creating it if needed.
+    fn insert_collection(&self, name: &str, docs: &[Document]) -> Result<()>;
+
+    /// Make collection `name` hold `docs` and nothing else, creating it if
+    /// needed.
+    fn replace_collection(&self, name: &str, docs: &[Document]) -> Result<()>;
+
+    /// `stream_collection` over every collection in turn, with the limit of
+    /// `query` applied to the whole stream.
+    fn stream_all(&self, query: &AstQuery) -> Result<AstStream<'_>> {
+        let per_collection = AstQuery { limit: None, ..query.clone() };
+        let streams = self
+            .collection_names()?
+            .into_iter()
+            .flat_map(move |name| match self.stream_collection(&name, &per_collection) {
+                Ok(stream) => stream,
+                Err(err) => Box::new(std::iter::once(Err(err.context(format!("collection `{}`", name))))),
+            });
+        Ok(limit_nodes(streams, query.limit))
+    }
+
+    /// Every non-empty collection, in the order of `collection_names`.
+    fn load_all(&self) -> Result<Vec<(String, Vec<Document>)>> {
+        let mut all_docs = Vec::new();
+        for name in self.collection_names()? {
+            let docs = self.load_collection(&name)?;
+            if !docs.is_empty() {
+                all_docs.push((name, docs));
+            }
+        }
+        Ok(all_docs)
+    }
+}
+
+/// Snippets stored in a MongoDB database, one collection per snippet group.
+#[derive(Debug, Clone)]
+pub struct MongoSource {
+    pub uri: String,
+    pub database: String,
+    // only these collections are read when set, all of them otherwise
+    pub collections: Option<Vec<String>>,
+}
+
+impl MongoSource {
+    pub fn new(uri: &str, database: &str) -> Self {
+        MongoSource {
+            uri: uri.to_string(),
+            database: database.to_string(),
+            collections: None,
+        }
+    }
+
+    /// Use the database named in the path of `uri`, e.g.
+    /// `mongodb://host:27017/my-corpus`, or the default one if there is none.
+    pub fn from_uri(uri: &str) -> Self {
+        let database = uri
+            .split_once("://")
+            .and_then(|(_, rest)| rest.split_once('/'))
+            .map(|(_, path)| path.split('?').next().unwrap_or(""))
+            .filter(|name| !name.is_empty())
+            .unwrap_or(DEFAULT_DATABASE);
+        Self::new(uri, database)
+    }
+
+    pub fn with_collections(mut self, collections: Vec<String>) -> Self {
+        self.collections = Some(collections);
+        self
+    }
+
+    fn database(&self) -> Result<mongodb::sync::Database> {
+        let client = Client::with_uri_str(&self.uri)?;
+        Ok(client.database(&self.database))
+    }
+}
+
+impl Default for MongoSource {
+    fn default() -> Self {
+        Self::new(DEFAULT_MONGO_URI, DEFAULT_DATABASE)
+    }
+}
+
+impl AstSource for MongoSource {
+    fn collection_names(&self) -> Result<Vec<String>> {
+        if let Some(collections) = &self.collections {
+            return Ok(collections.clone());
+        }
+        // 获取所有集合名称，需要调用 run() 方法
+        Ok(self.database()?.list_collection_names().run()?)
+    }
+
+    fn load_collection(&self, name: &str) -> Result<Vec<Document>> {
+        let collection: Collection<Document> = self.database()?.collection(name);
         // find() 方法只接受一个参数，返回 Find 结构体，需要调用 run() 执行
         let cursor = collection.find(doc! {}).run()?;
-        let docs: Vec<Document> = cursor.filter_map(|r| r.ok()).collect();
+        Ok(cursor.collect::<mongodb::error::Result<_>>()?)
+    }
+
+    // The opcode filter runs on the server, the rest of the query needs the
+    // decoded snippet since value types may be stored as names or codes.
+    fn stream_collection(&self, name: &str, query: &AstQuery) -> Result<AstStream<'_>> {
+        let collection: Collection<Document> = self.database()?.collection(name);
+        let mut filter = doc! {};
+        if let Some(opcodes) = &query.opcodes {
+            let opcodes: Vec<Bson> = opcodes.iter().map(|&op| Bson::Int64(op.into())).collect();
+            filter.insert("instr.opcode", doc! { "$in": opcodes });
+        }
+        let projection: Document = SNIPPET_FIELDS.iter().map(|field| (field.to_string(), Bson::Int32(1))).collect();
+        let mut find = collection.find(filter).projection(projection);
+        if let Some(batch_size) = query.batch_size {
+            find = find.batch_size(batch_size);
+        }
+        let cursor = find.run()?;
+        Ok(query.apply(cursor.map(|doc| Node::try_from(&doc?))))
+    }
 
-        if !docs.is_empty() {
-            all_docs_per_collection.push(docs);
+    fn insert_collection(&self, name: &str, docs: &[Document]) -> Result<()> {
+        // the server refuses an empty insert
+        if docs.is_empty() {
+            return Ok(());
         }
+        let collection: Collection<Document> = self.database()?.collection(name);
+        collection.insert_many(docs).run()?;
+        Ok(())
     }
 
-    Ok(all_docs_per_collection)
+    fn replace_collection(&self, name: &str, docs: &[Document]) -> Result<()> {
+        let collection: Collection<Document> = self.database()?.collection(name);
+        collection.delete_many(doc! {}).run()?;
+        self.insert_collection(name, docs)
+    }
 }
 
-pub fn load_ASTs_hashmap() -> Result<HashMap<String, Vec<Document>>> {
-    let client = Client::with_uri_str("mongodb://localhost:27017/")?;
-    let db = client.database("runtime-fuzz");
+/// Snippets stored as files, for working offline and in CI. Every `*.json`
+/// or `*.jsonl` file directly inside `root` is a collection named after the
+/// file stem, and so is every subdirectory, holding the documents of all its
+/// files. A `.json` file holds one document, an array of documents or an
+/// exported corpus file; a `.jsonl` file holds one document per line.
+#[derive(Debug, Clone)]
+pub struct DirSource {
+    pub root: PathBuf,
+}
 
-    let collection_names = db.list_collection_names().run()?;
-    let mut all_docs_per_collection: HashMap<String, Vec<Document>> = HashMap::new();
+impl DirSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirSource { root: root.into() }
+    }
 
-    for col_name in collection_names {
-        let collection: Collection<Document> = db.collection(&col_name);
-        let cursor = collection.find(doc! {}).run()?;
-        let docs: Vec<Document> = cursor.filter_map(|r| r.ok()).collect();
+    fn collection_path(&self, name: &str) -> Option<PathBuf> {
+        [None, Some("json"), Some("jsonl")]
+            .into_iter()
+            .map(|ext| collection_file(&self.root, name, ext))
+            .find(|path| path.exists())
+    }
+
+    fn collection_files(&self, name: &str) -> Result<Vec<PathBuf>> {
+        let Some(path) = self.collection_path(name) else {
+            bail!("no collection `{}` in {}", name, self.root.display());
+        };
+        if !path.is_dir() {
+            return Ok(vec![path]);
+        }
+        let mut files: Vec<PathBuf> = fs::read_dir(&path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        files.retain(|file| is_corpus_file(file));
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl AstSource for DirSource {
+    fn collection_names(&self) -> Result<Vec<String>> {
+        let entries = fs::read_dir(&self.root)
+            .with_context(|| format!("cannot read corpus directory {}", self.root.display()))?;
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            // names may hold dots, as in `i32.add`, so only a file loses its extension
+            let name = if path.is_dir() {
+                path.file_name()
+            } else if is_corpus_file(&path) {
+                path.file_stem()
+            } else {
+                None
+            };
+            if let Some(name) = name.and_then(|name| name.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    // also when the directory itself does not exist yet
+    fn has_collection(&self, name: &str) -> Result<bool> {
+        Ok(self.collection_path(name).is_some())
+    }
+
+    fn load_collection(&self, name: &str) -> Result<Vec<Document>> {
+        let mut docs = Vec::new();
+        for file in self.collection_files(name)? {
+            docs.extend(read_corpus_file(&file)?);
+        }
+        Ok(docs)
+    }
+
+    // `.jsonl` files are read line by line, `.json` files one at a time.
+    fn stream_collection(&self, name: &str, query: &AstQuery) -> Result<AstStream<'_>> {
+        let docs = self.collection_files(name)?.into_iter().flat_map(|file| {
+            let location = file.display().to_string();
+            corpus_file_docs(&file)
+                .map(move |doc| doc.and_then(|doc| Node::try_from(&doc)).with_context(|| location.clone()))
+        });
+        Ok(query.apply(docs))
+    }
 
-        if !docs.is_empty() {
-            all_docs_per_collection.insert(col_name.clone(), docs);
+    // Appended to `<name>.jsonl`, inside the directory if the collection is one.
+    fn insert_collection(&self, name: &str, docs: &[Document]) -> Result<()> {
+        let path = match self.collection_path(name) {
+            Some(path) if path.is_dir() => collection_file(&path, name, Some("jsonl")),
+            Some(path) if path.extension().is_some_and(|ext| ext == "json") => {
+                bail!("cannot append to {}, only .jsonl collections can grow", path.display());
+            }
+            _ => collection_file(&self.root, name, Some("jsonl")),
+        };
+        fs::create_dir_all(&self.root)?;
+        let lines = jsonl(docs)?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("cannot write {}", path.display()))?;
+        file.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+
+    // Written in the form the collection already has; the files of a
+    // directory give way to a single `<name>.jsonl`. The new contents are
+    // written next to the old ones first so that a failure loses nothing.
+    fn replace_collection(&self, name: &str, docs: &[Document]) -> Result<()> {
+        let existing = self.collection_path(name);
+        let (path, contents) = match &existing {
+            Some(path) if path.extension().is_some_and(|ext| ext == "json") => {
+                let docs: Vec<_> = docs.iter().map(document_to_json).collect();
+                (path.clone(), serde_json::to_string(&docs)?)
+            }
+            Some(path) if path.is_dir() => (collection_file(path, name, Some("jsonl")), jsonl(docs)?),
+            _ => (collection_file(&self.root, name, Some("jsonl")), jsonl(docs)?),
+        };
+        let stale = match &existing {
+            Some(dir) if dir.is_dir() => self.collection_files(name)?,
+            _ => Vec::new(),
+        };
+        fs::create_dir_all(&self.root)?;
+        let partial = path.with_file_name(format!(".{}.partial", name));
+        fs::write(&partial, contents).with_context(|| format!("cannot write {}", partial.display()))?;
+        fs::rename(&partial, &path).with_context(|| format!("cannot write {}", path.display()))?;
+        for file in stale.iter().filter(|file| **file != path) {
+            fs::remove_file(file)?;
         }
+        Ok(())
+    }
+}
+
+fn jsonl(docs: &[Document]) -> Result<String> {
+    let mut lines = String::new();
+    for doc in docs {
+        lines.push_str(&serde_json::to_string(&document_to_json(doc))?);
+        lines.push('\n');
     }
+    Ok(lines)
+}
+
+// `Path::with_extension` would replace the last dotted part of the name.
+fn collection_file(dir: &Path, name: &str, ext: Option<&str>) -> PathBuf {
+    match ext {
+        Some(ext) => dir.join(format!("{name}.{ext}")),
+        None => dir.join(name),
+    }
+}
 
-    Ok(all_docs_per_collection)
+fn is_corpus_file(path: &Path) -> bool {
+    path.is_file() && matches!(path.extension().and_then(|ext| ext.to_str()), Some("json" | "jsonl"))
+}
+
+fn read_corpus_file(path: &Path) -> Result<Vec<Document>> {
+    corpus_file_docs(path).collect()
+}
+
+type DocStream = Box<dyn Iterator<Item = Result<Document>>>;
+
+fn corpus_file_docs(path: &Path) -> DocStream {
+    let is_jsonl = path.extension().is_some_and(|ext| ext == "jsonl");
+    let docs = if is_jsonl { jsonl_docs(path) } else { json_docs(path) };
+    docs.unwrap_or_else(|err| Box::new(std::iter::once(Err(err))))
+}
+
+fn jsonl_docs(path: &Path) -> Result<DocStream> {
+    let file = fs::File::open(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let path = path.display().to_string();
+    let lines = BufReader::new(file).lines().enumerate();
+    Ok(Box::new(lines.filter_map(move |(line_no, line)| {
+        let location = || format!("{}:{}", path, line_no + 1);
+        let line = match line.with_context(location) {
+            Ok(line) if line.trim().is_empty() => return None,
+            Ok(line) => line,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(
+            serde_json::from_str(&line)
+                .map_err(anyhow::Error::from)
+                .and_then(|value| to_document(&value))
+                .with_context(|| format!("{}: invalid document", location())),
+        )
+    })))
+}
+
+fn json_docs(path: &Path) -> Result<DocStream> {
+    let text = fs::read_to_string(path).with_context(|| format!("cannot read {}", path.display()))?;
+    let value: Value = serde_json::from_str(&text)
+        .with_context(|| format!("{}: invalid JSON", path.display()))?;
+    let values = match value {
+        Value::Array(items) => items,
+        // an exported corpus file, see `corpus_file::CorpusFile`
+        Value::Object(mut map) if map.contains_key("schema_version") => {
+            match map.remove("snippets") {
+                Some(Value::Array(items)) => items,
+                _ => bail!("{}: corpus file without a `snippets` array", path.display()),
+            }
+        }
+        other => vec![other],
+    };
+    let path = path.display().to_string();
+    Ok(Box::new(values.into_iter().enumerate().map(move |(i, value)| {
+        to_document(&value).with_context(|| format!("{}: document {}", path, i))
+    })))
+}
+
+fn to_document(value: &Value) -> Result<Document> {
+    match value {
+        Value::Object(map) => Ok(json_to_document(map)),
+        _ => Err(anyhow!("not an object")),
+    }
+}
+
+/// Pick a backend from a single string: a `mongodb://` or `mongodb+srv://`
+/// URI selects MongoDB, anything else is taken as a corpus directory.
+pub fn open_source(spec: &str) -> Box<dyn AstSource> {
+    if spec.starts_with("mongodb://") || spec.starts_with("mongodb+srv://") {
+        Box::new(MongoSource::from_uri(spec))
+    } else {
+        Box::new(DirSource::new(spec))
+    }
+}
+
+pub fn load_ASTs() -> Result<Vec<Vec<Document>>> {
+    let all_docs = MongoSource::default().load_all()?;
+    Ok(all_docs.into_iter().map(|(_, docs)| docs).collect())
+}
+
+pub fn load_ASTs_hashmap() -> Result<HashMap<String, Vec<Document>>> {
+    Ok(MongoSource::default().load_all()?.into_iter().collect())
 }
 
 #[cfg(test)]
@@ -51,17 +505,109 @@ mod tests {
     use super::*;
 
     #[test]
+    fn test_load_directory_corpus() {
+        let root = std::env::temp_dir().join(format!("ast_loader_test_{}", std::process::id()));
+        fs::create_dir_all(root.join("locals")).unwrap();
+        fs::write(root.join("nops.jsonl"), "{\"id\": 1}\n\n{\"id\": 2}\n").unwrap();
+        fs::write(root.join("locals/a.json"), "[{\"id\": 3}, {\"id\": 4}]").unwrap();
+        fs::write(root.join("locals/b.json"), "{\"schema_version\": 1, \"snippets\": [{\"id\": 5}]}").unwrap();
+        fs::write(root.join("empty.jsonl"), "").unwrap();
+        fs::write(root.join("notes.txt"), "not a collection").unwrap();
+        fs::write(root.join("i32.add.jsonl"), "{\"id\": 6}\n").unwrap();
+        fs::create_dir_all(root.join("i64.add")).unwrap();
+        fs::write(root.join("i64.add/a.jsonl"), "{\"id\": 7}\n").unwrap();
+
+        let source = open_source(root.to_str().unwrap());
+        assert_eq!(source.collection_names().unwrap(), ["empty", "i32.add", "i64.add", "locals", "nops"]);
+        let collections: HashMap<_, _> = source.load_all().unwrap().into_iter().collect();
+        assert_eq!(collections.len(), 4);
+        assert_eq!(collections["i32.add"], vec![doc! { "id": 6 }]);
+        assert_eq!(collections["i64.add"], vec![doc! { "id": 7 }]);
+        assert_eq!(collections["nops"], vec![doc! { "id": 1 }, doc! { "id": 2 }]);
+        assert_eq!(collections["locals"].len(), 3);
+
+        fs::write(root.join("broken.jsonl"), "{\"id\": 1}\n{oops\n").unwrap();
+        let error = source.load_collection("broken").unwrap_err();
+        assert!(format!("{:#}", error).contains("broken.jsonl:2"));
+
+        source.insert_collection("i32.add", &[doc! { "id": 8 }]).unwrap();
+        source.insert_collection("i64.add", &[doc! { "id": 9 }]).unwrap();
+        source.insert_collection("f32.add", &[doc! { "id": 10 }]).unwrap();
+        assert_eq!(source.load_collection("i32.add").unwrap(), vec![doc! { "id": 6 }, doc! { "id": 8 }]);
+        assert_eq!(source.load_collection("i64.add").unwrap(), vec![doc! { "id": 7 }, doc! { "id": 9 }]);
+        assert!(root.join("i64.add/i64.add.jsonl").is_file() && root.join("f32.add.jsonl").is_file());
+
+        source.replace_collection("locals", &[doc! { "id": 11 }]).unwrap();
+        assert_eq!(source.load_collection("locals").unwrap(), vec![doc! { "id": 11 }]);
+        assert!(!root.join("locals/a.json").exists());
+        fs::write(root.join("pairs.json"), "[{\"id\": 1}, {\"id\": 2}]").unwrap();
+        source.replace_collection("pairs", &[doc! { "id": 12 }]).unwrap();
+        source.replace_collection("i32.add", &[]).unwrap();
+        assert_eq!(source.load_collection("pairs").unwrap(), vec![doc! { "id": 12 }]);
+        assert!(source.load_collection("i32.add").unwrap().is_empty());
+        assert!(source.has_collection("pairs").unwrap() && !source.has_collection("missing").unwrap());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_stream_with_query() {
+        let root = std::env::temp_dir().join(format!("ast_loader_stream_{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let local_get = |index: u32, ty: &str| {
+            format!(
+                "{{\"instr\": {{\"opcode\": 32, \"args\": {}}}, \"instr_type\": {{\"results\": [\"{}\"]}}, \
+                 \"context\": {{\"local_variable\": {{\"local_variable_type\": \"{}\"}}}}}}",
+                index, ty, ty
+            )
+        };
+        let global_get = "{\"instr\": {\"opcode\": 35, \"args\": 0}, \"instr_type\": {\"results\": [\"i32\"]}, \
+                          \"context\": {\"global_variable\": {\"global_variable_type\": \"i32\"}}}";
+        let lines = [
+            local_get(0, "i64"),
+            "{\"id\": 9, \"instr\": {\"opcode\": 32, \"args\": \"x\"}, \"instr_type\": {}}".to_string(),
+            global_get.to_string(),
+            local_get(1, "i32"),
+            local_get(2, "i32"),
+        ];
+        fs::write(root.join("gets.jsonl"), lines.join("\n")).unwrap();
+
+        let source = DirSource::new(&root);
+        let query = AstQuery::new()
+            .with_result_types(vec![ValType::I32])
+            .with_allowed_contexts(vec![ContextKind::LocalVariable])
+            .with_limit(1);
+        let items: Vec<Result<Node>> = source.stream_all(&query).unwrap().collect();
+        assert_eq!(items.len(), 2);
+        let error = format!("{:#}", items[0].as_ref().unwrap_err());
+        assert!(error.contains("gets.jsonl") && error.contains("document 9"), "{}", error);
+        assert_eq!(items[1].as_ref().unwrap().instr.args, crate::instruction::InstructionArgs::Simple(1));
+
+        let query = AstQuery::new().with_opcodes(vec![35]);
+        let nodes = source.stream_collection("gets", &query).unwrap().filter(Result::is_ok);
+        assert_eq!(nodes.count(), 1);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    #[ignore = "needs a MongoDB server on localhost"]
     fn test_load_all_runtime_fuzz_documents() {
         let result = load_ASTs_hashmap();
         assert!(result.is_ok(), "函数执行失败: {:?}", result.err());
-        
+
         let collections_docs = result.unwrap();
         println!("找到 {} 个集合", collections_docs.len());
-        
+
         for (col_name, docs) in &collections_docs {
             println!("集合 '{}': 包含 {} 个文档", col_name, docs.len());
         }
-        
+
         println!("测试通过：成功加载了 {} 个集合的数据", collections_docs.len());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_open_source_by_spec() {
+        let source = MongoSource::from_uri("mongodb://db:27017/corpus?retryWrites=true");
+        assert_eq!(source.database, "corpus");
+        assert_eq!(MongoSource::from_uri(DEFAULT_MONGO_URI).database, DEFAULT_DATABASE);
+    }
+}
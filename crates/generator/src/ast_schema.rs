@@ -0,0 +1,489 @@
+
+use std::fmt;
+use anyhow::Result;
+use mongodb::bson::{Bson, Document};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use common::opcodes::OpCode;
+use crate::AST::{
+    Context, FunctionTypeContext, GlobalVariableContext, InstructionType, LocalVariableContext,
+    MemoryContext, Node,
+};
+use crate::instruction::{BlockType, BrTableArgs, IfArgs, Instruction, InstructionArgs, MemArg, ValType};
+
+
+// Shape of a snippet document as stored in the corpus. Value types may be
+// written either as names ("i32") or as their binary codes (127); the
+// meaning of `instr.args` depends on the opcode and is resolved afterwards.
+// Constants are stored as the bits of their value (wrapped to a negative
+// number in BSON when above `i64::MAX`), immediates of prefixed
+// instructions that have no other shape as an array of their bytes. A
+// deduplicated snippet carries the size of its class in `count`.
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeDoc {
+    #[serde(default)]
+    pub id: Option<u64>,
+    pub instr: InstrDoc,
+    #[serde(default)]
+    pub sub_instrs: Vec<NodeDoc>,
+    pub instr_type: InstructionTypeDoc,
+    #[serde(default)]
+    pub context: Option<ContextDoc>,
+    #[serde(default)]
+    pub count: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstrDoc {
+    pub opcode: u32,
+    #[serde(default)]
+    pub args: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstructionTypeDoc {
+    #[serde(default)]
+    pub params: Vec<Value>,
+    #[serde(default)]
+    pub results: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContextDoc {
+    #[serde(default)]
+    pub local_variable: Option<LocalVariableContextDoc>,
+    #[serde(default)]
+    pub functype: Option<FunctionTypeContextDoc>,
+    #[serde(default)]
+    pub global_variable: Option<GlobalVariableContextDoc>,
+    #[serde(default)]
+    pub memory: Option<MemoryContextDoc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LocalVariableContextDoc {
+    pub local_variable_type: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FunctionTypeContextDoc {
+    #[serde(default)]
+    pub param_types: Vec<Value>,
+    #[serde(default)]
+    pub result_types: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GlobalVariableContextDoc {
+    pub global_variable_type: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemoryContextDoc {
+    pub max: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BlockTypeDoc {
+    #[serde(default)]
+    params: Vec<Value>,
+    #[serde(default)]
+    results: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct IfArgsDoc {
+    bt: BlockTypeDoc,
+    #[serde(default)]
+    instrs1: Vec<InstrDoc>,
+    #[serde(default)]
+    instrs2: Vec<InstrDoc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BrTableArgsDoc {
+    labels: Vec<u32>,
+    default_label: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MemArgDoc {
+    align: u32,
+    offset: u32,
+}
+
+/// A document that does not describe a valid AST. Names the document and the
+/// path of the offending field, e.g. `sub_instrs[1].instr.args.labels[0]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AstDocError {
+    pub doc_id: String,
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for AstDocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "." } else { &self.path };
+        write!(f, "document {}: field `{}`: {}", self.doc_id, path, self.message)
+    }
+}
+
+impl std::error::Error for AstDocError {}
+
+// (path, message) until the document id is attached at the top level
+type FieldResult<T> = std::result::Result<T, (String, String)>;
+
+fn join(path: &str, field: &str) -> String {
+    if path.is_empty() {
+        field.to_string()
+    } else if field.starts_with('[') {
+        format!("{}{}", path, field)
+    } else {
+        format!("{}.{}", path, field)
+    }
+}
+
+fn deserialize_at<T: DeserializeOwned>(value: &Value, path: &str) -> FieldResult<T> {
+    serde_path_to_error::deserialize(value.clone()).map_err(|e| {
+        let inner = e.path().to_string();
+        let full = if inner == "." { path.to_string() } else { join(path, &inner) };
+        (full, e.into_inner().to_string())
+    })
+}
+
+fn val_type_at(value: &Value, path: &str) -> FieldResult<ValType> {
+    let ty = match value {
+        Value::String(name) => match name.as_str() {
+            "i32"       => Some(ValType::I32),
+            "i64"       => Some(ValType::I64),
+            "f32"       => Some(ValType::F32),
+            "f64"       => Some(ValType::F64),
+            "v128"      => Some(ValType::V128),
+            "funcref"   => Some(ValType::FuncRef),
+            "externref" => Some(ValType::ExternRef),
+            _ => None,
+        },
+        Value::Number(code) => match code.as_u64() {
+            Some(0x7F) => Some(ValType::I32),
+            Some(0x7E) => Some(ValType::I64),
+            Some(0x7D) => Some(ValType::F32),
+            Some(0x7C) => Some(ValType::F64),
+            Some(0x7B) => Some(ValType::V128),
+            Some(0x70) => Some(ValType::FuncRef),
+            Some(0x6F) => Some(ValType::ExternRef),
+            _ => None,
+        },
+        _ => None,
+    };
+    ty.ok_or_else(|| (path.to_string(), format!("invalid value type {}", value)))
+}
+
+fn val_types_at(values: &[Value], path: &str) -> FieldResult<Vec<ValType>> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| val_type_at(value, &join(path, &format!("[{}]", i))))
+        .collect()
+}
+
+fn block_type_at(doc: &BlockTypeDoc, path: &str) -> FieldResult<BlockType> {
+    Ok(BlockType {
+        params: val_types_at(&doc.params, &join(path, "params"))?,
+        results: val_types_at(&doc.results, &join(path, "results"))?,
+    })
+}
+
+// Bits of a constant, also accepted as a negative number since BSON has no
+// unsigned 64-bit integers.
+fn const_bits_at(value: &Value, path: &str) -> FieldResult<u64> {
+    match value {
+        Value::Number(n) => n.as_u64().or_else(|| n.as_i64().map(|v| v as u64)),
+        _ => None,
+    }
+    .ok_or_else(|| (path.to_string(), format!("invalid constant bits {}", value)))
+}
+
+fn instruction_from_doc(doc: &InstrDoc, path: &str) -> FieldResult<Instruction> {
+    let opcode = OpCode::from_u32(doc.opcode);
+    let args_path = join(path, "args");
+    let args = &doc.args;
+    let args = match opcode {
+        OpCode::Block | OpCode::Loop => {
+            let bt = block_type_at(&deserialize_at(args, &args_path)?, &args_path)?;
+            if opcode == OpCode::Block { InstructionArgs::Block(bt) } else { InstructionArgs::Loop(bt) }
+        }
+        OpCode::If => {
+            let if_doc: IfArgsDoc = deserialize_at(args, &args_path)?;
+            let branch = |instrs: &[InstrDoc], field: &str| -> FieldResult<Vec<Instruction>> {
+                let branch_path = join(&args_path, field);
+                instrs
+                    .iter()
+                    .enumerate()
+                    .map(|(i, instr)| instruction_from_doc(instr, &join(&branch_path, &format!("[{}]", i))))
+                    .collect()
+            };
+            InstructionArgs::If(IfArgs {
+                bt: block_type_at(&if_doc.bt, &join(&args_path, "bt"))?,
+                instrs1: branch(&if_doc.instrs1, "instrs1")?,
+                instrs2: branch(&if_doc.instrs2, "instrs2")?,
+            })
+        }
+        OpCode::BrTable => {
+            let doc: BrTableArgsDoc = deserialize_at(args, &args_path)?;
+            InstructionArgs::BrTable(BrTableArgs { labels: doc.labels, default_label: doc.default_label })
+        }
+        OpCode::I32Const | OpCode::I64Const | OpCode::F32Const | OpCode::F64Const => {
+            InstructionArgs::Const(const_bits_at(args, &args_path)?)
+        }
+        // everything else is told apart by the shape of the arguments
+        _ => match args {
+            Value::Null => InstructionArgs::None,
+            Value::Object(_) => {
+                let doc: MemArgDoc = deserialize_at(args, &args_path)?;
+                InstructionArgs::Mem(MemArg { align: doc.align, offset: doc.offset })
+            }
+            Value::Array(_) => InstructionArgs::Raw(deserialize_at(args, &args_path)?),
+            _ => InstructionArgs::Simple(deserialize_at(args, &args_path)?),
+        },
+    };
+    Ok(Instruction::new(opcode, args))
+}
+
+fn instruction_type_from_doc(doc: &InstructionTypeDoc, path: &str) -> FieldResult<InstructionType> {
+    Ok(InstructionType {
+        params: val_types_at(&doc.params, &join(path, "params"))?,
+        results: val_types_at(&doc.results, &join(path, "results"))?,
+    })
+}
+
+fn context_from_doc(doc: &ContextDoc, path: &str) -> FieldResult<Context> {
+    let local_variable = match &doc.local_variable {
+        Some(local) => Some(LocalVariableContext {
+            local_variable_type: val_type_at(&local.local_variable_type, &join(path, "local_variable.local_variable_type"))?,
+        }),
+        None => None,
+    };
+    let functype = match &doc.functype {
+        Some(functype) => Some(FunctionTypeContext {
+            param_types: val_types_at(&functype.param_types, &join(path, "functype.param_types"))?,
+            result_types: val_types_at(&functype.result_types, &join(path, "functype.result_types"))?,
+        }),
+        None => None,
+    };
+    let global_variable = match &doc.global_variable {
+        Some(global) => Some(GlobalVariableContext {
+            global_variable_type: val_type_at(&global.global_variable_type, &join(path, "global_variable.global_variable_type"))?,
+        }),
+        None => None,
+    };
+    let memory = doc.memory.as_ref().map(|memory| MemoryContext { max: memory.max });
+    Ok(Context { local_variable, functype, global_variable, memory })
+}
+
+fn node_from_doc(doc: &NodeDoc, path: &str) -> FieldResult<Node> {
+    let instr = instruction_from_doc(&doc.instr, &join(path, "instr"))?;
+    let instr_type = instruction_type_from_doc(&doc.instr_type, &join(path, "instr_type"))?;
+    let context = match &doc.context {
+        Some(context) => Some(context_from_doc(context, &join(path, "context"))?),
+        None => None,
+    };
+    let mut node = Node::new(instr, instr_type, context);
+    node.id = doc.id;
+    node.count = doc.count;
+    for (i, sub) in doc.sub_instrs.iter().enumerate() {
+        node.sub_instrs.push(node_from_doc(sub, &join(path, &format!("sub_instrs[{}]", i)))?);
+    }
+    Ok(node)
+}
+
+fn doc_id(value: &Value) -> String {
+    match value.get("_id").or_else(|| value.get("id")) {
+        Some(Value::String(id)) => id.clone(),
+        Some(Value::Object(oid)) => match oid.get("$oid") {
+            Some(Value::String(id)) => id.clone(),
+            _ => Value::Object(oid.clone()).to_string(),
+        },
+        Some(id) => id.to_string(),
+        None => "<no id>".to_string(),
+    }
+}
+
+fn convert<D: DeserializeOwned, T>(value: &Value, build: impl FnOnce(&D) -> FieldResult<T>) -> Result<T> {
+    deserialize_at(value, "")
+        .and_then(|doc| build(&doc))
+        .map_err(|(path, message)| AstDocError { doc_id: doc_id(value), path, message }.into())
+}
+
+/// Plain JSON view of a BSON document. Integers stay integers, object ids
+/// become `{"$oid": ...}` like in extended JSON.
+pub fn bson_to_json(bson: &Bson) -> Value {
+    match bson {
+        Bson::Double(v) => serde_json::Number::from_f64(*v).map(Value::Number).unwrap_or(Value::Null),
+        Bson::String(s) => Value::String(s.clone()),
+        Bson::Array(items) => Value::Array(items.iter().map(bson_to_json).collect()),
+        Bson::Document(doc) => document_to_json(doc),
+        Bson::Boolean(b) => Value::Bool(*b),
+        Bson::Null | Bson::Undefined => Value::Null,
+        Bson::Int32(v) => Value::from(*v),
+        Bson::Int64(v) => Value::from(*v),
+        Bson::ObjectId(oid) => {
+            let mut map = Map::new();
+            map.insert("$oid".to_string(), Value::String(oid.to_hex()));
+            Value::Object(map)
+        }
+        other => Value::String(other.to_string()),
+    }
+}
+
+pub fn document_to_json(doc: &Document) -> Value {
+    Value::Object(doc.iter().map(|(k, v)| (k.clone(), bson_to_json(v))).collect())
+}
+
+/// Inverse of `bson_to_json`. Integers that fit become `Int32`, larger ones
+/// `Int64`; `{"$oid": ...}` objects turn back into object ids.
+pub fn json_to_bson(value: &Value) -> Bson {
+    match value {
+        Value::Null => Bson::Null,
+        Value::Bool(b) => Bson::Boolean(*b),
+        Value::Number(n) => match (n.as_i64(), n.as_u64(), n.as_f64()) {
+            (Some(v), _, _) => i32::try_from(v).map(Bson::Int32).unwrap_or(Bson::Int64(v)),
+            // only the bits of a constant go past `i64::MAX`; they wrap
+            // around and `instruction_from_doc` takes them back
+            (None, Some(v), _) => Bson::Int64(v as i64),
+            (None, None, Some(v)) => Bson::Double(v),
+            (None, None, None) => Bson::Null,
+        },
+        Value::String(s) => Bson::String(s.clone()),
+        Value::Array(items) => Bson::Array(items.iter().map(json_to_bson).collect()),
+        Value::Object(map) => {
+            if let (1, Some(Value::String(hex))) = (map.len(), map.get("$oid"))
+                && let Ok(oid) = mongodb::bson::oid::ObjectId::parse_str(hex)
+            {
+                return Bson::ObjectId(oid);
+            }
+            Bson::Document(json_to_document(map))
+        }
+    }
+}
+
+pub fn json_to_document(map: &Map<String, Value>) -> Document {
+    map.iter().map(|(k, v)| (k.clone(), json_to_bson(v))).collect()
+}
+
+/// Entry point used by the loader: one corpus document to one AST.
+pub fn json_to_ast_convert(json_ast: &Value) -> Result<Node> {
+    Node::try_from(json_ast)
+}
+
+impl TryFrom<&Value> for Node {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        convert(value, |doc: &NodeDoc| node_from_doc(doc, ""))
+    }
+}
+
+impl TryFrom<&Document> for Node {
+    type Error = anyhow::Error;
+
+    fn try_from(doc: &Document) -> Result<Self> {
+        Node::try_from(&document_to_json(doc))
+    }
+}
+
+impl TryFrom<&Value> for Instruction {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        convert(value, |doc: &InstrDoc| instruction_from_doc(doc, ""))
+    }
+}
+
+impl TryFrom<&Document> for Instruction {
+    type Error = anyhow::Error;
+
+    fn try_from(doc: &Document) -> Result<Self> {
+        Instruction::try_from(&document_to_json(doc))
+    }
+}
+
+// The meaning of `args` depends on the opcode, so instructions are read
+// through the same document shape as the corpus.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Instruction {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let doc = InstrDoc::deserialize(deserializer)?;
+        instruction_from_doc(&doc, "").map_err(|(path, message)| {
+            let path = if path.is_empty() { "." } else { &path };
+            serde::de::Error::custom(format!("field `{}`: {}", path, message))
+        })
+    }
+}
+
+impl TryFrom<&Value> for InstructionType {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        convert(value, |doc: &InstructionTypeDoc| instruction_type_from_doc(doc, ""))
+    }
+}
+
+impl TryFrom<&Document> for InstructionType {
+    type Error = anyhow::Error;
+
+    fn try_from(doc: &Document) -> Result<Self> {
+        InstructionType::try_from(&document_to_json(doc))
+    }
+}
+
+impl TryFrom<&Value> for Context {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        convert(value, |doc: &ContextDoc| context_from_doc(doc, ""))
+    }
+}
+
+impl TryFrom<&Document> for Context {
+    type Error = anyhow::Error;
+
+    fn try_from(doc: &Document) -> Result<Self> {
+        Context::try_from(&document_to_json(doc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+    use serde_json::json;
+
+    #[test]
+    fn test_convert_documents() {
+        let document = doc! {
+            "_id": 7_i64,
+            "instr": { "opcode": 0x21, "args": 0 },
+            "instr_type": { "params": [], "results": [] },
+            "context": { "local_variable": { "local_variable_type": "i64" } },
+            "sub_instrs": [{
+                "instr": { "opcode": 0x29, "args": { "align": 3, "offset": 8 } },
+                "instr_type": { "params": [127], "results": ["i64"] },
+            }],
+        };
+        let node = Node::try_from(&document).unwrap();
+        assert_eq!(node.instr, Instruction::new_simple(OpCode::LocalSet, 0));
+        assert_eq!(node.sub_instrs[0].instr, Instruction::new_mem(OpCode::I64Load, MemArg { align: 3, offset: 8 }));
+        assert_eq!(node.sub_instrs[0].instr_type.params, vec![ValType::I32]);
+
+        let bad = json!({
+            "_id": { "$oid": "65f0c0ffee" },
+            "instr": { "opcode": 0x0E, "args": { "labels": [0, "x"], "default_label": 0 } },
+            "instr_type": {},
+        });
+        let err = Node::try_from(&bad).unwrap_err().downcast::<AstDocError>().unwrap();
+        assert_eq!(err.doc_id, "65f0c0ffee");
+        assert_eq!(err.path, "instr.args.labels[1]");
+    }
+}
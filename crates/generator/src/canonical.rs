@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::num::FpCategory;
+use common::opcodes::OpCode;
+use crate::AST::{Context, Node};
+use crate::instruction::{BlockType, Instruction, InstructionArgs, ValType};
+
+
+/// What a constant is normalized to. Snippets that differ only in constants
+/// of the same class are considered the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConstClass {
+    Zero,
+    NegativeZero,
+    One,
+    MinusOne,
+    // the smallest and the largest value of an integer type
+    Min,
+    Max,
+    // 2 to 255 and their negations
+    SmallPositive,
+    SmallNegative,
+    // larger positive integers
+    PowerOfTwo,
+    Positive,
+    Negative,
+    // floats
+    Integral,
+    Fraction,
+    Subnormal,
+    Infinity,
+    NegativeInfinity,
+    NaN,
+}
+
+/// The class of the immediate of a `*.const` instruction, `None` for any
+/// other instruction.
+pub fn const_class(instr: &Instruction) -> Option<ConstClass> {
+    let InstructionArgs::Const(bits) = instr.args else {
+        return None;
+    };
+    Some(match instr.opcode {
+        OpCode::I32Const => int_class(bits as u32 as i32 as i64, i32::MIN as i64, i32::MAX as i64),
+        OpCode::I64Const => int_class(bits as i64, i64::MIN, i64::MAX),
+        OpCode::F32Const => {
+            let value = f32::from_bits(bits as u32);
+            float_class(value.classify(), value as f64)
+        }
+        OpCode::F64Const => {
+            let value = f64::from_bits(bits);
+            float_class(value.classify(), value)
+        }
+        _ => return None,
+    })
+}
+
+fn int_class(value: i64, min: i64, max: i64) -> ConstClass {
+    match value {
+        0 => ConstClass::Zero,
+        1 => ConstClass::One,
+        -1 => ConstClass::MinusOne,
+        _ if value == min => ConstClass::Min,
+        _ if value == max => ConstClass::Max,
+        2..=255 => ConstClass::SmallPositive,
+        -255..=-2 => ConstClass::SmallNegative,
+        _ if value > 0 && (value as u64).is_power_of_two() => ConstClass::PowerOfTwo,
+        _ if value > 0 => ConstClass::Positive,
+        _ => ConstClass::Negative,
+    }
+}
+
+fn float_class(category: FpCategory, value: f64) -> ConstClass {
+    match category {
+        FpCategory::Nan => ConstClass::NaN,
+        FpCategory::Infinite if value > 0.0 => ConstClass::Infinity,
+        FpCategory::Infinite => ConstClass::NegativeInfinity,
+        FpCategory::Zero if value.is_sign_negative() => ConstClass::NegativeZero,
+        FpCategory::Zero => ConstClass::Zero,
+        FpCategory::Subnormal => ConstClass::Subnormal,
+        FpCategory::Normal if value == 1.0 => ConstClass::One,
+        FpCategory::Normal if value == -1.0 => ConstClass::MinusOne,
+        FpCategory::Normal if value.fract() == 0.0 => ConstClass::Integral,
+        FpCategory::Normal => ConstClass::Fraction,
+    }
+}
+
+/// The representative of the class `node` belongs to: locals, globals,
+/// functions and types are renumbered in the order they are first used, the
+/// way `resolver::resolve` would, and constants are replaced by their class.
+/// Only meant for telling snippets apart, it does not compute what `node`
+/// does.
+pub fn canonicalize(node: &Node) -> Node {
+    let mut node = node.clone();
+    Canonicalizer::default().visit(&mut node);
+    node
+}
+
+/// A hash of `canonicalize(node)` that stays the same across runs, platforms
+/// and releases, so it can be stored next to the snippet.
+pub fn structural_hash(node: &Node) -> u64 {
+    fnv1a(&structural_encoding(node))
+}
+
+// The bytes `structural_hash` hashes: equal exactly for the snippets of a class.
+fn structural_encoding(node: &Node) -> Vec<u8> {
+    let mut encoder = StructuralEncoder::default();
+    encoder.node(&canonicalize(node));
+    encoder.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum IndexSpace {
+    Local,
+    Global,
+    Func,
+    Type,
+}
+
+#[derive(Default)]
+struct Canonicalizer {
+    renamed: HashMap<(IndexSpace, u32), u32>,
+    // next index of every space
+    next: HashMap<IndexSpace, u32>,
+}
+
+impl Canonicalizer {
+    fn visit(&mut self, node: &mut Node) {
+        node.id = None;
+        node.count = None;
+        // operands run first, the body of a block after the block itself
+        if !matches!(node.instr.args, InstructionArgs::Block(_) | InstructionArgs::Loop(_)) {
+            node.sub_instrs.iter_mut().for_each(|sub| self.visit(sub));
+            self.visit_instr(&mut node.instr);
+        } else {
+            self.visit_instr(&mut node.instr);
+            node.sub_instrs.iter_mut().for_each(|sub| self.visit(sub));
+        }
+    }
+
+    fn visit_instr(&mut self, instr: &mut Instruction) {
+        if let Some(class) = const_class(instr) {
+            instr.args = InstructionArgs::Const(class as u64);
+        }
+        let space = match instr.opcode {
+            OpCode::LocalGet | OpCode::LocalSet | OpCode::LocalTee => IndexSpace::Local,
+            OpCode::GlobalGet | OpCode::GlobalSet => IndexSpace::Global,
+            OpCode::Call | OpCode::RefFunc => IndexSpace::Func,
+            OpCode::CallIndirect => IndexSpace::Type,
+            _ => {
+                if let InstructionArgs::If(if_args) = &mut instr.args {
+                    if_args.instrs1.iter_mut().chain(&mut if_args.instrs2).for_each(|instr| self.visit_instr(instr));
+                }
+                return;
+            }
+        };
+        if let InstructionArgs::Simple(index) = &mut instr.args {
+            let next = self.next.entry(space).or_default();
+            *index = *self.renamed.entry((space, *index)).or_insert_with(|| {
+                *next += 1;
+                *next - 1
+            });
+        }
+    }
+}
+
+// FNV-1a over an explicit encoding of the tree; `std::hash` gives no
+// guarantee that its output stays the same between releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xCBF2_9CE4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01B3))
+}
+
+#[derive(Default)]
+struct StructuralEncoder(Vec<u8>);
+
+impl StructuralEncoder {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+
+    fn val_types(&mut self, types: &[ValType]) {
+        self.len(types.len());
+        for ty in types {
+            self.u32(*ty as u32);
+        }
+    }
+
+    fn block_type(&mut self, bt: &BlockType) {
+        self.val_types(&bt.params);
+        self.val_types(&bt.results);
+    }
+
+    fn instr(&mut self, instr: &Instruction) {
+        self.u32(instr.opcode.to_u32());
+        match &instr.args {
+            InstructionArgs::Block(bt) | InstructionArgs::Loop(bt) => {
+                self.bytes(&[0]);
+                self.block_type(bt);
+            }
+            InstructionArgs::If(if_args) => {
+                self.bytes(&[1]);
+                self.block_type(&if_args.bt);
+                for branch in [&if_args.instrs1, &if_args.instrs2] {
+                    self.len(branch.len());
+                    branch.iter().for_each(|instr| self.instr(instr));
+                }
+            }
+            InstructionArgs::Mem(memarg) => {
+                self.bytes(&[2]);
+                self.u32(memarg.align);
+                self.u32(memarg.offset);
+            }
+            InstructionArgs::BrTable(br_table) => {
+                self.bytes(&[3]);
+                self.len(br_table.labels.len());
+                br_table.labels.iter().for_each(|label| self.u32(*label));
+                self.u32(br_table.default_label);
+            }
+            InstructionArgs::Simple(index) => {
+                self.bytes(&[4]);
+                self.u32(*index);
+            }
+            InstructionArgs::Const(bits) => {
+                self.bytes(&[5]);
+                self.bytes(&bits.to_le_bytes());
+            }
+            InstructionArgs::Raw(bytes) => {
+                self.bytes(&[6]);
+                self.len(bytes.len());
+                self.bytes(bytes);
+            }
+            InstructionArgs::None => self.bytes(&[7]),
+        }
+    }
+
+    fn context(&mut self, context: &Option<Context>) {
+        let Some(context) = context else {
+            return self.bytes(&[0]);
+        };
+        self.bytes(&[1]);
+        let local = context.local_variable.as_ref().map(|local| local.local_variable_type);
+        let global = context.global_variable.as_ref().map(|global| global.global_variable_type);
+        self.val_types(local.as_slice());
+        self.val_types(global.as_slice());
+        match &context.functype {
+            Some(functype) => {
+                self.bytes(&[1]);
+                self.val_types(&functype.param_types);
+                self.val_types(&functype.result_types);
+            }
+            None => self.bytes(&[0]),
+        }
+        match &context.memory {
+            Some(memory) => {
+                self.bytes(&[1]);
+                self.u32(memory.max);
+            }
+            None => self.bytes(&[0]),
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        self.instr(&node.instr);
+        self.val_types(&node.instr_type.params);
+        self.val_types(&node.instr_type.results);
+        self.context(&node.context);
+        self.len(node.sub_instrs.len());
+        node.sub_instrs.iter().for_each(|sub| self.node(sub));
+    }
+}
+
+/// Snippets grouped by the canonical form `structural_hash` hashes, so that
+/// snippets whose hashes collide still make classes of their own. The first
+/// snippet of a class stands for it, its `count` says how many snippets the
+/// class has, which the composer uses as its weight.
+#[derive(Debug, Clone, Default)]
+pub struct SnippetClasses {
+    by_encoding: HashMap<Vec<u8>, usize>,
+    snippets: Vec<Node>,
+}
+
+impl SnippetClasses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `node`, counting it `node.count` times if that is set so that
+    /// stored classes can be merged. True if it starts a new class.
+    pub fn insert(&mut self, mut node: Node) -> bool {
+        let count = node.count.unwrap_or(1);
+        let encoding = structural_encoding(&node);
+        match self.by_encoding.get(&encoding) {
+            Some(&index) => {
+                let class = &mut self.snippets[index];
+                class.count = Some(class.count.unwrap_or(1).saturating_add(count));
+                false
+            }
+            None => {
+                node.count = Some(count);
+                self.by_encoding.insert(encoding, self.snippets.len());
+                self.snippets.push(node);
+                true
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.snippets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snippets.is_empty()
+    }
+
+    pub fn snippets(&self) -> &[Node] {
+        &self.snippets
+    }
+
+    pub fn into_snippets(self) -> Vec<Node> {
+        self.snippets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ParseEnv, parse_expr};
+
+    #[test]
+    fn test_snippet_classes() {
+        let env = ParseEnv {
+            locals: vec![ValType::I32; 4],
+            globals: vec![ValType::I32; 3],
+            ..ParseEnv::default()
+        };
+        let parse = |text: &str| parse_expr(text, &env).unwrap();
+        let same = [
+            "(i32.add (local.get 0) (local.tee 1 (i32.const 7)))",
+            "(i32.add (local.get 3) (local.tee 2 (i32.const 200)))",
+        ];
+        let different = [
+            // one local read twice instead of two
+            "(i32.add (local.get 1) (local.tee 1 (i32.const 7)))",
+            "(i32.add (local.get 0) (local.tee 1 (i32.const 256)))",
+            "(i32.add (global.get 0) (local.tee 1 (i32.const 7)))",
+        ];
+        assert_eq!(structural_hash(&parse(same[0])), structural_hash(&parse(same[1])));
+        assert_eq!(canonicalize(&parse(same[1])), canonicalize(&parse(same[0])));
+        for text in different {
+            assert_ne!(structural_hash(&parse(same[0])), structural_hash(&parse(text)), "{}", text);
+        }
+        assert_eq!(const_class(&parse("(f32.const -0)").instr), Some(ConstClass::NegativeZero));
+        assert_eq!(const_class(&parse("(f64.const nan:0x4)").instr), Some(ConstClass::NaN));
+        assert_eq!(const_class(&parse("(i64.const 0x8000000000000000)").instr), Some(ConstClass::Min));
+        // pinned so that stored hashes keep matching
+        assert_eq!(structural_hash(&parse("(i32.add (local.get 3) (i32.const 7))")), 0xec9f5b4db0f655cb);
+
+        let mut classes = SnippetClasses::new();
+        for text in same.iter().chain(&different) {
+            classes.insert(parse(text));
+        }
+        let mut stored = parse(same[1]);
+        stored.count = Some(5);
+        assert!(!classes.insert(stored));
+        assert_eq!(classes.len(), 4);
+        assert_eq!(classes.snippets()[0], Node { count: Some(7), ..parse(same[0]) });
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use anyhow::{Result, bail};
+use common::opcodes::OpCode;
+use crate::AST::{Context, InstructionType, LocalVariableContext, Node};
+use crate::instruction::{BlockType, Instruction, ValType};
+use crate::rng::{Choices, Rng};
+
+
+/// Limits for one composed function.
+#[derive(Debug, Clone)]
+pub struct ComposerConfig {
+    // how many snippets may be nested inside each other
+    pub max_depth: usize,
+    // total number of nodes in the composed tree
+    pub max_size: usize,
+    // snippets without results run before the value producing ones
+    pub max_statements: usize,
+    pub seed: u64,
+}
+
+impl Default for ComposerConfig {
+    fn default() -> Self {
+        ComposerConfig { max_depth: 4, max_size: 64, max_statements: 2, seed: 0 }
+    }
+}
+
+/// A function built by the composer. Locals `0..params.len()` are the
+/// params, the rest are the declared `locals`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Composition {
+    pub body: Node,
+    pub params: Vec<ValType>,
+    pub locals: Vec<ValType>,
+}
+
+/// Builds well-typed trees out of corpus snippets. A snippet whose params
+/// are not supplied in its own tree gets them from other snippets placed
+/// before it in a block, or from locals when the limits are reached.
+///
+/// Indices inside the snippets (locals, globals, functions, types) are kept
+/// as they were in the corpus, `resolver::resolve` makes them consistent.
+pub struct Composer {
+    snippets: Vec<Node>,
+    by_results: HashMap<Vec<ValType>, Vec<usize>>,
+    config: ComposerConfig,
+}
+
+impl Composer {
+    pub fn new(snippets: Vec<Node>, config: ComposerConfig) -> Self {
+        let mut by_results: HashMap<Vec<ValType>, Vec<usize>> = HashMap::new();
+        for (index, snippet) in snippets.iter().enumerate() {
+            by_results.entry(snippet.instr_type.results.clone()).or_default().push(index);
+        }
+        Composer { snippets, by_results, config }
+    }
+
+    pub fn snippets(&self) -> &[Node] {
+        &self.snippets
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.config.seed = seed;
+    }
+
+    /// Compose a body for a function of type `params -> results`. The same
+    /// seed always gives the same composition.
+    pub fn compose(&self, params: &[ValType], results: &[ValType]) -> Result<Composition> {
+        let mut state = State {
+            rng: Rng::new(self.config.seed),
+            params: params.to_vec(),
+            locals: Vec::new(),
+            size: 0,
+        };
+        // a leaf for every result and the block around them are kept in reserve
+        if results.len() + 1 > self.config.max_size {
+            bail!("max_size {} is too small for {} results", self.config.max_size, results.len());
+        }
+
+        let mut body = Vec::new();
+        for _ in 0..self.config.max_statements {
+            if state.rng.below(2) == 0 {
+                continue;
+            }
+            if let Some(statement) = self.pick(&[], 0, results.len() + 1, &mut state) {
+                body.push(statement);
+            }
+        }
+        for (i, ty) in results.iter().enumerate() {
+            body.push(self.produce(*ty, 0, results.len() - i, &mut state));
+        }
+
+        let body = match body.len() {
+            1 => body.pop().unwrap(),
+            _ => {
+                state.size += 1;
+                block(BlockType { params: vec![], results: results.to_vec() }, body)
+            }
+        };
+        Ok(Composition { body, params: state.params, locals: state.locals })
+    }
+
+    // A tree leaving exactly one `ty` on the stack.
+    fn produce(&self, ty: ValType, depth: usize, reserve: usize, state: &mut State) -> Node {
+        match self.pick(&[ty], depth, reserve, state) {
+            Some(node) => node,
+            None => {
+                state.size += 1;
+                state.leaf(ty)
+            }
+        }
+    }
+
+    // Pick a snippet producing `results` and wire up its params, or `None`
+    // to fall back to a leaf. `reserve` nodes are kept for what comes after.
+    fn pick(&self, results: &[ValType], depth: usize, reserve: usize, state: &mut State) -> Option<Node> {
+        if depth >= self.config.max_depth {
+            return None;
+        }
+        let budget = self.config.max_size.saturating_sub(state.size + reserve);
+        let candidates: Vec<usize> = self
+            .by_results
+            .get(results)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|&index| {
+                let snippet = &self.snippets[index];
+                let params = snippet.instr_type.params.len();
+                // the snippet, a block around it and a leaf for every param
+                node_count(snippet) + usize::from(params > 0) + params <= budget
+            })
+            .collect();
+        // leaving a value to a leaf is always one of the choices, as likely as
+        // any single snippet; among the snippets their corpus counts decide
+        let choice = state.rng.below(candidates.len() + usize::from(!results.is_empty()));
+        if choice == candidates.len() {
+            return None;
+        }
+        let weights: Vec<u32> = candidates.iter().map(|&index| self.snippets[index].count.unwrap_or(1)).collect();
+        let snippet = &self.snippets[candidates[state.rng.weighted(&weights)?]];
+        let params = &snippet.instr_type.params;
+        state.size += node_count(snippet);
+        if params.is_empty() {
+            return Some(snippet.clone());
+        }
+
+        state.size += 1;
+        let mut body = Vec::with_capacity(params.len() + 1);
+        for (i, ty) in params.iter().enumerate() {
+            body.push(self.produce(*ty, depth + 1, reserve + params.len() - i - 1, state));
+        }
+        body.push(snippet.clone());
+        Some(block(BlockType { params: vec![], results: results.to_vec() }, body))
+    }
+}
+
+struct State {
+    rng: Rng,
+    params: Vec<ValType>,
+    locals: Vec<ValType>,
+    size: usize,
+}
+
+impl State {
+    // `local.get` of a random param of type `ty`, or of a new local.
+    fn leaf(&mut self, ty: ValType) -> Node {
+        let matching: Vec<usize> = (0..self.params.len()).filter(|&i| self.params[i] == ty).collect();
+        let index = if matching.is_empty() {
+            self.locals.push(ty);
+            self.params.len() + self.locals.len() - 1
+        } else {
+            matching[self.rng.below(matching.len())]
+        };
+        let context = Context {
+            local_variable: Some(LocalVariableContext { local_variable_type: ty }),
+            functype: None,
+            global_variable: None,
+            memory: None,
+        };
+        Node::new(
+            Instruction::new_simple(OpCode::LocalGet, index as u32),
+            InstructionType { params: vec![], results: vec![ty] },
+            Some(context),
+        )
+    }
+}
+
+fn block(bt: BlockType, body: Vec<Node>) -> Node {
+    let instr_type = InstructionType { params: vec![], results: bt.results.clone() };
+    let mut node = Node::new(Instruction::new_block(OpCode::Block, bt), instr_type, None);
+    node.sub_instrs = body;
+    node
+}
+
+pub fn node_count(node: &Node) -> usize {
+    1 + node.sub_instrs.iter().map(node_count).sum::<usize>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_generator::encode_module;
+    use crate::parser::parse_snippets;
+
+    #[test]
+    fn test_compose_validates() {
+        let snippets = parse_snippets("(i32.load offset=4) (i32.load16_s) (i64.load) (drop (i64.load))").unwrap();
+        for seed in 0..32 {
+            let config = ComposerConfig { max_depth: 3, max_size: 20, max_statements: 2, seed };
+            let composer = Composer::new(snippets.clone(), config);
+            let composition = composer.compose(&[ValType::I32, ValType::F32], &[ValType::I32, ValType::I64]).unwrap();
+            assert_eq!(composition.body.instr_type.results, vec![ValType::I32, ValType::I64]);
+            assert!(node_count(&composition.body) <= 20, "seed {}", seed);
+            assert_eq!(composer.compose(&[ValType::I32, ValType::F32], &[ValType::I32, ValType::I64]).unwrap(), composition);
+
+            let wasm = encode_module(&composition.body).unwrap();
+            wasmparser::validate(&wasm).unwrap_or_else(|e| panic!("seed {}: {}", seed, e));
+        }
+    }
+}
@@ -0,0 +1,547 @@
+use std::borrow::Cow;
+use anyhow::{Result, anyhow, bail};
+use wasm_encoder::Encode;
+use common::leb128;
+use common::opcodes::OpCode;
+use crate::instruction::{BlockType, BrTableArgs, IfArgs, Instruction, InstructionArgs, MemArg, ValType};
+use crate::module_generator::TypeRegistry;
+use crate::parser::load_store_type;
+
+
+impl From<ValType> for wasm_encoder::ValType {
+    fn from(ty: ValType) -> Self {
+        match ty {
+            ValType::I32       => wasm_encoder::ValType::I32,
+            ValType::I64       => wasm_encoder::ValType::I64,
+            ValType::F32       => wasm_encoder::ValType::F32,
+            ValType::F64       => wasm_encoder::ValType::F64,
+            ValType::V128      => wasm_encoder::ValType::V128,
+            ValType::FuncRef   => wasm_encoder::ValType::FUNCREF,
+            ValType::ExternRef => wasm_encoder::ValType::EXTERNREF,
+        }
+    }
+}
+
+impl From<ValType> for wasmparser::ValType {
+    fn from(ty: ValType) -> Self {
+        match ty {
+            ValType::I32       => wasmparser::ValType::I32,
+            ValType::I64       => wasmparser::ValType::I64,
+            ValType::F32       => wasmparser::ValType::F32,
+            ValType::F64       => wasmparser::ValType::F64,
+            ValType::V128      => wasmparser::ValType::V128,
+            ValType::FuncRef   => wasmparser::ValType::FUNCREF,
+            ValType::ExternRef => wasmparser::ValType::EXTERNREF,
+        }
+    }
+}
+
+// Only the nullable `funcref` and `externref` have a counterpart, typed
+// references of the GC and function references proposals do not.
+impl TryFrom<wasmparser::ValType> for ValType {
+    type Error = anyhow::Error;
+
+    fn try_from(ty: wasmparser::ValType) -> Result<Self> {
+        Ok(match ty {
+            wasmparser::ValType::I32 => ValType::I32,
+            wasmparser::ValType::I64 => ValType::I64,
+            wasmparser::ValType::F32 => ValType::F32,
+            wasmparser::ValType::F64 => ValType::F64,
+            wasmparser::ValType::V128 => ValType::V128,
+            wasmparser::ValType::Ref(ref_type) if ref_type == wasmparser::RefType::FUNCREF => ValType::FuncRef,
+            wasmparser::ValType::Ref(ref_type) if ref_type == wasmparser::RefType::EXTERNREF => ValType::ExternRef,
+            wasmparser::ValType::Ref(ref_type) => bail!("unsupported reference type {}", ref_type),
+        })
+    }
+}
+
+impl From<&MemArg> for wasm_encoder::MemArg {
+    fn from(memarg: &MemArg) -> Self {
+        wasm_encoder::MemArg { offset: memarg.offset as u64, align: memarg.align, memory_index: 0 }
+    }
+}
+
+// `ref.null` and a typed `select` keep their type as its binary code in `Simple`.
+fn val_type_from_code(code: u32) -> Option<ValType> {
+    [ValType::I32, ValType::I64, ValType::F32, ValType::F64, ValType::V128, ValType::FuncRef, ValType::ExternRef]
+        .into_iter()
+        .find(|ty| *ty as u32 == code)
+}
+
+impl TryFrom<&wasmparser::Operator<'_>> for Instruction {
+    type Error = anyhow::Error;
+
+    /// Fails on block types given by a type index, use
+    /// `Instruction::from_operator` with the types of the module instead.
+    fn try_from(op: &wasmparser::Operator<'_>) -> Result<Self> {
+        Instruction::from_operator(op, &[])
+    }
+}
+
+impl Instruction {
+    /// Convert an operator read from a function body. `types` are the
+    /// function types of the module, needed for block types given by index.
+    ///
+    /// Instructions this crate has arguments for get them, anything else
+    /// keeps its immediates as `Raw` bytes so it encodes back unchanged.
+    /// An `if` comes without its branches, the operators following it
+    /// belong there. Fails on a block type index past the end of `types`
+    /// and on operators that have no binary form to keep.
+    pub fn from_operator(op: &wasmparser::Operator<'_>, types: &[wasmparser::FuncType]) -> Result<Instruction> {
+        use wasmparser::Operator as O;
+        // `None` for value types the AST has no name for
+        let block_type = |blockty: &wasmparser::BlockType| -> Result<Option<BlockType>> {
+            let val_types = |list: &[wasmparser::ValType]| -> Option<Vec<ValType>> {
+                list.iter().map(|ty| ValType::try_from(*ty).ok()).collect()
+            };
+            Ok(match blockty {
+                wasmparser::BlockType::Empty => Some(BlockType { params: vec![], results: vec![] }),
+                wasmparser::BlockType::Type(ty) => {
+                    ValType::try_from(*ty).ok().map(|ty| BlockType { params: vec![], results: vec![ty] })
+                }
+                wasmparser::BlockType::FuncType(index) => {
+                    let functype = types
+                        .get(*index as usize)
+                        .ok_or_else(|| anyhow!("block type {} out of {} types", index, types.len()))?;
+                    val_types(functype.params())
+                        .zip(val_types(functype.results()))
+                        .map(|(params, results)| BlockType { params, results })
+                }
+            })
+        };
+        let simple = |opcode: OpCode, index: u32| Some(Instruction::new_simple(opcode, index));
+        let none = |opcode: OpCode| Some(Instruction::new_none(opcode));
+
+        let instr = match op {
+            O::Block { blockty } => block_type(blockty)?.map(|bt| Instruction::new_block(OpCode::Block, bt)),
+            O::Loop { blockty } => block_type(blockty)?.map(|bt| Instruction::new_loop(OpCode::Loop, bt)),
+            O::If { blockty } => block_type(blockty)?
+                .map(|bt| Instruction::new_if(OpCode::If, IfArgs { bt, instrs1: vec![], instrs2: vec![] })),
+            O::Else => none(OpCode::Else_),
+            O::End => none(OpCode::End_),
+            O::Br { relative_depth } => simple(OpCode::Br, *relative_depth),
+            O::BrIf { relative_depth } => simple(OpCode::BrIf, *relative_depth),
+            O::BrTable { targets } => targets.targets().collect::<Result<Vec<u32>, _>>().ok().map(|labels| {
+                Instruction::new_br_table(OpCode::BrTable, BrTableArgs { labels, default_label: targets.default() })
+            }),
+            O::Call { function_index } => simple(OpCode::Call, *function_index),
+            O::CallIndirect { type_index, table_index: 0 } => simple(OpCode::CallIndirect, *type_index),
+            O::ReturnCall { function_index } => simple(OpCode::ReturnCall, *function_index),
+            O::ReturnCallIndirect { type_index, table_index: 0 } => simple(OpCode::ReturnCallIndirect, *type_index),
+            O::CallRef { type_index } => simple(OpCode::CallRef, *type_index),
+            O::ReturnCallRef { type_index } => simple(OpCode::ReturnCallRef, *type_index),
+            O::Throw { tag_index } => simple(OpCode::Throw, *tag_index),
+            O::BrOnNull { relative_depth } => simple(OpCode::BrOnNull, *relative_depth),
+            O::BrOnNonNull { relative_depth } => simple(OpCode::BrOnNonNull, *relative_depth),
+            O::TypedSelect { ty } => ValType::try_from(*ty).ok().and_then(|ty| simple(OpCode::SelectT, ty as u32)),
+            O::LocalGet { local_index } => simple(OpCode::LocalGet, *local_index),
+            O::LocalSet { local_index } => simple(OpCode::LocalSet, *local_index),
+            O::LocalTee { local_index } => simple(OpCode::LocalTee, *local_index),
+            O::GlobalGet { global_index } => simple(OpCode::GlobalGet, *global_index),
+            O::GlobalSet { global_index } => simple(OpCode::GlobalSet, *global_index),
+            O::TableGet { table: 0 } => simple(OpCode::TableGet, 0),
+            O::TableSet { table: 0 } => simple(OpCode::TableSet, 0),
+            O::MemorySize { mem: 0 } => none(OpCode::MemorySize),
+            O::MemoryGrow { mem: 0 } => none(OpCode::MemoryGrow),
+            O::I32Const { value } => Some(Instruction::new_const(OpCode::I32Const, *value as u32 as u64)),
+            O::I64Const { value } => Some(Instruction::new_const(OpCode::I64Const, *value as u64)),
+            O::F32Const { value } => Some(Instruction::new_const(OpCode::F32Const, value.bits() as u64)),
+            O::F64Const { value } => Some(Instruction::new_const(OpCode::F64Const, value.bits())),
+            O::RefNull { hty: wasmparser::HeapType::Abstract { shared: false, ty } } => match ty {
+                wasmparser::AbstractHeapType::Func => simple(OpCode::RefNull, ValType::FuncRef as u32),
+                wasmparser::AbstractHeapType::Extern => simple(OpCode::RefNull, ValType::ExternRef as u32),
+                _ => None,
+            },
+            O::RefFunc { function_index } => simple(OpCode::RefFunc, *function_index),
+            _ => None,
+        };
+        match instr {
+            Some(instr) => Ok(instr),
+            None => from_encoding(op),
+        }
+    }
+
+    /// Convert to the `wasm_encoder` form, interning the types of multi-value
+    /// blocks in `types`. Prefixed instructions and immediates kept as `Raw`
+    /// bytes have no such form, `to_bytes` writes those.
+    pub fn to_encoder(&self, types: &mut TypeRegistry) -> Result<wasm_encoder::Instruction<'static>> {
+        let instr = self;
+        use wasm_encoder::Instruction as I;
+        let simple = || match instr.args {
+            InstructionArgs::Simple(arg) => Ok(arg),
+            _ => Err(anyhow!("{} needs an index argument, found {:?}", instr.opcode.name(), instr.args)),
+        };
+        let mem = || match &instr.args {
+            InstructionArgs::Mem(memarg) => Ok(wasm_encoder::MemArg::from(memarg)),
+            _ => Err(anyhow!("{} needs a memory argument, found {:?}", instr.opcode.name(), instr.args)),
+        };
+        let bits = || match instr.args {
+            InstructionArgs::Const(bits) => Ok(bits),
+            _ => Err(anyhow!("{} needs a constant, found {:?}", instr.opcode.name(), instr.args)),
+        };
+        let encoded = match instr.opcode {
+            OpCode::Unreachable => I::Unreachable,
+            OpCode::Nop         => I::Nop,
+            OpCode::Block | OpCode::Loop | OpCode::If => {
+                let bt = match &instr.args {
+                    InstructionArgs::Block(bt) | InstructionArgs::Loop(bt) => bt,
+                    InstructionArgs::If(IfArgs { bt, .. }) => bt,
+                    _ => bail!("{} needs a block type, found {:?}", instr.opcode.name(), instr.args),
+                };
+                let bt = types.block_type(bt);
+                match instr.opcode {
+                    OpCode::Block => I::Block(bt),
+                    OpCode::Loop  => I::Loop(bt),
+                    _             => I::If(bt),
+                }
+            }
+            OpCode::Else_       => I::Else,
+            OpCode::Throw       => I::Throw(simple()?),
+            OpCode::ThrowRef    => I::ThrowRef,
+            OpCode::End_        => I::End,
+            OpCode::Br          => I::Br(simple()?),
+            OpCode::BrIf        => I::BrIf(simple()?),
+            OpCode::BrTable     => match &instr.args {
+                InstructionArgs::BrTable(br_table) => {
+                    I::BrTable(Cow::Owned(br_table.labels.clone()), br_table.default_label)
+                }
+                _ => bail!("br_table needs labels, found {:?}", instr.args),
+            },
+            OpCode::Return      => I::Return,
+            OpCode::Call        => I::Call(simple()?),
+            OpCode::CallIndirect => I::CallIndirect { type_index: simple()?, table_index: 0 },
+            OpCode::ReturnCall  => I::ReturnCall(simple()?),
+            OpCode::ReturnCallIndirect => I::ReturnCallIndirect { type_index: simple()?, table_index: 0 },
+            OpCode::CallRef     => I::CallRef(simple()?),
+            OpCode::ReturnCallRef => I::ReturnCallRef(simple()?),
+            OpCode::Drop        => I::Drop,
+            OpCode::Select      => I::Select,
+            OpCode::SelectT     => {
+                let ty = val_type_from_code(simple()?).ok_or_else(|| anyhow!("select_t needs a value type, found {:?}", instr.args))?;
+                I::TypedSelect(ty.into())
+            }
+            OpCode::TryTable    => bail!("try_table keeps its catch clauses as raw immediates, use to_bytes"),
+            OpCode::LocalGet   => I::LocalGet(simple()?),
+            OpCode::LocalSet   => I::LocalSet(simple()?),
+            OpCode::LocalTee   => I::LocalTee(simple()?),
+            OpCode::GlobalGet  => I::GlobalGet(simple()?),
+            OpCode::GlobalSet  => I::GlobalSet(simple()?),
+            OpCode::TableGet   => I::TableGet(0),
+            OpCode::TableSet   => I::TableSet(0),
+            OpCode::I32Load    => I::I32Load(mem()?),
+            OpCode::I64Load    => I::I64Load(mem()?),
+            OpCode::F32Load    => I::F32Load(mem()?),
+            OpCode::F64Load    => I::F64Load(mem()?),
+            OpCode::I32Load8S  => I::I32Load8S(mem()?),
+            OpCode::I32Load8U  => I::I32Load8U(mem()?),
+            OpCode::I32Load16S => I::I32Load16S(mem()?),
+            OpCode::I32Load16U => I::I32Load16U(mem()?),
+            OpCode::I64Load8S  => I::I64Load8S(mem()?),
+            OpCode::I64Load8U  => I::I64Load8U(mem()?),
+            OpCode::I64Load16S => I::I64Load16S(mem()?),
+            OpCode::I64Load16U => I::I64Load16U(mem()?),
+            OpCode::I64Load32S => I::I64Load32S(mem()?),
+            OpCode::I64Load32U => I::I64Load32U(mem()?),
+            OpCode::I32Store   => I::I32Store(mem()?),
+            OpCode::I64Store   => I::I64Store(mem()?),
+            OpCode::F32Store   => I::F32Store(mem()?),
+            OpCode::F64Store   => I::F64Store(mem()?),
+            OpCode::I32Store8  => I::I32Store8(mem()?),
+            OpCode::I32Store16 => I::I32Store16(mem()?),
+            OpCode::I64Store8  => I::I64Store8(mem()?),
+            OpCode::I64Store16 => I::I64Store16(mem()?),
+            OpCode::I64Store32 => I::I64Store32(mem()?),
+            OpCode::MemorySize => I::MemorySize(0),
+            OpCode::MemoryGrow => I::MemoryGrow(0),
+            OpCode::I32Const   => I::I32Const(bits()? as u32 as i32),
+            OpCode::I64Const   => I::I64Const(bits()? as i64),
+            OpCode::F32Const   => I::F32Const(wasm_encoder::Ieee32::new(bits()? as u32)),
+            OpCode::F64Const   => I::F64Const(wasm_encoder::Ieee64::new(bits()?)),
+            OpCode::I32Eqz            => I::I32Eqz,
+            OpCode::I32Eq             => I::I32Eq,
+            OpCode::I32Ne             => I::I32Ne,
+            OpCode::I32LtS            => I::I32LtS,
+            OpCode::I32LtU            => I::I32LtU,
+            OpCode::I32GtS            => I::I32GtS,
+            OpCode::I32GtU            => I::I32GtU,
+            OpCode::I32LeS            => I::I32LeS,
+            OpCode::I32LeU            => I::I32LeU,
+            OpCode::I32GeS            => I::I32GeS,
+            OpCode::I32GeU            => I::I32GeU,
+            OpCode::I64Eqz            => I::I64Eqz,
+            OpCode::I64Eq             => I::I64Eq,
+            OpCode::I64Ne             => I::I64Ne,
+            OpCode::I64LtS            => I::I64LtS,
+            OpCode::I64LtU            => I::I64LtU,
+            OpCode::I64GtS            => I::I64GtS,
+            OpCode::I64GtU            => I::I64GtU,
+            OpCode::I64LeS            => I::I64LeS,
+            OpCode::I64LeU            => I::I64LeU,
+            OpCode::I64GeS            => I::I64GeS,
+            OpCode::I64GeU            => I::I64GeU,
+            OpCode::F32Eq             => I::F32Eq,
+            OpCode::F32Ne             => I::F32Ne,
+            OpCode::F32Lt             => I::F32Lt,
+            OpCode::F32Gt             => I::F32Gt,
+            OpCode::F32Le             => I::F32Le,
+            OpCode::F32Ge             => I::F32Ge,
+            OpCode::F64Eq             => I::F64Eq,
+            OpCode::F64Ne             => I::F64Ne,
+            OpCode::F64Lt             => I::F64Lt,
+            OpCode::F64Gt             => I::F64Gt,
+            OpCode::F64Le             => I::F64Le,
+            OpCode::F64Ge             => I::F64Ge,
+            OpCode::I32Clz            => I::I32Clz,
+            OpCode::I32Ctz            => I::I32Ctz,
+            OpCode::I32Popcnt         => I::I32Popcnt,
+            OpCode::I32Add            => I::I32Add,
+            OpCode::I32Sub            => I::I32Sub,
+            OpCode::I32Mul            => I::I32Mul,
+            OpCode::I32DivS           => I::I32DivS,
+            OpCode::I32DivU           => I::I32DivU,
+            OpCode::I32RemS           => I::I32RemS,
+            OpCode::I32RemU           => I::I32RemU,
+            OpCode::I32And            => I::I32And,
+            OpCode::I32Or             => I::I32Or,
+            OpCode::I32Xor            => I::I32Xor,
+            OpCode::I32Shl            => I::I32Shl,
+            OpCode::I32ShrS           => I::I32ShrS,
+            OpCode::I32ShrU           => I::I32ShrU,
+            OpCode::I32Rotl           => I::I32Rotl,
+            OpCode::I32Rotr           => I::I32Rotr,
+            OpCode::I64Clz            => I::I64Clz,
+            OpCode::I64Ctz            => I::I64Ctz,
+            OpCode::I64Popcnt         => I::I64Popcnt,
+            OpCode::I64Add            => I::I64Add,
+            OpCode::I64Sub            => I::I64Sub,
+            OpCode::I64Mul            => I::I64Mul,
+            OpCode::I64DivS           => I::I64DivS,
+            OpCode::I64DivU           => I::I64DivU,
+            OpCode::I64RemS           => I::I64RemS,
+            OpCode::I64RemU           => I::I64RemU,
+            OpCode::I64And            => I::I64And,
+            OpCode::I64Or             => I::I64Or,
+            OpCode::I64Xor            => I::I64Xor,
+            OpCode::I64Shl            => I::I64Shl,
+            OpCode::I64ShrS           => I::I64ShrS,
+            OpCode::I64ShrU           => I::I64ShrU,
+            OpCode::I64Rotl           => I::I64Rotl,
+            OpCode::I64Rotr           => I::I64Rotr,
+            OpCode::F32Abs            => I::F32Abs,
+            OpCode::F32Neg            => I::F32Neg,
+            OpCode::F32Ceil           => I::F32Ceil,
+            OpCode::F32Floor          => I::F32Floor,
+            OpCode::F32Trunc          => I::F32Trunc,
+            OpCode::F32Nearest        => I::F32Nearest,
+            OpCode::F32Sqrt           => I::F32Sqrt,
+            OpCode::F32Add            => I::F32Add,
+            OpCode::F32Sub            => I::F32Sub,
+            OpCode::F32Mul            => I::F32Mul,
+            OpCode::F32Div            => I::F32Div,
+            OpCode::F32Min            => I::F32Min,
+            OpCode::F32Max            => I::F32Max,
+            OpCode::F32Copysign       => I::F32Copysign,
+            OpCode::F64Abs            => I::F64Abs,
+            OpCode::F64Neg            => I::F64Neg,
+            OpCode::F64Ceil           => I::F64Ceil,
+            OpCode::F64Floor          => I::F64Floor,
+            OpCode::F64Trunc          => I::F64Trunc,
+            OpCode::F64Nearest        => I::F64Nearest,
+            OpCode::F64Sqrt           => I::F64Sqrt,
+            OpCode::F64Add            => I::F64Add,
+            OpCode::F64Sub            => I::F64Sub,
+            OpCode::F64Mul            => I::F64Mul,
+            OpCode::F64Div            => I::F64Div,
+            OpCode::F64Min            => I::F64Min,
+            OpCode::F64Max            => I::F64Max,
+            OpCode::F64Copysign       => I::F64Copysign,
+            OpCode::I32WrapI64        => I::I32WrapI64,
+            OpCode::I32TruncF32S      => I::I32TruncF32S,
+            OpCode::I32TruncF32U      => I::I32TruncF32U,
+            OpCode::I32TruncF64S      => I::I32TruncF64S,
+            OpCode::I32TruncF64U      => I::I32TruncF64U,
+            OpCode::I64ExtendI32S     => I::I64ExtendI32S,
+            OpCode::I64ExtendI32U     => I::I64ExtendI32U,
+            OpCode::I64TruncF32S      => I::I64TruncF32S,
+            OpCode::I64TruncF32U      => I::I64TruncF32U,
+            OpCode::I64TruncF64S      => I::I64TruncF64S,
+            OpCode::I64TruncF64U      => I::I64TruncF64U,
+            OpCode::F32ConvertI32S    => I::F32ConvertI32S,
+            OpCode::F32ConvertI32U    => I::F32ConvertI32U,
+            OpCode::F32ConvertI64S    => I::F32ConvertI64S,
+            OpCode::F32ConvertI64U    => I::F32ConvertI64U,
+            OpCode::F32DemoteF64      => I::F32DemoteF64,
+            OpCode::F64ConvertI32S    => I::F64ConvertI32S,
+            OpCode::F64ConvertI32U    => I::F64ConvertI32U,
+            OpCode::F64ConvertI64S    => I::F64ConvertI64S,
+            OpCode::F64ConvertI64U    => I::F64ConvertI64U,
+            OpCode::F64PromoteF32     => I::F64PromoteF32,
+            OpCode::I32ReinterpretF32 => I::I32ReinterpretF32,
+            OpCode::I64ReinterpretF64 => I::I64ReinterpretF64,
+            OpCode::F32ReinterpretI32 => I::F32ReinterpretI32,
+            OpCode::F64ReinterpretI64 => I::F64ReinterpretI64,
+            OpCode::I32Extend8S       => I::I32Extend8S,
+            OpCode::I32Extend16S      => I::I32Extend16S,
+            OpCode::I64Extend8S       => I::I64Extend8S,
+            OpCode::I64Extend16S      => I::I64Extend16S,
+            OpCode::I64Extend32S      => I::I64Extend32S,
+            OpCode::RefNull    => match instr.args {
+                InstructionArgs::Simple(code) if code == ValType::ExternRef as u32 => I::RefNull(wasm_encoder::HeapType::EXTERN),
+                _ => I::RefNull(wasm_encoder::HeapType::FUNC),
+            },
+            OpCode::RefIsNull  => I::RefIsNull,
+            OpCode::RefFunc    => I::RefFunc(simple()?),
+            OpCode::RefEq      => I::RefEq,
+            OpCode::RefAsNonNull => I::RefAsNonNull,
+            OpCode::BrOnNull   => I::BrOnNull(simple()?),
+            OpCode::BrOnNonNull => I::BrOnNonNull(simple()?),
+            OpCode::Prefixed(..) => bail!("{} has no wasm_encoder form, use to_bytes", instr.opcode.name()),
+            OpCode::Unknown(value) => bail!("cannot encode unknown opcode 0x{:02x}", value),
+        };
+        Ok(encoded)
+    }
+
+    /// The binary form of the instruction itself, for an `if` without its
+    /// branches. Prefixed instructions and `Raw` immediates are written as
+    /// the opcode bytes followed by their immediates.
+    pub fn to_bytes(&self, types: &mut TypeRegistry) -> Result<Vec<u8>> {
+        if !matches!(self.opcode, OpCode::Prefixed(..)) && !matches!(self.args, InstructionArgs::Raw(_)) {
+            let mut bytes = Vec::new();
+            self.to_encoder(types)?.encode(&mut bytes);
+            return Ok(bytes);
+        }
+        let mut bytes = self.opcode.to_bytes();
+        match &self.args {
+            InstructionArgs::None => {}
+            InstructionArgs::Simple(index) => leb128::write_u32(*index, &mut bytes),
+            InstructionArgs::Mem(memarg) => {
+                leb128::write_u32(memarg.align, &mut bytes);
+                leb128::write_u32(memarg.offset, &mut bytes);
+            }
+            InstructionArgs::Raw(immediates) => bytes.extend(immediates),
+            args => bail!("{} cannot take {:?}", self.opcode.name(), args),
+        }
+        Ok(bytes)
+    }
+}
+
+impl TryFrom<&Instruction> for wasm_encoder::Instruction<'static> {
+    type Error = anyhow::Error;
+
+    /// Fails where `to_encoder` does, and for blocks whose type would need
+    /// an entry in the type section.
+    fn try_from(instr: &Instruction) -> Result<Self> {
+        let mut types = TypeRegistry::default();
+        let encoded = instr.to_encoder(&mut types)?;
+        if !types.section().is_empty() {
+            bail!("{} needs a function type for its block type", instr.opcode.name());
+        }
+        Ok(encoded)
+    }
+}
+
+// Operators without explicit arguments go through their binary form: the
+// opcode is read back and the remaining bytes are its immediates.
+fn from_encoding(op: &wasmparser::Operator<'_>) -> Result<Instruction> {
+    let mut bytes = Vec::new();
+    // only references into canonicalized rec groups fail, which a parsed
+    // function body does not contain
+    wasm_encoder::Instruction::try_from(op.clone())
+        .map_err(|_| anyhow!("{:?} has no binary form", op))?
+        .encode(&mut bytes);
+    let Some((opcode, len)) = OpCode::read(&bytes) else {
+        bail!("{:?} encodes to an unknown opcode {:02x?}", op, bytes);
+    };
+    let immediates = &bytes[len..];
+    let args = if immediates.is_empty() {
+        InstructionArgs::None
+    } else if let Some(memarg) = load_store_type(opcode).and_then(|_| read_mem_arg(immediates)) {
+        InstructionArgs::Mem(memarg)
+    } else {
+        InstructionArgs::Raw(immediates.to_vec())
+    };
+    Ok(Instruction::new(opcode, args))
+}
+
+// A memory argument `MemArg` can hold: memory 0 and an offset that fits a `u32`.
+fn read_mem_arg(bytes: &[u8]) -> Option<MemArg> {
+    let (align, align_len) = leb128::read_u32(bytes)?;
+    // bit 6 of the alignment announces an explicit memory index
+    if align & 0x40 != 0 {
+        return None;
+    }
+    let (offset, offset_len) = leb128::read_u32(&bytes[align_len..])?;
+    (align_len + offset_len == bytes.len()).then_some(MemArg { align, offset })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operators_round_trip() {
+        let wat = r#"(module
+            (type (func (param i32) (result i32 i64)))
+            (memory 1) (memory 1)
+            (table 2 funcref)
+            (func $f (param i32) (result i32)
+                (local.get 0) (block (type 0) (i64.const -5))
+                (drop) (drop)
+                (if (result i32) (i32.load8_u offset=3 (i32.const 0))
+                    (then (select (result f32) (f32.const nan:0x1) (f32.const -0) (i32.const 1)) (drop) (i32.const 7))
+                    (else (i32.load 1 (i32.const 4))))
+                (br_table 0 0 (i32.const 0))
+                (ref.func $f) (drop)
+                (i32x4.extract_lane 2 (i32x4.splat (i32.trunc_sat_f64_u (f64.const 1.5))))
+                (memory.size 1) (i32.add)
+                (call_indirect (type 0) (local.get 0) (i32.const 0)) (drop) (drop)
+                (block $l (try_table (catch $e 0) (catch_all $l) (throw $e (i32.const 1)))) (drop)
+                (return_call $f (local.get 0)))
+            (tag $e (param i32))
+            (elem declare func $f))"#;
+        let binary = wat::parse_str(wat).unwrap();
+        let mut types = Vec::new();
+        let mut original = Vec::new();
+        let mut instrs = Vec::new();
+        for payload in wasmparser::Parser::new(0).parse_all(&binary) {
+            match payload.unwrap() {
+                wasmparser::Payload::TypeSection(reader) => {
+                    for ty in reader.into_iter_err_on_gc_types() {
+                        types.push(ty.unwrap());
+                    }
+                }
+                wasmparser::Payload::CodeSectionEntry(body) => {
+                    let mut reader = body.get_operators_reader().unwrap();
+                    while !reader.eof() {
+                        let (op, offset) = reader.read_with_offset().unwrap();
+                        original.push((offset, reader.original_position()));
+                        instrs.push(Instruction::from_operator(&op, &types).unwrap());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let find = |name: &str| instrs.iter().find(|i| i.opcode.name() == name).unwrap();
+        assert_eq!(find("block").args, InstructionArgs::Block(BlockType { params: vec![ValType::I32], results: vec![ValType::I32, ValType::I64] }));
+        assert_eq!(find("i64.const").args, InstructionArgs::Const(-5i64 as u64));
+        assert_eq!(find("i32.load8_u").args, InstructionArgs::Mem(MemArg { align: 0, offset: 3 }));
+        assert!(matches!(find("i32.load").args, InstructionArgs::Raw(_)));
+        assert!(matches!(find("memory.size").args, InstructionArgs::Raw(_)));
+        assert_eq!(find("select_t").args, InstructionArgs::Simple(ValType::F32 as u32));
+        assert_eq!(find("i32x4.extract_lane").args, InstructionArgs::Raw(vec![2]));
+        assert_eq!(find("try_table").args, InstructionArgs::Raw(vec![0x40, 2, 0, 0, 0, 2, 0]));
+        assert_eq!(find("return_call").args, InstructionArgs::Simple(0));
+
+        let mut registry = TypeRegistry::default();
+        registry.pin(0, &[ValType::I32], &[ValType::I32, ValType::I64]).unwrap();
+        for (instr, (start, end)) in instrs.iter().zip(original) {
+            assert_eq!(instr.to_bytes(&mut registry).unwrap(), binary[start..end], "{}", instr.opcode.name());
+        }
+        assert!(wasm_encoder::Instruction::try_from(find("block")).is_err());
+        assert!(matches!(wasm_encoder::Instruction::try_from(find("br_table")), Ok(wasm_encoder::Instruction::BrTable(..))));
+
+        let block = wasmparser::Operator::Block { blockty: wasmparser::BlockType::FuncType(types.len() as u32) };
+        assert!(Instruction::from_operator(&block, &types).is_err());
+    }
+}
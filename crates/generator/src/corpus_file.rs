@@ -0,0 +1,120 @@
+use anyhow::{Result, bail};
+use mongodb::bson::Document;
+use serde::{Deserialize, Serialize};
+use crate::AST::Node;
+use crate::ast_schema::{document_to_json, json_to_document};
+
+
+/// Version of the exported snippet schema. Bump it whenever the serialized
+/// shape of `Node` or anything below it changes in a way older readers would
+/// misread; readers refuse files written with a newer version.
+///
+/// 2: constant bits above `i64::MAX` are stored in BSON as negative numbers.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// A snippet corpus as written to disk. The same shape is used for JSON, BSON
+/// and CBOR, and each snippet matches the documents stored in the database:
+/// opcodes are their binary values, value types their text names.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorpusFile {
+    pub schema_version: u32,
+    pub snippets: Vec<Node>,
+}
+
+impl CorpusFile {
+    pub fn new(snippets: Vec<Node>) -> Self {
+        CorpusFile { schema_version: SCHEMA_VERSION, snippets }
+    }
+
+    /// Pretty-printed with one field per line so that corpora diff well in git.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(text: &str) -> Result<Self> {
+        let mut de = serde_json::Deserializer::from_str(text);
+        check_version(serde_path_to_error::deserialize(&mut de)?)
+    }
+
+    pub fn to_cbor(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(self, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        check_version(ciborium::from_reader(bytes)?)
+    }
+
+    pub fn to_bson(&self) -> Result<Document> {
+        match serde_json::to_value(self)? {
+            serde_json::Value::Object(map) => Ok(json_to_document(&map)),
+            _ => unreachable!("a struct always serializes to an object"),
+        }
+    }
+
+    pub fn from_bson(doc: &Document) -> Result<Self> {
+        let value = document_to_json(doc);
+        check_version(serde_path_to_error::deserialize(&value)?)
+    }
+}
+
+fn check_version(file: CorpusFile) -> Result<CorpusFile> {
+    if file.schema_version > SCHEMA_VERSION {
+        bail!(
+            "corpus file has schema version {}, this build reads up to {}",
+            file.schema_version,
+            SCHEMA_VERSION
+        );
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::opcodes::OpCode;
+    use crate::AST::{Context, InstructionType, MemoryContext};
+    use crate::instruction::{BlockType, Instruction, MemArg, ValType};
+
+    #[test]
+    fn test_corpus_file_round_trip() {
+        let i32_type = InstructionType { params: vec![], results: vec![ValType::I32] };
+        let mut load = Node::new(
+            Instruction::new_mem(OpCode::I32Load, MemArg { align: 2, offset: 4 }),
+            InstructionType { params: vec![ValType::I32], results: vec![ValType::I32] },
+            Some(Context {
+                local_variable: None,
+                functype: None,
+                global_variable: None,
+                memory: Some(MemoryContext { max: 8 }),
+            }),
+        );
+        load.id = Some(7);
+        let bt = BlockType { params: vec![], results: vec![ValType::I32] };
+        let mut body = Node::new(Instruction::new_loop(OpCode::Loop, bt), i32_type.clone(), None);
+        body.sub_instrs.push(Node::new(Instruction::new_simple(OpCode::LocalGet, 0), i32_type, None));
+        // bits above `i64::MAX` have no BSON integer of their own
+        let i64_const = Node::new(
+            Instruction::new_const(OpCode::I64Const, -1i64 as u64),
+            InstructionType { params: vec![], results: vec![ValType::I64] },
+            None,
+        );
+        let f64_const = Node::new(
+            Instruction::new_const(OpCode::F64Const, (-1.5f64).to_bits()),
+            InstructionType { params: vec![], results: vec![ValType::F64] },
+            None,
+        );
+        let file = CorpusFile::new(vec![load, body, i64_const, f64_const]);
+
+        let json = file.to_json().unwrap();
+        assert!(json.contains("\"schema_version\": 2"));
+        assert!(json.contains("\"i32\""));
+        assert_eq!(CorpusFile::from_json(&json).unwrap(), file);
+        assert_eq!(CorpusFile::from_cbor(&file.to_cbor().unwrap()).unwrap(), file);
+        assert_eq!(CorpusFile::from_bson(&file.to_bson().unwrap()).unwrap(), file);
+
+        let newer = json.replace("\"schema_version\": 2", "\"schema_version\": 3");
+        assert!(CorpusFile::from_json(&newer).is_err());
+    }
+}
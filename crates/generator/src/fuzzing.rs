@@ -0,0 +1,73 @@
+use arbitrary::{Arbitrary, Unstructured};
+use crate::AST::Node;
+use crate::composer::Composition;
+use crate::instruction::ValType;
+use crate::random_generator::{RandomConfig, RandomGenerator};
+
+
+const VAL_TYPES: [ValType; 7] = [
+    ValType::I32, ValType::I64, ValType::F32, ValType::F64, ValType::V128, ValType::FuncRef, ValType::ExternRef,
+];
+
+/// A random function for libFuzzer. The fuzzer input picks the signature and
+/// limits and then drives every decision of the `RandomGenerator`, so the
+/// tree is always well typed and mutating the input mutates the program.
+#[derive(Debug, Clone)]
+pub struct ArbitraryProgram {
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+    pub composition: Composition,
+}
+
+impl<'a> Arbitrary<'a> for ArbitraryProgram {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let params = val_types(u, 3)?;
+        let results = val_types(u, 2)?;
+        let config = RandomConfig {
+            max_depth: u.int_in_range(1..=8)?,
+            max_size: u.int_in_range(results.len() + 1..=256)?,
+            ..RandomConfig::default()
+        };
+        let composition = RandomGenerator::new(config)
+            .generate_with(u, &params, &results)
+            .map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        Ok(ArbitraryProgram { params, results, composition })
+    }
+}
+
+impl<'a> Arbitrary<'a> for ValType {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.choose(&VAL_TYPES).copied()
+    }
+}
+
+/// Only well-typed trees, see `ArbitraryProgram`.
+impl<'a> Arbitrary<'a> for Node {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        ArbitraryProgram::arbitrary(u).map(|program| program.composition.body)
+    }
+}
+
+fn val_types(u: &mut Unstructured<'_>, max: usize) -> arbitrary::Result<Vec<ValType>> {
+    let len = u.int_in_range(0..=max)?;
+    (0..len).map(|_| ValType::arbitrary(u)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module_generator::encode_module;
+
+    #[test]
+    fn test_arbitrary_programs_validate() {
+        for seed in 0u32..256 {
+            let bytes: Vec<u8> = (0..512u32).map(|i| (i.wrapping_mul(seed * 2 + 1) >> 3) as u8).collect();
+            let program = ArbitraryProgram::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            assert_eq!(program.composition.body.instr_type.results, program.results);
+            let wasm = encode_module(&program.composition.body).unwrap();
+            wasmparser::validate(&wasm).unwrap_or_else(|e| panic!("input {}: {}", seed, e));
+        }
+        // an empty input still gives a program
+        assert!(Node::arbitrary(&mut Unstructured::new(&[])).is_ok());
+    }
+}
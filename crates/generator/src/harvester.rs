@@ -0,0 +1,642 @@
+use anyhow::{Result, anyhow, bail};
+use wasmparser::{CompositeInnerType, FunctionBody, Parser, Payload, TypeRef};
+use common::opcodes::OpCode;
+use rewriter::module::WasmModule;
+use crate::AST::{Context, FunctionTypeContext, InstructionType, Node};
+use crate::canonical::SnippetClasses;
+use crate::composer::node_count;
+use crate::instruction::{BlockType, Instruction, InstructionArgs, ValType};
+use crate::module_generator::encode_module;
+use crate::parser::{ParseEnv, flatten_node, signature};
+
+
+/// What the harvester keeps.
+#[derive(Debug, Clone)]
+pub struct HarvestConfig {
+    // largest snippet recorded, in nodes
+    pub max_size: usize,
+    // only keep snippets that `encode_module` turns into a valid module
+    pub validate: bool,
+}
+
+impl Default for HarvestConfig {
+    fn default() -> Self {
+        HarvestConfig { max_size: 32, validate: true }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HarvestStats {
+    pub functions: usize,
+    // functions using types or instructions the AST cannot express; the
+    // snippets found before the first such instruction are kept
+    pub skipped_functions: usize,
+    // snippets of classes not seen in any earlier module
+    pub new_snippets: usize,
+}
+
+/// Collects snippets from real-world modules. Every function body is turned
+/// back into expression trees by following the operand stack, and every
+/// subtree becomes a snippet whose operands that come from outside of it are
+/// params. Snippets are grouped into classes of `canonical::SnippetClasses`
+/// across all harvested modules, one snippet with its count per class.
+///
+/// Code after an unconditional branch is skipped, and so are subtrees that
+/// branch to a label outside of themselves or return.
+#[derive(Debug, Default)]
+pub struct Harvester {
+    config: HarvestConfig,
+    classes: SnippetClasses,
+}
+
+impl Harvester {
+    pub fn new(config: HarvestConfig) -> Self {
+        Harvester { config, classes: SnippetClasses::new() }
+    }
+
+    pub fn harvest_module(&mut self, module: &WasmModule) -> Result<HarvestStats> {
+        self.harvest_binary(&module.encode())
+    }
+
+    pub fn harvest_binary(&mut self, wasm_binary: &[u8]) -> Result<HarvestStats> {
+        let mut module = ModuleEnv::default();
+        let mut stats = HarvestStats::default();
+        let before = self.classes.len();
+        let mut next_defined = 0usize;
+
+        for payload in Parser::new(0).parse_all(wasm_binary) {
+            match payload? {
+                Payload::TypeSection(reader) => {
+                    for rec_group in reader {
+                        for sub_type in rec_group?.into_types() {
+                            let func_type = match sub_type.composite_type.inner {
+                                CompositeInnerType::Func(func_type) => Some(func_type),
+                                _ => None,
+                            };
+                            module.add_type(func_type);
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        match import?.ty {
+                            TypeRef::Func(type_index) => module.funcs.push(type_index),
+                            TypeRef::Global(global_type) => module.globals.push(ValType::try_from(global_type.content_type).ok()),
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for type_index in reader {
+                        let type_index = type_index?;
+                        module.defined.push(type_index);
+                        module.funcs.push(type_index);
+                    }
+                }
+                Payload::GlobalSection(reader) => {
+                    for global in reader {
+                        module.globals.push(ValType::try_from(global?.ty.content_type).ok());
+                    }
+                }
+                Payload::CodeSectionStart { .. } => module.build_parse_env(),
+                Payload::CodeSectionEntry(body) => {
+                    let type_index = *module.defined.get(next_defined).ok_or_else(|| anyhow!("function without a type"))?;
+                    next_defined += 1;
+                    stats.functions += 1;
+                    if self.harvest_function(&module, type_index, &body).is_err() {
+                        stats.skipped_functions += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+        stats.new_snippets = self.classes.len() - before;
+        Ok(stats)
+    }
+
+    pub fn snippets(&self) -> &[Node] {
+        self.classes.snippets()
+    }
+
+    pub fn into_snippets(self) -> Vec<Node> {
+        self.classes.into_snippets()
+    }
+
+    /// Merge the snippets harvested so far into `collection` of `source`:
+    /// the classes stored there already add to their counts, the others are
+    /// added, so harvesting the same modules again only raises counts.
+    #[cfg(feature = "serde")]
+    pub fn store(&self, source: &dyn crate::ast_loader::AstSource, collection: &str) -> Result<()> {
+        let mut classes = SnippetClasses::new();
+        if source.has_collection(collection)? {
+            for node in source.stream_collection(collection, &crate::ast_loader::AstQuery::new())? {
+                classes.insert(node.map_err(|e| e.context(format!("cannot merge into `{}`", collection)))?);
+            }
+        }
+        for node in self.snippets() {
+            classes.insert(node.clone());
+        }
+        let docs = classes
+            .snippets()
+            .iter()
+            .map(|node| match serde_json::to_value(node)? {
+                serde_json::Value::Object(map) => Ok(crate::ast_schema::json_to_document(&map)),
+                _ => unreachable!("a struct always serializes to an object"),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        source.replace_collection(collection, &docs)
+    }
+
+    fn harvest_function(&mut self, module: &ModuleEnv, type_index: u32, body: &FunctionBody) -> Result<()> {
+        let functype = module.functype(type_index)?;
+        let mut env = module.parse_env.clone();
+        env.locals = functype.param_types.clone();
+        for local in body.get_locals_reader()? {
+            let (count, ty) = local?;
+            let ty = ValType::try_from(ty)?;
+            env.locals.extend(std::iter::repeat_n(ty, count as usize));
+        }
+
+        let bt = BlockType { params: vec![], results: functype.result_types.clone() };
+        let mut frames = vec![Frame::new(None, bt, vec![])];
+        let mut reader = body.get_operators_reader()?;
+        while !reader.eof() {
+            let op = reader.read()?;
+            let instr = Instruction::from_operator(&op, &module.func_types)?;
+            let Some(frame) = frames.last_mut() else {
+                bail!("instructions after the end of the function");
+            };
+            if frame.unreachable {
+                match instr.opcode {
+                    OpCode::Block | OpCode::Loop | OpCode::If => {
+                        frame.dead_nesting += 1;
+                        continue;
+                    }
+                    OpCode::Else_ if frame.dead_nesting > 0 => continue,
+                    OpCode::End_ if frame.dead_nesting > 0 => {
+                        frame.dead_nesting -= 1;
+                        continue;
+                    }
+                    OpCode::Else_ | OpCode::End_ => {}
+                    _ => continue,
+                }
+            }
+            module.check(&instr)?;
+            self.step(instr, &env, &mut frames)?;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, instr: Instruction, env: &ParseEnv, frames: &mut Vec<Frame>) -> Result<()> {
+        let opcode = instr.opcode;
+        match opcode {
+            OpCode::Block | OpCode::Loop => {
+                let bt = match &instr.args {
+                    InstructionArgs::Block(bt) | InstructionArgs::Loop(bt) => bt.clone(),
+                    _ => bail!("unsupported block type"),
+                };
+                // the values a block takes stay where they are, the block only gets their types
+                let frame = frames.last_mut().unwrap();
+                frame.pop(bt.params.len())?;
+                frames.push(Frame::new(Some(instr), bt.clone(), bt.params));
+            }
+            OpCode::If => {
+                let InstructionArgs::If(if_args) = &instr.args else {
+                    bail!("unsupported block type");
+                };
+                let bt = if_args.bt.clone();
+                let mut params = bt.params.clone();
+                params.push(ValType::I32);
+                let (operands, missing) = frames.last_mut().unwrap().operands(params.len())?;
+                let mut frame = Frame::new(Some(instr), bt.clone(), bt.params);
+                frame.if_operands = operands;
+                frame.if_missing = missing;
+                frames.push(frame);
+            }
+            OpCode::Else_ => {
+                let frame = frames.last_mut().unwrap();
+                frame.then_body = Some(std::mem::take(&mut frame.body));
+                frame.stack = frame.bt.params.iter().map(|ty| StackValue { ty: *ty, producer: None }).collect();
+                frame.unreachable = false;
+            }
+            OpCode::End_ => {
+                let frame = frames.pop().unwrap();
+                let Some(mut instr) = frame.instr else {
+                    // the end of the function body
+                    return Ok(());
+                };
+                let (params, sub_instrs) = match &mut instr.args {
+                    InstructionArgs::If(if_args) => {
+                        let (then_body, else_body) = match frame.then_body {
+                            Some(then_body) => (then_body, frame.body),
+                            None => (frame.body, vec![]),
+                        };
+                        then_body.iter().for_each(|node| flatten_node(node, &mut if_args.instrs1));
+                        else_body.iter().for_each(|node| flatten_node(node, &mut if_args.instrs2));
+                        (params_of(&frame.if_missing, &frame.if_operands), frame.if_operands)
+                    }
+                    _ => (frame.bt.params.clone(), frame.body),
+                };
+                let instr_type = InstructionType { params, results: frame.bt.results.clone() };
+                let mut node = Node::new(instr, instr_type, None);
+                node.sub_instrs = sub_instrs;
+                self.push(node, frames.last_mut().unwrap());
+            }
+            OpCode::Br | OpCode::BrIf | OpCode::BrTable | OpCode::Return | OpCode::Unreachable => {
+                let label = |depth: u32| -> Result<Vec<ValType>> {
+                    let frame = frames
+                        .len()
+                        .checked_sub(depth as usize + 1)
+                        .and_then(|index| frames.get(index))
+                        .ok_or_else(|| anyhow!("branch to an unknown label"))?;
+                    Ok(frame.label_types())
+                };
+                let (mut params, results) = match &instr.args {
+                    InstructionArgs::Simple(depth) if opcode == OpCode::Br => (label(*depth)?, vec![]),
+                    InstructionArgs::Simple(depth) => (label(*depth)?, label(*depth)?),
+                    InstructionArgs::BrTable(br_table) => (label(br_table.default_label)?, vec![]),
+                    _ if opcode == OpCode::Return => (frames[0].label_types(), vec![]),
+                    _ => (vec![], vec![]),
+                };
+                if matches!(opcode, OpCode::BrIf | OpCode::BrTable) {
+                    params.push(ValType::I32);
+                }
+                self.apply(instr, params, results, None, frames.last_mut().unwrap())?;
+                if opcode != OpCode::BrIf {
+                    frames.last_mut().unwrap().unreachable = true;
+                }
+            }
+            _ => {
+                let frame = frames.last_mut().unwrap();
+                // only the polymorphic instructions look at what they are given
+                let arity = match opcode {
+                    OpCode::Drop | OpCode::RefIsNull => 1,
+                    OpCode::Select | OpCode::SelectT => 3,
+                    _ => 0,
+                };
+                let provided = frame.top_types(arity)?;
+                let (params, results, context) = signature(&instr, &provided, env)?;
+                self.apply(instr, params, results, context, frame)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Build the node for `instr`, taking as many of its operands from the
+    // trees in front of it as possible.
+    fn apply(
+        &mut self,
+        instr: Instruction,
+        params: Vec<ValType>,
+        results: Vec<ValType>,
+        context: Option<Context>,
+        frame: &mut Frame,
+    ) -> Result<()> {
+        let (sub_instrs, missing) = frame.operands(params.len())?;
+        let instr_type = InstructionType { params: params_of(&missing, &sub_instrs), results };
+        let mut node = Node::new(instr, instr_type, context);
+        node.sub_instrs = sub_instrs;
+        self.push(node, frame);
+        Ok(())
+    }
+
+    fn push(&mut self, node: Node, frame: &mut Frame) {
+        self.record(&node);
+        let index = frame.body.len();
+        frame.stack.extend(node.instr_type.results.iter().map(|ty| StackValue { ty: *ty, producer: Some(index) }));
+        frame.body.push(node);
+    }
+
+    fn record(&mut self, node: &Node) {
+        if node_count(node) > self.config.max_size || escapes(node, 0) {
+            return;
+        }
+        if self.config.validate && !encode_module(node).is_ok_and(|wasm| wasmparser::validate(&wasm).is_ok()) {
+            return;
+        }
+        self.classes.insert(node.clone());
+    }
+}
+
+// Missing operands are the leftmost ones, the params of the first operand
+// tree come right after them.
+fn params_of(missing: &[ValType], sub_instrs: &[Node]) -> Vec<ValType> {
+    let mut params = missing.to_vec();
+    for sub in sub_instrs {
+        params.extend(sub.instr_type.params.iter().copied());
+    }
+    params
+}
+
+// Whether `node` returns or branches to a label outside of itself. `depth`
+// is the number of labels opened inside the snippet around it.
+fn escapes(node: &Node, depth: u32) -> bool {
+    match &node.instr.args {
+        InstructionArgs::Block(_) | InstructionArgs::Loop(_) => {
+            node.sub_instrs.iter().any(|sub| escapes(sub, depth + 1))
+        }
+        _ => node.sub_instrs.iter().any(|sub| escapes(sub, depth)) || instr_escapes(&node.instr, depth),
+    }
+}
+
+fn instr_escapes(instr: &Instruction, depth: u32) -> bool {
+    match (&instr.opcode, &instr.args) {
+        (OpCode::Return, _) => true,
+        (OpCode::Br | OpCode::BrIf, InstructionArgs::Simple(label)) => *label >= depth,
+        (OpCode::BrTable, InstructionArgs::BrTable(br_table)) => {
+            br_table.labels.iter().chain([&br_table.default_label]).any(|label| *label >= depth)
+        }
+        (_, InstructionArgs::If(if_args)) => branch_escapes(&if_args.instrs1, depth + 1) || branch_escapes(&if_args.instrs2, depth + 1),
+        _ => false,
+    }
+}
+
+// Branches are flat, a nested block runs up to its `end`.
+fn branch_escapes(instrs: &[Instruction], mut depth: u32) -> bool {
+    for instr in instrs {
+        match instr.opcode {
+            OpCode::Block | OpCode::Loop => depth += 1,
+            OpCode::End_ => depth = depth.saturating_sub(1),
+            _ if instr_escapes(instr, depth) => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StackValue {
+    ty: ValType,
+    // index in the body of the tree that produced the value, `None` for the
+    // params of the block
+    producer: Option<usize>,
+}
+
+// An open block, loop, if or the function body itself.
+#[derive(Debug)]
+struct Frame {
+    // `None` for the function body
+    instr: Option<Instruction>,
+    bt: BlockType,
+    // the trees in the order they run
+    body: Vec<Node>,
+    stack: Vec<StackValue>,
+    // the body of the then branch once the else branch started
+    then_body: Option<Vec<Node>>,
+    // operand trees and missing operand types of an if
+    if_operands: Vec<Node>,
+    if_missing: Vec<ValType>,
+    unreachable: bool,
+    // blocks opened in unreachable code, skipped up to their end
+    dead_nesting: usize,
+}
+
+impl Frame {
+    fn new(instr: Option<Instruction>, bt: BlockType, params: Vec<ValType>) -> Self {
+        let stack = params.iter().map(|ty| StackValue { ty: *ty, producer: None }).collect();
+        Frame {
+            instr,
+            bt,
+            body: Vec::new(),
+            stack,
+            then_body: None,
+            if_operands: Vec::new(),
+            if_missing: Vec::new(),
+            unreachable: false,
+            dead_nesting: 0,
+        }
+    }
+
+    // A branch to a loop starts it over, to anything else it ends it.
+    fn label_types(&self) -> Vec<ValType> {
+        match self.instr.as_ref().map(|instr| instr.opcode) {
+            Some(OpCode::Loop) => self.bt.params.clone(),
+            _ => self.bt.results.clone(),
+        }
+    }
+
+    fn top_types(&self, count: usize) -> Result<Vec<ValType>> {
+        let start = self.stack.len().checked_sub(count).ok_or_else(|| anyhow!("operand stack underflow"))?;
+        Ok(self.stack[start..].iter().map(|value| value.ty).collect())
+    }
+
+    fn pop(&mut self, count: usize) -> Result<Vec<StackValue>> {
+        let start = self.stack.len().checked_sub(count).ok_or_else(|| anyhow!("operand stack underflow"))?;
+        Ok(self.stack.split_off(start))
+    }
+
+    // Pop `count` operands. The trees at the end of the body producing the
+    // rightmost ones become operand trees, the types of the rest are
+    // returned as missing. Only the leftmost operand tree may have params.
+    fn operands(&mut self, count: usize) -> Result<(Vec<Node>, Vec<ValType>)> {
+        let values = self.pop(count)?;
+        let mut trees = Vec::new();
+        let mut left = values.len();
+        while left > 0 {
+            let Some(producer) = values[left - 1].producer else {
+                break;
+            };
+            if producer + 1 != self.body.len() {
+                break;
+            }
+            let arity = self.body[producer].instr_type.results.len();
+            if arity > left || values[left - arity..left].iter().any(|value| value.producer != Some(producer)) {
+                break;
+            }
+            let tree = self.body.pop().unwrap();
+            left -= arity;
+            let has_params = !tree.instr_type.params.is_empty();
+            trees.push(tree);
+            if has_params {
+                break;
+            }
+        }
+        trees.reverse();
+        Ok((trees, values[..left].iter().map(|value| value.ty).collect()))
+    }
+}
+
+// Types and functions of the module being harvested, as far as the AST can
+// express them.
+#[derive(Debug, Default)]
+struct ModuleEnv {
+    // `None` where a type is not a function type or uses other value types
+    types: Vec<Option<FunctionTypeContext>>,
+    // for `Instruction::from_operator`, empty where `types` is `None`
+    func_types: Vec<wasmparser::FuncType>,
+    // type indices of the imported and then the defined functions
+    funcs: Vec<u32>,
+    defined: Vec<u32>,
+    globals: Vec<Option<ValType>>,
+    parse_env: ParseEnv,
+}
+
+impl ModuleEnv {
+    fn add_type(&mut self, func_type: Option<wasmparser::FuncType>) {
+        let val_types = |list: &[wasmparser::ValType]| -> Option<Vec<ValType>> {
+            list.iter().map(|ty| ValType::try_from(*ty).ok()).collect()
+        };
+        let context = func_type.as_ref().and_then(|func_type| {
+            Some(FunctionTypeContext {
+                param_types: val_types(func_type.params())?,
+                result_types: val_types(func_type.results())?,
+            })
+        });
+        self.func_types.push(match (&context, func_type) {
+            (Some(_), Some(func_type)) => func_type,
+            _ => wasmparser::FuncType::new([], []),
+        });
+        self.types.push(context);
+    }
+
+    fn functype(&self, type_index: u32) -> Result<&FunctionTypeContext> {
+        self.types
+            .get(type_index as usize)
+            .and_then(|ty| ty.as_ref())
+            .ok_or_else(|| anyhow!("type {} is not supported", type_index))
+    }
+
+    // Entities of unsupported types get a placeholder so that the indices of
+    // the others stay the same, `check` keeps instructions away from them.
+    fn build_parse_env(&mut self) {
+        let functype = |index: u32| {
+            self.functype(index)
+                .cloned()
+                .unwrap_or(FunctionTypeContext { param_types: vec![], result_types: vec![] })
+        };
+        let types = (0..self.types.len() as u32).map(functype).collect();
+        let funcs = self.funcs.iter().map(|index| functype(*index)).collect();
+        let globals = self.globals.iter().map(|ty| ty.unwrap_or(ValType::I32)).collect();
+        self.parse_env = ParseEnv { locals: vec![], globals, funcs, types };
+    }
+
+    // Whether `signature` can type `instr`: it refers to entities of supported
+    // types, and to table 0 where its type depends on the table.
+    fn check(&self, instr: &Instruction) -> Result<()> {
+        let supported = match (instr.opcode, &instr.args) {
+            (OpCode::Call, InstructionArgs::Simple(index)) => {
+                self.funcs.get(*index as usize).is_some_and(|ty| self.functype(*ty).is_ok())
+            }
+            (OpCode::CallIndirect, InstructionArgs::Simple(index)) => self.functype(*index).is_ok(),
+            (OpCode::GlobalGet | OpCode::GlobalSet, InstructionArgs::Simple(index)) => {
+                self.globals.get(*index as usize).is_some_and(Option::is_some)
+            }
+            (OpCode::CallIndirect | OpCode::TableGet | OpCode::TableSet, InstructionArgs::Raw(_)) => false,
+            _ => true,
+        };
+        if !supported {
+            bail!("{} {:?} is not supported", instr.opcode.name(), instr.args);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expr;
+
+    #[test]
+    fn test_harvest_module() {
+        let wat = r#"(module
+            (import "env" "f" (func $f (param i32) (result i64)))
+            (global $g (mut i32) (i32.const 0))
+            (memory 1)
+            (func (param i32 i32) (result i32) (local f32)
+                (local.set 0 (i32.add (local.get 0) (i32.const 1)))
+                (block $b (result i32)
+                    (drop (call $f (global.get $g)))
+                    (drop (br_if $b (local.get 0) (i32.load offset=4 (local.get 1))))
+                    (br $b (i32.const 9))
+                    (drop (f64.const 0.5)))
+                (if (result i32) (local.get 0) (then (i32.const 1)) (else (i32.const 2)))
+                (i32.add))
+            (table 1 funcref) (table $t 1 funcref)
+            (func (call_indirect $t (i32.const 0))))"#;
+        let module = WasmModule::new(&wat::parse_str(wat).unwrap());
+        let mut harvester = Harvester::new(HarvestConfig::default());
+        let stats = harvester.harvest_module(&module).unwrap();
+        assert_eq!((stats.functions, stats.skipped_functions), (2, 1));
+        assert_eq!(stats.new_snippets, harvester.snippets().len());
+
+        let env = ParseEnv {
+            locals: vec![ValType::I32, ValType::I32, ValType::F32],
+            globals: vec![ValType::I32],
+            funcs: vec![FunctionTypeContext { param_types: vec![ValType::I32], result_types: vec![ValType::I64] }],
+            ..ParseEnv::default()
+        };
+        for expected in ["(local.set 0 (i32.add (local.get 0) (i32.const 1)))", "(drop (call 0 (global.get 0)))"] {
+            let expected = Node { count: Some(1), ..parse_expr(expected, &env).unwrap() };
+            assert!(harvester.snippets().contains(&expected), "{:?} was not harvested", expected);
+        }
+        // the branches only leave the block, the code after `br` is dead
+        let snippets = harvester.snippets();
+        assert!(snippets.iter().any(|node| node.instr.opcode == OpCode::Block && node.sub_instrs.len() == 3));
+        assert!(!snippets.iter().any(|node| node.instr.opcode == OpCode::BrIf));
+        assert!(!snippets.iter().any(|node| node.instr.opcode == OpCode::F64Const));
+        let add = snippets.iter().find(|node| {
+            node.instr.opcode == OpCode::I32Add && node.sub_instrs.first().is_some_and(|sub| sub.instr.opcode == OpCode::Block)
+        });
+        assert_eq!(add.unwrap().sub_instrs[1].instr.opcode, OpCode::If);
+
+        assert_eq!(harvester.harvest_module(&module).unwrap().new_snippets, 0);
+        assert!(harvester.snippets().iter().all(|node| node.count.is_some_and(|count| count % 2 == 0)));
+
+        #[cfg(feature = "serde")]
+        {
+            use crate::ast_loader::DirSource;
+            let root = std::env::temp_dir().join(format!("harvester_test_{}", std::process::id()));
+            let source = DirSource::new(&root);
+            check_store(&harvester, &source, "harvested");
+            check_store(&harvester, &Projected(&source), "projected");
+            std::fs::remove_dir_all(&root).unwrap();
+        }
+    }
+
+    // Storing twice keeps one snippet per class with twice the counts.
+    #[cfg(feature = "serde")]
+    fn check_store(harvester: &Harvester, source: &dyn crate::ast_loader::AstSource, collection: &str) {
+        use crate::ast_loader::AstQuery;
+        harvester.store(source, collection).unwrap();
+        let stored: Vec<Node> = source.stream_collection(collection, &AstQuery::new()).unwrap().map(Result::unwrap).collect();
+        assert_eq!(stored, harvester.snippets());
+
+        harvester.store(source, collection).unwrap();
+        let merged: Vec<Node> = source.stream_collection(collection, &AstQuery::new()).unwrap().map(Result::unwrap).collect();
+        assert_eq!(merged.len(), stored.len());
+        let doubled: Vec<Option<u32>> = stored.iter().map(|node| node.count.map(|count| count * 2)).collect();
+        assert_eq!(merged.iter().map(|node| node.count).collect::<Vec<_>>(), doubled);
+    }
+
+    // Streams only the fields MongoDB is asked for, like `MongoSource`.
+    #[cfg(feature = "serde")]
+    struct Projected<'a>(&'a dyn crate::ast_loader::AstSource);
+
+    #[cfg(feature = "serde")]
+    impl crate::ast_loader::AstSource for Projected<'_> {
+        fn collection_names(&self) -> Result<Vec<String>> {
+            self.0.collection_names()
+        }
+
+        fn load_collection(&self, name: &str) -> Result<Vec<bson::Document>> {
+            self.0.load_collection(name)
+        }
+
+        fn stream_collection(&self, name: &str, query: &crate::ast_loader::AstQuery) -> Result<crate::ast_loader::AstStream<'_>> {
+            let nodes = self.0.load_collection(name)?.into_iter().map(|doc| {
+                let doc: bson::Document =
+                    doc.into_iter().filter(|(field, _)| crate::ast_loader::SNIPPET_FIELDS.contains(&field.as_str())).collect();
+                Node::try_from(&doc)
+            });
+            Ok(query.apply(nodes))
+        }
+
+        fn insert_collection(&self, name: &str, docs: &[bson::Document]) -> Result<()> {
+            self.0.insert_collection(name, docs)
+        }
+
+        fn replace_collection(&self, name: &str, docs: &[bson::Document]) -> Result<()> {
+            self.0.replace_collection(name, docs)
+        }
+    }
+}
@@ -1,8 +1,10 @@
 
+use common::metadata::ValKind;
 use common::opcodes::OpCode;
 
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Instruction {
     pub opcode: OpCode,
     pub args: InstructionArgs,
@@ -38,6 +40,10 @@ impl Instruction {
         Instruction { opcode, args: InstructionArgs::If(if_args) }
     }
 
+    pub fn new_const(opcode: OpCode, bits: u64) -> Self {
+        Instruction { opcode, args: InstructionArgs::Const(bits) }
+    }
+
     pub fn new_none(opcode: OpCode) -> Self {
         Instruction { opcode, args: InstructionArgs::None }
     }
@@ -48,12 +54,14 @@ impl Instruction {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockType {
     pub params: Vec<ValType>,
     pub results: Vec<ValType>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IfArgs {
     pub bt: BlockType,           // 块类型
     pub instrs1: Vec<Instruction>, // then 分支
@@ -61,18 +69,24 @@ pub struct IfArgs {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BrTableArgs {
     pub labels: Vec<u32>,    // 向量类型
     pub default_label: u32,  // 默认标签
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemArg {
     pub align: u32,
     pub offset: u32,
 }
 
+// Serialized without a tag, the shape of the arguments is enough except for
+// block and loop, which `Instruction` tells apart by its opcode.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
 pub enum InstructionArgs {
     Block(BlockType),
     Loop(BlockType),
@@ -80,10 +94,17 @@ pub enum InstructionArgs {
     Mem(MemArg),
     BrTable(BrTableArgs),
     Simple(u32),
+    // immediate of a `*.const`, as the bits of the value zero-extended to 64
+    Const(u64),
+    // immediates of a prefixed instruction that fit none of the above, as
+    // they are encoded in the binary format
+    Raw(Vec<u8>),
     None,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum ValType {
     I32 = 0x7F,
     I64 = 0x7E,
@@ -92,4 +113,38 @@ pub enum ValType {
     V128 = 0x7B,
     FuncRef = 0x70,
     ExternRef = 0x6F,
-}
\ No newline at end of file
+}
+
+impl ValType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValType::I32       => "i32",
+            ValType::I64       => "i64",
+            ValType::F32       => "f32",
+            ValType::F64       => "f64",
+            ValType::V128      => "v128",
+            ValType::FuncRef   => "funcref",
+            ValType::ExternRef => "externref",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ValType::I32, ValType::I64, ValType::F32, ValType::F64, ValType::V128, ValType::FuncRef, ValType::ExternRef]
+            .into_iter()
+            .find(|ty| ty.name() == name)
+    }
+}
+
+impl From<ValKind> for ValType {
+    fn from(kind: ValKind) -> Self {
+        match kind {
+            ValKind::I32       => ValType::I32,
+            ValKind::I64       => ValType::I64,
+            ValKind::F32       => ValType::F32,
+            ValKind::F64       => ValType::F64,
+            ValKind::V128      => ValType::V128,
+            ValKind::FuncRef   => ValType::FuncRef,
+            ValKind::ExternRef => ValType::ExternRef,
+        }
+    }
+}
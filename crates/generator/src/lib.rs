@@ -1,8 +1,23 @@
 
 pub mod ast_loader;
+pub mod ast_schema;
+pub mod canonical;
+pub mod composer;
+pub mod convert;
+#[cfg(feature = "serde")]
+pub mod corpus_file;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
+pub mod harvester;
 pub mod instruction;
 pub mod AST;
 pub mod module_generator;
+pub mod mutator;
+pub mod parser;
+pub mod printer;
+pub mod random_generator;
+pub mod resolver;
+pub use common::rng;
 
 
 pub fn add(left: u64, right: u64) -> u64 {
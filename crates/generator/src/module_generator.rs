@@ -1,42 +1,457 @@
 
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use anyhow::{anyhow, bail, Result};
+use wasm_encoder::{
+    CodeSection, ConstExpr, ElementSection, Elements, ExportKind, ExportSection,
+    Function, FunctionSection, GlobalSection, GlobalType, MemorySection, MemoryType, Module,
+    RefType, TableSection, TableType, TypeSection,
+};
 use common::opcodes::OpCode;
-use crate::AST::{Node, InstructionType, Context};
-use crate::instruction::{Instruction, InstructionArgs, BlockType, IfArgs, MemArg};
-
-
-
-// fn json_to_ast_convert(json_ast: &JsonAst) -> Node {
-//     let instr_data = &json_ast.instr;
-    
-//     // 转换操作码
-//     let opcode = OpCode::from_u32(instr_data.opcode);
-    
-//     // 转换参数（根据操作码类型）
-//     let args = match opcode {
-//         OpCode::Block | OpCode::Loop => {
-//             InstructionArgs::Block(parse_block_type(&instr_data.args))
-//         }
-//         OpCode::If => {
-//             InstructionArgs::If(parse_if_args(&instr_data.args))
-//         }
-//         OpCode::I32Load | OpCode::I64Load => {
-//             InstructionArgs::Mem(parse_mem_arg(&instr_data.args))
-//         }
-//         OpCode::Unknown(_) => {
-//             // 对于未知操作码，尝试作为简单参数解析
-//             InstructionArgs::Simple(instr_data.args.as_u32().unwrap_or(0))
-//         }
-//         _ => {
-//             // 默认尝试作为简单参数
-//             InstructionArgs::Simple(instr_data.args.as_u32().unwrap_or(0))
-//         }
-//     };
-    
-//     let instr = Instruction::new(opcode, args);
-    
-//     Node::new(
-//         instr,
-//         parse_instr_type(&json_ast.instr_type),
-//         parse_context(&json_ast.context),
-//     )
-// }
\ No newline at end of file
+use crate::AST::Node;
+use crate::instruction::{Instruction, InstructionArgs, BlockType, ValType};
+
+
+pub const MAIN_EXPORT: &str = "main";
+const PAGE_SIZE: u64 = 65536;
+
+/// Function types of a module under construction. Indices demanded by the
+/// snippet (`call_indirect`) are pinned, everything else is appended and
+/// deduplicated.
+#[derive(Debug, Default, Clone)]
+pub struct TypeRegistry {
+    types: Vec<(Vec<ValType>, Vec<ValType>)>,
+}
+
+impl TypeRegistry {
+    pub fn pin(&mut self, index: u32, params: &[ValType], results: &[ValType]) -> Result<()> {
+        let index = index as usize;
+        while self.types.len() <= index {
+            self.types.push((vec![], vec![]));
+        }
+        let ty = (params.to_vec(), results.to_vec());
+        if self.types[index] != ty && self.types[index] != (vec![], vec![]) {
+            bail!("type {} is required to be both {:?} and {:?}", index, self.types[index], ty);
+        }
+        self.types[index] = ty;
+        Ok(())
+    }
+
+    pub fn intern(&mut self, params: &[ValType], results: &[ValType]) -> u32 {
+        let ty = (params.to_vec(), results.to_vec());
+        match self.types.iter().position(|t| *t == ty) {
+            Some(index) => index as u32,
+            None => {
+                self.types.push(ty);
+                (self.types.len() - 1) as u32
+            }
+        }
+    }
+
+    pub(crate) fn block_type(&mut self, bt: &BlockType) -> wasm_encoder::BlockType {
+        match (bt.params.as_slice(), bt.results.as_slice()) {
+            ([], []) => wasm_encoder::BlockType::Empty,
+            ([], [result]) => wasm_encoder::BlockType::Result((*result).into()),
+            (params, results) => wasm_encoder::BlockType::FunctionType(self.intern(params, results)),
+        }
+    }
+
+    /// `(params, results)` of every type, by index.
+    pub fn types(&self) -> &[(Vec<ValType>, Vec<ValType>)] {
+        &self.types
+    }
+
+    pub fn section(&self) -> TypeSection {
+        let mut section = TypeSection::new();
+        for (params, results) in &self.types {
+            section.ty().function(
+                params.iter().map(|t| (*t).into()),
+                results.iter().map(|t| (*t).into()),
+            );
+        }
+        section
+    }
+}
+
+/// Lower a snippet tree into a function body. `locals` are the declared
+/// (non-parameter) locals of the function.
+pub fn encode_function(node: &Node, locals: &[ValType], types: &mut TypeRegistry) -> Result<Function> {
+    let mut function = Function::new_with_locals_types(locals.iter().map(|t| (*t).into()));
+    encode_node(node, &mut function, types)?;
+    function.instruction(&wasm_encoder::Instruction::End);
+    Ok(function)
+}
+
+fn encode_node(node: &Node, function: &mut Function, types: &mut TypeRegistry) -> Result<()> {
+    match &node.instr.args {
+        InstructionArgs::Block(_) | InstructionArgs::Loop(_) => {
+            emit(function, &node.instr, node, types)?;
+            for sub in &node.sub_instrs {
+                encode_node(sub, function, types)?;
+            }
+            function.instruction(&wasm_encoder::Instruction::End);
+        }
+        InstructionArgs::If(if_args) => {
+            for sub in &node.sub_instrs {
+                encode_node(sub, function, types)?;
+            }
+            emit(function, &node.instr, node, types)?;
+            encode_instrs(&if_args.instrs1, node, function, types)?;
+            if !if_args.instrs2.is_empty() {
+                function.instruction(&wasm_encoder::Instruction::Else);
+                encode_instrs(&if_args.instrs2, node, function, types)?;
+            }
+            function.instruction(&wasm_encoder::Instruction::End);
+        }
+        _ => {
+            for sub in &node.sub_instrs {
+                encode_node(sub, function, types)?;
+            }
+            emit(function, &node.instr, node, types)?;
+        }
+    }
+    Ok(())
+}
+
+// Branch bodies of an `if` are flat lists, nested structured instructions
+// carry their own `else`/`end` as separate entries.
+fn encode_instrs(instrs: &[Instruction], node: &Node, function: &mut Function, types: &mut TypeRegistry) -> Result<()> {
+    for instr in instrs {
+        if let InstructionArgs::If(if_args) = &instr.args {
+            emit(function, instr, node, types)?;
+            encode_instrs(&if_args.instrs1, node, function, types)?;
+            if !if_args.instrs2.is_empty() {
+                function.instruction(&wasm_encoder::Instruction::Else);
+                encode_instrs(&if_args.instrs2, node, function, types)?;
+            }
+            function.instruction(&wasm_encoder::Instruction::End);
+        } else {
+            emit(function, instr, node, types)?;
+        }
+    }
+    Ok(())
+}
+
+fn emit(function: &mut Function, instr: &Instruction, node: &Node, types: &mut TypeRegistry) -> Result<()> {
+    match (instr.opcode, &instr.args) {
+        // snippets written without the type take it from their result
+        (OpCode::SelectT, InstructionArgs::None) => {
+            let ty = node.instr_type.results.first().ok_or_else(|| anyhow!("select_t without a result type"))?;
+            function.instruction(&wasm_encoder::Instruction::TypedSelect((*ty).into()));
+        }
+        (OpCode::Prefixed(..), _) | (_, InstructionArgs::Raw(_)) => {
+            function.raw(instr.to_bytes(types)?);
+        }
+        _ => {
+            function.instruction(&instr.to_encoder(types)?);
+        }
+    }
+    Ok(())
+}
+
+/// Everything a snippet tree needs from its surrounding module, gathered from
+/// the `Context` of every node and from the instructions themselves.
+#[derive(Debug, Default, Clone)]
+pub struct ModuleRequirements {
+    pub locals: BTreeMap<u32, ValType>,
+    pub globals: BTreeMap<u32, ValType>,
+    pub funcs: BTreeMap<u32, (Vec<ValType>, Vec<ValType>)>,
+    pub types: BTreeMap<u32, (Vec<ValType>, Vec<ValType>)>,
+    pub ref_funcs: BTreeSet<u32>,
+    pub memory_bytes: Option<u64>,
+    pub table: bool,
+}
+
+impl ModuleRequirements {
+    pub fn collect(node: &Node) -> Result<Self> {
+        let mut requirements = ModuleRequirements::default();
+        requirements.visit(node)?;
+        Ok(requirements)
+    }
+
+    fn visit(&mut self, node: &Node) -> Result<()> {
+        let index = match node.instr.args {
+            InstructionArgs::Simple(index) => index,
+            _ => 0,
+        };
+        let context = node.context.as_ref();
+        let functype = context.and_then(|c| c.functype.as_ref());
+        match node.instr.opcode {
+            OpCode::LocalGet | OpCode::LocalSet | OpCode::LocalTee => {
+                let ty = context
+                    .and_then(|c| c.local_variable.as_ref())
+                    .map(|l| l.local_variable_type)
+                    .ok_or_else(|| anyhow!("{} {} has no local variable context", node.instr.opcode.name(), index))?;
+                insert_unique(&mut self.locals, index, ty, "local")?;
+            }
+            OpCode::GlobalGet | OpCode::GlobalSet => {
+                let ty = context
+                    .and_then(|c| c.global_variable.as_ref())
+                    .map(|g| g.global_variable_type)
+                    .ok_or_else(|| anyhow!("{} {} has no global variable context", node.instr.opcode.name(), index))?;
+                insert_unique(&mut self.globals, index, ty, "global")?;
+            }
+            OpCode::Call => {
+                let functype = functype.ok_or_else(|| anyhow!("call {} has no function type context", index))?;
+                let sig = (functype.param_types.clone(), functype.result_types.clone());
+                insert_unique(&mut self.funcs, index, sig, "function")?;
+            }
+            OpCode::CallIndirect => {
+                let functype = functype.ok_or_else(|| anyhow!("call_indirect {} has no function type context", index))?;
+                let sig = (functype.param_types.clone(), functype.result_types.clone());
+                insert_unique(&mut self.types, index, sig, "type")?;
+                self.table = true;
+            }
+            OpCode::TableGet | OpCode::TableSet => self.table = true,
+            OpCode::MemorySize | OpCode::MemoryGrow => self.memory_bytes = Some(self.memory_bytes.unwrap_or(0)),
+            OpCode::RefFunc => {
+                // the function only has to exist, putting it in the table
+                // declares it for `ref.func`
+                self.ref_funcs.insert(index);
+                self.table = true;
+            }
+            _ => {}
+        }
+        if let InstructionArgs::Mem(memarg) = &node.instr.args {
+            // at least enough memory for the static offset of every access
+            let needed = memarg.offset as u64 + 8;
+            let declared = context.and_then(|c| c.memory.as_ref()).map(|m| m.max as u64).unwrap_or(0);
+            let bytes = self.memory_bytes.unwrap_or(0).max(needed).max(declared);
+            self.memory_bytes = Some(bytes);
+        }
+        for sub in &node.sub_instrs {
+            self.visit(sub)?;
+        }
+        Ok(())
+    }
+}
+
+fn insert_unique<T: PartialEq + std::fmt::Debug>(map: &mut BTreeMap<u32, T>, index: u32, value: T, what: &str) -> Result<()> {
+    match map.get(&index) {
+        Some(existing) if *existing != value => {
+            bail!("{} {} is required to be both {:?} and {:?}", what, index, existing, value)
+        }
+        _ => {
+            map.insert(index, value);
+            Ok(())
+        }
+    }
+}
+
+/// Wrap a snippet into a complete module. Every global, type and local the
+/// snippet refers to is declared at the index it uses; called and referenced
+/// functions are renumbered densely in the order of their indices and become
+/// stubs returning zeros. Snippet params are fed with zeros and the snippet
+/// itself is exported as `main`.
+pub fn encode_module(node: &Node) -> Result<Vec<u8>> {
+    // a snippet from a large module may call any index, one stub per index
+    // up to it would make the module as large as the index
+    let mut node = node.clone();
+    let mut called = BTreeSet::new();
+    func_indices(&mut node, &mut |index| {
+        called.insert(*index);
+    });
+    let dense: BTreeMap<u32, u32> = called.iter().enumerate().map(|(i, &index)| (index, i as u32)).collect();
+    func_indices(&mut node, &mut |index| *index = dense[index]);
+    let node = &node;
+
+    let requirements = ModuleRequirements::collect(node)?;
+    let mut types = TypeRegistry::default();
+    for (index, (params, results)) in &requirements.types {
+        types.pin(*index, params, results)?;
+    }
+
+    let mut functions = FunctionSection::new();
+    let mut code = CodeSection::new();
+    // functions only called from the flat branches of an `if` are `[] -> []`
+    let num_stubs = called.len() as u32;
+    for index in 0..num_stubs {
+        let (params, results) = requirements.funcs.get(&index).cloned().unwrap_or_default();
+        functions.function(types.intern(&params, &results));
+        let mut stub = Function::new([]);
+        for result in &results {
+            stub.instruction(&default_value(*result));
+        }
+        stub.instruction(&wasm_encoder::Instruction::End);
+        code.function(&stub);
+    }
+
+    let num_locals = requirements.locals.keys().next_back().map(|i| i + 1).unwrap_or(0);
+    let locals: Vec<ValType> = (0..num_locals)
+        .map(|i| requirements.locals.get(&i).copied().unwrap_or(ValType::I32))
+        .collect();
+    let main_index = num_stubs;
+    functions.function(types.intern(&[], &node.instr_type.results));
+    let mut main = Function::new_with_locals_types(locals.iter().map(|t| (*t).into()));
+    for param in &node.instr_type.params {
+        main.instruction(&default_value(*param));
+    }
+    encode_node(node, &mut main, &mut types)?;
+    main.instruction(&wasm_encoder::Instruction::End);
+    code.function(&main);
+
+    let mut module = Module::new();
+    module.section(&types.section());
+    module.section(&functions);
+
+    if requirements.table {
+        let mut tables = TableSection::new();
+        tables.table(TableType {
+            element_type: RefType::FUNCREF,
+            table64: false,
+            minimum: main_index as u64 + 1,
+            maximum: None,
+            shared: false,
+        });
+        module.section(&tables);
+    }
+
+    let mut exports = ExportSection::new();
+    exports.export(MAIN_EXPORT, ExportKind::Func, main_index);
+    if let Some(bytes) = requirements.memory_bytes {
+        let mut memories = MemorySection::new();
+        memories.memory(MemoryType {
+            minimum: bytes.div_ceil(PAGE_SIZE).max(1),
+            maximum: None,
+            memory64: false,
+            shared: false,
+            page_size_log2: None,
+        });
+        module.section(&memories);
+        exports.export("memory", ExportKind::Memory, 0);
+    }
+
+    let num_globals = requirements.globals.keys().next_back().map(|i| i + 1).unwrap_or(0);
+    if num_globals > 0 {
+        let mut globals = GlobalSection::new();
+        for index in 0..num_globals {
+            let ty = requirements.globals.get(&index).copied().unwrap_or(ValType::I32);
+            let global_type = GlobalType { val_type: ty.into(), mutable: true, shared: false };
+            globals.global(global_type, &default_const(ty));
+        }
+        module.section(&globals);
+    }
+    module.section(&exports);
+
+    if requirements.table {
+        // every function is reachable through the table at its own index
+        let funcs: Vec<u32> = (0..=main_index).collect();
+        let mut elements = ElementSection::new();
+        elements.active(None, &ConstExpr::i32_const(0), Elements::Functions(Cow::Owned(funcs)));
+        module.section(&elements);
+    }
+    module.section(&code);
+    Ok(module.finish())
+}
+
+// Apply `f` to the index of every `call` and `ref.func` of the tree,
+// branches of `if` included.
+fn func_indices(node: &mut Node, f: &mut impl FnMut(&mut u32)) {
+    instr_func_indices(&mut node.instr, f);
+    for sub in &mut node.sub_instrs {
+        func_indices(sub, f);
+    }
+}
+
+fn instr_func_indices(instr: &mut Instruction, f: &mut impl FnMut(&mut u32)) {
+    match (instr.opcode, &mut instr.args) {
+        (OpCode::Call | OpCode::ReturnCall | OpCode::RefFunc, InstructionArgs::Simple(index)) => f(index),
+        (_, InstructionArgs::If(if_args)) => {
+            for instr in if_args.instrs1.iter_mut().chain(&mut if_args.instrs2) {
+                instr_func_indices(instr, f);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn default_value(ty: ValType) -> wasm_encoder::Instruction<'static> {
+    use wasm_encoder::Instruction as I;
+    match ty {
+        ValType::I32       => I::I32Const(0),
+        ValType::I64       => I::I64Const(0),
+        ValType::F32       => I::F32Const(0.0f32.into()),
+        ValType::F64       => I::F64Const(0.0f64.into()),
+        ValType::V128      => I::V128Const(0),
+        ValType::FuncRef   => I::RefNull(wasm_encoder::HeapType::FUNC),
+        ValType::ExternRef => I::RefNull(wasm_encoder::HeapType::EXTERN),
+    }
+}
+
+fn default_const(ty: ValType) -> ConstExpr {
+    match ty {
+        ValType::I32       => ConstExpr::i32_const(0),
+        ValType::I64       => ConstExpr::i64_const(0),
+        ValType::F32       => ConstExpr::f32_const(0.0f32.into()),
+        ValType::F64       => ConstExpr::f64_const(0.0f64.into()),
+        ValType::V128      => ConstExpr::v128_const(0),
+        ValType::FuncRef   => ConstExpr::ref_null(wasm_encoder::HeapType::FUNC),
+        ValType::ExternRef => ConstExpr::ref_null(wasm_encoder::HeapType::EXTERN),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_func;
+
+    #[test]
+    fn test_encode_module_validates() {
+        let node = parse_func("(func (param i32) (local i64) (result i64) (i64.load offset=16 (local.tee 0 (i32.load (local.get 0)))))").unwrap();
+        let binary = encode_module(&node).unwrap();
+        wasmparser::Validator::new().validate_all(&binary).unwrap();
+    }
+
+    #[test]
+    fn test_encode_prefixed_instructions() {
+        use crate::AST::InstructionType;
+        let i32_type = InstructionType { params: vec![], results: vec![ValType::I32] };
+        let mut trunc = Node::new(Instruction::new_none(OpCode::from_name("i32.trunc_sat_f32_s").unwrap()), i32_type.clone(), None);
+        trunc.sub_instrs = vec![Node::new(
+            Instruction::new_const(OpCode::F32Const, 3.5f32.to_bits() as u64),
+            InstructionType { params: vec![], results: vec![ValType::F32] },
+            None,
+        )];
+        // i32x4.extract_lane takes its lane as a raw byte
+        let mut lane = Node::new(
+            Instruction::new(OpCode::from_name("i32x4.extract_lane").unwrap(), InstructionArgs::Raw(vec![3])),
+            i32_type,
+            None,
+        );
+        lane.sub_instrs = vec![Node::new(
+            Instruction::new_none(OpCode::from_name("i32x4.splat").unwrap()),
+            InstructionType { params: vec![], results: vec![ValType::V128] },
+            None,
+        )];
+        lane.sub_instrs[0].sub_instrs = vec![trunc];
+
+        let binary = encode_module(&lane).unwrap();
+        wasmparser::Validator::new().validate_all(&binary).unwrap();
+        assert!(binary.windows(3).any(|w| w == [0xFD, 0x1B, 0x03]));
+    }
+
+    #[test]
+    fn test_encode_calls_densely() {
+        use crate::AST::{Context, FunctionTypeContext, InstructionType};
+        let call = |index| {
+            let functype = FunctionTypeContext { param_types: vec![], result_types: vec![ValType::I32] };
+            let context = Context { local_variable: None, functype: Some(functype), global_variable: None, memory: None };
+            let i32_type = InstructionType { params: vec![], results: vec![ValType::I32] };
+            Node::new(Instruction::new_simple(OpCode::Call, index), i32_type, Some(context))
+        };
+        let mut add = Node::new(
+            Instruction::new_none(OpCode::I32Add),
+            InstructionType { params: vec![], results: vec![ValType::I32] },
+            None,
+        );
+        add.sub_instrs = vec![call(40000), call(5)];
+
+        let binary = encode_module(&add).unwrap();
+        wasmparser::Validator::new().validate_all(&binary).unwrap();
+        let text = wasmprinter::print_bytes(&binary).unwrap();
+        // two stubs and `main`, calls keep their order of indices
+        assert_eq!(text.matches("(func (;").count(), 3, "{}", text);
+        assert!(text.contains("call 1\n") && text.contains("call 0\n"), "{}", text);
+        assert!(text.find("call 1").unwrap() < text.find("call 0").unwrap(), "{}", text);
+    }
+}
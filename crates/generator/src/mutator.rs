@@ -0,0 +1,163 @@
+use anyhow::{Result, bail};
+use wasm_encoder::CodeSection;
+use wasmparser::{CompositeInnerType, Parser, Payload, TypeRef};
+use common::opcodes::OpCode;
+use rewriter::custom_sections::CustomSectionPolicy;
+use rewriter::module::WasmModule;
+use crate::instruction::ValType;
+use crate::module_generator::{TypeRegistry, encode_function};
+use crate::random_generator::{RandomConfig, RandomGenerator};
+use crate::rng::{Choices, Rng};
+
+
+/// Opcodes a replacement body may use: none of them needs anything from the
+/// module besides the params and locals of the function itself.
+pub const MUTATION_OPCODES: &[OpCode] = &[
+    OpCode::Nop, OpCode::Block, OpCode::Loop, OpCode::If, OpCode::Drop, OpCode::Select,
+    OpCode::SelectT, OpCode::LocalGet, OpCode::LocalSet, OpCode::LocalTee,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mutation {
+    pub wasm: Vec<u8>,
+    // index of the replaced function, imports included
+    pub function: u32,
+}
+
+/// Replace the body of one defined function of `wasm_binary` with a random
+/// body of the same type. The function is picked with `config.seed`, which
+/// also seeds the new body; only the weights of `MUTATION_OPCODES` are kept
+/// from the config. Functions whose type the AST cannot express are never
+/// picked. Everything else in the module is left untouched, block types the
+/// new body needs are appended to the type section. Debug info goes through
+/// the default `CustomSectionPolicy` so that it matches the new code.
+pub fn mutate_binary(wasm_binary: &[u8], config: &RandomConfig) -> Result<Mutation> {
+    // `(params, results)` by type index, `None` for the types the AST cannot express
+    let mut types: Vec<Option<(Vec<ValType>, Vec<ValType>)>> = Vec::new();
+    let mut imported_funcs = 0u32;
+    let mut defined: Vec<u32> = Vec::new();
+    let mut bodies = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm_binary) {
+        match payload? {
+            Payload::TypeSection(reader) => {
+                for rec_group in reader {
+                    for sub_type in rec_group?.into_types() {
+                        // the rewriter drops them, which would shift the type indices
+                        let CompositeInnerType::Func(func_type) = sub_type.composite_type.inner else {
+                            bail!("modules with GC types are not supported");
+                        };
+                        types.push(val_types(func_type.params()).zip(val_types(func_type.results())));
+                    }
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    if let TypeRef::Func(_) = import?.ty {
+                        imported_funcs += 1;
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => {
+                for type_index in reader {
+                    defined.push(type_index?);
+                }
+            }
+            Payload::CodeSectionEntry(body) => bodies.push(body.range()),
+            _ => {}
+        }
+    }
+
+    let candidates: Vec<usize> = (0..defined.len())
+        .filter(|&i| matches!(types.get(defined[i] as usize), Some(Some(_))))
+        .collect();
+    if candidates.is_empty() {
+        bail!("no defined function has a type that can be generated");
+    }
+    let target = candidates[Rng::new(config.seed).below(candidates.len())];
+    let (params, results) = types[defined[target] as usize].clone().expect("candidate type");
+
+    let mut config = config.clone();
+    config.weights.retain(|opcode, _| MUTATION_OPCODES.contains(opcode));
+    let composition = RandomGenerator::new(config).generate(&params, &results)?;
+
+    // existing types keep their indices, unexpressible ones are left as `[] -> []`
+    // placeholders that block types never match
+    let mut registry = TypeRegistry::default();
+    for (index, ty) in types.iter().enumerate() {
+        let (params, results) = ty.clone().unwrap_or_default();
+        registry.pin(index as u32, &params, &results)?;
+    }
+    let function = encode_function(&composition.body, &composition.locals, &mut registry)?;
+
+    let mut module = WasmModule::try_new(wasm_binary)?;
+    for (params, results) in &registry.types()[types.len()..] {
+        module.type_section.ty().function(
+            params.iter().map(|t| (*t).into()),
+            results.iter().map(|t| (*t).into()),
+        );
+    }
+    let mut code = CodeSection::new();
+    for (index, range) in bodies.into_iter().enumerate() {
+        if index == target {
+            code.function(&function);
+        } else {
+            code.raw(&wasm_binary[range]);
+        }
+    }
+    module.code_section = code;
+    let wasm = module.encode_with_policy(&CustomSectionPolicy::default())?;
+    Ok(Mutation { wasm, function: imported_funcs + target as u32 })
+}
+
+fn val_types(types: &[wasmparser::ValType]) -> Option<Vec<ValType>> {
+    types.iter().map(|ty| ValType::try_from(*ty).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutate_binary() {
+        let wasm = wat::parse_str(r#"
+            (module
+              (import "env" "f" (func $f (param i32)))
+              (memory 1)
+              (func (export "keep") (result i32)
+                i32.const 42)
+              (func (export "add") (param i32 i64) (result i64 i32)
+                local.get 1
+                local.get 0)
+              (data (i32.const 0) "data")
+              (@custom "sourceMappingURL" "add.map")
+              (@custom ".debug_aranges" "\00\00\00\00")
+              (@custom "producers" "\00"))
+        "#).unwrap();
+
+        let mut replaced = Vec::new();
+        for seed in 0..16 {
+            let config = RandomConfig { seed, ..RandomConfig::default() };
+            let mutation = mutate_binary(&wasm, &config).unwrap();
+            wasmparser::validate(&mutation.wasm).unwrap();
+            assert!(matches!(mutation.function, 1 | 2), "{}", mutation.function);
+            replaced.push(mutation.function);
+
+            let text = wasmprinter::print_bytes(&mutation.wasm).unwrap();
+            assert!(text.contains("(data (;0;) (i32.const 0) \"data\")"), "{}", text);
+            if mutation.function == 2 {
+                assert!(text.contains("i32.const 42"), "{}", text);
+            }
+            // debug info of the old code is gone, other sections are kept
+            let custom: Vec<String> = Parser::new(0)
+                .parse_all(&mutation.wasm)
+                .filter_map(|payload| match payload.unwrap() {
+                    Payload::CustomSection(reader) => Some(reader.name().to_string()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(custom, vec!["name", "producers"]);
+            assert_eq!(mutate_binary(&wasm, &config).unwrap(), mutation);
+        }
+        assert!(replaced.contains(&1) && replaced.contains(&2));
+    }
+}
@@ -0,0 +1,611 @@
+
+use anyhow::{anyhow, bail, Result};
+use common::metadata::{StackSignature, ValKind};
+use common::opcodes::OpCode;
+use crate::AST::{
+    Context, FunctionTypeContext, GlobalVariableContext, InstructionType, LocalVariableContext,
+    MemoryContext, Node,
+};
+use crate::instruction::{BlockType, BrTableArgs, IfArgs, Instruction, InstructionArgs, MemArg, ValType};
+
+
+/// Types of the entities a snippet may refer to by index.
+#[derive(Debug, Clone, Default)]
+pub struct ParseEnv {
+    pub locals: Vec<ValType>,
+    pub globals: Vec<ValType>,
+    pub funcs: Vec<FunctionTypeContext>,
+    pub types: Vec<FunctionTypeContext>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SExpr {
+    Atom(String),
+    List(Vec<SExpr>),
+}
+
+/// Parse one folded expression such as `(i32.load (local.get 0))`.
+pub fn parse_expr(text: &str, env: &ParseEnv) -> Result<Node> {
+    let forms = read_sexprs(text)?;
+    match forms.as_slice() {
+        [form] => node_from_sexpr(form, env),
+        _ => bail!("expected exactly one expression, found {}", forms.len()),
+    }
+}
+
+/// Parse `(func (param ..) (result ..) (local ..) expr..)`. Params and locals
+/// become the local environment of the body. A body with several expressions
+/// is wrapped in a block producing the function results.
+pub fn parse_func(text: &str) -> Result<Node> {
+    let forms = read_sexprs(text)?;
+    match forms.as_slice() {
+        [form] => func_from_sexpr(form),
+        _ => bail!("expected exactly one func, found {}", forms.len()),
+    }
+}
+
+/// Parse a snippet file: a sequence of `(func ..)` forms and bare expressions.
+pub fn parse_snippets(text: &str) -> Result<Vec<Node>> {
+    let env = ParseEnv::default();
+    read_sexprs(text)?
+        .iter()
+        .enumerate()
+        .map(|(index, form)| {
+            let node = if head(form) == Some("func") {
+                func_from_sexpr(form)
+            } else {
+                node_from_sexpr(form, &env)
+            };
+            node.map_err(|e| anyhow!("snippet #{}: {}", index, e))
+        })
+        .collect()
+}
+
+fn func_from_sexpr(form: &SExpr) -> Result<Node> {
+    let items = list(form)?;
+    if head(form) != Some("func") {
+        bail!("expected (func ...)");
+    }
+    let mut env = ParseEnv::default();
+    let mut results = Vec::new();
+    let mut body = Vec::new();
+    for item in &items[1..] {
+        match head(item) {
+            Some("param") => env.locals.extend(val_types(item)?),
+            Some("local") => env.locals.extend(val_types(item)?),
+            Some("result") => results.extend(val_types(item)?),
+            Some("export") => {}
+            _ if matches!(item, SExpr::Atom(a) if a.starts_with('$')) => {}
+            _ => body.push(item),
+        }
+    }
+    let mut nodes = body
+        .into_iter()
+        .map(|item| node_from_sexpr(item, &env))
+        .collect::<Result<Vec<_>>>()?;
+    if nodes.len() == 1 {
+        return Ok(nodes.remove(0));
+    }
+    let bt = BlockType { params: vec![], results: results.clone() };
+    let mut block = Node::new(
+        Instruction::new_block(OpCode::Block, bt),
+        InstructionType { params: vec![], results },
+        None,
+    );
+    block.sub_instrs = nodes;
+    Ok(block)
+}
+
+fn node_from_sexpr(form: &SExpr, env: &ParseEnv) -> Result<Node> {
+    let items = list(form)?;
+    let name = atom(&items[0])?;
+    let opcode = OpCode::from_name(name).ok_or_else(|| anyhow!("unknown instruction `{}`", name))?;
+    let mut rest = &items[1..];
+
+    let args = match opcode {
+        OpCode::Block | OpCode::Loop => {
+            let bt = block_type(&mut rest)?;
+            if opcode == OpCode::Block {
+                InstructionArgs::Block(bt)
+            } else {
+                InstructionArgs::Loop(bt)
+            }
+        }
+        OpCode::If => {
+            let bt = block_type(&mut rest)?;
+            let mut instrs1 = Vec::new();
+            let mut instrs2 = Vec::new();
+            let mut operands = Vec::new();
+            for item in rest {
+                match head(item) {
+                    Some("then") => instrs1 = flatten_branch(item, env)?,
+                    Some("else") => instrs2 = flatten_branch(item, env)?,
+                    _ => operands.push(item.clone()),
+                }
+            }
+            let instr = Instruction::new_if(opcode, IfArgs { bt, instrs1, instrs2 });
+            return typed_node(instr, &operands, env);
+        }
+        OpCode::BrTable => {
+            let mut labels = Vec::new();
+            while let Some(SExpr::Atom(text)) = rest.first() {
+                labels.push(parse_u32(text)?);
+                rest = &rest[1..];
+            }
+            let default_label = labels.pop().ok_or_else(|| anyhow!("br_table needs a default label"))?;
+            InstructionArgs::BrTable(BrTableArgs { labels, default_label })
+        }
+        OpCode::CallIndirect => {
+            let index = match rest.first() {
+                Some(item) if head(item) == Some("type") => {
+                    rest = &rest[1..];
+                    let index = list(item)?.get(1).ok_or_else(|| anyhow!("(type) needs an index"))?;
+                    parse_u32(atom(index)?)?
+                }
+                _ => bail!("call_indirect needs (type N)"),
+            };
+            InstructionArgs::Simple(index)
+        }
+        OpCode::I32Const | OpCode::I64Const | OpCode::F32Const | OpCode::F64Const => {
+            let text = match rest.first() {
+                Some(SExpr::Atom(text)) => text,
+                _ => bail!("{} needs a value", name),
+            };
+            rest = &rest[1..];
+            InstructionArgs::Const(parse_const(opcode, text)?)
+        }
+        OpCode::RefNull => {
+            let ty = match rest.first() {
+                Some(SExpr::Atom(text)) if text == "func" => ValType::FuncRef,
+                Some(SExpr::Atom(text)) if text == "extern" => ValType::ExternRef,
+                _ => bail!("ref.null needs a heap type, `func` or `extern`"),
+            };
+            rest = &rest[1..];
+            InstructionArgs::Simple(ty as u32)
+        }
+        _ if natural_align(opcode).is_some() => {
+            let mut memarg = MemArg { align: natural_align(opcode).unwrap(), offset: 0 };
+            while let Some(SExpr::Atom(text)) = rest.first() {
+                if let Some(value) = text.strip_prefix("offset=") {
+                    memarg.offset = parse_u32(value)?;
+                } else if let Some(value) = text.strip_prefix("align=") {
+                    let bytes = parse_u32(value)?;
+                    if !bytes.is_power_of_two() {
+                        bail!("alignment must be a power of two: {}", bytes);
+                    }
+                    memarg.align = bytes.trailing_zeros();
+                } else {
+                    break;
+                }
+                rest = &rest[1..];
+            }
+            InstructionArgs::Mem(memarg)
+        }
+        _ => match rest.first() {
+            Some(SExpr::Atom(text)) => {
+                rest = &rest[1..];
+                InstructionArgs::Simple(parse_u32(text)?)
+            }
+            _ => InstructionArgs::None,
+        },
+    };
+
+    let instr = Instruction::new(opcode, args);
+    if matches!(opcode, OpCode::Block | OpCode::Loop) {
+        // the items of a block are its body, not its operands
+        let bt = match &instr.args {
+            InstructionArgs::Block(bt) | InstructionArgs::Loop(bt) => bt.clone(),
+            _ => unreachable!(),
+        };
+        let mut node = Node::new(
+            instr,
+            InstructionType { params: bt.params, results: bt.results },
+            None,
+        );
+        node.sub_instrs = rest.iter().map(|item| node_from_sexpr(item, env)).collect::<Result<_>>()?;
+        return Ok(node);
+    }
+    typed_node(instr, rest, env)
+}
+
+// Build the node for `instr` applied to `operands` and work out the type of
+// the whole subtree: operands the expression does not supply become params.
+fn typed_node(instr: Instruction, operands: &[SExpr], env: &ParseEnv) -> Result<Node> {
+    let sub_instrs = operands
+        .iter()
+        .map(|item| node_from_sexpr(item, env))
+        .collect::<Result<Vec<_>>>()?;
+    let provided: Vec<ValType> = sub_instrs.iter().flat_map(|n| n.instr_type.results.clone()).collect();
+    let (needed, results, context) = signature(&instr, &provided, env)?;
+
+    if provided.len() > needed.len() || needed[needed.len() - provided.len()..] != provided[..] {
+        bail!(
+            "{} expects {:?} but its operands produce {:?}",
+            instr.opcode.name(),
+            needed,
+            provided
+        );
+    }
+    let mut params = needed[..needed.len() - provided.len()].to_vec();
+    for sub in &sub_instrs {
+        params.extend(sub.instr_type.params.iter().copied());
+    }
+
+    let mut node = Node::new(instr, InstructionType { params, results }, context);
+    node.sub_instrs = sub_instrs;
+    Ok(node)
+}
+
+pub(crate) type Signature = (Vec<ValType>, Vec<ValType>, Option<Context>);
+
+pub(crate) fn signature(instr: &Instruction, provided: &[ValType], env: &ParseEnv) -> Result<Signature> {
+    use ValType::*;
+    let index = match instr.args {
+        InstructionArgs::Simple(index) => index as usize,
+        _ => 0,
+    };
+    let operand = |what: &str| -> Result<ValType> {
+        provided.first().copied().ok_or_else(|| anyhow!("cannot infer the operand type of {}", what))
+    };
+    let empty = Context { local_variable: None, functype: None, global_variable: None, memory: None };
+
+    let sig = match instr.opcode {
+        OpCode::Unreachable | OpCode::Return | OpCode::Br => (provided.to_vec(), vec![], None),
+        OpCode::BrIf | OpCode::BrTable => {
+            let mut params = provided.to_vec();
+            if params.last() != Some(&I32) {
+                params.push(I32);
+            }
+            (params, vec![], None)
+        }
+        OpCode::Nop => (vec![], vec![], None),
+        OpCode::If => match &instr.args {
+            InstructionArgs::If(if_args) => {
+                let mut params = if_args.bt.params.clone();
+                params.push(I32);
+                (params, if_args.bt.results.clone(), None)
+            }
+            _ => unreachable!(),
+        },
+        OpCode::Call | OpCode::CallIndirect => {
+            let table = if instr.opcode == OpCode::Call { &env.funcs } else { &env.types };
+            let functype = table
+                .get(index)
+                .ok_or_else(|| anyhow!("{} {} is not declared", instr.opcode.name(), index))?;
+            let mut params = functype.param_types.clone();
+            if instr.opcode == OpCode::CallIndirect {
+                params.push(I32);
+            }
+            let context = Context { functype: Some(functype.clone()), ..empty };
+            (params, functype.result_types.clone(), Some(context))
+        }
+        OpCode::Drop => (vec![operand("drop")?], vec![], None),
+        OpCode::Select | OpCode::SelectT => {
+            let ty = operand("select")?;
+            (vec![ty, ty, I32], vec![ty], None)
+        }
+        OpCode::LocalGet | OpCode::LocalSet | OpCode::LocalTee => {
+            let ty = *env.locals.get(index).ok_or_else(|| anyhow!("local {} is not declared", index))?;
+            let context = Context { local_variable: Some(LocalVariableContext { local_variable_type: ty }), ..empty };
+            match instr.opcode {
+                OpCode::LocalGet => (vec![], vec![ty], Some(context)),
+                OpCode::LocalSet => (vec![ty], vec![], Some(context)),
+                _ => (vec![ty], vec![ty], Some(context)),
+            }
+        }
+        OpCode::GlobalGet | OpCode::GlobalSet => {
+            let ty = *env.globals.get(index).ok_or_else(|| anyhow!("global {} is not declared", index))?;
+            let context = Context { global_variable: Some(GlobalVariableContext { global_variable_type: ty }), ..empty };
+            if instr.opcode == OpCode::GlobalGet {
+                (vec![], vec![ty], Some(context))
+            } else {
+                (vec![ty], vec![], Some(context))
+            }
+        }
+        OpCode::TableGet => (vec![I32], vec![FuncRef], None),
+        OpCode::TableSet => (vec![I32, FuncRef], vec![], None),
+        OpCode::MemorySize | OpCode::MemoryGrow => {
+            let context = Context { memory: Some(MemoryContext { max: 0 }), ..empty };
+            let params = if instr.opcode == OpCode::MemoryGrow { vec![I32] } else { vec![] };
+            (params, vec![I32], Some(context))
+        }
+        OpCode::RefNull => {
+            let ty = if index == ExternRef as usize { ExternRef } else { FuncRef };
+            (vec![], vec![ty], None)
+        }
+        OpCode::RefIsNull => (vec![operand("ref.is_null")?], vec![I32], None),
+        opcode => match (&instr.args, load_store_type(opcode), opcode.info().map(|info| info.signature)) {
+            (InstructionArgs::Mem(memarg), Some((ty, width, is_store)), _) => {
+                let max = memarg
+                    .offset
+                    .checked_add(width)
+                    .ok_or_else(|| anyhow!("{} offset={} reaches past 4 GiB", opcode.name(), memarg.offset))?;
+                let context = Context { memory: Some(MemoryContext { max }), ..empty };
+                if is_store {
+                    (vec![I32, ty], vec![], Some(context))
+                } else {
+                    (vec![I32], vec![ty], Some(context))
+                }
+            }
+            (_, _, Some(StackSignature::Fixed { params, results })) => {
+                let types = |kinds: &[ValKind]| kinds.iter().map(|kind| ValType::from(*kind)).collect();
+                (types(params), types(results), None)
+            }
+            _ => bail!("no typing rule for {}", opcode.name()),
+        },
+    };
+    Ok(sig)
+}
+
+// (value type, access width in bytes, is a store)
+pub(crate) fn load_store_type(opcode: OpCode) -> Option<(ValType, u32, bool)> {
+    use ValType::*;
+    Some(match opcode {
+        OpCode::I32Load    => (I32, 4, false),
+        OpCode::I64Load    => (I64, 8, false),
+        OpCode::F32Load    => (F32, 4, false),
+        OpCode::F64Load    => (F64, 8, false),
+        OpCode::I32Load8S  => (I32, 1, false),
+        OpCode::I32Load8U  => (I32, 1, false),
+        OpCode::I32Load16S => (I32, 2, false),
+        OpCode::I32Load16U => (I32, 2, false),
+        OpCode::I64Load8S  => (I64, 1, false),
+        OpCode::I64Load8U  => (I64, 1, false),
+        OpCode::I64Load16S => (I64, 2, false),
+        OpCode::I64Load16U => (I64, 2, false),
+        OpCode::I64Load32S => (I64, 4, false),
+        OpCode::I64Load32U => (I64, 4, false),
+        OpCode::I32Store   => (I32, 4, true),
+        OpCode::I64Store   => (I64, 8, true),
+        OpCode::F32Store   => (F32, 4, true),
+        OpCode::F64Store   => (F64, 8, true),
+        OpCode::I32Store8  => (I32, 1, true),
+        OpCode::I32Store16 => (I32, 2, true),
+        OpCode::I64Store8  => (I64, 1, true),
+        OpCode::I64Store16 => (I64, 2, true),
+        OpCode::I64Store32 => (I64, 4, true),
+        _ => return None,
+    })
+}
+
+fn natural_align(opcode: OpCode) -> Option<u32> {
+    load_store_type(opcode).map(|(_, width, _)| width.trailing_zeros())
+}
+
+// `(then ..)` / `(else ..)` bodies are stored as flat instruction lists.
+fn flatten_branch(form: &SExpr, env: &ParseEnv) -> Result<Vec<Instruction>> {
+    let mut instrs = Vec::new();
+    for item in &list(form)?[1..] {
+        flatten_node(&node_from_sexpr(item, env)?, &mut instrs);
+    }
+    Ok(instrs)
+}
+
+pub(crate) fn flatten_node(node: &Node, instrs: &mut Vec<Instruction>) {
+    match node.instr.opcode {
+        OpCode::Block | OpCode::Loop => {
+            instrs.push(node.instr.clone());
+            for sub in &node.sub_instrs {
+                flatten_node(sub, instrs);
+            }
+            instrs.push(Instruction::new_none(OpCode::End_));
+        }
+        _ => {
+            for sub in &node.sub_instrs {
+                flatten_node(sub, instrs);
+            }
+            instrs.push(node.instr.clone());
+        }
+    }
+}
+
+fn block_type(rest: &mut &[SExpr]) -> Result<BlockType> {
+    let mut bt = BlockType { params: vec![], results: vec![] };
+    while let Some(item) = rest.first() {
+        match head(item) {
+            Some("param") => bt.params.extend(val_types(item)?),
+            Some("result") => bt.results.extend(val_types(item)?),
+            _ => break,
+        }
+        *rest = &rest[1..];
+    }
+    Ok(bt)
+}
+
+fn val_types(form: &SExpr) -> Result<Vec<ValType>> {
+    list(form)?[1..]
+        .iter()
+        .filter(|item| !matches!(item, SExpr::Atom(a) if a.starts_with('$')))
+        .map(|item| val_type(atom(item)?))
+        .collect()
+}
+
+fn val_type(name: &str) -> Result<ValType> {
+    Ok(match name {
+        "i32"       => ValType::I32,
+        "i64"       => ValType::I64,
+        "f32"       => ValType::F32,
+        "f64"       => ValType::F64,
+        "v128"      => ValType::V128,
+        "funcref"   => ValType::FuncRef,
+        "externref" => ValType::ExternRef,
+        _ => bail!("unknown value type `{}`", name),
+    })
+}
+
+fn parse_u32(text: &str) -> Result<u32> {
+    let text = text.replace('_', "");
+    let value = match text.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    value.map_err(|_| anyhow!("expected an unsigned integer, found `{}`", text))
+}
+
+// The bits of a `*.const` immediate. Integers may be written signed or
+// unsigned, floats in decimal or as `inf`, `nan` and `nan:0x..`.
+fn parse_const(opcode: OpCode, text: &str) -> Result<u64> {
+    let text = text.replace('_', "");
+    let (negative, magnitude) = match text.strip_prefix('-') {
+        Some(magnitude) => (true, magnitude),
+        None => (false, text.strip_prefix('+').unwrap_or(&text)),
+    };
+    let invalid = || anyhow!("invalid {} value `{}`", opcode.name(), text);
+    match opcode {
+        OpCode::I32Const | OpCode::I64Const => {
+            let width = if opcode == OpCode::I32Const { 32 } else { 64 };
+            let value = match magnitude.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => magnitude.parse(),
+            }
+            .map_err(|_| invalid())?;
+            let mask = u64::MAX >> (64 - width);
+            let limit = if negative { 1 << (width - 1) } else { mask };
+            if value > limit {
+                bail!("{} value `{}` is out of range", opcode.name(), text);
+            }
+            Ok(if negative { value.wrapping_neg() & mask } else { value })
+        }
+        _ => {
+            let is_f64 = opcode == OpCode::F64Const;
+            let (sign, infinity, mantissa): (u64, u64, u64) = if is_f64 {
+                (1 << 63, 0x7FF0_0000_0000_0000, (1 << 52) - 1)
+            } else {
+                (1 << 31, 0x7F80_0000, (1 << 23) - 1)
+            };
+            let bits = if magnitude == "inf" {
+                infinity
+            } else if magnitude == "nan" {
+                // the canonical NaN, only the top bit of the payload set
+                infinity | (mantissa + 1) >> 1
+            } else if let Some(payload) = magnitude.strip_prefix("nan:0x") {
+                let payload = u64::from_str_radix(payload, 16).map_err(|_| invalid())?;
+                if payload == 0 || payload > mantissa {
+                    return Err(invalid());
+                }
+                infinity | payload
+            } else if magnitude.starts_with("0x") {
+                bail!("hexadecimal float `{}` is not supported", text);
+            } else if is_f64 {
+                magnitude.parse::<f64>().map_err(|_| invalid())?.to_bits()
+            } else {
+                magnitude.parse::<f32>().map_err(|_| invalid())?.to_bits() as u64
+            };
+            Ok(if negative { bits | sign } else { bits })
+        }
+    }
+}
+
+fn head(form: &SExpr) -> Option<&str> {
+    match form {
+        SExpr::List(items) => match items.first() {
+            Some(SExpr::Atom(a)) => Some(a.as_str()),
+            _ => None,
+        },
+        SExpr::Atom(_) => None,
+    }
+}
+
+fn list(form: &SExpr) -> Result<&[SExpr]> {
+    match form {
+        SExpr::List(items) if !items.is_empty() => Ok(items),
+        _ => Err(anyhow!("expected a non-empty list, found {:?}", form)),
+    }
+}
+
+fn atom(form: &SExpr) -> Result<&str> {
+    match form {
+        SExpr::Atom(a) => Ok(a),
+        SExpr::List(_) => Err(anyhow!("expected an atom, found a list")),
+    }
+}
+
+fn read_sexprs(text: &str) -> Result<Vec<SExpr>> {
+    let mut stack: Vec<Vec<SExpr>> = vec![Vec::new()];
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            ';' if chars.peek() == Some(&';') => {
+                while chars.next().is_some_and(|c| c != '\n') {}
+            }
+            '(' if chars.peek() == Some(&';') => {
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == ';' && c == ')' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                let items = stack.pop().unwrap();
+                stack.last_mut().ok_or_else(|| anyhow!("unbalanced `)`"))?.push(SExpr::List(items));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+                stack.last_mut().unwrap().push(SExpr::Atom(token));
+            }
+        }
+    }
+    if stack.len() != 1 {
+        bail!("unbalanced `(`");
+    }
+    Ok(stack.pop().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::printer::print_node;
+    use common::printing::InstrStyle;
+
+    #[test]
+    fn test_parse_func_snippet() {
+        let node = parse_func("(func (param i32) (result i64) (i64.load offset=8 (local.get 0)))").unwrap();
+        assert_eq!(node.instr.opcode, OpCode::I64Load);
+        assert_eq!(node.instr_type, InstructionType { params: vec![], results: vec![ValType::I64] });
+        assert_eq!(node.sub_instrs[0].context.as_ref().unwrap().local_variable,
+                   Some(LocalVariableContext { local_variable_type: ValType::I32 }));
+        assert_eq!(print_node(&node, InstrStyle::Folded).unwrap(), "(i64.load offset=8 align=8\n  (local.get 0))\n");
+
+        // a missing operand becomes a param of the snippet
+        let node = parse_expr("(drop (i32.load))", &ParseEnv::default()).unwrap();
+        assert_eq!(node.instr_type.params, vec![ValType::I32]);
+        assert!(parse_expr("(drop (i32.load offset=4294967295))", &ParseEnv::default()).is_err());
+    }
+
+    #[test]
+    fn test_parse_numeric_snippet() {
+        let text = "(i64.store8 offset=2 (i32.wrap_i64) \
+                    (i64.extend_i32_u (i32.add (i32.const -1) (i32.trunc_f32_s (f32.const -nan:0x200001)))))";
+        let node = parse_expr(text, &ParseEnv::default()).unwrap();
+        assert_eq!(node.instr_type.params, vec![ValType::I64]);
+        assert_eq!(node.sub_instrs[1].sub_instrs[0].sub_instrs[0].instr.args, InstructionArgs::Const(0xFFFF_FFFF));
+        assert_eq!(node.context.as_ref().unwrap().memory, Some(MemoryContext { max: 3 }));
+
+        let printed = print_node(&node, InstrStyle::Folded).unwrap();
+        assert!(printed.contains("(f32.const -nan:0x200001)"), "{}", printed);
+        assert_eq!(parse_expr(&printed, &ParseEnv::default()).unwrap(), node);
+        wasmparser::validate(&crate::module_generator::encode_module(&node).unwrap()).unwrap();
+
+        assert!(parse_expr("(i32.const 4294967296)", &ParseEnv::default()).is_err());
+        assert_eq!(
+            parse_expr("(f64.const -0.5)", &ParseEnv::default()).unwrap().instr.args,
+            InstructionArgs::Const((-0.5f64).to_bits())
+        );
+
+        // prefixed instructions are typed from the opcode metadata
+        let node = parse_expr("(i32x4.extract_lane 3 (i32x4.splat (i32.trunc_sat_f32_s)))", &ParseEnv::default()).unwrap();
+        assert_eq!(node.instr_type, InstructionType { params: vec![ValType::F32], results: vec![ValType::I32] });
+        wasmparser::validate(&crate::module_generator::encode_module(&node).unwrap()).unwrap();
+    }
+}
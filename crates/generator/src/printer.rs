@@ -0,0 +1,458 @@
+
+use anyhow::{Result, bail};
+use common::metadata::Immediate;
+use common::opcodes::{OpCode, Prefix};
+use common::printing::InstrStyle;
+use wasmparser::{BinaryReader, Catch};
+use crate::AST::Node;
+use crate::instruction::{BlockType, Instruction, InstructionArgs, MemArg, ValType};
+
+
+const INDENT: &str = "  ";
+
+/// Render a snippet tree as WebAssembly text. Fails on immediates kept as
+/// raw bytes that do not decode as the immediates of their opcode.
+pub fn print_node(node: &Node, style: InstrStyle) -> Result<String> {
+    let mut out = String::new();
+    match style {
+        InstrStyle::Flat => write_flat(node, 0, &mut out)?,
+        InstrStyle::Folded => write_folded(node, 0, &mut out)?,
+    }
+    Ok(out)
+}
+
+/// Render a snippet as a function taking its `InstructionType` params and
+/// returning its results, so it can be pasted into a module and run.
+pub fn print_node_func(node: &Node, name: &str, style: InstrStyle) -> Result<String> {
+    let mut out = format!("(func ${} (export \"{}\")", name, name);
+    for param in &node.instr_type.params {
+        out.push_str(&format!(" (param {})", param.name()));
+    }
+    for result in &node.instr_type.results {
+        out.push_str(&format!(" (result {})", result.name()));
+    }
+    out.push('\n');
+    for index in 0..node.instr_type.params.len() {
+        push_line(&mut out, 1, &format!("local.get {}", index));
+    }
+    match style {
+        InstrStyle::Flat => write_flat(node, 1, &mut out)?,
+        InstrStyle::Folded => write_folded(node, 1, &mut out)?,
+    }
+    out.push_str(")\n");
+    Ok(out)
+}
+
+// Flat style: operands first, then the instruction itself.
+fn write_flat(node: &Node, depth: usize, out: &mut String) -> Result<()> {
+    let instr = &node.instr;
+    match &instr.args {
+        InstructionArgs::Block(bt) | InstructionArgs::Loop(bt) => {
+            push_line(out, depth, &format!("{}{}", instr.opcode.name(), block_type(bt)));
+            for sub in &node.sub_instrs {
+                write_flat(sub, depth + 1, out)?;
+            }
+            push_line(out, depth, "end");
+        }
+        InstructionArgs::If(if_args) => {
+            for sub in &node.sub_instrs {
+                write_flat(sub, depth, out)?;
+            }
+            push_line(out, depth, &format!("if{}", block_type(&if_args.bt)));
+            write_instrs_flat(&if_args.instrs1, depth + 1, out)?;
+            if !if_args.instrs2.is_empty() {
+                push_line(out, depth, "else");
+                write_instrs_flat(&if_args.instrs2, depth + 1, out)?;
+            }
+            push_line(out, depth, "end");
+        }
+        _ => {
+            for sub in &node.sub_instrs {
+                write_flat(sub, depth, out)?;
+            }
+            push_line(out, depth, &plain_instr(instr)?);
+        }
+    }
+    Ok(())
+}
+
+// Folded style: `(op immediates operand...)`.
+fn write_folded(node: &Node, depth: usize, out: &mut String) -> Result<()> {
+    let instr = &node.instr;
+    match &instr.args {
+        InstructionArgs::Block(bt) | InstructionArgs::Loop(bt) => {
+            push_line(out, depth, &format!("({}{}", instr.opcode.name(), block_type(bt)));
+            for sub in &node.sub_instrs {
+                write_folded(sub, depth + 1, out)?;
+            }
+            close(out);
+        }
+        InstructionArgs::If(if_args) => {
+            push_line(out, depth, &format!("(if{}", block_type(&if_args.bt)));
+            for sub in &node.sub_instrs {
+                write_folded(sub, depth + 1, out)?;
+            }
+            push_line(out, depth + 1, "(then");
+            write_instrs_flat(&if_args.instrs1, depth + 2, out)?;
+            close(out);
+            if !if_args.instrs2.is_empty() {
+                push_line(out, depth + 1, "(else");
+                write_instrs_flat(&if_args.instrs2, depth + 2, out)?;
+                close(out);
+            }
+            close(out);
+        }
+        _ if node.sub_instrs.is_empty() => {
+            push_line(out, depth, &format!("({})", plain_instr(instr)?));
+        }
+        _ => {
+            push_line(out, depth, &format!("({}", plain_instr(instr)?));
+            for sub in &node.sub_instrs {
+                write_folded(sub, depth + 1, out)?;
+            }
+            close(out);
+        }
+    }
+    Ok(())
+}
+
+// The branches of an `if` are kept as plain instruction lists, nested
+// blocks inside them have no body of their own to print.
+fn write_instrs_flat(instrs: &[Instruction], depth: usize, out: &mut String) -> Result<()> {
+    for instr in instrs {
+        match &instr.args {
+            InstructionArgs::Block(bt) | InstructionArgs::Loop(bt) => {
+                push_line(out, depth, &format!("{}{}", instr.opcode.name(), block_type(bt)));
+            }
+            InstructionArgs::If(if_args) => {
+                push_line(out, depth, &format!("if{}", block_type(&if_args.bt)));
+                write_instrs_flat(&if_args.instrs1, depth + 1, out)?;
+                if !if_args.instrs2.is_empty() {
+                    push_line(out, depth, "else");
+                    write_instrs_flat(&if_args.instrs2, depth + 1, out)?;
+                }
+                push_line(out, depth, "end");
+            }
+            _ => push_line(out, depth, &plain_instr(instr)?),
+        }
+    }
+    Ok(())
+}
+
+fn plain_instr(instr: &Instruction) -> Result<String> {
+    let name = instr.opcode.name();
+    let text = match &instr.args {
+        InstructionArgs::Simple(index) if instr.opcode == OpCode::CallIndirect => {
+            format!("{} (type {})", name, index)
+        }
+        InstructionArgs::Simple(code) if instr.opcode == OpCode::RefNull => {
+            let heap_type = if *code == ValType::ExternRef as u32 { "extern" } else { "func" };
+            format!("{} {}", name, heap_type)
+        }
+        InstructionArgs::Simple(arg) if takes_immediate(instr.opcode) => {
+            format!("{} {}", name, arg)
+        }
+        InstructionArgs::Mem(memarg) => format!("{}{}", name, mem_arg(memarg)),
+        InstructionArgs::Const(bits) => format!("{} {}", name, const_value(instr.opcode, *bits)),
+        InstructionArgs::Raw(immediates) => return raw_instr(instr.opcode, immediates),
+        InstructionArgs::BrTable(br_table) => {
+            let mut text = name;
+            for label in &br_table.labels {
+                text.push_str(&format!(" {}", label));
+            }
+            text.push_str(&format!(" {}", br_table.default_label));
+            text
+        }
+        _ => name,
+    };
+    Ok(text)
+}
+
+// Opcodes without immediates store a placeholder in `Simple`. Unknown
+// opcodes keep whatever they were given.
+fn takes_immediate(opcode: OpCode) -> bool {
+    opcode.info().is_none_or(|info| !info.immediates.is_empty())
+}
+
+// Immediates kept as bytes, read back with the immediates `metadata` lists
+// for the opcode and printed in their text form.
+fn raw_instr(opcode: OpCode, bytes: &[u8]) -> Result<String> {
+    let Some(info) = opcode.info() else {
+        bail!("no immediates are known for {}", opcode.name());
+    };
+    let mut name = if opcode == OpCode::SelectT { "select".to_string() } else { opcode.name() };
+    // nullability of the reference types of a cast, in the order of the heap types
+    let mut nullable = match opcode {
+        OpCode::Prefixed(Prefix::Gc, 0x14 | 0x16) => vec![false],
+        OpCode::Prefixed(Prefix::Gc, sub @ (0x15 | 0x17)) => {
+            name = if sub == 0x15 { "ref.test" } else { "ref.cast" }.to_string();
+            vec![true]
+        }
+        _ => vec![],
+    }
+    .into_iter();
+    let mut reader = BinaryReader::new(bytes, 0);
+    let mut text = name;
+    for immediate in info.immediates {
+        match immediate {
+            Immediate::LabelIndex
+            | Immediate::FuncIndex
+            | Immediate::TypeIndex
+            | Immediate::TagIndex
+            | Immediate::TableIndex
+            | Immediate::LocalIndex
+            | Immediate::GlobalIndex
+            | Immediate::MemoryIndex
+            | Immediate::DataIndex
+            | Immediate::ElemIndex
+            | Immediate::FieldIndex
+            | Immediate::Count => text.push_str(&format!(" {}", reader.read_var_u32()?)),
+            Immediate::Lane => text.push_str(&format!(" {}", reader.read_u8()?)),
+            Immediate::MemArg => {
+                let align = reader.read_var_u32()?;
+                // bit 6 of the alignment announces an explicit memory index
+                if align & 0x40 != 0 {
+                    text.push_str(&format!(" {}", reader.read_var_u32()?));
+                }
+                let offset = reader.read_var_u64()?;
+                if align & !0x40 >= 64 {
+                    bail!("{} has alignment 2^{}", opcode.name(), align & !0x40);
+                }
+                text.push_str(&mem_arg_text(align & !0x40, offset));
+            }
+            Immediate::ConstI32 => text.push_str(&format!(" {}", reader.read_var_i32()?)),
+            Immediate::ConstI64 => text.push_str(&format!(" {}", reader.read_var_i64()?)),
+            Immediate::ConstF32 => {
+                let bits = reader.read_f32()?.bits() as u64;
+                text.push_str(&format!(" {}", const_value(OpCode::F32Const, bits)));
+            }
+            Immediate::ConstF64 => {
+                let bits = reader.read_f64()?.bits();
+                text.push_str(&format!(" {}", const_value(OpCode::F64Const, bits)));
+            }
+            Immediate::ConstV128 => {
+                text.push_str(" i32x4");
+                for _ in 0..4 {
+                    let lane = reader.read_bytes(4)?;
+                    text.push_str(&format!(" 0x{:08x}", u32::from_le_bytes([lane[0], lane[1], lane[2], lane[3]])));
+                }
+            }
+            Immediate::Shuffle => {
+                for lane in reader.read_bytes(16)? {
+                    text.push_str(&format!(" {}", lane));
+                }
+            }
+            Immediate::HeapType => {
+                let heap_type = heap_type(reader.read_var_s33()?)?;
+                match nullable.next() {
+                    Some(true) => text.push_str(&format!(" (ref null {})", heap_type)),
+                    Some(false) => text.push_str(&format!(" (ref {})", heap_type)),
+                    None => text.push_str(&format!(" {}", heap_type)),
+                }
+            }
+            Immediate::ValTypes => {
+                text.push_str(" (result");
+                for _ in 0..reader.read_var_u32()? {
+                    text.push_str(&format!(" {}", val_type(&mut reader)?));
+                }
+                text.push(')');
+            }
+            // bit 0 and 1 make the source and the target type nullable
+            Immediate::CastFlags => {
+                let flags = reader.read_u8()?;
+                nullable = vec![flags & 1 != 0, flags & 2 != 0].into_iter();
+            }
+            Immediate::Reserved => {
+                reader.read_u8()?;
+            }
+            // no results, a value type or a type index as a positive s33
+            Immediate::BlockType if opcode == OpCode::TryTable => match bytes.get(reader.current_position()) {
+                Some(0x40) => {
+                    reader.read_u8()?;
+                }
+                Some(code) if code & 0xC0 == 0x40 => text.push_str(&format!(" (result {})", val_type(&mut reader)?)),
+                _ => text.push_str(&format!(" (type {})", reader.read_var_s33()?)),
+            },
+            Immediate::CatchTable => {
+                for _ in 0..reader.read_var_u32()? {
+                    let clause = match reader.read::<Catch>()? {
+                        Catch::One { tag, label } => format!("catch {} {}", tag, label),
+                        Catch::OneRef { tag, label } => format!("catch_ref {} {}", tag, label),
+                        Catch::All { label } => format!("catch_all {}", label),
+                        Catch::AllRef { label } => format!("catch_all_ref {}", label),
+                    };
+                    text.push_str(&format!(" ({})", clause));
+                }
+            }
+            Immediate::BlockType | Immediate::LabelTable => {
+                bail!("{} keeps its {:?} apart from raw immediates", opcode.name(), immediate);
+            }
+        }
+    }
+    if !reader.eof() {
+        bail!("{} has {} bytes of immediates too many", opcode.name(), reader.bytes_remaining());
+    }
+    Ok(text)
+}
+
+// A heap type as encoded in a signed LEB128, negative for the abstract ones.
+fn heap_type(code: i64) -> Result<String> {
+    if code >= 0 {
+        return Ok(code.to_string());
+    }
+    let name = match code & 0x7F {
+        0x70 => "func",
+        0x6F => "extern",
+        0x6E => "any",
+        0x6D => "eq",
+        0x6C => "i31",
+        0x6B => "struct",
+        0x6A => "array",
+        0x69 => "exn",
+        0x73 => "nofunc",
+        0x72 => "noextern",
+        0x71 => "none",
+        0x74 => "noexn",
+        _ => bail!("unknown heap type 0x{:02x}", code & 0x7F),
+    };
+    Ok(name.to_string())
+}
+
+fn val_type(reader: &mut BinaryReader) -> Result<String> {
+    let text = match reader.read_u8()? {
+        0x7F => "i32".to_string(),
+        0x7E => "i64".to_string(),
+        0x7D => "f32".to_string(),
+        0x7C => "f64".to_string(),
+        0x7B => "v128".to_string(),
+        0x64 => format!("(ref {})", heap_type(reader.read_var_s33()?)?),
+        0x63 => format!("(ref null {})", heap_type(reader.read_var_s33()?)?),
+        // the short forms of nullable abstract references, e.g. `funcref`
+        code => format!("{}ref", heap_type(code as i64 - 0x80)?),
+    };
+    Ok(text)
+}
+
+fn block_type(bt: &BlockType) -> String {
+    let mut text = String::new();
+    for param in &bt.params {
+        text.push_str(&format!(" (param {})", param.name()));
+    }
+    for result in &bt.results {
+        text.push_str(&format!(" (result {})", result.name()));
+    }
+    text
+}
+
+// Floats are printed so that they read back to the same bits, NaNs with
+// their payload.
+fn const_value(opcode: OpCode, bits: u64) -> String {
+    match opcode {
+        OpCode::I32Const => (bits as u32 as i32).to_string(),
+        OpCode::I64Const => (bits as i64).to_string(),
+        OpCode::F32Const => {
+            let value = f32::from_bits(bits as u32);
+            float_value(value.is_nan(), value.is_sign_negative(), bits & 0x7F_FFFF, format!("{:?}", value))
+        }
+        _ => {
+            let value = f64::from_bits(bits);
+            float_value(value.is_nan(), value.is_sign_negative(), bits & 0xF_FFFF_FFFF_FFFF, format!("{:?}", value))
+        }
+    }
+}
+
+fn float_value(is_nan: bool, negative: bool, payload: u64, text: String) -> String {
+    if !is_nan {
+        return text;
+    }
+    let sign = if negative { "-" } else { "" };
+    format!("{}nan:0x{:x}", sign, payload)
+}
+
+// `align` is stored as log2 like in the binary format, the text format wants bytes.
+fn mem_arg(memarg: &MemArg) -> String {
+    mem_arg_text(memarg.align, memarg.offset as u64)
+}
+
+fn mem_arg_text(align: u32, offset: u64) -> String {
+    let mut text = String::new();
+    if offset != 0 {
+        text.push_str(&format!(" offset={}", offset));
+    }
+    text.push_str(&format!(" align={}", 1u64 << align));
+    text
+}
+
+fn push_line(out: &mut String, depth: usize, text: &str) {
+    for _ in 0..depth {
+        out.push_str(INDENT);
+    }
+    out.push_str(text);
+    out.push('\n');
+}
+
+fn close(out: &mut String) {
+    if out.ends_with('\n') {
+        out.pop();
+    }
+    out.push_str(")\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AST::InstructionType;
+    use crate::instruction::ValType;
+
+    #[test]
+    fn test_print_node_styles() {
+        let i32_type = InstructionType { params: vec![], results: vec![ValType::I32] };
+        let mut tee = Node::new(Instruction::new_simple(OpCode::LocalTee, 1), i32_type.clone(), None);
+        tee.sub_instrs.push(Node::new(Instruction::new_simple(OpCode::LocalGet, 0), i32_type.clone(), None));
+        let mut load = Node::new(
+            Instruction::new_mem(OpCode::I32Load, MemArg { align: 2, offset: 8 }),
+            i32_type,
+            None,
+        );
+        load.sub_instrs.push(tee);
+
+        assert_eq!(
+            print_node(&load, InstrStyle::Flat).unwrap(),
+            "local.get 0\nlocal.tee 1\ni32.load offset=8 align=4\n"
+        );
+        assert_eq!(
+            print_node(&load, InstrStyle::Folded).unwrap(),
+            "(i32.load offset=8 align=4\n  (local.tee 1\n    (local.get 0)))\n"
+        );
+    }
+
+    #[test]
+    fn test_print_raw_immediates() {
+        let print = |name: &str, bytes: &[u8]| {
+            plain_instr(&Instruction::new(OpCode::from_name(name).unwrap(), InstructionArgs::Raw(bytes.to_vec())))
+        };
+        assert_eq!(print("memory.copy", &[0, 1]).unwrap(), "memory.copy 0 1");
+        assert_eq!(print("i32x4.extract_lane", &[2]).unwrap(), "i32x4.extract_lane 2");
+        assert_eq!(print("v128.load", &[0x44, 1, 16]).unwrap(), "v128.load 1 offset=16 align=16");
+        assert_eq!(print("v128.load8_lane", &[0, 0, 15]).unwrap(), "v128.load8_lane align=1 15");
+        let mut v128 = vec![0xFF; 4];
+        v128.extend([1, 0, 0, 0, 0, 0, 0, 0x80, 0, 0, 0, 0]);
+        assert_eq!(print("v128.const", &v128).unwrap(), "v128.const i32x4 0xffffffff 0x00000001 0x80000000 0x00000000");
+        assert_eq!(print("select", &[]).unwrap(), "select");
+        assert_eq!(print("ref.cast_null", &[0x6E]).unwrap(), "ref.cast (ref null any)");
+        assert_eq!(print("ref.test", &[3]).unwrap(), "ref.test (ref 3)");
+        assert_eq!(print("br_on_cast", &[1, 0, 0x6E, 0x6C]).unwrap(), "br_on_cast 0 (ref null any) (ref i31)");
+        let select = Instruction::new(OpCode::SelectT, InstructionArgs::Raw(vec![2, 0x7F, 0x70]));
+        assert_eq!(plain_instr(&select).unwrap(), "select (result i32 funcref)");
+        assert_eq!(print("throw", &[1]).unwrap(), "throw 1");
+        assert_eq!(print("try_table", &[0x40, 0]).unwrap(), "try_table");
+        assert_eq!(
+            print("try_table", &[0x7F, 2, 0, 3, 0, 2, 1]).unwrap(),
+            "try_table (result i32) (catch 3 0) (catch_all 1)"
+        );
+        assert_eq!(print("try_table", &[5, 1, 3, 0]).unwrap(), "try_table (type 5) (catch_all_ref 0)");
+
+        assert!(print("i32x4.extract_lane", &[2, 3]).is_err());
+        assert!(print("memory.copy", &[0]).is_err());
+    }
+}
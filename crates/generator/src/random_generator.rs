@@ -0,0 +1,575 @@
+use std::collections::{HashMap, HashSet};
+use anyhow::{Result, bail};
+use common::metadata::{Immediate, OPCODES, StackSignature, ValKind};
+use common::opcodes::OpCode;
+use crate::AST::{
+    Context, FunctionTypeContext, GlobalVariableContext, InstructionType, LocalVariableContext,
+    MemoryContext, Node,
+};
+use crate::composer::Composition;
+use crate::instruction::{BlockType, IfArgs, Instruction, MemArg, ValType};
+use crate::parser::{flatten_node, load_store_type};
+use crate::rng::{Choices, Rng};
+
+
+/// Opcodes the random generator knows how to place besides the ones of
+/// `numeric_opcodes`.
+pub const GENERATED_OPCODES: &[OpCode] = &[
+    OpCode::Nop, OpCode::Block, OpCode::Loop, OpCode::If, OpCode::Call, OpCode::CallIndirect,
+    OpCode::Drop, OpCode::Select, OpCode::SelectT, OpCode::LocalGet, OpCode::LocalSet,
+    OpCode::LocalTee, OpCode::GlobalGet, OpCode::GlobalSet, OpCode::TableGet, OpCode::TableSet,
+    OpCode::I32Load, OpCode::I64Load, OpCode::F32Load, OpCode::F64Load,
+    OpCode::I32Load8S, OpCode::I32Load8U, OpCode::I32Load16S, OpCode::I32Load16U,
+    OpCode::I64Load8S, OpCode::I64Load8U, OpCode::I64Load16S, OpCode::I64Load16U,
+    OpCode::I64Load32S, OpCode::I64Load32U, OpCode::I32Store,
+];
+
+const NUMERIC: [ValType; 4] = [ValType::I32, ValType::I64, ValType::F32, ValType::F64];
+const OFFSETS: [u32; 6] = [0, 1, 2, 4, 8, 16];
+// constants besides small integers, as the bits `InstructionArgs::Const` holds
+const I32_EDGES: &[u64] = &[0x8000_0000, 0x7FFF_FFFF, 0xFFFF_FFFF, 0xFF, 0xFFFF];
+const I64_EDGES: &[u64] = &[1 << 63, u64::MAX >> 1, u64::MAX, 0xFFFF_FFFF, 1 << 32];
+const F32_EDGES: &[u64] = &[0x8000_0000, 0x7F80_0000, 0xFF80_0000, 0x7FC0_0000, 0x0000_0001, 0x7F7F_FFFF, 0x4F00_0000];
+const F64_EDGES: &[u64] = &[
+    1 << 63, 0x7FF0_0000_0000_0000, 0xFFF0_0000_0000_0000, 0x7FF8_0000_0000_0000,
+    0x0000_0000_0000_0001, 0x7FEF_FFFF_FFFF_FFFF, 0x41E0_0000_0000_0000,
+];
+
+/// The constants and the numeric operators of `metadata::OPCODES`: every
+/// opcode taking numbers to one number whose only immediate, if any, is its
+/// constant. In opcode order.
+pub fn numeric_opcodes() -> Vec<OpCode> {
+    OPCODES.iter().map(|info| info.opcode).filter(|&op| numeric_signature(op).is_some()).collect()
+}
+
+// Params and result of a numeric opcode.
+fn numeric_signature(op: OpCode) -> Option<(&'static [ValKind], ValKind)> {
+    let info = op.info()?;
+    let StackSignature::Fixed { params, results: &[result] } = info.signature else {
+        return None;
+    };
+    let numeric = |kind: &ValKind| matches!(kind, ValKind::I32 | ValKind::I64 | ValKind::F32 | ValKind::F64);
+    let immediates = matches!(
+        info.immediates,
+        [] | [Immediate::ConstI32 | Immediate::ConstI64 | Immediate::ConstF32 | Immediate::ConstF64]
+    );
+    (immediates && numeric(&result) && params.iter().all(numeric)).then_some((params, result))
+}
+
+/// Limits and opcode weights for the random generator.
+#[derive(Debug, Clone)]
+pub struct RandomConfig {
+    pub seed: u64,
+    // nesting depth of instructions with operands or bodies
+    pub max_depth: usize,
+    // total number of nodes, the instructions in the branches of an `if`
+    // count as nodes too
+    pub max_size: usize,
+    // statements placed before the value of a block body
+    pub max_statements: usize,
+    // opcodes missing from the map are never generated
+    pub weights: HashMap<OpCode, u32>,
+}
+
+impl RandomConfig {
+    pub fn with_weight(mut self, opcode: OpCode, weight: u32) -> Self {
+        self.weights.insert(opcode, weight);
+        self
+    }
+
+    fn weight(&self, opcode: OpCode) -> u32 {
+        self.weights.get(&opcode).copied().unwrap_or(0)
+    }
+}
+
+impl Default for RandomConfig {
+    fn default() -> Self {
+        RandomConfig {
+            seed: 0,
+            max_depth: 5,
+            max_size: 64,
+            max_statements: 2,
+            weights: GENERATED_OPCODES.iter().copied().chain(numeric_opcodes()).map(|opcode| (opcode, 1)).collect(),
+        }
+    }
+}
+
+/// Builds random well-typed trees without a corpus, following the typing
+/// rules of each opcode. Every local, global, function, type and memory the
+/// tree refers to is described by the context of some node, so the result
+/// can go straight to `module_generator::encode_module`.
+pub struct RandomGenerator {
+    config: RandomConfig,
+    numeric: Vec<OpCode>,
+}
+
+impl RandomGenerator {
+    pub fn new(config: RandomConfig) -> Self {
+        RandomGenerator { config, numeric: numeric_opcodes() }
+    }
+
+    /// Generate a body for a function of type `params -> results`. The same
+    /// config always gives the same tree.
+    pub fn generate(&self, params: &[ValType], results: &[ValType]) -> Result<Composition> {
+        self.generate_with(&mut Rng::new(self.config.seed), params, results)
+    }
+
+    /// Like `generate`, taking every decision from `choices` instead of the
+    /// seed of the config.
+    pub fn generate_with(&self, choices: &mut impl Choices, params: &[ValType], results: &[ValType]) -> Result<Composition> {
+        if results.len() + 1 > self.config.max_size {
+            bail!("max_size {} is too small for {} results", self.config.max_size, results.len());
+        }
+        let mut state = State {
+            config: &self.config,
+            rng: choices,
+            slots: params.to_vec(),
+            seen_slots: HashSet::new(),
+            globals: Vec::new(),
+            funcs: Vec::new(),
+            types: Vec::new(),
+            memory_max: None,
+            table: false,
+            size: 1,
+            numeric: &self.numeric,
+        };
+        let body = state.body(results, 0, 0, false);
+        let bt = BlockType { params: vec![], results: results.to_vec() };
+        let body = block_node(OpCode::Block, bt, body);
+        Ok(Composition { body, params: params.to_vec(), locals: state.slots[params.len()..].to_vec() })
+    }
+}
+
+struct State<'a, C> {
+    config: &'a RandomConfig,
+    rng: &'a mut C,
+    // params followed by the locals declared so far
+    slots: Vec<ValType>,
+    // locals referred to by a node with a context, the only ones the flat
+    // branches of an `if` may use since their instructions have no context
+    seen_slots: HashSet<u32>,
+    globals: Vec<ValType>,
+    funcs: Vec<FunctionTypeContext>,
+    types: Vec<FunctionTypeContext>,
+    memory_max: Option<u32>,
+    table: bool,
+    size: usize,
+    numeric: &'a [OpCode],
+}
+
+impl<C: Choices> State<'_, C> {
+    // Statements followed by one value for each of `results`.
+    fn body(&mut self, results: &[ValType], depth: usize, reserve: usize, flat: bool) -> Vec<Node> {
+        let mut body = Vec::new();
+        for _ in 0..self.config.max_statements {
+            if self.rng.below(2) == 0 {
+                continue;
+            }
+            if let Some(statement) = self.statement(depth, reserve + results.len(), flat) {
+                body.push(statement);
+            }
+        }
+        for (i, ty) in results.iter().enumerate() {
+            body.push(self.value(*ty, depth, reserve + results.len() - i - 1, flat));
+        }
+        body
+    }
+
+    // Room for an instruction with `operands` operands, one node each.
+    fn fits(&self, depth: usize, operands: usize, reserve: usize) -> bool {
+        depth < self.config.max_depth && self.size + 1 + operands + reserve <= self.config.max_size
+    }
+
+    fn pick(&mut self, candidates: &[OpCode]) -> Option<OpCode> {
+        let weights: Vec<u32> = candidates.iter().map(|&op| self.config.weight(op)).collect();
+        self.rng.weighted(&weights).map(|i| candidates[i])
+    }
+
+    // A tree leaving one `ty`. `reserve` nodes are kept for what comes after;
+    // a leaf is always possible since one node is reserved for it.
+    fn value(&mut self, ty: ValType, depth: usize, reserve: usize, flat: bool) -> Node {
+        let numeric = self.numeric;
+        let mut candidates = vec![OpCode::LocalGet, OpCode::GlobalGet];
+        let mut push = |op, operands| {
+            if self.fits(depth, operands, reserve) {
+                candidates.push(op);
+            }
+        };
+        push(OpCode::LocalTee, 1);
+        push(OpCode::Block, 1);
+        push(OpCode::Loop, 1);
+        push(OpCode::If, 3);
+        push(OpCode::SelectT, 3);
+        push(OpCode::Call, 1);
+        push(OpCode::CallIndirect, 1);
+        if NUMERIC.contains(&ty) {
+            push(OpCode::Select, 3);
+        }
+        if ty == ValType::FuncRef {
+            push(OpCode::TableGet, 1);
+        }
+        for &op in GENERATED_OPCODES {
+            if matches!(load_store_type(op), Some((load_ty, _, false)) if load_ty == ty) {
+                push(op, 1);
+            }
+        }
+        // constants are leaves
+        let mut constants = Vec::new();
+        for &op in numeric {
+            match numeric_signature(op) {
+                Some((&[], result)) if ValType::from(result) == ty => constants.push(op),
+                Some((params, result)) if ValType::from(result) == ty => push(op, params.len()),
+                _ => {}
+            }
+        }
+        candidates.extend(constants);
+        candidates.retain(|&op| self.possible(op, ty, flat));
+
+        self.size += 1;
+        let Some(op) = self.pick(&candidates) else {
+            return self.leaf(ty, flat);
+        };
+        let results = vec![ty];
+        match op {
+            OpCode::LocalGet => self.leaf(ty, flat),
+            OpCode::GlobalGet => {
+                let index = self.global(ty, flat);
+                let context = global_context(ty);
+                node(Instruction::new_simple(op, index), vec![], results, Some(context), vec![])
+            }
+            OpCode::LocalTee => {
+                let index = self.local(ty, flat);
+                let operand = self.value(ty, depth + 1, reserve, flat);
+                node(Instruction::new_simple(op, index), vec![], results, Some(local_context(ty)), vec![operand])
+            }
+            OpCode::Block | OpCode::Loop => {
+                let body = self.body(&results, depth + 1, reserve, flat);
+                block_node(op, BlockType { params: vec![], results }, body)
+            }
+            OpCode::If => {
+                let condition = self.value(ValType::I32, depth + 1, reserve + 2, flat);
+                let then = self.branch(ty, depth + 1, reserve + 1);
+                let otherwise = self.branch(ty, depth + 1, reserve);
+                let if_args = IfArgs { bt: BlockType { params: vec![], results: results.clone() }, instrs1: then, instrs2: otherwise };
+                node(Instruction::new_if(op, if_args), vec![], results, None, vec![condition])
+            }
+            OpCode::Select | OpCode::SelectT => {
+                let operands = vec![
+                    self.value(ty, depth + 1, reserve + 2, flat),
+                    self.value(ty, depth + 1, reserve + 1, flat),
+                    self.value(ValType::I32, depth + 1, reserve, flat),
+                ];
+                node(Instruction::new_none(op), vec![], results, None, operands)
+            }
+            OpCode::Call | OpCode::CallIndirect => self.call(op, results, depth, reserve, flat),
+            OpCode::TableGet => {
+                self.table = true;
+                let operand = self.value(ValType::I32, depth + 1, reserve, flat);
+                node(Instruction::new_simple(op, 0), vec![], results, None, vec![operand])
+            }
+            OpCode::I32Const | OpCode::I64Const | OpCode::F32Const | OpCode::F64Const => {
+                let bits = self.constant(ty);
+                node(Instruction::new_const(op, bits), vec![], results, None, vec![])
+            }
+            _ if load_store_type(op).is_none() => {
+                let (params, _) = numeric_signature(op).expect("numeric opcode");
+                let count = params.len();
+                let operands = params
+                    .iter()
+                    .enumerate()
+                    .map(|(i, kind)| self.value(ValType::from(*kind), depth + 1, reserve + count - i - 1, flat))
+                    .collect();
+                node(Instruction::new_none(op), vec![], results, None, operands)
+            }
+            _ => {
+                let (memarg, context) = self.mem_arg(op, flat);
+                let address = self.value(ValType::I32, depth + 1, reserve, flat);
+                node(Instruction::new_mem(op, memarg), vec![], results, Some(context), vec![address])
+            }
+        }
+    }
+
+    // A tree leaving nothing on the stack, `None` if nothing fits.
+    fn statement(&mut self, depth: usize, reserve: usize, flat: bool) -> Option<Node> {
+        let mut candidates = vec![OpCode::Nop];
+        let mut push = |op, operands| {
+            if self.fits(depth, operands, reserve) {
+                candidates.push(op);
+            }
+        };
+        push(OpCode::Drop, 1);
+        push(OpCode::LocalSet, 1);
+        push(OpCode::GlobalSet, 1);
+        push(OpCode::Call, 0);
+        push(OpCode::I32Store, 2);
+        push(OpCode::TableSet, 2);
+        let ty = NUMERIC[self.rng.below(NUMERIC.len())];
+        candidates.retain(|&op| self.possible(op, ty, flat));
+        if self.size + 1 + reserve > self.config.max_size {
+            return None;
+        }
+
+        let op = self.pick(&candidates)?;
+        self.size += 1;
+        Some(match op {
+            OpCode::Drop => {
+                let operand = self.value(ty, depth + 1, reserve, flat);
+                node(Instruction::new_none(op), vec![], vec![], None, vec![operand])
+            }
+            OpCode::LocalSet => {
+                let index = self.local(ty, flat);
+                let operand = self.value(ty, depth + 1, reserve, flat);
+                node(Instruction::new_simple(op, index), vec![], vec![], Some(local_context(ty)), vec![operand])
+            }
+            OpCode::GlobalSet => {
+                let index = self.global(ty, flat);
+                let operand = self.value(ty, depth + 1, reserve, flat);
+                node(Instruction::new_simple(op, index), vec![], vec![], Some(global_context(ty)), vec![operand])
+            }
+            OpCode::Call => self.call(op, vec![], depth, reserve, flat),
+            OpCode::I32Store => {
+                let (memarg, context) = self.mem_arg(op, flat);
+                let operands = vec![
+                    self.value(ValType::I32, depth + 1, reserve + 1, flat),
+                    self.value(ValType::I32, depth + 1, reserve, flat),
+                ];
+                node(Instruction::new_mem(op, memarg), vec![], vec![], Some(context), operands)
+            }
+            OpCode::TableSet => {
+                self.table = true;
+                let operands = vec![
+                    self.value(ValType::I32, depth + 1, reserve + 1, flat),
+                    self.value(ValType::FuncRef, depth + 1, reserve, flat),
+                ];
+                node(Instruction::new_simple(op, 0), vec![], vec![], None, operands)
+            }
+            _ => node(Instruction::new_none(op), vec![], vec![], None, vec![]),
+        })
+    }
+
+    // Whether `op` can be generated at all. Inside the flat branches of an
+    // `if` nothing new may be declared, only what other nodes already use.
+    fn possible(&self, op: OpCode, ty: ValType, flat: bool) -> bool {
+        if !flat {
+            return true;
+        }
+        match op {
+            OpCode::LocalGet | OpCode::LocalTee | OpCode::LocalSet => self.seen_local(ty),
+            OpCode::GlobalGet | OpCode::GlobalSet => self.globals.contains(&ty),
+            OpCode::Call => !self.funcs.is_empty(),
+            OpCode::CallIndirect => self.table && !self.types.is_empty(),
+            OpCode::TableGet | OpCode::TableSet => self.table,
+            // encoded with the result type of the enclosing node
+            OpCode::SelectT => false,
+            _ => match load_store_type(op) {
+                Some((_, width, _)) => self.memory_max.is_some_and(|max| max >= width),
+                None => true,
+            },
+        }
+    }
+
+    // A small integer half of the time, else an edge of the type.
+    fn constant(&mut self, ty: ValType) -> u64 {
+        let number = self.rng.below(17) as i64 - 8;
+        let small = self.rng.below(2) == 0;
+        let edges = match ty {
+            ValType::I32 => I32_EDGES,
+            ValType::I64 => I64_EDGES,
+            ValType::F32 => F32_EDGES,
+            _ => F64_EDGES,
+        };
+        match ty {
+            _ if !small => edges[self.rng.below(edges.len())],
+            ValType::I32 => number as i32 as u32 as u64,
+            ValType::I64 => number as u64,
+            ValType::F32 => (number as f32).to_bits() as u64,
+            _ => (number as f64).to_bits(),
+        }
+    }
+
+    fn seen_local(&self, ty: ValType) -> bool {
+        self.seen_slots.iter().any(|&i| self.slots[i as usize] == ty)
+    }
+
+    // `local.get` of a param or local of type `ty`, `unreachable` inside a
+    // branch that has none to use.
+    fn leaf(&mut self, ty: ValType, flat: bool) -> Node {
+        if flat && !self.seen_local(ty) {
+            return node(Instruction::new_none(OpCode::Unreachable), vec![], vec![ty], None, vec![]);
+        }
+        let index = self.local(ty, flat);
+        node(Instruction::new_simple(OpCode::LocalGet, index), vec![], vec![ty], Some(local_context(ty)), vec![])
+    }
+
+    fn local(&mut self, ty: ValType, flat: bool) -> u32 {
+        let candidates: Vec<u32> = (0..self.slots.len() as u32)
+            .filter(|&i| self.slots[i as usize] == ty && (!flat || self.seen_slots.contains(&i)))
+            .collect();
+        // a new local now and then even when one of the type exists
+        let index = if !flat && (candidates.is_empty() || self.rng.below(candidates.len() + 1) == 0) {
+            self.slots.push(ty);
+            self.slots.len() as u32 - 1
+        } else {
+            candidates[self.rng.below(candidates.len())]
+        };
+        if !flat {
+            self.seen_slots.insert(index);
+        }
+        index
+    }
+
+    fn global(&mut self, ty: ValType, flat: bool) -> u32 {
+        let candidates: Vec<u32> = (0..self.globals.len() as u32).filter(|&i| self.globals[i as usize] == ty).collect();
+        if !flat && (candidates.is_empty() || self.rng.below(candidates.len() + 1) == 0) {
+            self.globals.push(ty);
+            return self.globals.len() as u32 - 1;
+        }
+        candidates[self.rng.below(candidates.len())]
+    }
+
+    // Calls get a new signature with up to two params, inside a branch they
+    // reuse one with the right results or fall back to a leaf.
+    fn call(&mut self, op: OpCode, results: Vec<ValType>, depth: usize, reserve: usize, flat: bool) -> Node {
+        let indirect = op == OpCode::CallIndirect;
+        // every operand needs at least one node
+        let budget = self.config.max_size.saturating_sub(self.size + reserve + usize::from(indirect));
+        let existing = if indirect { &self.types } else { &self.funcs };
+        let reusable: Vec<u32> = (0..existing.len() as u32)
+            .filter(|&i| existing[i as usize].result_types == results && existing[i as usize].param_types.len() <= budget)
+            .collect();
+        let index = if flat || (!reusable.is_empty() && self.rng.below(2) == 0) {
+            match reusable.first() {
+                Some(_) => reusable[self.rng.below(reusable.len())],
+                None => return self.leaf_or_nop(results, flat),
+            }
+        } else {
+            let count = self.rng.below(3).min(budget);
+            let params = (0..count).map(|_| NUMERIC[self.rng.below(NUMERIC.len())]).collect();
+            let functype = FunctionTypeContext { param_types: params, result_types: results.clone() };
+            let list = if indirect { &mut self.types } else { &mut self.funcs };
+            list.push(functype);
+            list.len() as u32 - 1
+        };
+        let functype = if indirect { &self.types } else { &self.funcs }[index as usize].clone();
+        let mut operand_types = functype.param_types.clone();
+        if indirect {
+            self.table = true;
+            operand_types.push(ValType::I32);
+        }
+        let count = operand_types.len();
+        let operands = operand_types
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| self.value(*ty, depth + 1, reserve + count - i - 1, flat))
+            .collect();
+        let context = Context { local_variable: None, functype: Some(functype), global_variable: None, memory: None };
+        node(Instruction::new_simple(op, index), vec![], results, Some(context), operands)
+    }
+
+    fn leaf_or_nop(&mut self, results: Vec<ValType>, flat: bool) -> Node {
+        match results.first() {
+            Some(ty) => self.leaf(*ty, flat),
+            None => node(Instruction::new_none(OpCode::Nop), vec![], vec![], None, vec![]),
+        }
+    }
+
+    fn mem_arg(&mut self, op: OpCode, flat: bool) -> (MemArg, Context) {
+        let (_, width, _) = load_store_type(op).expect("memory opcode");
+        let limit = if flat { self.memory_max.unwrap_or(0) } else { u32::MAX };
+        let offsets: Vec<u32> = OFFSETS.iter().copied().filter(|offset| offset + width <= limit).collect();
+        let offset = offsets[self.rng.below(offsets.len())];
+        let align = self.rng.below(width.trailing_zeros() as usize + 1) as u32;
+        if !flat {
+            self.memory_max = Some(self.memory_max.unwrap_or(0).max(offset + width));
+        }
+        let context = Context {
+            local_variable: None,
+            functype: None,
+            global_variable: None,
+            memory: Some(MemoryContext { max: offset + width }),
+        };
+        (MemArg { align, offset }, context)
+    }
+
+    // Branch bodies are flat lists whose instructions carry no context.
+    fn branch(&mut self, ty: ValType, depth: usize, reserve: usize) -> Vec<Instruction> {
+        let mut instrs = Vec::new();
+        for node in self.body(&[ty], depth, reserve, true) {
+            flatten_node(&node, &mut instrs);
+        }
+        instrs
+    }
+}
+
+fn node(instr: Instruction, params: Vec<ValType>, results: Vec<ValType>, context: Option<Context>, sub_instrs: Vec<Node>) -> Node {
+    let mut node = Node::new(instr, InstructionType { params, results }, context);
+    node.sub_instrs = sub_instrs;
+    node
+}
+
+fn block_node(op: OpCode, bt: BlockType, body: Vec<Node>) -> Node {
+    let results = bt.results.clone();
+    let instr = if op == OpCode::Loop { Instruction::new_loop(op, bt) } else { Instruction::new_block(op, bt) };
+    node(instr, vec![], results, None, body)
+}
+
+fn local_context(ty: ValType) -> Context {
+    Context {
+        local_variable: Some(LocalVariableContext { local_variable_type: ty }),
+        functype: None,
+        global_variable: None,
+        memory: None,
+    }
+}
+
+fn global_context(ty: ValType) -> Context {
+    Context {
+        local_variable: None,
+        functype: None,
+        global_variable: Some(GlobalVariableContext { global_variable_type: ty }),
+        memory: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::composer::node_count;
+    use crate::module_generator::encode_module;
+
+    #[test]
+    fn test_generate_validates() {
+        for seed in 0..64 {
+            let config = RandomConfig { seed, max_size: 40, ..RandomConfig::default() };
+            let generator = RandomGenerator::new(config);
+            let composition = generator.generate(&[ValType::I32], &[ValType::I64, ValType::F32]).unwrap();
+            assert!(node_count(&composition.body) <= 40, "seed {}", seed);
+            assert_eq!(generator.generate(&[ValType::I32], &[ValType::I64, ValType::F32]).unwrap(), composition);
+            let wasm = encode_module(&composition.body).unwrap();
+            wasmparser::validate(&wasm).unwrap_or_else(|e| panic!("seed {}: {}", seed, e));
+        }
+
+        // with only loads enabled the tree is loads down to the leaves
+        let config = RandomConfig { weights: HashMap::new(), ..RandomConfig::default() }.with_weight(OpCode::I32Load, 1);
+        let composition = RandomGenerator::new(config).generate(&[], &[ValType::I32]).unwrap();
+        assert_eq!(composition.body.sub_instrs[0].instr.opcode, OpCode::I32Load);
+
+        let numeric = numeric_opcodes();
+        assert!(numeric.contains(&OpCode::F64Const) && numeric.contains(&OpCode::I64Extend32S));
+        assert!(!numeric.contains(&OpCode::I32Load) && !numeric.contains(&OpCode::MemorySize));
+        // numbers computed from constants only
+        let config = RandomConfig { weights: HashMap::new(), max_size: 200, ..RandomConfig::default() };
+        let config = numeric.iter().fold(config, |config, &op| config.with_weight(op, 1));
+        let mut opcodes = HashSet::new();
+        for seed in 0..32 {
+            let config = RandomConfig { seed, ..config.clone() };
+            let composition = RandomGenerator::new(config).generate(&[], &[ValType::F32]).unwrap();
+            let mut instrs = Vec::new();
+            flatten_node(&composition.body, &mut instrs);
+            opcodes.extend(instrs.iter().map(|instr| instr.opcode));
+            let wasm = encode_module(&composition.body).unwrap();
+            wasmparser::validate(&wasm).unwrap_or_else(|e| panic!("seed {}: {}", seed, e));
+        }
+        assert!(opcodes.contains(&OpCode::F32Const) && opcodes.len() > 20, "{:?}", opcodes);
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use anyhow::{Result, anyhow, bail};
+use common::opcodes::OpCode;
+use crate::AST::{FunctionTypeContext, Node};
+use crate::instruction::{Instruction, InstructionArgs, ValType};
+
+
+type Signature = (Vec<ValType>, Vec<ValType>);
+
+/// The entities a tree refers to after `resolve`, indexed by their new index.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Resolution {
+    // declared locals after the params
+    pub locals: Vec<ValType>,
+    pub globals: Vec<ValType>,
+    pub funcs: Vec<FunctionTypeContext>,
+    pub types: Vec<FunctionTypeContext>,
+    // bytes of memory 0 the tree may touch
+    pub memory_max: Option<u32>,
+}
+
+/// Give a tree assembled from snippets of different functions one consistent
+/// set of locals, globals, functions and types, and rewrite the `Simple`
+/// indices of its instructions to point at them.
+///
+/// An index keeps referring to one entity for a given type: `local.get 0`
+/// of an `i32` and `local.get 0` of an `i64` get two different locals, two
+/// uses of local 0 as `i32` share one. `params` and the already declared
+/// `locals` keep their indices, so leaves reading them are left alone.
+/// Functions and types are shared by every use with the same signature,
+/// and all memory accesses go to memory 0, sized for the largest one.
+pub fn resolve(node: &mut Node, params: &[ValType], locals: &[ValType]) -> Result<Resolution> {
+    let mut resolver = Resolver::default();
+    for (index, ty) in params.iter().chain(locals).enumerate() {
+        resolver.locals.insert((index as u32, *ty), index as u32);
+    }
+    resolver.num_params = params.len();
+    resolver.resolution.locals = locals.to_vec();
+    resolver.visit(node)?;
+    Ok(resolver.resolution)
+}
+
+#[derive(Default)]
+struct Resolver {
+    num_params: usize,
+    locals: HashMap<(u32, ValType), u32>,
+    globals: HashMap<(u32, ValType), u32>,
+    funcs: HashMap<Signature, u32>,
+    types: HashMap<Signature, u32>,
+    // old index to new index of calls, for the branches of an `if`
+    calls: HashMap<(OpCode, u32), u32>,
+    resolution: Resolution,
+}
+
+impl Resolver {
+    fn visit(&mut self, node: &mut Node) -> Result<()> {
+        for sub in &mut node.sub_instrs {
+            self.visit(sub)?;
+        }
+        if let Some(memory) = node.context.as_ref().and_then(|c| c.memory.as_ref()) {
+            let max = self.resolution.memory_max.unwrap_or(0).max(memory.max);
+            self.resolution.memory_max = Some(max);
+        }
+        if let InstructionArgs::If(if_args) = &mut node.instr.args {
+            for instr in if_args.instrs1.iter_mut().chain(&mut if_args.instrs2) {
+                self.visit_branch(instr)?;
+            }
+            return Ok(());
+        }
+        let opcode = node.instr.opcode;
+        let InstructionArgs::Simple(index) = &mut node.instr.args else {
+            return Ok(());
+        };
+        let context = node.context.as_ref();
+        let missing = || anyhow!("{} {} has no context", opcode.name(), index);
+        *index = match opcode {
+            OpCode::LocalGet | OpCode::LocalSet | OpCode::LocalTee => {
+                let ty = context.and_then(|c| c.local_variable.as_ref()).ok_or_else(missing)?;
+                self.local(*index, ty.local_variable_type)
+            }
+            OpCode::GlobalGet | OpCode::GlobalSet => {
+                let ty = context.and_then(|c| c.global_variable.as_ref()).ok_or_else(missing)?;
+                self.global(*index, ty.global_variable_type)
+            }
+            OpCode::Call | OpCode::CallIndirect => {
+                let functype = context.and_then(|c| c.functype.as_ref()).ok_or_else(missing)?;
+                let new = self.signature(opcode, functype);
+                self.calls.insert((opcode, *index), new);
+                new
+            }
+            _ => return Ok(()),
+        };
+        Ok(())
+    }
+
+    // Branch instructions carry no context, their indices can only follow
+    // what the rest of the tree already resolved them to.
+    fn visit_branch(&mut self, instr: &mut Instruction) -> Result<()> {
+        if let InstructionArgs::If(if_args) = &mut instr.args {
+            for instr in if_args.instrs1.iter_mut().chain(&mut if_args.instrs2) {
+                self.visit_branch(instr)?;
+            }
+            return Ok(());
+        }
+        let opcode = instr.opcode;
+        let InstructionArgs::Simple(index) = &mut instr.args else {
+            return Ok(());
+        };
+        let seen = |map: &HashMap<(u32, ValType), u32>| -> Result<u32> {
+            let mut matches = map.iter().filter(|((old, _), _)| old == index).map(|(_, new)| *new);
+            match (matches.next(), matches.next()) {
+                (Some(new), None) => Ok(new),
+                (None, _) => bail!("{} {} in an if branch has no known type", opcode.name(), index),
+                (Some(_), Some(_)) => bail!("{} {} in an if branch is used with several types", opcode.name(), index),
+            }
+        };
+        *index = match opcode {
+            OpCode::LocalGet | OpCode::LocalSet | OpCode::LocalTee => seen(&self.locals)?,
+            OpCode::GlobalGet | OpCode::GlobalSet => seen(&self.globals)?,
+            OpCode::Call | OpCode::CallIndirect => *self
+                .calls
+                .get(&(opcode, *index))
+                .ok_or_else(|| anyhow!("{} {} in an if branch has no known type", opcode.name(), index))?,
+            _ => return Ok(()),
+        };
+        Ok(())
+    }
+
+    fn local(&mut self, index: u32, ty: ValType) -> u32 {
+        let next = (self.num_params + self.resolution.locals.len()) as u32;
+        *self.locals.entry((index, ty)).or_insert_with(|| {
+            self.resolution.locals.push(ty);
+            next
+        })
+    }
+
+    fn global(&mut self, index: u32, ty: ValType) -> u32 {
+        let next = self.resolution.globals.len() as u32;
+        *self.globals.entry((index, ty)).or_insert_with(|| {
+            self.resolution.globals.push(ty);
+            next
+        })
+    }
+
+    fn signature(&mut self, opcode: OpCode, functype: &FunctionTypeContext) -> u32 {
+        let (map, list) = if opcode == OpCode::Call {
+            (&mut self.funcs, &mut self.resolution.funcs)
+        } else {
+            (&mut self.types, &mut self.resolution.types)
+        };
+        let sig = (functype.param_types.clone(), functype.result_types.clone());
+        *map.entry(sig).or_insert_with(|| {
+            list.push(functype.clone());
+            list.len() as u32 - 1
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AST::InstructionType;
+    use crate::instruction::BlockType;
+    use crate::module_generator::encode_module;
+    use crate::parser::{ParseEnv, parse_expr, parse_func};
+
+    #[test]
+    fn test_resolve_conflicting_snippets() {
+        let call_env = ParseEnv {
+            globals: vec![ValType::I32],
+            funcs: vec![FunctionTypeContext { param_types: vec![ValType::I32], result_types: vec![ValType::I64] }],
+            ..ParseEnv::default()
+        };
+        let snippets = vec![
+            parse_func("(func (param i32) (result i32) (i32.load (local.get 0)))").unwrap(),
+            parse_func("(func (param i64) (result i64) (local.tee 0 (local.get 0)))").unwrap(),
+            parse_expr("(call 0 (global.get 0))", &call_env).unwrap(),
+            parse_expr("(drop (global.get 0))", &ParseEnv { globals: vec![ValType::F32], ..ParseEnv::default() })
+                .unwrap(),
+        ];
+        let results = vec![ValType::I32, ValType::I64, ValType::I64];
+        let mut tree = Node::new(
+            Instruction::new_block(OpCode::Block, BlockType { params: vec![], results: results.clone() }),
+            InstructionType { params: vec![], results },
+            None,
+        );
+        tree.sub_instrs = snippets;
+        assert!(encode_module(&tree).is_err());
+
+        let resolution = resolve(&mut tree, &[ValType::I32], &[]).unwrap();
+        assert_eq!(resolution.locals, vec![ValType::I64]);
+        assert_eq!(resolution.globals, vec![ValType::I32, ValType::F32]);
+        assert_eq!(resolution.funcs.len(), 1);
+        assert_eq!(tree.sub_instrs[1].instr.args, InstructionArgs::Simple(1));
+        assert_eq!(tree.sub_instrs[3].sub_instrs[0].instr.args, InstructionArgs::Simple(1));
+        wasmparser::validate(&encode_module(&tree).unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result, bail};
+use crate::engine::{Call, Engine, Outcome};
+use crate::value::Value;
+
+
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+// how long the pipes may take to close once the runtime is killed
+const KILL_GRACE: Duration = Duration::from_millis(100);
+
+/// A runtime run as an external program, one process per call.
+///
+/// The command line is split on whitespace, then `{module}` is replaced by
+/// the path of the module and `{export}` by the name of the function; an
+/// `{args}` word becomes one word per argument (see `Value`'s `Display`).
+/// For example `wasmtime run --invoke {export} {module} {args}`.
+///
+/// The runtime is expected to print one result per line. A non-zero exit
+/// code is a trap, death by a signal a crash. On unix the runtime runs in a
+/// process group of its own, which is killed as a whole on timeout, so that
+/// launcher scripts cannot leave anything running.
+#[derive(Debug, Clone)]
+pub struct CommandRuntime {
+    name: String,
+    command: Vec<String>,
+    timeout: Duration,
+}
+
+impl CommandRuntime {
+    pub fn new(name: &str, command: &str, timeout: Duration) -> Result<Self> {
+        let command: Vec<String> = command.split_whitespace().map(String::from).collect();
+        if command.is_empty() {
+            bail!("runtime `{}` has an empty command line", name);
+        }
+        Ok(CommandRuntime { name: name.to_string(), command, timeout })
+    }
+
+    /// `name=command line`, as given on the command line of the tool.
+    pub fn from_spec(spec: &str, timeout: Duration) -> Result<Self> {
+        match spec.split_once('=') {
+            Some((name, command)) if !name.is_empty() => CommandRuntime::new(name, command, timeout),
+            _ => bail!("runtime `{}` is not of the form name=command", spec),
+        }
+    }
+
+    fn command_line(&self, call: &Call) -> Vec<String> {
+        let path = call.path.to_string_lossy();
+        let mut words = Vec::new();
+        for word in &self.command {
+            if word == "{args}" {
+                words.extend(call.args.iter().map(Value::to_string));
+            } else {
+                words.push(word.replace("{module}", &path).replace("{export}", call.export));
+            }
+        }
+        words
+    }
+}
+
+impl Engine for CommandRuntime {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn invoke(&self, call: &Call) -> Result<Outcome> {
+        let words = self.command_line(call);
+        let mut command = Command::new(&words[0]);
+        command.args(&words[1..]).stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let child = command
+            .spawn()
+            .with_context(|| format!("cannot start runtime `{}` ({})", self.name, words[0]))?;
+        let Some((status, stdout, stderr)) = wait(child, self.timeout)? else {
+            return Ok(Outcome::Timeout);
+        };
+
+        if let Some(signal) = signal(&status) {
+            return Ok(Outcome::Crashed { signal: Some(signal), stderr });
+        }
+        match status.code() {
+            Some(0) => {}
+            Some(_) => return Ok(Outcome::Trapped { message: stderr.trim().to_string() }),
+            None => return Ok(Outcome::Crashed { signal: None, stderr }),
+        }
+
+        let lines: Vec<&str> = stdout.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        let values: Option<Vec<Value>> = (lines.len() == call.results.len())
+            .then(|| lines.iter().zip(call.results).map(|(line, ty)| Value::parse(*ty, line).ok()).collect())
+            .flatten();
+        Ok(match values {
+            Some(values) => Outcome::Returned { values },
+            None => Outcome::Malformed { output: stdout },
+        })
+    }
+}
+
+// Status, stdout and stderr of the child, `None` if it had to be killed. The
+// pipes are drained on their own threads so that a chatty child cannot block.
+// Processes the child started may keep the pipes open after it exited; they
+// are killed too once the deadline has passed.
+fn wait(mut child: Child, timeout: Duration) -> Result<Option<(ExitStatus, String, String)>> {
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            kill(&mut child);
+            child.wait()?;
+            break None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    let left = || deadline.saturating_duration_since(Instant::now());
+    let (mut stdout_text, mut stderr_text) = (stdout.recv_timeout(left()).ok(), stderr.recv_timeout(left()).ok());
+    if stdout_text.is_none() || stderr_text.is_none() {
+        kill(&mut child);
+        stdout_text = stdout_text.or_else(|| stdout.recv_timeout(KILL_GRACE).ok());
+        stderr_text = stderr_text.or_else(|| stderr.recv_timeout(KILL_GRACE).ok());
+    }
+    Ok(status.map(|status| (status, stdout_text.unwrap_or_default(), stderr_text.unwrap_or_default())))
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        let _ = sender.send(String::from_utf8_lossy(&bytes).into_owned());
+    });
+    receiver
+}
+
+// Kill the process group of the child, errors mean it is gone already.
+#[cfg(unix)]
+fn kill(child: &mut Child) {
+    // SAFETY: `killpg` has no memory effects, the group id is the pid of
+    // the child since it was spawned with `process_group(0)`
+    unsafe {
+        libc::killpg(child.id() as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+}
+
+#[cfg(unix)]
+fn signal(status: &ExitStatus) -> Option<i32> {
+    std::os::unix::process::ExitStatusExt::signal(status)
+}
+
+#[cfg(not(unix))]
+fn signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::path::Path;
+    use wasmparser::ValType;
+    use super::*;
+
+    // A runtime running `script` with `sh`, which leaves a `sleep` behind.
+    fn script_runtime(name: &str, script: &str) -> (CommandRuntime, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("command-test-{}-{}.sh", name, std::process::id()));
+        std::fs::write(&path, script).unwrap();
+        let runtime = CommandRuntime::new(name, &format!("sh {}", path.display()), Duration::from_millis(200)).unwrap();
+        (runtime, path)
+    }
+
+    #[test]
+    fn test_kill_process_group() {
+        let call = Call { path: Path::new("m.wasm"), wasm: &[], export: "f", args: &[], results: &[ValType::I32] };
+
+        let (runtime, path) = script_runtime("slow", "sleep 5 & sleep 5\n");
+        let start = Instant::now();
+        assert_eq!(runtime.invoke(&call).unwrap(), Outcome::Timeout);
+        assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+        std::fs::remove_file(&path).unwrap();
+
+        // the runtime answers but its background job holds the pipe open
+        let (runtime, path) = script_runtime("lingering", "sleep 5 &\necho 7\n");
+        let start = Instant::now();
+        assert_eq!(runtime.invoke(&call).unwrap(), Outcome::Returned { values: vec![Value::I32(7)] });
+        assert!(start.elapsed() < Duration::from_secs(2), "{:?}", start.elapsed());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::Path;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use wasmparser::{CompositeInnerType, ExternalKind, Parser, Payload, ValType};
+use crate::engine::{Call, Engine, Outcome};
+use crate::triage::failure_class;
+use crate::value::Value;
+
+
+/// An exported function the harness can call: all params and results are
+/// numbers or vectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Export {
+    pub name: String,
+    pub params: Vec<ValType>,
+    pub results: Vec<ValType>,
+}
+
+/// The exported functions of a module that can be called from outside.
+/// Modules with imports are rejected since the runtimes have nothing to
+/// link them with.
+pub fn callable_exports(wasm: &[u8]) -> Result<Vec<Export>> {
+    let mut types: Vec<Option<(Vec<ValType>, Vec<ValType>)>> = Vec::new();
+    let mut funcs: Vec<u32> = Vec::new();
+    let mut exports = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::TypeSection(reader) => {
+                for rec_group in reader {
+                    for sub_type in rec_group?.into_types() {
+                        types.push(match sub_type.composite_type.inner {
+                            CompositeInnerType::Func(func_type) => {
+                                Some((func_type.params().to_vec(), func_type.results().to_vec()))
+                            }
+                            _ => None,
+                        });
+                    }
+                }
+            }
+            Payload::ImportSection(reader) if reader.count() > 0 => {
+                bail!("modules with imports cannot be run");
+            }
+            Payload::FunctionSection(reader) => {
+                for type_index in reader {
+                    funcs.push(type_index?);
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        exports.push((export.name.to_string(), export.index));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut callable = Vec::new();
+    for (name, func) in exports {
+        let Some(Some((params, results))) = funcs.get(func as usize).and_then(|ty| types.get(*ty as usize)) else {
+            continue;
+        };
+        if params.iter().chain(results).all(|ty| Value::zero(*ty).is_some()) {
+            callable.push(Export { name, params: params.clone(), results: results.clone() });
+        }
+    }
+    Ok(callable)
+}
+
+/// One argument vector of zeros, the inputs when nothing better is known.
+pub fn zero_arguments(export: &Export) -> Vec<Vec<Value>> {
+    vec![export.params.iter().filter_map(|ty| Value::zero(*ty)).collect()]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeOutcome {
+    pub runtime: String,
+    pub outcome: Outcome,
+}
+
+/// A call on which the runtimes disagree, or on which one of them failed on
+/// its own account (see `failure_class`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Divergence {
+    pub module: String,
+    pub export: String,
+    pub args: Vec<Value>,
+    pub outcomes: Vec<RuntimeOutcome>,
+}
+
+impl Divergence {
+    /// The record as one line of JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("a divergence always serializes")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    // calls per engine
+    pub calls: usize,
+    // calls at least one engine reported as unsupported
+    pub unsupported: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+/// Runs the exports of a module on several engines and compares what they
+/// do. Engines are compared with the first one added that supports the call.
+#[derive(Default)]
+pub struct Harness {
+    engines: Vec<Box<dyn Engine>>,
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        Harness::default()
+    }
+
+    pub fn with_engine(mut self, engine: Box<dyn Engine>) -> Self {
+        self.engines.push(engine);
+        self
+    }
+
+    pub fn engines(&self) -> impl Iterator<Item = &str> {
+        self.engines.iter().map(|engine| engine.name())
+    }
+
+    /// Call every callable export of the module at `path` with each argument
+    /// vector `inputs` gives for it, on every engine.
+    pub fn run(&self, path: &Path, mut inputs: impl FnMut(&Export) -> Vec<Vec<Value>>) -> Result<Report> {
+        if self.engines.is_empty() {
+            bail!("no engine to run on");
+        }
+        let wasm = std::fs::read(path).with_context(|| format!("cannot read {}", path.display()))?;
+        let mut report = Report::default();
+        for export in callable_exports(&wasm)? {
+            for args in inputs(&export) {
+                let call = Call { path, wasm: &wasm, export: &export.name, args: &args, results: &export.results };
+                let outcomes = self
+                    .engines
+                    .iter()
+                    .map(|engine| {
+                        Ok(RuntimeOutcome { runtime: engine.name().to_string(), outcome: engine.invoke(&call)? })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                report.calls += 1;
+                if outcomes.iter().any(|runtime| matches!(runtime.outcome, Outcome::Unsupported { .. })) {
+                    report.unsupported += 1;
+                }
+                let supported = || outcomes.iter().filter(|runtime| !matches!(runtime.outcome, Outcome::Unsupported { .. }));
+                let diverges = supported().next().is_some_and(|first| {
+                    supported().any(|runtime| {
+                        failure_class(&runtime.outcome).is_some() || !runtime.outcome.agrees_with(&first.outcome)
+                    })
+                });
+                if diverges {
+                    report.divergences.push(Divergence {
+                        module: path.display().to_string(),
+                        export: export.name.clone(),
+                        args,
+                        outcomes,
+                    });
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+    use crate::command::CommandRuntime;
+    use crate::interpreter::Interpreter;
+
+    fn runtime(spec: &str) -> Box<dyn Engine> {
+        Box::new(CommandRuntime::from_spec(spec, Duration::from_millis(500)).unwrap())
+    }
+
+    #[test]
+    fn test_run_harness() {
+        let wasm = wat::parse_str(r#"
+            (module
+              (func (export "id") (param i32) (result i32) local.get 0)
+              (func (export "refs") (param externref))
+              (func (export "pair") (result i64 f32) i64.const 0 f32.const 0))
+        "#).unwrap();
+        let path = std::env::temp_dir().join(format!("harness-test-{}.wasm", std::process::id()));
+        std::fs::write(&path, &wasm).unwrap();
+
+        let exports = callable_exports(&wasm).unwrap();
+        let names: Vec<&str> = exports.iter().map(|export| export.name.as_str()).collect();
+        assert_eq!(names, vec!["id", "pair"]);
+
+        // `echo` plays a runtime computing the identity, `echo 1` one always returning 1
+        let harness = Harness::new().with_engine(runtime("echo=echo {args}")).with_engine(runtime("one=echo 1"));
+        let report = harness
+            .run(&path, |export| match export.name.as_str() {
+                "id" => vec![vec![Value::I32(1)], vec![Value::I32(2)]],
+                _ => zero_arguments(export),
+            })
+            .unwrap();
+        assert_eq!((report.calls, report.unsupported), (3, 0));
+        let [first, second] = report.divergences.as_slice() else {
+            panic!("{:?}", report.divergences);
+        };
+        assert_eq!((first.export.as_str(), first.args.as_slice()), ("id", [Value::I32(2)].as_slice()));
+        assert_eq!(first.outcomes[0].outcome, Outcome::Returned { values: vec![Value::I32(2)] });
+        assert_eq!(second.export, "pair");
+        assert!(matches!(second.outcomes[0].outcome, Outcome::Malformed { .. }));
+
+        let harness = Harness::new().with_engine(runtime("fails=false")).with_engine(runtime("slow=sleep 5"));
+        let report = harness.run(&path, zero_arguments).unwrap();
+        let outcomes: Vec<&Outcome> = report.divergences[0].outcomes.iter().map(|runtime| &runtime.outcome).collect();
+        assert_eq!(outcomes, vec![&Outcome::Trapped { message: String::new() }, &Outcome::Timeout]);
+        let json = report.divergences[0].to_json();
+        assert!(json.contains(r#"{"runtime":"slow","outcome":{"kind":"timeout"}}"#), "{}", json);
+        assert_eq!(serde_json::from_str::<Divergence>(&json).unwrap(), report.divergences[0]);
+
+        // the interpreter has no SIMD, the call is only counted
+        let simd = wat::parse_str(r#"(module (func (export "lane") (result i32) v128.const i32x4 3 0 0 0 i32x4.extract_lane 0))"#);
+        std::fs::write(&path, simd.unwrap()).unwrap();
+        let harness = Harness::new().with_engine(Box::new(Interpreter::default())).with_engine(runtime("three=echo 3"));
+        let report = harness.run(&path, zero_arguments).unwrap();
+        assert_eq!((report.calls, report.unsupported, report.divergences.len()), (1, 1, 0));
+
+        assert!(harness.run(Path::new("/nonexistent.wasm"), zero_arguments).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
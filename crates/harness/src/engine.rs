@@ -0,0 +1,60 @@
+use std::path::Path;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use wasmparser::ValType;
+use crate::value::Value;
+
+
+/// How one call of an export ended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Outcome {
+    Returned { values: Vec<Value> },
+    /// The runtime reported an error, normally a trap, and exited.
+    Trapped { message: String },
+    /// The runtime died from a signal, or the host reported an abnormal
+    /// termination without an exit code.
+    Crashed { signal: Option<i32>, stderr: String },
+    Timeout,
+    /// The runtime exited normally but its output is not one value per
+    /// result.
+    Malformed { output: String },
+    /// The engine does not implement something the call needs; it takes no
+    /// part in the comparison.
+    Unsupported { reason: String },
+}
+
+impl Outcome {
+    /// Whether two runtimes agree on a call. Traps agree whatever their
+    /// message, crashes never agree with anything.
+    pub fn agrees_with(&self, other: &Outcome) -> bool {
+        match (self, other) {
+            (Outcome::Returned { values: a }, Outcome::Returned { values: b }) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same(b))
+            }
+            (Outcome::Trapped { .. }, Outcome::Trapped { .. }) => true,
+            (Outcome::Timeout, Outcome::Timeout) => true,
+            (Outcome::Malformed { output: a }, Outcome::Malformed { output: b }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// One call of an exported function.
+#[derive(Debug, Clone, Copy)]
+pub struct Call<'a> {
+    // the module as a file, for engines running in another process
+    pub path: &'a Path,
+    pub wasm: &'a [u8],
+    pub export: &'a str,
+    pub args: &'a [Value],
+    pub results: &'a [ValType],
+}
+
+/// Something that can run an export of a module. An error means the engine
+/// itself could not be used, not that the call failed.
+pub trait Engine {
+    fn name(&self) -> &str;
+
+    fn invoke(&self, call: &Call) -> Result<Outcome>;
+}
@@ -0,0 +1,212 @@
+use std::collections::HashSet;
+use anyhow::Result;
+use common::rng::{Choices, Rng};
+use wasmparser::{Operator, OperatorsReader, Parser, Payload, ValType};
+use crate::differential::Export;
+use crate::value::Value;
+
+
+const DEFAULT_COUNT: usize = 8;
+// tries to find a vector not given yet before settling for fewer
+const ATTEMPTS_PER_VECTOR: usize = 4;
+
+const I32_BOUNDARIES: &[i32] = &[0, 1, -1, 2, i32::MIN, i32::MAX, i32::MIN + 1, 0x7F, 0x80, 0xFF, 0x7FFF, 0x8000, 0xFFFF, 31, 32];
+const I64_BOUNDARIES: &[i64] = &[
+    0, 1, -1, 2, i64::MIN, i64::MAX, i64::MIN + 1,
+    i32::MIN as i64, i32::MAX as i64, u32::MAX as i64, 1 << 32, 0x80, 0xFF, 0x8000, 0xFFFF, 63, 64,
+];
+const F32_BOUNDARIES: &[u32] = &[
+    0x0000_0000, 0x8000_0000,         // ±0.0
+    0x3F80_0000, 0xBF80_0000,         // ±1.0
+    0x3F00_0000, 0xBF00_0000,         // ±0.5, and the ties of `nearest`
+    0x3FC0_0000, 0x4020_0000,         // 1.5, 2.5
+    0x7F80_0000, 0xFF80_0000,         // ±inf
+    0x7FC0_0000, 0xFFC0_0000,         // canonical NaNs
+    0x7FA0_0001, 0x7FC0_0001,         // signalling and quiet NaNs with a payload
+    0x0000_0001, 0x807F_FFFF,         // subnormals
+    0x0080_0000, 0x7F7F_FFFF, 0xFF7F_FFFF, // MIN_POSITIVE, MAX, MIN
+    0x4F00_0000, 0xCF00_0000,         // ±2^31, limits of the integer conversions
+    0x4F80_0000, 0x5F00_0000, 0x5F80_0000, // 2^32, 2^63, 2^64
+];
+const F64_BOUNDARIES: &[u64] = &[
+    0x0000_0000_0000_0000, 0x8000_0000_0000_0000,
+    0x3FF0_0000_0000_0000, 0xBFF0_0000_0000_0000,
+    0x3FE0_0000_0000_0000, 0xBFE0_0000_0000_0000,
+    0x3FF8_0000_0000_0000, 0x4004_0000_0000_0000,
+    0x7FF0_0000_0000_0000, 0xFFF0_0000_0000_0000,
+    0x7FF8_0000_0000_0000, 0xFFF8_0000_0000_0000,
+    0x7FF4_0000_0000_0001, 0x7FF8_0000_0000_0001,
+    0x0000_0000_0000_0001, 0x800F_FFFF_FFFF_FFFF,
+    0x0010_0000_0000_0000, 0x7FEF_FFFF_FFFF_FFFF, 0xFFEF_FFFF_FFFF_FFFF,
+    0x41E0_0000_0000_0000, 0xC1E0_0000_0020_0000, // 2^31, -2^31 - 1
+    0x41F0_0000_0000_0000, 0x43E0_0000_0000_0000, 0x43F0_0000_0000_0000,
+    0x3CB0_0000_0000_0000,                        // half an ulp of 1.0
+];
+// lane patterns a v128 is built from, as 32-bit lanes
+const V128_LANES: &[u32] = &[0, 0xFFFF_FFFF, 0x8000_0000, 0x7FFF_FFFF, 0x8080_8080, 0x8000_8000, 0x0000_0001, 0x007F_FFFF, 0x7FC0_0000, 0x7FA0_0001, 0x7F80_0000];
+
+/// Argument vectors for the exports of a module: boundary values of each
+/// type, random values and the constants the module itself contains, mixed
+/// per argument. The first vector of an export is all zeros.
+pub struct InputGenerator {
+    rng: Rng,
+    count: usize,
+    // constants of the module and their integer neighbours, by type
+    constants: Vec<Value>,
+}
+
+impl InputGenerator {
+    pub fn new(wasm: &[u8], seed: u64) -> Result<Self> {
+        Ok(InputGenerator { rng: Rng::new(seed), count: DEFAULT_COUNT, constants: constants(wasm)? })
+    }
+
+    /// Number of vectors per export; fewer when the parameters cannot take
+    /// that many different values, none at all for 0.
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.count = count;
+        self
+    }
+
+    pub fn arguments(&mut self, export: &Export) -> Vec<Vec<Value>> {
+        if self.count == 0 {
+            return Vec::new();
+        }
+        let zeros: Vec<Value> = export.params.iter().filter_map(|ty| Value::zero(*ty)).collect();
+        let mut seen = HashSet::from([zeros.clone()]);
+        let mut vectors = vec![zeros];
+        for _ in 0..self.count.saturating_sub(1) * ATTEMPTS_PER_VECTOR {
+            if vectors.len() >= self.count {
+                break;
+            }
+            let args: Vec<Value> = export.params.iter().map(|ty| self.value(*ty)).collect();
+            if seen.insert(args.clone()) {
+                vectors.push(args);
+            }
+        }
+        vectors
+    }
+
+    fn value(&mut self, ty: ValType) -> Value {
+        match self.rng.below(4) {
+            0 | 1 => self.boundary(ty),
+            2 => self.random(ty),
+            _ => {
+                let constants: Vec<&Value> = self.constants.iter().filter(|value| value.ty() == ty).collect();
+                match constants.len() {
+                    0 => self.boundary(ty),
+                    len => *constants[self.rng.below(len)],
+                }
+            }
+        }
+    }
+
+    fn boundary(&mut self, ty: ValType) -> Value {
+        match ty {
+            ValType::I32 => Value::I32(I32_BOUNDARIES[self.rng.below(I32_BOUNDARIES.len())]),
+            ValType::I64 => Value::I64(I64_BOUNDARIES[self.rng.below(I64_BOUNDARIES.len())]),
+            ValType::F32 => Value::F32(F32_BOUNDARIES[self.rng.below(F32_BOUNDARIES.len())]),
+            ValType::F64 => Value::F64(F64_BOUNDARIES[self.rng.below(F64_BOUNDARIES.len())]),
+            // one pattern splat, or four picked lane by lane
+            ValType::V128 => {
+                let splat = self.rng.below(2) == 0;
+                let first = V128_LANES[self.rng.below(V128_LANES.len())];
+                Value::V128((0..4).fold(0, |bits, _| {
+                    let lane = if splat { first } else { V128_LANES[self.rng.below(V128_LANES.len())] };
+                    bits << 32 | lane as u128
+                }))
+            }
+            ValType::Ref(_) => unreachable!("exports with references are not callable"),
+        }
+    }
+
+    // Any bits half of the time, else a small number.
+    fn random(&mut self, ty: ValType) -> Value {
+        let bits = self.rng.next_u64();
+        let small = self.rng.below(2) == 0;
+        let number = self.rng.below(201) as i64 - 100;
+        match ty {
+            ValType::I32 => Value::I32(if small { number as i32 } else { bits as i32 }),
+            ValType::I64 => Value::I64(if small { number } else { bits as i64 }),
+            ValType::F32 => Value::F32(if small { (number as f32 / 4.0).to_bits() } else { bits as u32 }),
+            ValType::F64 => Value::F64(if small { (number as f64 / 4.0).to_bits() } else { bits }),
+            ValType::V128 => Value::V128((bits as u128) << 64 | self.rng.next_u64() as u128),
+            ValType::Ref(_) => unreachable!("exports with references are not callable"),
+        }
+    }
+}
+
+// Constants of the code and of the initializers of globals, in order of
+// appearance and without repeats. Integers come with their neighbours, where
+// comparisons and bounds checks change their mind.
+fn constants(wasm: &[u8]) -> Result<Vec<Value>> {
+    let mut constants = Vec::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::GlobalSection(reader) => {
+                for global in reader {
+                    collect(global?.init_expr.get_operators_reader(), &mut constants)?;
+                }
+            }
+            Payload::CodeSectionEntry(body) => collect(body.get_operators_reader()?, &mut constants)?,
+            _ => {}
+        }
+    }
+    let mut seen = HashSet::new();
+    constants.retain(|value| seen.insert(*value));
+    Ok(constants)
+}
+
+fn collect(mut reader: OperatorsReader, constants: &mut Vec<Value>) -> Result<()> {
+    while !reader.eof() {
+        match reader.read()? {
+            Operator::I32Const { value } => {
+                constants.extend([value, value.wrapping_sub(1), value.wrapping_add(1)].map(Value::I32));
+            }
+            Operator::I64Const { value } => {
+                constants.extend([value, value.wrapping_sub(1), value.wrapping_add(1)].map(Value::I64));
+            }
+            Operator::F32Const { value } => constants.push(Value::F32(value.bits())),
+            Operator::F64Const { value } => constants.push(Value::F64(value.bits())),
+            Operator::V128Const { value } => constants.push(Value::V128(value.i128() as u128)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::differential::callable_exports;
+
+    #[test]
+    fn test_arguments() {
+        let wasm = wat::parse_str(r#"
+            (module
+              (global f64 (f64.const 2.5))
+              (func (export "f") (param i32 f64 v128) (result i32)
+                local.get 0 i32.const 1234 i32.lt_u)
+              (func (export "none")))
+        "#).unwrap();
+        let exports = callable_exports(&wasm).unwrap();
+        let mut inputs = InputGenerator::new(&wasm, 7).unwrap().with_count(64);
+        let vectors = inputs.arguments(&exports[0]);
+        assert_eq!(vectors.len(), 64);
+        assert_eq!(vectors[0], vec![Value::I32(0), Value::F64(0), Value::V128(0)]);
+        assert!(vectors.iter().all(|args| args.iter().map(Value::ty).eq(exports[0].params.iter().copied())));
+        assert_eq!(vectors.iter().collect::<HashSet<_>>().len(), 64);
+        let has = |value: Value| vectors.iter().any(|args| args.contains(&value));
+        assert!(has(Value::I32(1233)) || has(Value::I32(1234)) || has(Value::I32(1235)));
+        assert!(has(Value::I32(i32::MIN)) || has(Value::I32(i32::MAX)));
+        assert!(has(Value::F64(2.5f64.to_bits())));
+        assert!(vectors.iter().any(|args| matches!(args[1], Value::F64(bits) if f64::from_bits(bits).is_nan())));
+
+        assert_eq!(inputs.arguments(&exports[1]), vec![Vec::<Value>::new()]);
+        let mut again = InputGenerator::new(&wasm, 7).unwrap().with_count(64);
+        assert_eq!(again.arguments(&exports[0]), vectors);
+        let mut other = InputGenerator::new(&wasm, 8).unwrap().with_count(64);
+        assert_ne!(other.arguments(&exports[0]), vectors);
+        let mut none = InputGenerator::new(&wasm, 7).unwrap().with_count(0);
+        assert!(none.arguments(&exports[0]).is_empty() && none.arguments(&exports[1]).is_empty());
+    }
+}
@@ -0,0 +1,1285 @@
+use std::collections::HashMap;
+use std::fmt;
+use anyhow::{Result, anyhow};
+use wasmparser::{
+    BlockType, CompositeInnerType, ConstExpr, DataKind, ElementItems, ElementKind, ExternalKind, MemArg,
+    Operator, Parser, Payload, TableInit, ValType, Validator, WasmFeatures,
+};
+use rewriter::module::WasmModule;
+use crate::engine::{Call, Engine, Outcome};
+use crate::value::Value;
+
+
+const PAGE_SIZE: usize = 65536;
+const CANONICAL_NAN32: u32 = 0x7FC0_0000;
+const CANONICAL_NAN64: u64 = 0x7FF8_0000_0000_0000;
+// room for `max_call_depth` nested calls of `execute`
+const STACK_SIZE: usize = 256 << 20;
+
+/// Limits of the interpreter. Running out of fuel is reported as a timeout.
+#[derive(Debug, Clone)]
+pub struct InterpreterConfig {
+    // instructions executed per instance, start function included
+    pub fuel: u64,
+    // memories never grow beyond this many pages, whatever their maximum
+    pub max_pages: u32,
+    pub max_table_size: u32,
+    pub max_call_depth: usize,
+}
+
+impl Default for InterpreterConfig {
+    fn default() -> Self {
+        InterpreterConfig { fuel: 50_000_000, max_pages: 16384, max_table_size: 1 << 20, max_call_depth: 10_000 }
+    }
+}
+
+/// The traps of the spec, displayed with the messages of its test suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trap {
+    Unreachable,
+    IntegerDivideByZero,
+    IntegerOverflow,
+    InvalidConversionToInteger,
+    OutOfBoundsMemoryAccess,
+    OutOfBoundsTableAccess,
+    UndefinedElement,
+    UninitializedElement,
+    IndirectCallTypeMismatch,
+    CallStackExhausted,
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Trap::Unreachable                => "unreachable",
+            Trap::IntegerDivideByZero        => "integer divide by zero",
+            Trap::IntegerOverflow            => "integer overflow",
+            Trap::InvalidConversionToInteger => "invalid conversion to integer",
+            Trap::OutOfBoundsMemoryAccess    => "out of bounds memory access",
+            Trap::OutOfBoundsTableAccess     => "out of bounds table access",
+            Trap::UndefinedElement           => "undefined element",
+            Trap::UninitializedElement       => "uninitialized element",
+            Trap::IndirectCallTypeMismatch   => "indirect call type mismatch",
+            Trap::CallStackExhausted         => "call stack exhausted",
+        })
+    }
+}
+
+/// Why an instantiation or a call did not return.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Trap(Trap),
+    OutOfFuel,
+    /// The module or the call needs something the interpreter does not
+    /// implement, e.g. SIMD, imports or several memories.
+    Unsupported(String),
+    /// The module does not validate, or the call does not fit the export.
+    Invalid(String),
+}
+
+impl From<Trap> for Stop {
+    fn from(trap: Trap) -> Self {
+        Stop::Trap(trap)
+    }
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Trap(trap) => write!(f, "trap: {}", trap),
+            Stop::OutOfFuel => f.write_str("out of fuel"),
+            Stop::Unsupported(reason) => write!(f, "unsupported: {}", reason),
+            Stop::Invalid(reason) => write!(f, "invalid: {}", reason),
+        }
+    }
+}
+
+type Exec<T> = std::result::Result<T, Stop>;
+
+fn unsupported<T>(what: impl fmt::Display) -> Exec<T> {
+    Err(Stop::Unsupported(what.to_string()))
+}
+
+fn invalid(e: impl fmt::Display) -> Stop {
+    Stop::Invalid(e.to_string())
+}
+
+/// A value on the stack. Function and extern references are both `Ref`,
+/// holding a function index or an opaque handle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Val {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+    V128(u128),
+    Ref(Option<u32>),
+}
+
+impl Val {
+    fn default(ty: ValType) -> Self {
+        match ty {
+            ValType::I32 => Val::I32(0),
+            ValType::I64 => Val::I64(0),
+            ValType::F32 => Val::F32(0),
+            ValType::F64 => Val::F64(0),
+            ValType::V128 => Val::V128(0),
+            ValType::Ref(_) => Val::Ref(None),
+        }
+    }
+
+    // Results of arithmetic: every NaN becomes the positive canonical NaN.
+    fn f32(x: f32) -> Self {
+        Val::F32(if x.is_nan() { CANONICAL_NAN32 } else { x.to_bits() })
+    }
+
+    fn f64(x: f64) -> Self {
+        Val::F64(if x.is_nan() { CANONICAL_NAN64 } else { x.to_bits() })
+    }
+}
+
+impl From<Value> for Val {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::I32(v) => Val::I32(v),
+            Value::I64(v) => Val::I64(v),
+            Value::F32(v) => Val::F32(v),
+            Value::F64(v) => Val::F64(v),
+            Value::V128(v) => Val::V128(v),
+        }
+    }
+}
+
+type FuncType = (Vec<ValType>, Vec<ValType>);
+
+struct Code<'a> {
+    locals: Vec<ValType>,
+    ops: Vec<Operator<'a>>,
+    // for `block`, `loop`, `if` and `else`: index of the matching `end`
+    ends: Vec<usize>,
+    // `if` with an `else`: index of the `else`
+    elses: HashMap<usize, usize>,
+}
+
+impl<'a> Code<'a> {
+    fn new(locals: Vec<ValType>, ops: Vec<Operator<'a>>) -> Self {
+        let mut ends = vec![0; ops.len()];
+        let mut elses = HashMap::new();
+        let mut open: Vec<usize> = Vec::new();
+        for (pc, op) in ops.iter().enumerate() {
+            match op {
+                Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } => open.push(pc),
+                Operator::Else => {
+                    elses.insert(*open.last().expect("validated"), pc);
+                }
+                Operator::End => {
+                    if let Some(opener) = open.pop() {
+                        ends[opener] = pc;
+                        if let Some(&else_pc) = elses.get(&opener) {
+                            ends[else_pc] = pc;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Code { locals, ops, ends, elses }
+    }
+}
+
+// What does not change while the module runs.
+#[derive(Default)]
+struct Module<'a> {
+    types: Vec<FuncType>,
+    // type index of every function
+    funcs: Vec<u32>,
+    codes: Vec<Code<'a>>,
+    exports: HashMap<String, u32>,
+}
+
+impl Module<'_> {
+    fn func_type(&self, func: u32) -> &FuncType {
+        &self.types[self.funcs[func as usize] as usize]
+    }
+
+    // (params, results) of a block
+    fn block_arity(&self, blockty: &BlockType) -> (usize, usize) {
+        match blockty {
+            BlockType::Empty => (0, 0),
+            BlockType::Type(_) => (0, 1),
+            BlockType::FuncType(index) => {
+                let (params, results) = &self.types[*index as usize];
+                (params.len(), results.len())
+            }
+        }
+    }
+}
+
+struct Table {
+    elements: Vec<Val>,
+    max: u32,
+}
+
+// What does.
+struct Store<'a> {
+    stack: Vec<Val>,
+    globals: Vec<Val>,
+    memory: Vec<u8>,
+    max_pages: u32,
+    tables: Vec<Table>,
+    elements: Vec<Vec<Val>>,
+    datas: Vec<&'a [u8]>,
+    fuel: u64,
+    max_call_depth: usize,
+}
+
+struct Label {
+    // values kept by a branch to the label
+    arity: usize,
+    height: usize,
+    target: usize,
+    is_loop: bool,
+}
+
+/// An instantiated module for the reference interpreter. The module is
+/// validated with every feature enabled, but only the MVP, sign extension,
+/// saturating conversions, bulk memory, reference types and multi-value are
+/// implemented; anything else is reported as `Stop::Unsupported` when it is
+/// reached. That leaves out SIMD, where v128 values can be passed around but
+/// every `0xFD` instruction, `v128.const` included, is unsupported, as well
+/// as threads, exceptions, tail calls, GC and memory64, and modules with
+/// imports or several memories. Such calls take no part in a differential
+/// run, `run --oracle` counts them.
+///
+/// Results are deterministic: every NaN produced by arithmetic is the
+/// positive canonical NaN, while instructions that only move bits (`abs`,
+/// `neg`, `copysign`, reinterpretations, loads and stores) keep payloads.
+pub struct Instance<'a> {
+    module: Module<'a>,
+    store: Store<'a>,
+}
+
+impl<'a> Instance<'a> {
+    /// Instantiate, running active segments and the start function.
+    pub fn new(wasm: &'a [u8], config: &InterpreterConfig) -> Exec<Self> {
+        Validator::new_with_features(WasmFeatures::all()).validate_all(wasm).map_err(invalid)?;
+        let mut module = Module::default();
+        let mut store = Store {
+            stack: Vec::new(),
+            globals: Vec::new(),
+            memory: Vec::new(),
+            max_pages: 0,
+            tables: Vec::new(),
+            elements: Vec::new(),
+            datas: Vec::new(),
+            fuel: config.fuel,
+            max_call_depth: config.max_call_depth,
+        };
+        let mut active_elements = Vec::new();
+        let mut active_datas = Vec::new();
+        let mut start = None;
+        let mut has_memory = false;
+
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload.map_err(invalid)? {
+                Payload::TypeSection(reader) => {
+                    for rec_group in reader {
+                        for sub_type in rec_group.map_err(invalid)?.into_types() {
+                            let CompositeInnerType::Func(func_type) = sub_type.composite_type.inner else {
+                                return unsupported("GC types");
+                            };
+                            module.types.push((func_type.params().to_vec(), func_type.results().to_vec()));
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) if reader.count() > 0 => return unsupported("imports"),
+                Payload::FunctionSection(reader) => {
+                    for type_index in reader {
+                        module.funcs.push(type_index.map_err(invalid)?);
+                    }
+                }
+                Payload::TableSection(reader) => {
+                    for table in reader {
+                        let table = table.map_err(invalid)?;
+                        if table.ty.table64 || table.ty.initial > config.max_table_size as u64 {
+                            return unsupported("table64 or a huge table");
+                        }
+                        let init = match table.init {
+                            TableInit::RefNull => Val::Ref(None),
+                            TableInit::Expr(expr) => store.eval(&expr)?,
+                        };
+                        let max = table.ty.maximum.map_or(config.max_table_size as u64, |max| max.min(config.max_table_size as u64));
+                        store.tables.push(Table { elements: vec![init; table.ty.initial as usize], max: max as u32 });
+                    }
+                }
+                Payload::MemorySection(reader) => {
+                    for memory in reader {
+                        let memory = memory.map_err(invalid)?;
+                        if has_memory || memory.memory64 || memory.shared || memory.page_size_log2.is_some() {
+                            return unsupported("several, 64-bit, shared or custom-page-size memories");
+                        }
+                        if memory.initial > config.max_pages as u64 {
+                            return unsupported(format!("a memory of {} pages", memory.initial));
+                        }
+                        has_memory = true;
+                        store.memory = vec![0; memory.initial as usize * PAGE_SIZE];
+                        store.max_pages = memory.maximum.map_or(config.max_pages as u64, |max| max.min(config.max_pages as u64)) as u32;
+                    }
+                }
+                Payload::TagSection(_) => return unsupported("exception handling"),
+                Payload::GlobalSection(reader) => {
+                    for global in reader {
+                        let value = store.eval(&global.map_err(invalid)?.init_expr)?;
+                        store.globals.push(value);
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export.map_err(invalid)?;
+                        if export.kind == ExternalKind::Func {
+                            module.exports.insert(export.name.to_string(), export.index);
+                        }
+                    }
+                }
+                Payload::StartSection { func, .. } => start = Some(func),
+                Payload::ElementSection(reader) => {
+                    for element in reader {
+                        let element = element.map_err(invalid)?;
+                        let items = match element.items {
+                            ElementItems::Functions(funcs) => funcs
+                                .into_iter()
+                                .map(|func| func.map(|func| Val::Ref(Some(func))).map_err(invalid))
+                                .collect::<Exec<Vec<_>>>()?,
+                            ElementItems::Expressions(_, exprs) => exprs
+                                .into_iter()
+                                .map(|expr| store.eval(&expr.map_err(invalid)?))
+                                .collect::<Exec<Vec<_>>>()?,
+                        };
+                        let index = store.elements.len() as u32;
+                        let declared = matches!(element.kind, ElementKind::Declared);
+                        match element.kind {
+                            ElementKind::Active { table_index, offset_expr } => {
+                                active_elements.push((index, table_index.unwrap_or(0), store.eval(&offset_expr)?));
+                            }
+                            ElementKind::Passive | ElementKind::Declared => {}
+                        }
+                        // declared segments are dropped right away, active ones once applied
+                        store.elements.push(if declared { Vec::new() } else { items });
+                    }
+                }
+                Payload::DataSection(reader) => {
+                    for data in reader {
+                        let data = data.map_err(invalid)?;
+                        if let DataKind::Active { offset_expr, .. } = &data.kind {
+                            active_datas.push((store.datas.len() as u32, store.eval(offset_expr)?));
+                        }
+                        store.datas.push(data.data);
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut locals = Vec::new();
+                    for local in body.get_locals_reader().map_err(invalid)? {
+                        let (count, ty) = local.map_err(invalid)?;
+                        locals.extend(std::iter::repeat_n(ty, count as usize));
+                    }
+                    let mut reader = body.get_operators_reader().map_err(invalid)?;
+                    let mut ops = Vec::new();
+                    while !reader.eof() {
+                        ops.push(reader.read().map_err(invalid)?);
+                    }
+                    module.codes.push(Code::new(locals, ops));
+                }
+                _ => {}
+            }
+        }
+
+        for (index, table, offset) in active_elements {
+            let Val::I32(offset) = offset else { return unsupported("64-bit table offsets") };
+            let len = store.elements[index as usize].len() as u32;
+            store.table_init(table, index, offset as u32, 0, len)?;
+            store.elements[index as usize].clear();
+        }
+        for (index, offset) in active_datas {
+            let Val::I32(offset) = offset else { return unsupported("64-bit memory offsets") };
+            let len = store.datas[index as usize].len() as u32;
+            store.memory_init(index, offset as u32, 0, len)?;
+            store.datas[index as usize] = &[];
+        }
+        let mut instance = Instance { module, store };
+        if let Some(func) = start {
+            instance.store.call(&instance.module, func, 0)?;
+        }
+        Ok(instance)
+    }
+
+    /// Call an exported function.
+    pub fn invoke(&mut self, export: &str, args: &[Value]) -> Exec<Vec<Value>> {
+        let func = *self.module.exports.get(export).ok_or_else(|| invalid(format!("no function export `{}`", export)))?;
+        let (params, results) = self.module.func_type(func).clone();
+        if params.len() != args.len() || params.iter().zip(args).any(|(ty, arg)| *ty != arg.ty()) {
+            return Err(invalid(format!("`{}` takes {:?}, not {:?}", export, params, args)));
+        }
+        self.store.stack.clear();
+        self.store.stack.extend(args.iter().map(|arg| Val::from(*arg)));
+        self.store.call(&self.module, func, 0)?;
+        let values = self.store.stack.split_off(self.store.stack.len() - results.len());
+        values
+            .into_iter()
+            .map(|value| match value {
+                Val::I32(v) => Ok(Value::I32(v)),
+                Val::I64(v) => Ok(Value::I64(v)),
+                Val::F32(v) => Ok(Value::F32(v)),
+                Val::F64(v) => Ok(Value::F64(v)),
+                Val::V128(v) => Ok(Value::V128(v)),
+                Val::Ref(_) => unsupported("reference results"),
+            })
+            .collect()
+    }
+}
+
+/// Instantiate `wasm` and call one export, see `Instance`.
+pub fn invoke_binary(wasm: &[u8], export: &str, args: &[Value], config: &InterpreterConfig) -> Exec<Vec<Value>> {
+    Instance::new(wasm, config)?.invoke(export, args)
+}
+
+/// Encode `module` and call one export, for checking a rewritten or mutated
+/// module without writing it out.
+pub fn invoke_module(module: &WasmModule, export: &str, args: &[Value], config: &InterpreterConfig) -> Exec<Vec<Value>> {
+    invoke_binary(&module.encode(), export, args, config)
+}
+
+/// Name of the interpreter among the engines of a harness.
+pub const ENGINE_NAME: &str = "interpreter";
+
+/// The interpreter as an engine of the differential harness, on a fresh
+/// instance for every call like the external runtimes.
+#[derive(Debug, Clone, Default)]
+pub struct Interpreter {
+    config: InterpreterConfig,
+}
+
+impl Interpreter {
+    pub fn new(config: InterpreterConfig) -> Self {
+        Interpreter { config }
+    }
+}
+
+impl Engine for Interpreter {
+    fn name(&self) -> &str {
+        ENGINE_NAME
+    }
+
+    fn invoke(&self, call: &Call) -> Result<Outcome> {
+        // deep recursion needs more than the default stack of a thread
+        let result = std::thread::scope(|scope| {
+            std::thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, || invoke_binary(call.wasm, call.export, call.args, &self.config))
+                .map(|thread| thread.join())
+        })?;
+        // a panic is a bug of the interpreter, reported like a crashing runtime
+        let result = match result {
+            Ok(result) => result,
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                let stderr = format!("the interpreter panicked at `{}`: {}", call.export, message);
+                return Ok(Outcome::Crashed { signal: None, stderr });
+            }
+        };
+        Ok(match result {
+            Ok(values) => Outcome::Returned { values },
+            Err(Stop::Trap(trap)) => Outcome::Trapped { message: trap.to_string() },
+            Err(Stop::OutOfFuel) => Outcome::Timeout,
+            Err(Stop::Unsupported(reason)) => Outcome::Unsupported { reason },
+            Err(Stop::Invalid(reason)) => return Err(anyhow!("the interpreter cannot run `{}`: {}", call.export, reason)),
+        })
+    }
+}
+
+macro_rules! pop {
+    ($store:expr, $variant:ident) => {
+        match $store.pop() {
+            Val::$variant(v) => v,
+            other => unreachable!("validated: expected {}, found {:?}", stringify!($variant), other),
+        }
+    };
+}
+
+impl<'a> Store<'a> {
+    fn pop(&mut self) -> Val {
+        self.stack.pop().expect("validated: operand stack underflow")
+    }
+
+    fn push(&mut self, value: Val) {
+        self.stack.push(value);
+    }
+
+    fn pop_u32(&mut self) -> u32 {
+        pop!(self, I32) as u32
+    }
+
+    fn pop_f32(&mut self) -> f32 {
+        f32::from_bits(pop!(self, F32))
+    }
+
+    fn pop_f64(&mut self) -> f64 {
+        f64::from_bits(pop!(self, F64))
+    }
+
+    // A constant expression of a global, a segment or a table.
+    fn eval(&self, expr: &ConstExpr) -> Exec<Val> {
+        let mut stack = Vec::new();
+        let mut reader = expr.get_operators_reader();
+        while !reader.eof() {
+            let value = match reader.read().map_err(invalid)? {
+                Operator::I32Const { value } => Val::I32(value),
+                Operator::I64Const { value } => Val::I64(value),
+                Operator::F32Const { value } => Val::F32(value.bits()),
+                Operator::F64Const { value } => Val::F64(value.bits()),
+                Operator::V128Const { value } => Val::V128(value.i128() as u128),
+                Operator::RefNull { .. } => Val::Ref(None),
+                Operator::RefFunc { function_index } => Val::Ref(Some(function_index)),
+                Operator::GlobalGet { global_index } => self.globals[global_index as usize],
+                op @ (Operator::I32Add | Operator::I32Sub | Operator::I32Mul) => {
+                    let (Some(Val::I32(b)), Some(Val::I32(a))) = (stack.pop(), stack.pop()) else { unreachable!("validated") };
+                    Val::I32(match op {
+                        Operator::I32Add => a.wrapping_add(b),
+                        Operator::I32Sub => a.wrapping_sub(b),
+                        _ => a.wrapping_mul(b),
+                    })
+                }
+                op @ (Operator::I64Add | Operator::I64Sub | Operator::I64Mul) => {
+                    let (Some(Val::I64(b)), Some(Val::I64(a))) = (stack.pop(), stack.pop()) else { unreachable!("validated") };
+                    Val::I64(match op {
+                        Operator::I64Add => a.wrapping_add(b),
+                        Operator::I64Sub => a.wrapping_sub(b),
+                        _ => a.wrapping_mul(b),
+                    })
+                }
+                Operator::End => break,
+                op => return unsupported(format!("{:?} in a constant expression", op)),
+            };
+            stack.push(value);
+        }
+        Ok(stack.pop().expect("validated"))
+    }
+
+    fn call(&mut self, module: &Module, func: u32, depth: usize) -> Exec<()> {
+        if depth >= self.max_call_depth {
+            return Err(Trap::CallStackExhausted.into());
+        }
+        let code = &module.codes[func as usize];
+        let (params, results) = module.func_type(func);
+        let mut locals: Vec<Val> = self.stack.split_off(self.stack.len() - params.len());
+        locals.extend(code.locals.iter().map(|ty| Val::default(*ty)));
+        let base = self.stack.len();
+        self.execute(module, code, &mut locals, depth)?;
+        // on `return` or a branch to the function label, extra values are left below the results
+        let results = self.stack.split_off(self.stack.len() - results.len());
+        self.stack.truncate(base);
+        self.stack.extend(results);
+        Ok(())
+    }
+
+    fn execute(&mut self, module: &Module, code: &Code, locals: &mut [Val], depth: usize) -> Exec<()> {
+        let mut labels: Vec<Label> = Vec::new();
+        let mut pc = 0;
+        loop {
+            if self.fuel == 0 {
+                return Err(Stop::OutOfFuel);
+            }
+            self.fuel -= 1;
+            match &code.ops[pc] {
+                Operator::Unreachable => return Err(Trap::Unreachable.into()),
+                Operator::Nop => {}
+                Operator::Block { blockty } | Operator::If { blockty } => {
+                    let (params, results) = module.block_arity(blockty);
+                    let taken = !matches!(code.ops[pc], Operator::If { .. }) || pop!(self, I32) != 0;
+                    let height = self.stack.len() - params;
+                    labels.push(Label { arity: results, height, target: code.ends[pc] + 1, is_loop: false });
+                    if !taken {
+                        // the `end` pops the label when there is no `else`
+                        pc = code.elses.get(&pc).map_or(code.ends[pc], |else_pc| else_pc + 1);
+                        continue;
+                    }
+                }
+                Operator::Loop { blockty } => {
+                    let (params, _) = module.block_arity(blockty);
+                    labels.push(Label { arity: params, height: self.stack.len() - params, target: pc + 1, is_loop: true });
+                }
+                Operator::Else => {
+                    // end of the then branch
+                    pc = code.ends[pc];
+                    continue;
+                }
+                Operator::End => {
+                    if labels.pop().is_none() {
+                        return Ok(());
+                    }
+                }
+                Operator::Br { relative_depth } => match self.branch(&mut labels, *relative_depth) {
+                    Some(target) => {
+                        pc = target;
+                        continue;
+                    }
+                    None => return Ok(()),
+                },
+                Operator::BrIf { relative_depth } => {
+                    if pop!(self, I32) != 0 {
+                        match self.branch(&mut labels, *relative_depth) {
+                            Some(target) => {
+                                pc = target;
+                                continue;
+                            }
+                            None => return Ok(()),
+                        }
+                    }
+                }
+                Operator::BrTable { targets } => {
+                    let index = self.pop_u32();
+                    let depth = match targets.targets().nth(index as usize) {
+                        Some(depth) => depth.map_err(invalid)?,
+                        None => targets.default(),
+                    };
+                    match self.branch(&mut labels, depth) {
+                        Some(target) => {
+                            pc = target;
+                            continue;
+                        }
+                        None => return Ok(()),
+                    }
+                }
+                Operator::Return => return Ok(()),
+                Operator::Call { function_index } => self.call(module, *function_index, depth + 1)?,
+                Operator::CallIndirect { type_index, table_index } => {
+                    let index = self.pop_u32();
+                    let table = &self.tables[*table_index as usize];
+                    let func = match table.elements.get(index as usize) {
+                        None => return Err(Trap::UndefinedElement.into()),
+                        Some(Val::Ref(None)) => return Err(Trap::UninitializedElement.into()),
+                        Some(Val::Ref(Some(func))) => *func,
+                        Some(other) => unreachable!("validated: {:?} in a table", other),
+                    };
+                    if *module.func_type(func) != module.types[*type_index as usize] {
+                        return Err(Trap::IndirectCallTypeMismatch.into());
+                    }
+                    self.call(module, func, depth + 1)?;
+                }
+                Operator::Drop => {
+                    self.pop();
+                }
+                Operator::Select | Operator::TypedSelect { .. } => {
+                    let condition = pop!(self, I32);
+                    let b = self.pop();
+                    let a = self.pop();
+                    self.push(if condition != 0 { a } else { b });
+                }
+                Operator::LocalGet { local_index } => self.push(locals[*local_index as usize]),
+                Operator::LocalSet { local_index } => locals[*local_index as usize] = self.pop(),
+                Operator::LocalTee { local_index } => {
+                    locals[*local_index as usize] = *self.stack.last().expect("validated");
+                }
+                Operator::GlobalGet { global_index } => self.push(self.globals[*global_index as usize]),
+                Operator::GlobalSet { global_index } => self.globals[*global_index as usize] = self.pop(),
+
+                Operator::I32Load { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I32(i32::from_le_bytes(bytes)));
+                }
+                Operator::I64Load { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I64(i64::from_le_bytes(bytes)));
+                }
+                Operator::F32Load { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::F32(u32::from_le_bytes(bytes)));
+                }
+                Operator::F64Load { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::F64(u64::from_le_bytes(bytes)));
+                }
+                Operator::I32Load8S { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I32(i8::from_le_bytes(bytes) as i32));
+                }
+                Operator::I32Load8U { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I32(u8::from_le_bytes(bytes) as i32));
+                }
+                Operator::I32Load16S { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I32(i16::from_le_bytes(bytes) as i32));
+                }
+                Operator::I32Load16U { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I32(u16::from_le_bytes(bytes) as i32));
+                }
+                Operator::I64Load8S { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I64(i8::from_le_bytes(bytes) as i64));
+                }
+                Operator::I64Load8U { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I64(u8::from_le_bytes(bytes) as i64));
+                }
+                Operator::I64Load16S { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I64(i16::from_le_bytes(bytes) as i64));
+                }
+                Operator::I64Load16U { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I64(u16::from_le_bytes(bytes) as i64));
+                }
+                Operator::I64Load32S { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I64(i32::from_le_bytes(bytes) as i64));
+                }
+                Operator::I64Load32U { memarg } => {
+                    let bytes = self.load(memarg)?;
+                    self.push(Val::I64(u32::from_le_bytes(bytes) as i64));
+                }
+                Operator::I32Store { memarg } => {
+                    let value = pop!(self, I32);
+                    self.store(memarg, &value.to_le_bytes())?;
+                }
+                Operator::I64Store { memarg } => {
+                    let value = pop!(self, I64);
+                    self.store(memarg, &value.to_le_bytes())?;
+                }
+                Operator::F32Store { memarg } => {
+                    let value = pop!(self, F32);
+                    self.store(memarg, &value.to_le_bytes())?;
+                }
+                Operator::F64Store { memarg } => {
+                    let value = pop!(self, F64);
+                    self.store(memarg, &value.to_le_bytes())?;
+                }
+                Operator::I32Store8 { memarg } => {
+                    let value = pop!(self, I32);
+                    self.store(memarg, &(value as u8).to_le_bytes())?;
+                }
+                Operator::I32Store16 { memarg } => {
+                    let value = pop!(self, I32);
+                    self.store(memarg, &(value as u16).to_le_bytes())?;
+                }
+                Operator::I64Store8 { memarg } => {
+                    let value = pop!(self, I64);
+                    self.store(memarg, &(value as u8).to_le_bytes())?;
+                }
+                Operator::I64Store16 { memarg } => {
+                    let value = pop!(self, I64);
+                    self.store(memarg, &(value as u16).to_le_bytes())?;
+                }
+                Operator::I64Store32 { memarg } => {
+                    let value = pop!(self, I64);
+                    self.store(memarg, &(value as u32).to_le_bytes())?;
+                }
+                Operator::MemorySize { .. } => self.push(Val::I32((self.memory.len() / PAGE_SIZE) as i32)),
+                Operator::MemoryGrow { .. } => {
+                    let delta = self.pop_u32() as usize;
+                    let pages = self.memory.len() / PAGE_SIZE;
+                    if pages + delta > self.max_pages as usize {
+                        self.push(Val::I32(-1));
+                    } else {
+                        self.memory.resize((pages + delta) * PAGE_SIZE, 0);
+                        self.push(Val::I32(pages as i32));
+                    }
+                }
+                Operator::MemoryFill { .. } => {
+                    let len = self.pop_u32() as usize;
+                    let value = pop!(self, I32) as u8;
+                    let dst = self.pop_u32() as usize;
+                    let range = checked_range(dst, len, self.memory.len()).ok_or(Trap::OutOfBoundsMemoryAccess)?;
+                    self.memory[range].fill(value);
+                }
+                Operator::MemoryCopy { .. } => {
+                    let len = self.pop_u32() as usize;
+                    let src = self.pop_u32() as usize;
+                    let dst = self.pop_u32() as usize;
+                    let src = checked_range(src, len, self.memory.len()).ok_or(Trap::OutOfBoundsMemoryAccess)?;
+                    checked_range(dst, len, self.memory.len()).ok_or(Trap::OutOfBoundsMemoryAccess)?;
+                    self.memory.copy_within(src, dst);
+                }
+                Operator::MemoryInit { data_index, .. } => {
+                    let len = self.pop_u32();
+                    let src = self.pop_u32();
+                    let dst = self.pop_u32();
+                    self.memory_init(*data_index, dst, src, len)?;
+                }
+                Operator::DataDrop { data_index } => self.datas[*data_index as usize] = &[],
+
+                Operator::I32Const { value } => self.push(Val::I32(*value)),
+                Operator::I64Const { value } => self.push(Val::I64(*value)),
+                Operator::F32Const { value } => self.push(Val::F32(value.bits())),
+                Operator::F64Const { value } => self.push(Val::F64(value.bits())),
+
+                Operator::RefNull { .. } => self.push(Val::Ref(None)),
+                Operator::RefIsNull => {
+                    let value = pop!(self, Ref);
+                    self.push(Val::I32(value.is_none() as i32));
+                }
+                Operator::RefFunc { function_index } => self.push(Val::Ref(Some(*function_index))),
+                Operator::TableGet { table } => {
+                    let index = self.pop_u32() as usize;
+                    let value = *self.tables[*table as usize].elements.get(index).ok_or(Trap::OutOfBoundsTableAccess)?;
+                    self.push(value);
+                }
+                Operator::TableSet { table } => {
+                    let value = self.pop();
+                    let index = self.pop_u32() as usize;
+                    let slot = self.tables[*table as usize].elements.get_mut(index).ok_or(Trap::OutOfBoundsTableAccess)?;
+                    *slot = value;
+                }
+                Operator::TableSize { table } => {
+                    let size = self.tables[*table as usize].elements.len();
+                    self.push(Val::I32(size as i32));
+                }
+                Operator::TableGrow { table } => {
+                    let delta = self.pop_u32() as usize;
+                    let init = self.pop();
+                    let table = &mut self.tables[*table as usize];
+                    let size = table.elements.len();
+                    if size + delta > table.max as usize {
+                        self.push(Val::I32(-1));
+                    } else {
+                        table.elements.resize(size + delta, init);
+                        self.push(Val::I32(size as i32));
+                    }
+                }
+                Operator::TableFill { table } => {
+                    let len = self.pop_u32() as usize;
+                    let value = self.pop();
+                    let dst = self.pop_u32() as usize;
+                    let elements = &mut self.tables[*table as usize].elements;
+                    let range = checked_range(dst, len, elements.len()).ok_or(Trap::OutOfBoundsTableAccess)?;
+                    elements[range].fill(value);
+                }
+                Operator::TableCopy { dst_table, src_table } => {
+                    let len = self.pop_u32() as usize;
+                    let src = self.pop_u32() as usize;
+                    let dst = self.pop_u32() as usize;
+                    let src_len = self.tables[*src_table as usize].elements.len();
+                    let dst_len = self.tables[*dst_table as usize].elements.len();
+                    let src = checked_range(src, len, src_len).ok_or(Trap::OutOfBoundsTableAccess)?;
+                    let dst = checked_range(dst, len, dst_len).ok_or(Trap::OutOfBoundsTableAccess)?;
+                    let values = self.tables[*src_table as usize].elements[src].to_vec();
+                    self.tables[*dst_table as usize].elements[dst].copy_from_slice(&values);
+                }
+                Operator::TableInit { elem_index, table } => {
+                    let len = self.pop_u32();
+                    let src = self.pop_u32();
+                    let dst = self.pop_u32();
+                    self.table_init(*table, *elem_index, dst, src, len)?;
+                }
+                Operator::ElemDrop { elem_index } => self.elements[*elem_index as usize].clear(),
+
+                op => self.numeric(op)?,
+            }
+            pc += 1;
+        }
+    }
+
+    // Where execution continues after a branch to `depth`, `None` to return
+    // from the function.
+    fn branch(&mut self, labels: &mut Vec<Label>, depth: u32) -> Option<usize> {
+        let depth = depth as usize;
+        if depth == labels.len() {
+            return None;
+        }
+        let label = &labels[labels.len() - 1 - depth];
+        let values = self.stack.split_off(self.stack.len() - label.arity);
+        self.stack.truncate(label.height);
+        self.stack.extend(values);
+        let target = label.target;
+        // a loop label stays, the body runs again
+        let keep = if label.is_loop { labels.len() - depth } else { labels.len() - 1 - depth };
+        labels.truncate(keep);
+        Some(target)
+    }
+
+    fn address(&mut self, memarg: &MemArg, size: usize) -> Exec<usize> {
+        let base = self.pop_u32() as u64;
+        let start = base + memarg.offset;
+        if start + size as u64 > self.memory.len() as u64 {
+            return Err(Trap::OutOfBoundsMemoryAccess.into());
+        }
+        Ok(start as usize)
+    }
+
+    fn load<const N: usize>(&mut self, memarg: &MemArg) -> Exec<[u8; N]> {
+        let start = self.address(memarg, N)?;
+        Ok(self.memory[start..start + N].try_into().expect("N bytes"))
+    }
+
+    fn store(&mut self, memarg: &MemArg, bytes: &[u8]) -> Exec<()> {
+        let start = self.address(memarg, bytes.len())?;
+        self.memory[start..start + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn memory_init(&mut self, data: u32, dst: u32, src: u32, len: u32) -> Exec<()> {
+        let bytes = self.datas[data as usize];
+        let src = checked_range(src as usize, len as usize, bytes.len()).ok_or(Trap::OutOfBoundsMemoryAccess)?;
+        let dst = checked_range(dst as usize, len as usize, self.memory.len()).ok_or(Trap::OutOfBoundsMemoryAccess)?;
+        self.memory[dst].copy_from_slice(&bytes[src]);
+        Ok(())
+    }
+
+    fn table_init(&mut self, table: u32, element: u32, dst: u32, src: u32, len: u32) -> Exec<()> {
+        let items = &self.elements[element as usize];
+        let src = checked_range(src as usize, len as usize, items.len()).ok_or(Trap::OutOfBoundsTableAccess)?;
+        let elements = &mut self.tables[table as usize].elements;
+        let dst = checked_range(dst as usize, len as usize, elements.len()).ok_or(Trap::OutOfBoundsTableAccess)?;
+        elements[dst].copy_from_slice(&items[src]);
+        Ok(())
+    }
+
+    // Instructions without immediates that only work on the operand stack.
+    fn numeric(&mut self, op: &Operator) -> Exec<()> {
+        macro_rules! unary {
+            ($ty:ident, |$a:ident| $result:expr) => {{
+                let $a = pop!(self, $ty);
+                self.push($result);
+            }};
+        }
+        macro_rules! binary {
+            ($ty:ident, |$a:ident, $b:ident| $result:expr) => {{
+                let $b = pop!(self, $ty);
+                let $a = pop!(self, $ty);
+                self.push($result);
+            }};
+        }
+        macro_rules! float_unary {
+            ($pop:ident, |$a:ident| $result:expr) => {{
+                let $a = self.$pop();
+                self.push($result);
+            }};
+        }
+        macro_rules! float_binary {
+            ($pop:ident, |$a:ident, $b:ident| $result:expr) => {{
+                let $b = self.$pop();
+                let $a = self.$pop();
+                self.push($result);
+            }};
+        }
+        let bool = |b: bool| Val::I32(b as i32);
+
+        match op {
+            Operator::I32Eqz => unary!(I32, |a| bool(a == 0)),
+            Operator::I32Eq => binary!(I32, |a, b| bool(a == b)),
+            Operator::I32Ne => binary!(I32, |a, b| bool(a != b)),
+            Operator::I32LtS => binary!(I32, |a, b| bool(a < b)),
+            Operator::I32LtU => binary!(I32, |a, b| bool((a as u32) < (b as u32))),
+            Operator::I32GtS => binary!(I32, |a, b| bool(a > b)),
+            Operator::I32GtU => binary!(I32, |a, b| bool((a as u32) > (b as u32))),
+            Operator::I32LeS => binary!(I32, |a, b| bool(a <= b)),
+            Operator::I32LeU => binary!(I32, |a, b| bool((a as u32) <= (b as u32))),
+            Operator::I32GeS => binary!(I32, |a, b| bool(a >= b)),
+            Operator::I32GeU => binary!(I32, |a, b| bool((a as u32) >= (b as u32))),
+            Operator::I64Eqz => unary!(I64, |a| bool(a == 0)),
+            Operator::I64Eq => binary!(I64, |a, b| bool(a == b)),
+            Operator::I64Ne => binary!(I64, |a, b| bool(a != b)),
+            Operator::I64LtS => binary!(I64, |a, b| bool(a < b)),
+            Operator::I64LtU => binary!(I64, |a, b| bool((a as u64) < (b as u64))),
+            Operator::I64GtS => binary!(I64, |a, b| bool(a > b)),
+            Operator::I64GtU => binary!(I64, |a, b| bool((a as u64) > (b as u64))),
+            Operator::I64LeS => binary!(I64, |a, b| bool(a <= b)),
+            Operator::I64LeU => binary!(I64, |a, b| bool((a as u64) <= (b as u64))),
+            Operator::I64GeS => binary!(I64, |a, b| bool(a >= b)),
+            Operator::I64GeU => binary!(I64, |a, b| bool((a as u64) >= (b as u64))),
+            Operator::F32Eq => float_binary!(pop_f32, |a, b| bool(a == b)),
+            Operator::F32Ne => float_binary!(pop_f32, |a, b| bool(a != b)),
+            Operator::F32Lt => float_binary!(pop_f32, |a, b| bool(a < b)),
+            Operator::F32Gt => float_binary!(pop_f32, |a, b| bool(a > b)),
+            Operator::F32Le => float_binary!(pop_f32, |a, b| bool(a <= b)),
+            Operator::F32Ge => float_binary!(pop_f32, |a, b| bool(a >= b)),
+            Operator::F64Eq => float_binary!(pop_f64, |a, b| bool(a == b)),
+            Operator::F64Ne => float_binary!(pop_f64, |a, b| bool(a != b)),
+            Operator::F64Lt => float_binary!(pop_f64, |a, b| bool(a < b)),
+            Operator::F64Gt => float_binary!(pop_f64, |a, b| bool(a > b)),
+            Operator::F64Le => float_binary!(pop_f64, |a, b| bool(a <= b)),
+            Operator::F64Ge => float_binary!(pop_f64, |a, b| bool(a >= b)),
+
+            Operator::I32Clz => unary!(I32, |a| Val::I32(a.leading_zeros() as i32)),
+            Operator::I32Ctz => unary!(I32, |a| Val::I32(a.trailing_zeros() as i32)),
+            Operator::I32Popcnt => unary!(I32, |a| Val::I32(a.count_ones() as i32)),
+            Operator::I32Add => binary!(I32, |a, b| Val::I32(a.wrapping_add(b))),
+            Operator::I32Sub => binary!(I32, |a, b| Val::I32(a.wrapping_sub(b))),
+            Operator::I32Mul => binary!(I32, |a, b| Val::I32(a.wrapping_mul(b))),
+            Operator::I32DivS => binary!(I32, |a, b| Val::I32(match (a, b) {
+                (_, 0) => return Err(Trap::IntegerDivideByZero.into()),
+                (i32::MIN, -1) => return Err(Trap::IntegerOverflow.into()),
+                _ => a / b,
+            })),
+            Operator::I32DivU => binary!(I32, |a, b| Val::I32(match b {
+                0 => return Err(Trap::IntegerDivideByZero.into()),
+                _ => ((a as u32) / (b as u32)) as i32,
+            })),
+            Operator::I32RemS => binary!(I32, |a, b| Val::I32(match b {
+                0 => return Err(Trap::IntegerDivideByZero.into()),
+                _ => a.wrapping_rem(b),
+            })),
+            Operator::I32RemU => binary!(I32, |a, b| Val::I32(match b {
+                0 => return Err(Trap::IntegerDivideByZero.into()),
+                _ => ((a as u32) % (b as u32)) as i32,
+            })),
+            Operator::I32And => binary!(I32, |a, b| Val::I32(a & b)),
+            Operator::I32Or => binary!(I32, |a, b| Val::I32(a | b)),
+            Operator::I32Xor => binary!(I32, |a, b| Val::I32(a ^ b)),
+            Operator::I32Shl => binary!(I32, |a, b| Val::I32(a.wrapping_shl(b as u32))),
+            Operator::I32ShrS => binary!(I32, |a, b| Val::I32(a.wrapping_shr(b as u32))),
+            Operator::I32ShrU => binary!(I32, |a, b| Val::I32((a as u32).wrapping_shr(b as u32) as i32)),
+            Operator::I32Rotl => binary!(I32, |a, b| Val::I32(a.rotate_left(b as u32 % 32))),
+            Operator::I32Rotr => binary!(I32, |a, b| Val::I32(a.rotate_right(b as u32 % 32))),
+            Operator::I64Clz => unary!(I64, |a| Val::I64(a.leading_zeros() as i64)),
+            Operator::I64Ctz => unary!(I64, |a| Val::I64(a.trailing_zeros() as i64)),
+            Operator::I64Popcnt => unary!(I64, |a| Val::I64(a.count_ones() as i64)),
+            Operator::I64Add => binary!(I64, |a, b| Val::I64(a.wrapping_add(b))),
+            Operator::I64Sub => binary!(I64, |a, b| Val::I64(a.wrapping_sub(b))),
+            Operator::I64Mul => binary!(I64, |a, b| Val::I64(a.wrapping_mul(b))),
+            Operator::I64DivS => binary!(I64, |a, b| Val::I64(match (a, b) {
+                (_, 0) => return Err(Trap::IntegerDivideByZero.into()),
+                (i64::MIN, -1) => return Err(Trap::IntegerOverflow.into()),
+                _ => a / b,
+            })),
+            Operator::I64DivU => binary!(I64, |a, b| Val::I64(match b {
+                0 => return Err(Trap::IntegerDivideByZero.into()),
+                _ => ((a as u64) / (b as u64)) as i64,
+            })),
+            Operator::I64RemS => binary!(I64, |a, b| Val::I64(match b {
+                0 => return Err(Trap::IntegerDivideByZero.into()),
+                _ => a.wrapping_rem(b),
+            })),
+            Operator::I64RemU => binary!(I64, |a, b| Val::I64(match b {
+                0 => return Err(Trap::IntegerDivideByZero.into()),
+                _ => ((a as u64) % (b as u64)) as i64,
+            })),
+            Operator::I64And => binary!(I64, |a, b| Val::I64(a & b)),
+            Operator::I64Or => binary!(I64, |a, b| Val::I64(a | b)),
+            Operator::I64Xor => binary!(I64, |a, b| Val::I64(a ^ b)),
+            Operator::I64Shl => binary!(I64, |a, b| Val::I64(a.wrapping_shl(b as u32))),
+            Operator::I64ShrS => binary!(I64, |a, b| Val::I64(a.wrapping_shr(b as u32))),
+            Operator::I64ShrU => binary!(I64, |a, b| Val::I64((a as u64).wrapping_shr(b as u32) as i64)),
+            Operator::I64Rotl => binary!(I64, |a, b| Val::I64(a.rotate_left((b as u64 % 64) as u32))),
+            Operator::I64Rotr => binary!(I64, |a, b| Val::I64(a.rotate_right((b as u64 % 64) as u32))),
+
+            Operator::F32Abs => unary!(F32, |a| Val::F32(a & 0x7FFF_FFFF)),
+            Operator::F32Neg => unary!(F32, |a| Val::F32(a ^ 0x8000_0000)),
+            Operator::F32Copysign => binary!(F32, |a, b| Val::F32((a & 0x7FFF_FFFF) | (b & 0x8000_0000))),
+            Operator::F32Ceil => float_unary!(pop_f32, |a| Val::f32(a.ceil())),
+            Operator::F32Floor => float_unary!(pop_f32, |a| Val::f32(a.floor())),
+            Operator::F32Trunc => float_unary!(pop_f32, |a| Val::f32(a.trunc())),
+            Operator::F32Nearest => float_unary!(pop_f32, |a| Val::f32(a.round_ties_even())),
+            Operator::F32Sqrt => float_unary!(pop_f32, |a| Val::f32(a.sqrt())),
+            Operator::F32Add => float_binary!(pop_f32, |a, b| Val::f32(a + b)),
+            Operator::F32Sub => float_binary!(pop_f32, |a, b| Val::f32(a - b)),
+            Operator::F32Mul => float_binary!(pop_f32, |a, b| Val::f32(a * b)),
+            Operator::F32Div => float_binary!(pop_f32, |a, b| Val::f32(a / b)),
+            Operator::F32Min => float_binary!(pop_f32, |a, b| Val::f32(fmin(a as f64, b as f64) as f32)),
+            Operator::F32Max => float_binary!(pop_f32, |a, b| Val::f32(fmax(a as f64, b as f64) as f32)),
+            Operator::F64Abs => unary!(F64, |a| Val::F64(a & 0x7FFF_FFFF_FFFF_FFFF)),
+            Operator::F64Neg => unary!(F64, |a| Val::F64(a ^ 0x8000_0000_0000_0000)),
+            Operator::F64Copysign => {
+                binary!(F64, |a, b| Val::F64((a & 0x7FFF_FFFF_FFFF_FFFF) | (b & 0x8000_0000_0000_0000)))
+            }
+            Operator::F64Ceil => float_unary!(pop_f64, |a| Val::f64(a.ceil())),
+            Operator::F64Floor => float_unary!(pop_f64, |a| Val::f64(a.floor())),
+            Operator::F64Trunc => float_unary!(pop_f64, |a| Val::f64(a.trunc())),
+            Operator::F64Nearest => float_unary!(pop_f64, |a| Val::f64(a.round_ties_even())),
+            Operator::F64Sqrt => float_unary!(pop_f64, |a| Val::f64(a.sqrt())),
+            Operator::F64Add => float_binary!(pop_f64, |a, b| Val::f64(a + b)),
+            Operator::F64Sub => float_binary!(pop_f64, |a, b| Val::f64(a - b)),
+            Operator::F64Mul => float_binary!(pop_f64, |a, b| Val::f64(a * b)),
+            Operator::F64Div => float_binary!(pop_f64, |a, b| Val::f64(a / b)),
+            Operator::F64Min => float_binary!(pop_f64, |a, b| Val::f64(fmin(a, b))),
+            Operator::F64Max => float_binary!(pop_f64, |a, b| Val::f64(fmax(a, b))),
+
+            Operator::I32WrapI64 => unary!(I64, |a| Val::I32(a as i32)),
+            Operator::I32TruncF32S => float_unary!(pop_f32, |a| Val::I32(truncate(a as f64, I32_RANGE)? as i32)),
+            Operator::I32TruncF32U => float_unary!(pop_f32, |a| Val::I32(truncate(a as f64, U32_RANGE)? as u32 as i32)),
+            Operator::I32TruncF64S => float_unary!(pop_f64, |a| Val::I32(truncate(a, I32_RANGE)? as i32)),
+            Operator::I32TruncF64U => float_unary!(pop_f64, |a| Val::I32(truncate(a, U32_RANGE)? as u32 as i32)),
+            Operator::I64ExtendI32S => unary!(I32, |a| Val::I64(a as i64)),
+            Operator::I64ExtendI32U => unary!(I32, |a| Val::I64(a as u32 as i64)),
+            Operator::I64TruncF32S => float_unary!(pop_f32, |a| Val::I64(truncate(a as f64, I64_RANGE)? as i64)),
+            Operator::I64TruncF32U => float_unary!(pop_f32, |a| Val::I64(truncate(a as f64, U64_RANGE)? as u64 as i64)),
+            Operator::I64TruncF64S => float_unary!(pop_f64, |a| Val::I64(truncate(a, I64_RANGE)? as i64)),
+            Operator::I64TruncF64U => float_unary!(pop_f64, |a| Val::I64(truncate(a, U64_RANGE)? as u64 as i64)),
+            Operator::F32ConvertI32S => unary!(I32, |a| Val::f32(a as f32)),
+            Operator::F32ConvertI32U => unary!(I32, |a| Val::f32(a as u32 as f32)),
+            Operator::F32ConvertI64S => unary!(I64, |a| Val::f32(a as f32)),
+            Operator::F32ConvertI64U => unary!(I64, |a| Val::f32(a as u64 as f32)),
+            Operator::F32DemoteF64 => float_unary!(pop_f64, |a| Val::f32(a as f32)),
+            Operator::F64ConvertI32S => unary!(I32, |a| Val::f64(a as f64)),
+            Operator::F64ConvertI32U => unary!(I32, |a| Val::f64(a as u32 as f64)),
+            Operator::F64ConvertI64S => unary!(I64, |a| Val::f64(a as f64)),
+            Operator::F64ConvertI64U => unary!(I64, |a| Val::f64(a as u64 as f64)),
+            Operator::F64PromoteF32 => float_unary!(pop_f32, |a| Val::f64(a as f64)),
+            Operator::I32ReinterpretF32 => unary!(F32, |a| Val::I32(a as i32)),
+            Operator::I64ReinterpretF64 => unary!(F64, |a| Val::I64(a as i64)),
+            Operator::F32ReinterpretI32 => unary!(I32, |a| Val::F32(a as u32)),
+            Operator::F64ReinterpretI64 => unary!(I64, |a| Val::F64(a as u64)),
+
+            Operator::I32Extend8S => unary!(I32, |a| Val::I32(a as i8 as i32)),
+            Operator::I32Extend16S => unary!(I32, |a| Val::I32(a as i16 as i32)),
+            Operator::I64Extend8S => unary!(I64, |a| Val::I64(a as i8 as i64)),
+            Operator::I64Extend16S => unary!(I64, |a| Val::I64(a as i16 as i64)),
+            Operator::I64Extend32S => unary!(I64, |a| Val::I64(a as i32 as i64)),
+
+            // `as` saturates and maps NaN to 0, exactly like these
+            Operator::I32TruncSatF32S => float_unary!(pop_f32, |a| Val::I32(a as i32)),
+            Operator::I32TruncSatF32U => float_unary!(pop_f32, |a| Val::I32(a as u32 as i32)),
+            Operator::I32TruncSatF64S => float_unary!(pop_f64, |a| Val::I32(a as i32)),
+            Operator::I32TruncSatF64U => float_unary!(pop_f64, |a| Val::I32(a as u32 as i32)),
+            Operator::I64TruncSatF32S => float_unary!(pop_f32, |a| Val::I64(a as i64)),
+            Operator::I64TruncSatF32U => float_unary!(pop_f32, |a| Val::I64(a as u64 as i64)),
+            Operator::I64TruncSatF64S => float_unary!(pop_f64, |a| Val::I64(a as i64)),
+            Operator::I64TruncSatF64U => float_unary!(pop_f64, |a| Val::I64(a as u64 as i64)),
+
+            op => return unsupported(format!("{:?}", op)),
+        }
+        Ok(())
+    }
+}
+
+// `start..start + len` if it fits in `size`.
+fn checked_range(start: usize, len: usize, size: usize) -> Option<std::ops::Range<usize>> {
+    let end = start.checked_add(len)?;
+    (end <= size).then_some(start..end)
+}
+
+// Integers a truncated float must fall in, as `min <= x < max`.
+const I32_RANGE: (f64, f64) = (-2147483648.0, 2147483648.0);
+const U32_RANGE: (f64, f64) = (0.0, 4294967296.0);
+const I64_RANGE: (f64, f64) = (-9223372036854775808.0, 9223372036854775808.0);
+const U64_RANGE: (f64, f64) = (0.0, 18446744073709551616.0);
+
+// The trapping float to integer conversions; `-0.0` passes as `0.0`.
+fn truncate(x: f64, (min, max): (f64, f64)) -> Exec<f64> {
+    if x.is_nan() {
+        return Err(Trap::InvalidConversionToInteger.into());
+    }
+    let truncated = x.trunc();
+    if truncated < min || truncated >= max {
+        return Err(Trap::IntegerOverflow.into());
+    }
+    Ok(truncated)
+}
+
+// `min` and `max` of the spec: NaN wins and `-0.0` is below `0.0`. Exact
+// for `f32` operands too.
+fn fmin(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == 0.0 && b == 0.0 {
+        if a.is_sign_negative() { a } else { b }
+    } else {
+        a.min(b)
+    }
+}
+
+fn fmax(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == 0.0 && b == 0.0 {
+        if a.is_sign_positive() { a } else { b }
+    } else {
+        a.max(b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use super::*;
+
+    #[test]
+    fn test_interpreter() {
+        let wasm = wat::parse_str(r#"
+            (module
+              (type $binary (func (param i32 i32) (result i32)))
+              (memory 1 2)
+              (table 2 funcref)
+              (elem (i32.const 0) $div)
+              (data (i32.const 8) "\2a")
+              (global $calls (mut i32) (i32.const 0))
+              (func $div (export "div") (type $binary) local.get 0 local.get 1 i32.div_s)
+              (func (export "indirect") (param i32) (result i32)
+                i32.const 7 i32.const 2 local.get 0 call_indirect (type $binary))
+              (func (export "fac") (param i64) (result i64) (local $acc i64)
+                i64.const 1 local.set $acc
+                block $done
+                  loop $next
+                    local.get 0 i64.eqz br_if $done
+                    local.get $acc local.get 0 i64.mul local.set $acc
+                    local.get 0 i64.const 1 i64.sub local.set 0
+                    br $next
+                  end
+                end
+                local.get $acc)
+              (func (export "pick") (param i32) (result i32 i32)
+                i32.const 1 i32.const 2
+                block (param i32 i32) (result i32 i32) local.get 0 br_table 0 1 end
+                i32.add i32.const 0)
+              (func (export "sign") (param i32) (result i32) (local i32)
+                local.get 0 i32.const 0 i32.lt_s if i32.const 1 local.set 1 end
+                local.get 0 if (result i32) i32.const 1 else i32.const 0 end local.get 1 i32.sub)
+              (func (export "nan") (param f32) (result f32 f32 f32)
+                local.get 0 f32.neg  local.get 0 f32.const 1 f32.add  f32.const -0 f32.const 0 f32.min)
+              (func (export "trunc") (param f64) (result i32) local.get 0 i32.trunc_f64_u)
+              (func (export "load") (param i32) (result i32)
+                local.get 0 i32.load8_u offset=8 global.get $calls i32.const 1 i32.add global.set $calls)
+              (func (export "spin") loop br 0 end)
+              (func $recurse (export "recurse") (result i32) call $recurse)
+              (func (export "simd") (result i32) v128.const i32x4 1 2 3 4 i32x4.extract_lane 0))
+        "#).unwrap();
+        let config = InterpreterConfig { fuel: 10_000, max_call_depth: 100, ..InterpreterConfig::default() };
+        let invoke = |export, args: &[Value]| invoke_binary(&wasm, export, args, &config);
+
+        assert_eq!(invoke("div", &[Value::I32(-7), Value::I32(2)]), Ok(vec![Value::I32(-3)]));
+        assert_eq!(invoke("div", &[Value::I32(1), Value::I32(0)]), Err(Stop::Trap(Trap::IntegerDivideByZero)));
+        assert_eq!(invoke("div", &[Value::I32(i32::MIN), Value::I32(-1)]), Err(Stop::Trap(Trap::IntegerOverflow)));
+        assert_eq!(invoke("indirect", &[Value::I32(0)]), Ok(vec![Value::I32(3)]));
+        assert_eq!(invoke("indirect", &[Value::I32(1)]), Err(Stop::Trap(Trap::UninitializedElement)));
+        assert_eq!(invoke("indirect", &[Value::I32(2)]), Err(Stop::Trap(Trap::UndefinedElement)));
+        assert_eq!(invoke("fac", &[Value::I64(20)]), Ok(vec![Value::I64(2432902008176640000)]));
+        assert_eq!(invoke("pick", &[Value::I32(0)]), Ok(vec![Value::I32(3), Value::I32(0)]));
+        assert_eq!(invoke("pick", &[Value::I32(5)]), Ok(vec![Value::I32(1), Value::I32(2)]));
+
+        assert_eq!(invoke("sign", &[Value::I32(-5)]), Ok(vec![Value::I32(0)]));
+        assert_eq!(invoke("sign", &[Value::I32(0)]), Ok(vec![Value::I32(0)]));
+        assert_eq!(invoke("sign", &[Value::I32(5)]), Ok(vec![Value::I32(1)]));
+
+        // a NaN with a payload keeps it through `neg` but not through arithmetic
+        let results = invoke("nan", &[Value::F32(0x7FA0_0001)]).unwrap();
+        assert_eq!(results, vec![Value::F32(0xFFA0_0001), Value::F32(CANONICAL_NAN32), Value::F32(0x8000_0000)]);
+        assert_eq!(invoke("trunc", &[Value::F64((-0.9f64).to_bits())]), Ok(vec![Value::I32(0)]));
+        assert_eq!(invoke("trunc", &[Value::F64(4294967296.0f64.to_bits())]), Err(Stop::Trap(Trap::IntegerOverflow)));
+        assert_eq!(invoke("trunc", &[Value::F64(f64::NAN.to_bits())]), Err(Stop::Trap(Trap::InvalidConversionToInteger)));
+
+        let mut instance = Instance::new(&wasm, &config).unwrap();
+        assert_eq!(instance.invoke("load", &[Value::I32(0)]), Ok(vec![Value::I32(42)]));
+        assert_eq!(instance.invoke("load", &[Value::I32(65528)]), Err(Stop::Trap(Trap::OutOfBoundsMemoryAccess)));
+        assert_eq!(instance.store.globals, vec![Val::I32(1)]);
+
+        assert_eq!(invoke("spin", &[]), Err(Stop::OutOfFuel));
+        assert_eq!(invoke("recurse", &[]), Err(Stop::Trap(Trap::CallStackExhausted)));
+        assert!(matches!(invoke("simd", &[]), Err(Stop::Unsupported(_))));
+        assert!(matches!(invoke("div", &[Value::I64(1), Value::I32(1)]), Err(Stop::Invalid(_))));
+
+        let interpreter = Interpreter::new(config.clone());
+        let call = Call { path: Path::new("unused"), wasm: &wasm, export: "spin", args: &[], results: &[] };
+        assert_eq!(interpreter.invoke(&call).unwrap(), Outcome::Timeout);
+        let call = Call { export: "div", args: &[Value::I32(6), Value::I32(0)], results: &[ValType::I32], ..call };
+        assert_eq!(interpreter.invoke(&call).unwrap(), Outcome::Trapped { message: "integer divide by zero".to_string() });
+        assert!(interpreter.invoke(&Call { wasm: b"\0asm", ..call }).is_err());
+
+        let module = WasmModule::new(&wasm);
+        assert_eq!(invoke_module(&module, "fac", &[Value::I64(5)], &config), Ok(vec![Value::I64(120)]));
+        assert!(matches!(invoke_module(&module, "missing", &[], &config), Err(Stop::Invalid(_))));
+    }
+}
@@ -0,0 +1,7 @@
+pub mod command;
+pub mod differential;
+pub mod engine;
+pub mod inputs;
+pub mod interpreter;
+pub mod triage;
+pub mod value;
@@ -0,0 +1,410 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::differential::{Divergence, RuntimeOutcome};
+use crate::engine::Outcome;
+use crate::interpreter;
+
+
+const MAX_FRAMES: usize = 5;
+const MAX_MESSAGE: usize = 120;
+const SIGABRT: i32 = 6;
+const SIGKILL: i32 = 9;
+
+// Matched against lowercased runtime output.
+const ASSERTION: &[&str] = &[
+    "assertion", "panicked at", "check failed", "dcheck", "unreachable code", "internal error", "fatal error",
+];
+const OUT_OF_MEMORY: &[&str] = &["out of memory", "memory allocation", "failed to allocate", "cannot allocate", "allocation failed"];
+const VALIDATION: &[&str] = &[
+    "validation", "validate", "invalid module", "compileerror", "failed to compile", "failed to parse", "malformed",
+];
+
+// Frames of the panic and signal machinery, which every crash shares.
+const NOISE: &[&str] = &[
+    "std::", "core::", "alloc::", "rust_begin_unwind", "rust_panic", "__rust", "__libc", "__gi_", "__pthread", "abort",
+    "raise", "gsignal", "backtrace", "_start", "<unknown>", "0x",
+];
+
+/// Kind of a divergence, most severe first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Class {
+    /// The runtime hit one of its own assertions or panicked.
+    Assertion,
+    /// The runtime died from a signal.
+    CrashSignal,
+    OutOfMemory,
+    /// The runtime rejected a module the generator validated.
+    ValidationDisagreement,
+    /// One side trapped, the other returned.
+    TrapMismatch,
+    WrongResult,
+    Timeout,
+}
+
+impl Class {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Class::Assertion              => "assertion",
+            Class::CrashSignal            => "crash_signal",
+            Class::OutOfMemory            => "out_of_memory",
+            Class::ValidationDisagreement => "validation_disagreement",
+            Class::TrapMismatch           => "trap_mismatch",
+            Class::WrongResult            => "wrong_result",
+            Class::Timeout                => "timeout",
+        }
+    }
+}
+
+/// What a divergence is, blamed on one runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Classification {
+    pub class: Class,
+    pub runtime: String,
+    /// Stack signature or normalized message; what tells bugs of the same
+    /// class and runtime apart.
+    pub signature: String,
+}
+
+impl Classification {
+    /// Stays the same across runs, platforms and releases.
+    pub fn bucket_id(&self) -> String {
+        let mut hash: u64 = 0xCBF2_9CE4_8422_2325;
+        for part in [self.class.name(), &self.runtime, &self.signature] {
+            for byte in part.bytes().chain([0]) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+            }
+        }
+        format!("{:016x}", hash)
+    }
+}
+
+/// Classify a divergence. When the reference interpreter took part its
+/// outcome is right; otherwise the outcome most runtimes agree with is, the
+/// first winning ties. The divergence is blamed on the runtime with the most
+/// severe disagreement. `None` if nothing disagrees.
+pub fn classify(divergence: &Divergence) -> Option<Classification> {
+    let outcomes: Vec<&RuntimeOutcome> = divergence
+        .outcomes
+        .iter()
+        .filter(|runtime| !matches!(runtime.outcome, Outcome::Unsupported { .. }))
+        .collect();
+    let candidates: Vec<&RuntimeOutcome> =
+        outcomes.iter().copied().filter(|runtime| failure(&runtime.outcome).is_none()).collect();
+    let votes = |runtime: &RuntimeOutcome| outcomes.iter().filter(|other| other.outcome.agrees_with(&runtime.outcome)).count();
+    let reference = match candidates.iter().find(|runtime| runtime.runtime == interpreter::ENGINE_NAME) {
+        Some(oracle) => Some(&oracle.outcome),
+        // `max_by_key` keeps the last of equal elements
+        None => candidates.iter().rev().max_by_key(|runtime| votes(runtime)).map(|runtime| &runtime.outcome),
+    };
+
+    outcomes
+        .iter()
+        .filter_map(|runtime| {
+            let (class, signature) = classify_outcome(&runtime.outcome, reference)?;
+            Some(Classification { class, runtime: runtime.runtime.clone(), signature })
+        })
+        .min_by_key(|classification| classification.class)
+}
+
+/// How a runtime failed on its own account, whatever the others did: it
+/// crashed, or its error output shows an assertion, exhausted memory or a
+/// rejected module. Such outcomes diverge even when every runtime trapped.
+pub fn failure_class(outcome: &Outcome) -> Option<Class> {
+    failure(outcome).map(|(class, _)| class)
+}
+
+fn failure(outcome: &Outcome) -> Option<(Class, String)> {
+    match outcome {
+        Outcome::Crashed { signal, stderr } => {
+            let text = stderr.to_lowercase();
+            let class = if contains_any(&text, ASSERTION) || *signal == Some(SIGABRT) {
+                Class::Assertion
+            } else if contains_any(&text, OUT_OF_MEMORY) || *signal == Some(SIGKILL) {
+                Class::OutOfMemory
+            } else {
+                Class::CrashSignal
+            };
+            let signal = signal.map_or("no signal".to_string(), |signal| format!("signal {}", signal));
+            Some((class, format!("{}: {}", signal, stack_signature(stderr))))
+        }
+        // a panicking Rust runtime exits with 101, a rejected module with 1
+        Outcome::Trapped { message } => {
+            let text = message.to_lowercase();
+            if contains_any(&text, ASSERTION) {
+                Some((Class::Assertion, stack_signature(message)))
+            } else if contains_any(&text, OUT_OF_MEMORY) {
+                Some((Class::OutOfMemory, stack_signature(message)))
+            } else if contains_any(&text, VALIDATION) {
+                Some((Class::ValidationDisagreement, normalize(message)))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+// Class and signature of one outcome against the reference outcome.
+fn classify_outcome(outcome: &Outcome, reference: Option<&Outcome>) -> Option<(Class, String)> {
+    if let Some(failure) = failure(outcome) {
+        return Some(failure);
+    }
+    let reference = reference?;
+    if matches!(outcome, Outcome::Unsupported { .. }) || outcome.agrees_with(reference) {
+        return None;
+    }
+    Some(match (outcome, reference) {
+        (Outcome::Timeout, _) | (_, Outcome::Timeout) => (Class::Timeout, String::new()),
+        (Outcome::Trapped { message }, _) | (_, Outcome::Trapped { message }) => (Class::TrapMismatch, normalize(message)),
+        // results of different types mostly come from different bugs
+        (Outcome::Returned { values }, _) | (_, Outcome::Returned { values }) => {
+            let types: Vec<String> = values.iter().map(|value| format!("{:?}", value.ty()).to_lowercase()).collect();
+            (Class::WrongResult, types.join(","))
+        }
+        _ => (Class::WrongResult, String::new()),
+    })
+}
+
+fn contains_any(text: &str, patterns: &[&str]) -> bool {
+    patterns.iter().any(|pattern| text.contains(pattern))
+}
+
+/// The innermost frames of the backtrace in `stderr` that are not panic or
+/// signal handling, innermost first: Rust backtraces (`3: crate::f`), gdb
+/// and sanitizer reports (`#3 0x... in f`) and JavaScript stacks (`at f
+/// (file)`). Without frames, the location of a Rust panic or else the first
+/// line of `stderr`, with numbers blanked out.
+pub fn stack_signature(stderr: &str) -> String {
+    let frames: Vec<String> = stderr
+        .lines()
+        .filter_map(frame)
+        .filter(|name| !NOISE.iter().any(|noise| name.to_lowercase().starts_with(noise)))
+        .take(MAX_FRAMES)
+        .collect();
+    if !frames.is_empty() {
+        return frames.join(" <- ");
+    }
+    if let Some((_, location)) = stderr.split_once("panicked at ") {
+        // `src/lib.rs:12:5:` without the column
+        let location = location.split_whitespace().next().unwrap_or_default().trim_end_matches(':');
+        return location.rsplit_once(':').map_or(location, |(line, _)| line).to_string();
+    }
+    stderr.lines().map(str::trim).find(|line| !line.is_empty()).map(normalize).unwrap_or_default()
+}
+
+// Name of the function of a backtrace line.
+fn frame(line: &str) -> Option<String> {
+    let line = line.trim();
+    let name = if let Some(rest) = line.strip_prefix('#') {
+        rest.split_once(" in ")?.1
+    } else if let Some(rest) = line.strip_prefix("at ") {
+        rest.contains('(').then_some(rest)?
+    } else {
+        let (index, rest) = line.split_once(": ")?;
+        (!index.is_empty() && index.bytes().all(|byte| byte.is_ascii_digit())).then_some(rest)?
+    };
+    let name = name.split_whitespace().next()?;
+    // Rust symbols end in a hash that changes with every build
+    let name = match name.rsplit_once("::h") {
+        Some((path, hash)) if hash.len() == 16 && hash.bytes().all(|byte| byte.is_ascii_hexdigit()) => path,
+        _ => name,
+    };
+    Some(name.to_string())
+}
+
+// A message without the numbers that change from one module to the next,
+// such as offsets, addresses and indices.
+fn normalize(message: &str) -> String {
+    let words: Vec<&str> = message
+        .split_whitespace()
+        .map(|word| if word.bytes().any(|byte| byte.is_ascii_digit()) { "_" } else { word })
+        .collect();
+    let mut text = words.join(" ");
+    if text.len() > MAX_MESSAGE {
+        let end = (0..=MAX_MESSAGE).rev().find(|end| text.is_char_boundary(*end)).unwrap_or(0);
+        text.truncate(end);
+    }
+    text
+}
+
+/// Divergences of one class and signature on one runtime. The first one
+/// stands for the bucket.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bucket {
+    pub id: String,
+    #[serde(flatten)]
+    pub classification: Classification,
+    pub count: u64,
+    pub representative: Divergence,
+}
+
+/// Buckets of a campaign, kept as JSON in one file so that later runs add to
+/// the counts of earlier ones.
+#[derive(Debug, Clone)]
+pub struct Triage {
+    path: PathBuf,
+    buckets: Vec<Bucket>,
+    by_id: HashMap<String, usize>,
+}
+
+impl Triage {
+    /// Load the buckets stored at `path`, none if the file does not exist.
+    pub fn open(path: &Path) -> Result<Self> {
+        let buckets: Vec<Bucket> = match std::fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).with_context(|| format!("cannot parse {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("cannot read {}", path.display())),
+        };
+        let by_id = buckets.iter().enumerate().map(|(index, bucket)| (bucket.id.clone(), index)).collect();
+        Ok(Triage { path: path.to_path_buf(), buckets, by_id })
+    }
+
+    /// Count `divergence` in its bucket. The bucket, and true if the
+    /// divergence opened it; `None` if it is no divergence.
+    pub fn add(&mut self, divergence: &Divergence) -> Option<(&Bucket, bool)> {
+        let classification = classify(divergence)?;
+        let id = classification.bucket_id();
+        match self.by_id.get(&id) {
+            Some(&index) => {
+                let bucket = &mut self.buckets[index];
+                bucket.count += 1;
+                Some((bucket, false))
+            }
+            None => {
+                self.by_id.insert(id.clone(), self.buckets.len());
+                self.buckets.push(Bucket { id, classification, count: 1, representative: divergence.clone() });
+                Some((self.buckets.last().expect("just pushed"), true))
+            }
+        }
+    }
+
+    pub fn buckets(&self) -> &[Bucket] {
+        &self.buckets
+    }
+
+    /// Write the buckets back, replacing the file in one step.
+    pub fn save(&self) -> Result<()> {
+        let text = serde_json::to_string_pretty(&self.buckets)?;
+        let temporary = self.path.with_extension("json.tmp");
+        std::fs::write(&temporary, text).with_context(|| format!("cannot write {}", temporary.display()))?;
+        std::fs::rename(&temporary, &self.path).with_context(|| format!("cannot write {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::differential::{Harness, zero_arguments};
+    use crate::engine::{Call, Engine};
+    use crate::interpreter::Interpreter;
+    use crate::value::Value;
+
+    fn divergence(outcomes: Vec<(&str, Outcome)>) -> Divergence {
+        Divergence {
+            module: "module-0.wasm".to_string(),
+            export: "main".to_string(),
+            args: vec![],
+            outcomes: outcomes
+                .into_iter()
+                .map(|(runtime, outcome)| RuntimeOutcome { runtime: runtime.to_string(), outcome })
+                .collect(),
+        }
+    }
+
+    fn returned(value: i32) -> Outcome {
+        Outcome::Returned { values: vec![Value::I32(value)] }
+    }
+
+    fn trapped(message: &str) -> Outcome {
+        Outcome::Trapped { message: message.to_string() }
+    }
+
+    #[test]
+    fn test_triage() {
+        let stderr = "\
+thread 'main' panicked at cranelift/src/lower.rs:120:9:
+assertion failed: ty.is_int()
+stack backtrace:
+   0: rust_begin_unwind
+   1: core::panicking::panic
+   2: cranelift::lower::lower_insn::h0123456789abcdef
+             at ./cranelift/src/lower.rs:120:9
+   3: cranelift::compile
+";
+        assert_eq!(stack_signature(stderr), "cranelift::lower::lower_insn <- cranelift::compile");
+        assert_eq!(stack_signature("#0 0x7f00 in __libc_raise\n#1 0x5500 in v8::Isolate::Run (a.cc:3)"), "v8::Isolate::Run");
+        assert_eq!(stack_signature("Error: boom\n    at lower (/x.js:1:2)\n    at file:///y.js:3:4"), "lower");
+        assert_eq!(stack_signature("thread 'main' panicked at src/main.rs:7:5:\nexplicit panic"), "src/main.rs:7");
+        assert_eq!(stack_signature("\nerror at offset 0x1f: bad local 12\n"), "error at offset _ bad local _");
+
+        let crashed = |signal, stderr: &str| Outcome::Crashed { signal, stderr: stderr.to_string() };
+        let cases = [
+            (vec![("a", returned(1)), ("b", crashed(Some(11), ""))], Class::CrashSignal, "b"),
+            (vec![("a", returned(1)), ("b", crashed(Some(6), ""))], Class::Assertion, "b"),
+            (vec![("a", returned(1)), ("b", crashed(None, stderr))], Class::Assertion, "b"),
+            (vec![("a", returned(1)), ("b", trapped("thread 'main' panicked at src/x.rs:1:1"))], Class::Assertion, "b"),
+            (vec![("a", returned(1)), ("b", trapped("fatal: out of memory"))], Class::OutOfMemory, "b"),
+            (vec![("a", returned(1)), ("b", trapped("CompileError: invalid local index"))], Class::ValidationDisagreement, "b"),
+            (vec![("a", returned(1)), ("b", trapped("integer overflow"))], Class::TrapMismatch, "b"),
+            (vec![("a", returned(1)), ("b", returned(2))], Class::WrongResult, "b"),
+            (vec![("a", returned(1)), ("b", Outcome::Timeout)], Class::Timeout, "b"),
+            // without the oracle the majority is right, the first only wins ties
+            (vec![("a", returned(1)), ("b", returned(2)), ("c", returned(2))], Class::WrongResult, "a"),
+            (vec![("interpreter", returned(1)), ("b", returned(2)), ("c", returned(2))], Class::WrongResult, "b"),
+            // traps agree, but not with a runtime that panicked
+            (vec![("interpreter", trapped("unreachable")), ("b", trapped("thread 'main' panicked at src/x.rs:1:1"))], Class::Assertion, "b"),
+            (vec![("a", trapped("unreachable")), ("b", trapped("CompileError: bad type"))], Class::ValidationDisagreement, "b"),
+            (vec![("a", Outcome::Unsupported { reason: String::new() }), ("b", returned(1)), ("c", Outcome::Timeout)], Class::Timeout, "c"),
+        ];
+        for (outcomes, class, runtime) in cases {
+            let classification = classify(&divergence(outcomes.clone())).unwrap();
+            assert_eq!((classification.class, classification.runtime.as_str()), (class, runtime), "{:?}", outcomes);
+        }
+        assert_eq!(classify(&divergence(vec![("a", returned(1)), ("b", returned(1))])), None);
+
+        // the harness reports these too
+        struct Panics;
+        impl Engine for Panics {
+            fn name(&self) -> &str {
+                "panics"
+            }
+
+            fn invoke(&self, _call: &Call) -> Result<Outcome> {
+                Ok(trapped("thread 'main' panicked at src/lower.rs:3:1:\nassertion failed"))
+            }
+        }
+        let wasm = wat::parse_str(r#"(module (func (export "main") unreachable))"#).unwrap();
+        let module = std::env::temp_dir().join(format!("triage-test-{}.wasm", std::process::id()));
+        std::fs::write(&module, wasm).unwrap();
+        let harness = Harness::new().with_engine(Box::new(Interpreter::default())).with_engine(Box::new(Panics));
+        let report = harness.run(&module, zero_arguments).unwrap();
+        let classification = classify(&report.divergences[0]).unwrap();
+        assert_eq!((classification.class, classification.runtime.as_str()), (Class::Assertion, "panics"));
+        assert_eq!(classification.signature, "src/lower.rs:3");
+        std::fs::remove_file(&module).unwrap();
+
+        let path = std::env::temp_dir().join(format!("triage-test-{}.json", std::process::id()));
+        let mut triage = Triage::open(&path).unwrap();
+        let first = divergence(vec![("a", returned(1)), ("b", trapped("wasm trap at 0x10: integer overflow"))]);
+        let second = divergence(vec![("a", returned(7)), ("b", trapped("wasm trap at 0x2a: integer overflow"))]);
+        let other = divergence(vec![("a", returned(1)), ("b", returned(2))]);
+        let (bucket, new) = triage.add(&first).unwrap();
+        assert!(new);
+        let id = bucket.id.clone();
+        assert_eq!(id, classify(&first).unwrap().bucket_id());
+        assert_eq!(triage.add(&second).map(|(bucket, new)| (bucket.id.clone(), new)), Some((id.clone(), false)));
+        assert!(triage.add(&other).unwrap().1);
+        triage.save().unwrap();
+
+        let mut reopened = Triage::open(&path).unwrap();
+        assert_eq!(reopened.buckets(), triage.buckets());
+        let (bucket, new) = reopened.add(&second).unwrap();
+        assert_eq!((bucket.count, new, &bucket.representative), (3, false, &first));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt;
+use anyhow::{Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+use wasmparser::ValType;
+
+
+/// An argument or result of an exported function. Floats are kept as their
+/// bits so that NaN payloads survive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(u32),
+    F64(u64),
+    V128(u128),
+}
+
+impl Value {
+    /// Zero of a numeric or vector type, `None` for references.
+    pub fn zero(ty: ValType) -> Option<Self> {
+        match ty {
+            ValType::I32 => Some(Value::I32(0)),
+            ValType::I64 => Some(Value::I64(0)),
+            ValType::F32 => Some(Value::F32(0)),
+            ValType::F64 => Some(Value::F64(0)),
+            ValType::V128 => Some(Value::V128(0)),
+            ValType::Ref(_) => None,
+        }
+    }
+
+    pub fn ty(&self) -> ValType {
+        match self {
+            Value::I32(_) => ValType::I32,
+            Value::I64(_) => ValType::I64,
+            Value::F32(_) => ValType::F32,
+            Value::F64(_) => ValType::F64,
+            Value::V128(_) => ValType::V128,
+        }
+    }
+
+    /// Read a value of type `ty` as printed by a runtime: integers signed,
+    /// unsigned or in `0x` hex, floats in decimal, as `inf` or as `nan`,
+    /// optionally with the payload as in `-nan:0x200000`, vectors in `0x` hex.
+    pub fn parse(ty: ValType, text: &str) -> Result<Self> {
+        let value = match ty {
+            ValType::I32 => {
+                let wide = parse_int(text)?;
+                let narrow = i32::try_from(wide).or_else(|_| u32::try_from(wide).map(|v| v as i32));
+                Value::I32(narrow.map_err(|_| anyhow!("`{}` does not fit an i32", text))?)
+            }
+            ValType::I64 => {
+                let wide = parse_int(text)?;
+                let narrow = i64::try_from(wide).or_else(|_| u64::try_from(wide).map(|v| v as i64));
+                Value::I64(narrow.map_err(|_| anyhow!("`{}` does not fit an i64", text))?)
+            }
+            ValType::F32 => Value::F32(match nan_bits(text, 32)? {
+                Some(bits) => bits as u32,
+                None => text.parse::<f32>().map_err(|e| anyhow!("`{}` is not an f32: {}", text, e))?.to_bits(),
+            }),
+            ValType::F64 => Value::F64(match nan_bits(text, 64)? {
+                Some(bits) => bits,
+                None => text.parse::<f64>().map_err(|e| anyhow!("`{}` is not an f64: {}", text, e))?.to_bits(),
+            }),
+            ValType::V128 => match text.strip_prefix("0x") {
+                Some(hex) => Value::V128(u128::from_str_radix(hex, 16)?),
+                None => bail!("`{}` is not a v128", text),
+            },
+            ValType::Ref(_) => bail!("reference results cannot be compared"),
+        };
+        Ok(value)
+    }
+
+    /// Equal, taking every NaN of a type as the same value since runtimes
+    /// seldom print the payload.
+    pub fn same(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::F32(a), Value::F32(b)) => a == b || (f32::from_bits(*a).is_nan() && f32::from_bits(*b).is_nan()),
+            (Value::F64(a), Value::F64(b)) => a == b || (f64::from_bits(*a).is_nan() && f64::from_bits(*b).is_nan()),
+            _ => self == other,
+        }
+    }
+}
+
+fn parse_int(text: &str) -> Result<i128> {
+    let wide = match text.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16),
+        None => text.parse::<i128>(),
+    };
+    wide.map_err(|e| anyhow!("`{}` is not an integer: {}", text, e))
+}
+
+// The bits of a `nan:0x…` of a float `width` bits wide, with the sign of an
+// optional `-`. `None` if `text` is not of that form.
+fn nan_bits(text: &str, width: u32) -> Result<Option<u64>> {
+    let (sign, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => (1u64, rest),
+        None => (0, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let Some(payload) = unsigned.strip_prefix("nan:0x") else {
+        return Ok(None);
+    };
+    let mantissa = if width == 32 { 23 } else { 52 };
+    let payload = u64::from_str_radix(payload, 16).map_err(|e| anyhow!("`{}` is not a NaN: {}", text, e))?;
+    if payload == 0 || payload >> mantissa != 0 {
+        bail!("`{}` has no NaN payload of {} bits", text, mantissa);
+    }
+    let exponent = (1u64 << (width - 1 - mantissa)) - 1;
+    Ok(Some(sign << (width - 1) | exponent << mantissa | payload))
+}
+
+fn fmt_nan(f: &mut fmt::Formatter<'_>, bits: u64, width: u32) -> fmt::Result {
+    let mantissa = if width == 32 { 23 } else { 52 };
+    let sign = if bits >> (width - 1) & 1 == 1 { "-" } else { "" };
+    write!(f, "{}nan:0x{:x}", sign, bits & ((1 << mantissa) - 1))
+}
+
+/// The form passed on command lines: decimal, floats with as many digits as
+/// it takes to read them back exactly and NaNs with their sign and payload.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::I32(v) => write!(f, "{}", v),
+            Value::I64(v) => write!(f, "{}", v),
+            Value::F32(bits) if f32::from_bits(*bits).is_nan() => fmt_nan(f, *bits as u64, 32),
+            Value::F64(bits) if f64::from_bits(*bits).is_nan() => fmt_nan(f, *bits, 64),
+            Value::F32(bits) => write!(f, "{}", f32::from_bits(*bits)),
+            Value::F64(bits) => write!(f, "{}", f64::from_bits(*bits)),
+            Value::V128(bits) => write!(f, "0x{:032x}", bits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        assert_eq!(Value::parse(ValType::I32, "-1").unwrap(), Value::I32(-1));
+        assert_eq!(Value::parse(ValType::I32, "4294967295").unwrap(), Value::I32(-1));
+        assert_eq!(Value::parse(ValType::I64, "0x8000000000000000").unwrap(), Value::I64(i64::MIN));
+        assert!(Value::parse(ValType::I32, "4294967296").is_err());
+        assert_eq!(Value::parse(ValType::F32, "-0").unwrap(), Value::F32(0x8000_0000));
+        assert_eq!(Value::parse(ValType::F64, "inf").unwrap(), Value::F64(f64::INFINITY.to_bits()));
+
+        let nan = Value::parse(ValType::F32, "NaN").unwrap();
+        assert!(nan.same(&Value::F32(0x7FA0_0001)));
+        assert!(!Value::F32(0).same(&Value::F32(0x8000_0000)));
+
+        assert_eq!(Value::F32(0xFFA0_0001).to_string(), "-nan:0x200001");
+        assert_eq!(Value::F64(0x7FF8_0000_0000_0000).to_string(), "nan:0x8000000000000");
+        assert!(Value::parse(ValType::F32, "nan:0x800000").is_err());
+        assert!(Value::parse(ValType::F64, "nan:0x0").is_err());
+
+        let values = [
+            Value::I32(i32::MIN), Value::F32(1), Value::F64(0.1f64.to_bits()), Value::V128(u128::MAX),
+            Value::F32(0x7FC0_0000), Value::F32(0xFFA0_0001), Value::F64(0xFFF8_0000_0000_0000), Value::F64(0x7FF4_0000_0000_0001),
+        ];
+        for value in values {
+            assert_eq!(Value::parse(value.ty(), &value.to_string()).unwrap(), value);
+        }
+    }
+}
@@ -0,0 +1,662 @@
+use std::collections::{BTreeSet, HashMap};
+
+use anyhow::{Result, bail};
+use wasmparser::{
+    CompositeInnerType, ElementItems, ElementKind, ExternalKind, FuncType, Operator, Parser,
+    Payload, TypeRef,
+};
+
+use crate::module::WasmModule;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CallKind {
+    Call,
+    ReturnCall,
+    CallRef,
+    ReturnCallRef,
+    CallIndirect,
+    ReturnCallIndirect,
+}
+
+impl CallKind {
+    pub fn is_tail(&self) -> bool {
+        matches!(
+            self,
+            CallKind::ReturnCall | CallKind::ReturnCallRef | CallKind::ReturnCallIndirect
+        )
+    }
+
+    pub fn is_indirect(&self) -> bool {
+        !matches!(self, CallKind::Call | CallKind::ReturnCall)
+    }
+}
+
+/// One resolved edge. Indirect call sites produce one edge per possible target.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CallEdge {
+    pub caller: u32,
+    pub callee: u32,
+    pub kind: CallKind,
+    /// Offset of the call instruction in the original binary.
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryPoint {
+    Export { func: u32, name: String },
+    Start { func: u32 },
+}
+
+impl EntryPoint {
+    pub fn func(&self) -> u32 {
+        match self {
+            EntryPoint::Export { func, .. } => *func,
+            EntryPoint::Start { func } => *func,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionInfo {
+    pub type_index: u32,
+    pub imported: bool,
+    /// Appears in an element segment or in a `ref.func`, i.e. may be called indirectly.
+    pub address_taken: bool,
+}
+
+/// Module-level call graph built from `call`, `return_call`, `call_ref` and
+/// `call_indirect` (and their tail variants).
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    pub functions: Vec<FunctionInfo>,
+    pub edges: Vec<CallEdge>,
+    pub entry_points: Vec<EntryPoint>,
+    /// Call sites whose target set could not be resolved to any function.
+    pub unresolved: Vec<(u32, CallKind, usize)>,
+}
+
+#[derive(Default)]
+struct Collector {
+    types: Vec<Option<FuncType>>,
+    functions: Vec<FunctionInfo>,
+    // table index -> functions placed in that table by active segments
+    table_elements: HashMap<u32, BTreeSet<u32>>,
+    // passive segments can be copied into any table with `table.init`
+    passive_elements: BTreeSet<u32>,
+    entry_points: Vec<EntryPoint>,
+    call_sites: Vec<(u32, CallKind, CallTarget, usize)>,
+}
+
+enum CallTarget {
+    Direct(u32),
+    Table { type_index: u32, table_index: u32 },
+    Ref { type_index: u32 },
+}
+
+impl CallGraph {
+    pub fn from_module(module: &WasmModule) -> Result<Self> {
+        Self::from_binary(&module.encode())
+    }
+
+    pub fn from_binary(wasm_binary: &[u8]) -> Result<Self> {
+        let mut collector = Collector::default();
+        let mut num_imported = 0u32;
+        let mut next_defined = 0u32;
+
+        for payload in Parser::new(0).parse_all(wasm_binary) {
+            match payload? {
+                Payload::TypeSection(reader) => {
+                    for rec_group in reader {
+                        for sub_type in rec_group?.into_types() {
+                            let func_type = match sub_type.composite_type.inner {
+                                CompositeInnerType::Func(func_type) => Some(func_type),
+                                _ => None,
+                            };
+                            collector.types.push(func_type);
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        if let TypeRef::Func(type_index) = import?.ty {
+                            collector.functions.push(FunctionInfo {
+                                type_index,
+                                imported: true,
+                                address_taken: false,
+                            });
+                            num_imported += 1;
+                        }
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for type_index in reader {
+                        collector.functions.push(FunctionInfo {
+                            type_index: type_index?,
+                            imported: false,
+                            address_taken: false,
+                        });
+                    }
+                }
+                Payload::TableSection(reader) => {
+                    for table in reader {
+                        if let wasmparser::TableInit::Expr(expr) = table?.init {
+                            for func in ref_funcs(expr.get_operators_reader())? {
+                                collector.mark_address_taken(func);
+                            }
+                        }
+                    }
+                }
+                Payload::GlobalSection(reader) => {
+                    for global in reader {
+                        for func in ref_funcs(global?.init_expr.get_operators_reader())? {
+                            collector.mark_address_taken(func);
+                        }
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export?;
+                        if export.kind == ExternalKind::Func {
+                            collector.entry_points.push(EntryPoint::Export {
+                                func: export.index,
+                                name: export.name.to_string(),
+                            });
+                        }
+                    }
+                }
+                Payload::StartSection { func, .. } => {
+                    collector.entry_points.push(EntryPoint::Start { func });
+                }
+                Payload::ElementSection(reader) => {
+                    for element in reader {
+                        let element = element?;
+                        let mut funcs = Vec::new();
+                        match element.items {
+                            ElementItems::Functions(reader) => {
+                                for func in reader {
+                                    funcs.push(func?);
+                                }
+                            }
+                            ElementItems::Expressions(_, reader) => {
+                                for expr in reader {
+                                    funcs.extend(ref_funcs(expr?.get_operators_reader())?);
+                                }
+                            }
+                        }
+                        for &func in &funcs {
+                            collector.mark_address_taken(func);
+                        }
+                        match element.kind {
+                            ElementKind::Active { table_index, .. } => {
+                                collector
+                                    .table_elements
+                                    .entry(table_index.unwrap_or(0))
+                                    .or_default()
+                                    .extend(funcs);
+                            }
+                            ElementKind::Passive => collector.passive_elements.extend(funcs),
+                            // declared segments only make functions referencable by `ref.func`
+                            ElementKind::Declared => {}
+                        }
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let caller = num_imported + next_defined;
+                    next_defined += 1;
+                    let mut reader = body.get_operators_reader()?;
+                    while !reader.eof() {
+                        let (op, offset) = reader.read_with_offset()?;
+                        let site = match op {
+                            Operator::Call { function_index } => {
+                                Some((CallKind::Call, CallTarget::Direct(function_index)))
+                            }
+                            Operator::ReturnCall { function_index } => {
+                                Some((CallKind::ReturnCall, CallTarget::Direct(function_index)))
+                            }
+                            Operator::CallIndirect {
+                                type_index,
+                                table_index,
+                            } => Some((
+                                CallKind::CallIndirect,
+                                CallTarget::Table {
+                                    type_index,
+                                    table_index,
+                                },
+                            )),
+                            Operator::ReturnCallIndirect {
+                                type_index,
+                                table_index,
+                            } => Some((
+                                CallKind::ReturnCallIndirect,
+                                CallTarget::Table {
+                                    type_index,
+                                    table_index,
+                                },
+                            )),
+                            Operator::CallRef { type_index } => {
+                                Some((CallKind::CallRef, CallTarget::Ref { type_index }))
+                            }
+                            Operator::ReturnCallRef { type_index } => {
+                                Some((CallKind::ReturnCallRef, CallTarget::Ref { type_index }))
+                            }
+                            Operator::RefFunc { function_index } => {
+                                collector.mark_address_taken(function_index);
+                                None
+                            }
+                            _ => None,
+                        };
+                        if let Some((kind, target)) = site {
+                            collector.call_sites.push((caller, kind, target, offset));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if next_defined as usize + num_imported as usize != collector.functions.len() {
+            bail!(
+                "function and code section disagree: {} declared, {} bodies",
+                collector.functions.len() - num_imported as usize,
+                next_defined
+            );
+        }
+        collector.check_indices()?;
+        Ok(collector.finish())
+    }
+
+    pub fn num_functions(&self) -> u32 {
+        self.functions.len() as u32
+    }
+
+    pub fn callees(&self, func: u32) -> BTreeSet<u32> {
+        self.edges
+            .iter()
+            .filter(|e| e.caller == func)
+            .map(|e| e.callee)
+            .collect()
+    }
+
+    pub fn callers(&self, func: u32) -> BTreeSet<u32> {
+        self.edges
+            .iter()
+            .filter(|e| e.callee == func)
+            .map(|e| e.caller)
+            .collect()
+    }
+
+    /// Functions reachable from any entry point (exports and start function).
+    /// Entry points and edges naming no function are ignored.
+    pub fn reachable(&self) -> BTreeSet<u32> {
+        let adjacency = self.adjacency();
+        let mut seen = BTreeSet::new();
+        let mut stack: Vec<u32> = self
+            .entry_points
+            .iter()
+            .map(|e| e.func())
+            .filter(|&func| (func as usize) < adjacency.len())
+            .collect();
+        while let Some(func) = stack.pop() {
+            if !seen.insert(func) {
+                continue;
+            }
+            stack.extend(adjacency[func as usize].iter().copied());
+        }
+        seen
+    }
+
+    /// Recursion cycles: strongly connected components with more than one
+    /// function, or a single function that calls itself. Each cycle is sorted.
+    pub fn recursion_cycles(&self) -> Vec<Vec<u32>> {
+        let adjacency = self.adjacency();
+        strongly_connected_components(&adjacency)
+            .into_iter()
+            .filter(|scc| scc.len() > 1 || adjacency[scc[0] as usize].contains(&scc[0]))
+            .collect()
+    }
+
+    pub fn is_recursive(&self, func: u32) -> bool {
+        self.recursion_cycles()
+            .iter()
+            .any(|cycle| cycle.contains(&func))
+    }
+
+    /// Number of call edges into each function, plus one for every entry point
+    /// that names it. Used to rank functions when choosing what to mutate.
+    /// Edges and entry points naming a function that does not exist are not
+    /// counted.
+    pub fn incoming_calls(&self) -> Vec<u32> {
+        let len = self.functions.len();
+        let mut counts = vec![0u32; len];
+        let edges = self.edges.iter().filter(|e| (e.caller as usize) < len);
+        let targets = self
+            .entry_points
+            .iter()
+            .map(|e| e.func())
+            .chain(edges.map(|e| e.callee));
+        for func in targets {
+            if let Some(count) = counts.get_mut(func as usize) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    // Edges from or to an index outside of `functions` are left out.
+    fn adjacency(&self) -> Vec<BTreeSet<u32>> {
+        let len = self.functions.len();
+        let mut adjacency = vec![BTreeSet::new(); len];
+        for edge in self.edges.iter().filter(|e| (e.callee as usize) < len) {
+            if let Some(callees) = adjacency.get_mut(edge.caller as usize) {
+                callees.insert(edge.callee);
+            }
+        }
+        adjacency
+    }
+}
+
+impl Collector {
+    // Exports, the start function and direct calls must name a function;
+    // unlike table entries they are not filtered by signature later.
+    fn check_indices(&self) -> Result<()> {
+        let len = self.functions.len();
+        for entry in &self.entry_points {
+            if entry.func() as usize >= len {
+                bail!("{:?} names function {} of {}", entry, entry.func(), len);
+            }
+        }
+        for (caller, kind, target, offset) in &self.call_sites {
+            if let CallTarget::Direct(func) = target
+                && *func as usize >= len
+            {
+                bail!(
+                    "{:?} at offset {} in function {} to function {} of {}",
+                    kind,
+                    offset,
+                    caller,
+                    func,
+                    len
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn mark_address_taken(&mut self, func: u32) {
+        if let Some(info) = self.functions.get_mut(func as usize) {
+            info.address_taken = true;
+        }
+    }
+
+    fn same_signature(&self, a: u32, b: u32) -> bool {
+        if a == b {
+            return true;
+        }
+        match (self.types.get(a as usize), self.types.get(b as usize)) {
+            (Some(Some(a)), Some(Some(b))) => a == b,
+            _ => false,
+        }
+    }
+
+    fn finish(self) -> CallGraph {
+        let mut edges = Vec::new();
+        let mut unresolved = Vec::new();
+        let address_taken: Vec<u32> = (0..self.functions.len() as u32)
+            .filter(|&f| self.functions[f as usize].address_taken)
+            .collect();
+
+        for (caller, kind, target, offset) in &self.call_sites {
+            let candidates: Vec<u32> = match target {
+                CallTarget::Direct(func) => vec![*func],
+                CallTarget::Table {
+                    type_index,
+                    table_index,
+                } => self
+                    .table_elements
+                    .get(table_index)
+                    .into_iter()
+                    .flatten()
+                    .chain(self.passive_elements.iter())
+                    .copied()
+                    .collect::<BTreeSet<u32>>()
+                    .into_iter()
+                    .filter(|&f| self.signature_matches(f, *type_index))
+                    .collect(),
+                CallTarget::Ref { type_index } => address_taken
+                    .iter()
+                    .copied()
+                    .filter(|&f| self.signature_matches(f, *type_index))
+                    .collect(),
+            };
+            if candidates.is_empty() {
+                unresolved.push((*caller, *kind, *offset));
+            }
+            for callee in candidates {
+                edges.push(CallEdge {
+                    caller: *caller,
+                    callee,
+                    kind: *kind,
+                    offset: *offset,
+                });
+            }
+        }
+
+        CallGraph {
+            functions: self.functions,
+            edges,
+            entry_points: self.entry_points,
+            unresolved,
+        }
+    }
+
+    fn signature_matches(&self, func: u32, type_index: u32) -> bool {
+        self.functions
+            .get(func as usize)
+            .is_some_and(|info| self.same_signature(info.type_index, type_index))
+    }
+}
+
+fn ref_funcs(mut reader: wasmparser::OperatorsReader<'_>) -> Result<Vec<u32>> {
+    let mut funcs = Vec::new();
+    while !reader.eof() {
+        if let Operator::RefFunc { function_index } = reader.read()? {
+            funcs.push(function_index);
+        }
+    }
+    Ok(funcs)
+}
+
+// Iterative Tarjan, call graphs of real binaries are deep enough to blow the
+// stack with the recursive version.
+fn strongly_connected_components(adjacency: &[BTreeSet<u32>]) -> Vec<Vec<u32>> {
+    let n = adjacency.len();
+    let mut index = vec![usize::MAX; n];
+    let mut low_link = vec![0usize; n];
+    let mut on_stack = vec![false; n];
+    let mut stack = Vec::new();
+    let mut next_index = 0;
+    let mut components = Vec::new();
+
+    for root in 0..n {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        let mut work: Vec<(usize, Vec<u32>)> = vec![(root, Vec::new())];
+        index[root] = next_index;
+        low_link[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        work[0].1 = adjacency[root].iter().rev().copied().collect();
+
+        while let Some((node, pending)) = work.last_mut() {
+            let node = *node;
+            if let Some(next) = pending.pop() {
+                let next = next as usize;
+                if index[next] == usize::MAX {
+                    index[next] = next_index;
+                    low_link[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    work.push((next, adjacency[next].iter().rev().copied().collect()));
+                } else if on_stack[next] {
+                    low_link[node] = low_link[node].min(index[next]);
+                }
+                continue;
+            }
+            work.pop();
+            if let Some((parent, _)) = work.last() {
+                low_link[*parent] = low_link[*parent].min(low_link[node]);
+            }
+            if low_link[node] == index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack[member] = false;
+                    component.push(member as u32);
+                    if member == node {
+                        break;
+                    }
+                }
+                component.sort_unstable();
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_encoder::{
+        CodeSection, ElementSection, Elements, ExportKind, ExportSection, Function,
+        FunctionSection, Instruction, Module, RefType, TableSection, TableType, TypeSection,
+        ValType,
+    };
+
+    // f0 -> f1 -> f2 -> f1, f0 call_indirect (i32)->i32 via table containing f2 and f3
+    fn sample_module() -> Vec<u8> {
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        types.ty().function([ValType::I32], [ValType::I32]);
+
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        functions.function(0);
+        functions.function(0);
+        functions.function(1);
+
+        let mut tables = TableSection::new();
+        tables.table(TableType {
+            element_type: RefType::FUNCREF,
+            table64: false,
+            minimum: 2,
+            maximum: None,
+            shared: false,
+        });
+
+        let mut exports = ExportSection::new();
+        exports.export("main", ExportKind::Func, 0);
+
+        let mut elements = ElementSection::new();
+        elements.active(
+            Some(0),
+            &wasm_encoder::ConstExpr::i32_const(0),
+            Elements::Functions([2u32, 3].as_slice().into()),
+        );
+
+        let mut code = CodeSection::new();
+        let mut f0 = Function::new([]);
+        f0.instruction(&Instruction::Call(1));
+        f0.instruction(&Instruction::I32Const(7));
+        f0.instruction(&Instruction::I32Const(1));
+        f0.instruction(&Instruction::CallIndirect {
+            type_index: 1,
+            table_index: 0,
+        });
+        f0.instruction(&Instruction::Drop);
+        f0.instruction(&Instruction::End);
+        code.function(&f0);
+        let mut f1 = Function::new([]);
+        f1.instruction(&Instruction::Call(2));
+        f1.instruction(&Instruction::End);
+        code.function(&f1);
+        let mut f2 = Function::new([]);
+        f2.instruction(&Instruction::Call(1));
+        f2.instruction(&Instruction::End);
+        code.function(&f2);
+        let mut f3 = Function::new([]);
+        f3.instruction(&Instruction::LocalGet(0));
+        f3.instruction(&Instruction::End);
+        code.function(&f3);
+
+        let mut module = Module::new();
+        module.section(&types);
+        module.section(&functions);
+        module.section(&tables);
+        module.section(&exports);
+        module.section(&elements);
+        module.section(&code);
+        module.finish()
+    }
+
+    #[test]
+    fn test_call_graph_cycles_and_indirect_targets() {
+        let binary = sample_module();
+        let module = WasmModule::new(&binary);
+        let graph = CallGraph::from_module(&module).unwrap();
+
+        assert_eq!(graph.num_functions(), 4);
+        assert_eq!(graph.callees(0), BTreeSet::from([1, 3]));
+        assert_eq!(graph.recursion_cycles(), vec![vec![1, 2]]);
+        assert_eq!(
+            graph.entry_points,
+            vec![EntryPoint::Export {
+                func: 0,
+                name: "main".to_string()
+            }]
+        );
+        assert_eq!(graph.reachable(), BTreeSet::from([0, 1, 2, 3]));
+        assert!(graph.unresolved.is_empty());
+
+        // indices past the last function are skipped rather than indexed
+        let mut broken = graph.clone();
+        broken.edges.push(CallEdge {
+            caller: 0,
+            callee: 9,
+            kind: CallKind::Call,
+            offset: 0,
+        });
+        broken.edges.push(CallEdge {
+            caller: 9,
+            callee: 0,
+            kind: CallKind::Call,
+            offset: 0,
+        });
+        broken.entry_points.push(EntryPoint::Start { func: 7 });
+        assert_eq!(broken.reachable(), BTreeSet::from([0, 1, 2, 3]));
+        assert_eq!(broken.incoming_calls(), vec![1, 2, 1, 1]);
+        assert_eq!(broken.recursion_cycles(), vec![vec![1, 2]]);
+
+        let mut module = Module::new();
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        let mut code = CodeSection::new();
+        let mut f0 = Function::new([]);
+        f0.instruction(&Instruction::Call(5));
+        f0.instruction(&Instruction::End);
+        code.function(&f0);
+        module.section(&types);
+        module.section(&functions);
+        module.section(&code);
+        let error = CallGraph::from_binary(&module.finish()).unwrap_err();
+        assert!(
+            error.to_string().contains("to function 5 of 1"),
+            "{}",
+            error
+        );
+    }
+}
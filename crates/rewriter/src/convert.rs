@@ -1,7 +1,3 @@
-pub trait ParserToEncoderCompositeInnerType {
-    fn convert(&self) -> wasm_encoder::CompositeInnerType;
-}
-
 pub trait ParserToEncoderValType {
     fn to_encoder_type(&self) -> wasm_encoder::ValType;
 }
@@ -57,12 +53,13 @@ impl ParserToEncoderGlobalType for wasmparser::GlobalType {
     }
 }
 
-pub trait ParserToEncoderTableType {
-    fn to_encoder_type(&self) -> wasm_encoder::TableType;
+pub trait ParserToEncoderRefType {
+    fn to_encoder_type(&self) -> wasm_encoder::RefType;
 }
-impl ParserToEncoderTableType for wasmparser::TableType {
-    fn to_encoder_type(&self) -> wasm_encoder::TableType {
-        let element_type = match self.element_type {
+
+impl ParserToEncoderRefType for wasmparser::RefType {
+    fn to_encoder_type(&self) -> wasm_encoder::RefType {
+        match *self {
             wasmparser::RefType::ANYREF => wasm_encoder::RefType::ANYREF,
             wasmparser::RefType::EQREF => wasm_encoder::RefType::EQREF,
             wasmparser::RefType::FUNCREF => wasm_encoder::RefType::FUNCREF,
@@ -70,11 +67,20 @@ impl ParserToEncoderTableType for wasmparser::TableType {
             wasmparser::RefType::I31REF => wasm_encoder::RefType::I31REF,
             wasmparser::RefType::ARRAYREF => wasm_encoder::RefType::ARRAYREF,
             wasmparser::RefType::EXNREF => wasm_encoder::RefType::EXNREF,
-            _ => panic!("Unsupported RefType in TableType"),
-        };
+            _ => panic!("Unsupported RefType"),
+        }
+    }
+}
+
+pub trait ParserToEncoderTableType {
+    fn to_encoder_type(&self) -> wasm_encoder::TableType;
+}
+impl ParserToEncoderTableType for wasmparser::TableType {
+    fn to_encoder_type(&self) -> wasm_encoder::TableType {
+        let element_type = self.element_type.to_encoder_type();
 
         wasm_encoder::TableType {
-            element_type: element_type,
+            element_type,
             table64: self.table64,
             minimum: self.initial,
             maximum: self.maximum,
@@ -91,7 +97,6 @@ impl ParserToEncoderTagType for wasmparser::TagType {
     fn to_encoder_type(&self) -> wasm_encoder::TagType {
         let tag_kind = match self.kind {
             wasmparser::TagKind::Exception => wasm_encoder::TagKind::Exception,
-            _ => panic!("Unsupported TagKind"),
         };
         wasm_encoder::TagType {
             kind: tag_kind,
@@ -99,3 +104,33 @@ impl ParserToEncoderTagType for wasmparser::TagType {
         }
     }
 }
+
+pub trait ParserToEncoderConstExpr {
+    fn to_encoder_type(&self) -> wasm_encoder::ConstExpr;
+}
+
+impl ParserToEncoderConstExpr for wasmparser::ConstExpr<'_> {
+    fn to_encoder_type(&self) -> wasm_encoder::ConstExpr {
+        let mut reader = self.get_binary_reader();
+        let bytes = reader.read_bytes(reader.bytes_remaining()).unwrap();
+        // wasm_encoder appends the trailing `end` itself
+        let bytes = bytes.strip_suffix(&[0x0B]).unwrap_or(bytes);
+        wasm_encoder::ConstExpr::raw(bytes.iter().copied())
+    }
+}
+
+pub trait ParserToEncoderExportKind {
+    fn to_encoder_type(&self) -> wasm_encoder::ExportKind;
+}
+
+impl ParserToEncoderExportKind for wasmparser::ExternalKind {
+    fn to_encoder_type(&self) -> wasm_encoder::ExportKind {
+        match self {
+            wasmparser::ExternalKind::Func => wasm_encoder::ExportKind::Func,
+            wasmparser::ExternalKind::Table => wasm_encoder::ExportKind::Table,
+            wasmparser::ExternalKind::Memory => wasm_encoder::ExportKind::Memory,
+            wasmparser::ExternalKind::Global => wasm_encoder::ExportKind::Global,
+            wasmparser::ExternalKind::Tag => wasm_encoder::ExportKind::Tag,
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use anyhow::{Result, anyhow, bail};
+use gimli::write::{Address, EndianVec, Sections};
+use gimli::{EndianSlice, LittleEndian, SectionId};
+use wasm_encoder::CustomSection;
+use wasmparser::{Parser, Payload};
+
+// DWARF sections that are read and written again when code moves. The
+// others, like `.debug_aranges` or `.debug_frame`, hold addresses that would
+// go stale.
+const RELOCATED_DWARF: &[SectionId] = &[
+    SectionId::DebugAbbrev,
+    SectionId::DebugAddr,
+    SectionId::DebugInfo,
+    SectionId::DebugLine,
+    SectionId::DebugLineStr,
+    SectionId::DebugLoc,
+    SectionId::DebugLocLists,
+    SectionId::DebugRanges,
+    SectionId::DebugRngLists,
+    SectionId::DebugStr,
+    SectionId::DebugStrOffsets,
+    SectionId::DebugTypes,
+];
+
+/// What to do with a custom section when the module is encoded again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomSectionAction {
+    /// Copy the section verbatim.
+    Keep,
+    /// Leave the section out of the output.
+    Drop,
+    /// Make the section agree with the new code section. DWARF sections are
+    /// relocated, any other section is kept only if the code is unchanged.
+    /// DWARF sections that cannot be relocated are an error once the code
+    /// changed; give them a `Keep` or `Drop` rule of their own, as the
+    /// default policy does.
+    Rewrite,
+}
+
+/// Per-name policy for custom sections. Rules are matched in insertion order,
+/// a pattern ending in `*` matches by prefix, anything else must match exactly.
+#[derive(Debug, Clone)]
+pub struct CustomSectionPolicy {
+    rules: Vec<(String, CustomSectionAction)>,
+    default_action: CustomSectionAction,
+}
+
+impl CustomSectionPolicy {
+    pub fn new(default_action: CustomSectionAction) -> Self {
+        CustomSectionPolicy {
+            rules: Vec::new(),
+            default_action,
+        }
+    }
+
+    /// Copy every custom section verbatim, the behaviour of `WasmModule::encode`.
+    pub fn keep_all() -> Self {
+        Self::new(CustomSectionAction::Keep)
+    }
+
+    pub fn with_rule(mut self, pattern: &str, action: CustomSectionAction) -> Self {
+        self.rules.push((pattern.to_string(), action));
+        self
+    }
+
+    pub fn action_for(&self, name: &str) -> CustomSectionAction {
+        for (pattern, action) in &self.rules {
+            let matched = match pattern.strip_suffix('*') {
+                Some(prefix) => name.starts_with(prefix),
+                None => name == pattern,
+            };
+            if matched {
+                return *action;
+            }
+        }
+        self.default_action
+    }
+}
+
+impl Default for CustomSectionPolicy {
+    /// Relocate the DWARF sections that can be, drop the other ones (e.g.
+    /// `.debug_aranges`), keep `sourceMappingURL` only while it is still
+    /// valid and copy everything else.
+    fn default() -> Self {
+        RELOCATED_DWARF
+            .iter()
+            .fold(Self::keep_all(), |policy, id| {
+                policy.with_rule(id.name(), CustomSectionAction::Rewrite)
+            })
+            .with_rule(".debug_*", CustomSectionAction::Drop)
+            .with_rule("sourceMappingURL", CustomSectionAction::Rewrite)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BodyLayout {
+    // offsets relative to the start of the code section payload, which is
+    // what DWARF for wasm uses as addresses
+    start: u64,
+    end: u64,
+    hash: u64,
+}
+
+/// Position of every function body inside the code section.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeLayout {
+    bodies: Vec<BodyLayout>,
+}
+
+impl CodeLayout {
+    pub fn from_binary(wasm_binary: &[u8]) -> Result<Self> {
+        let mut layout = CodeLayout::default();
+        let mut code_start = 0;
+        for payload in Parser::new(0).parse_all(wasm_binary) {
+            match payload? {
+                Payload::CodeSectionStart { range, .. } => code_start = range.start,
+                Payload::CodeSectionEntry(body) => {
+                    layout.push(code_start, body.range(), &wasm_binary[body.range()])
+                }
+                _ => {}
+            }
+        }
+        Ok(layout)
+    }
+
+    pub(crate) fn push(&mut self, code_start: usize, range: std::ops::Range<usize>, body: &[u8]) {
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        self.bodies.push(BodyLayout {
+            start: (range.start - code_start) as u64,
+            end: (range.end - code_start) as u64,
+            hash: hasher.finish(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bodies.is_empty()
+    }
+
+    /// Translate a code offset of this layout into the corresponding offset in
+    /// `new`. Offsets inside an unchanged body move with it, offsets inside a
+    /// changed body are clamped to the new body, anything outside a body
+    /// (e.g. tombstones of dead code) is left alone.
+    pub fn map_address(&self, new: &CodeLayout, address: u64) -> u64 {
+        let index = self
+            .bodies
+            .iter()
+            .position(|body| body.start <= address && address <= body.end);
+        let (old, new) = match index.and_then(|i| Some((&self.bodies[i], new.bodies.get(i)?))) {
+            Some(pair) => pair,
+            None => return address,
+        };
+        let delta = address - old.start;
+        if old.hash == new.hash || address == old.start {
+            new.start + delta
+        } else if address == old.end {
+            new.end
+        } else {
+            new.start + delta.min(new.end - new.start)
+        }
+    }
+
+    fn same_code(&self, other: &CodeLayout) -> bool {
+        self == other
+    }
+}
+
+/// Apply `policy` to `sections`, which belonged to a module whose code was laid
+/// out as `old`, so that they can be attached to a module laid out as `new`.
+pub fn apply_policy<'a>(
+    sections: &[CustomSection<'a>],
+    policy: &CustomSectionPolicy,
+    old: &CodeLayout,
+    new: &CodeLayout,
+) -> Result<Vec<CustomSection<'a>>> {
+    let mut output = Vec::new();
+    let mut dwarf = Vec::new();
+    for section in sections {
+        match policy.action_for(&section.name) {
+            CustomSectionAction::Keep => output.push(section.clone()),
+            CustomSectionAction::Drop => {}
+            CustomSectionAction::Rewrite if section.name.starts_with(".debug_") => {
+                dwarf.push(section)
+            }
+            CustomSectionAction::Rewrite => {
+                if old.same_code(new) {
+                    output.push(section.clone());
+                }
+            }
+        }
+    }
+    if !dwarf.is_empty() {
+        if old.same_code(new) {
+            output.extend(dwarf.into_iter().cloned());
+        } else {
+            output.extend(rewrite_dwarf(&dwarf, old, new)?);
+        }
+    }
+    Ok(output)
+}
+
+fn rewrite_dwarf<'a>(
+    sections: &[&CustomSection<'_>],
+    old: &CodeLayout,
+    new: &CodeLayout,
+) -> Result<Vec<CustomSection<'a>>> {
+    let unsupported: Vec<&str> = sections
+        .iter()
+        .map(|section| section.name.as_ref())
+        .filter(|name| !RELOCATED_DWARF.iter().any(|id| id.name() == *name))
+        .collect();
+    if !unsupported.is_empty() {
+        bail!(
+            "cannot relocate the DWARF sections {}, give them a rule to keep or drop them",
+            unsupported.join(", ")
+        );
+    }
+    let load = |id: SectionId| -> Result<EndianSlice<'_, LittleEndian>, gimli::Error> {
+        let data = sections
+            .iter()
+            .find(|section| section.name == id.name())
+            .map(|section| section.data.as_ref())
+            .unwrap_or(&[]);
+        Ok(EndianSlice::new(data, LittleEndian))
+    };
+    let read_dwarf = gimli::Dwarf::load(load)?;
+    let convert_address = |address| Some(Address::Constant(old.map_address(new, address)));
+    let mut write_dwarf = gimli::write::Dwarf::from(&read_dwarf, &convert_address)
+        .map_err(|e| anyhow!("failed to convert DWARF: {e}"))?;
+
+    let mut written = Sections::new(EndianVec::new(LittleEndian));
+    write_dwarf.write(&mut written)?;
+    let mut output = Vec::new();
+    written.for_each(|id, data| -> Result<()> {
+        if !data.slice().is_empty() {
+            output.push(CustomSection {
+                name: Cow::Borrowed(id.name()),
+                data: Cow::Owned(data.slice().to_vec()),
+            });
+        }
+        Ok(())
+    })?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::WasmModule;
+
+    fn layout(bodies: &[(u64, u64, u64)]) -> CodeLayout {
+        CodeLayout {
+            bodies: bodies
+                .iter()
+                .map(|&(start, end, hash)| BodyLayout { start, end, hash })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_policy_rules_and_address_mapping() {
+        let policy = CustomSectionPolicy::default().with_rule("name", CustomSectionAction::Drop);
+        assert_eq!(
+            policy.action_for(".debug_line"),
+            CustomSectionAction::Rewrite
+        );
+        assert_eq!(policy.action_for("name"), CustomSectionAction::Drop);
+        assert_eq!(policy.action_for("producers"), CustomSectionAction::Keep);
+
+        // the first body grew by 4 bytes, the second one is unchanged
+        let old = layout(&[(1, 11, 1), (12, 22, 2)]);
+        let new = layout(&[(1, 15, 3), (16, 26, 2)]);
+        assert_eq!(old.map_address(&new, 1), 1);
+        assert_eq!(old.map_address(&new, 5), 5);
+        assert_eq!(old.map_address(&new, 11), 15);
+        assert_eq!(old.map_address(&new, 14), 18);
+        assert_eq!(old.map_address(&new, 0xffff_ffff), 0xffff_ffff);
+
+        let sections = vec![CustomSection {
+            name: Cow::Borrowed("sourceMappingURL"),
+            data: Cow::Borrowed(b"a.map"),
+        }];
+        assert!(
+            apply_policy(&sections, &policy, &old, &new)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            apply_policy(&sections, &policy, &old, &old).unwrap().len(),
+            1
+        );
+    }
+
+    // The sections of a compile unit covering `low_pc..high_pc`.
+    fn dwarf_sections(low_pc: u64, high_pc: u64) -> Vec<CustomSection<'static>> {
+        use gimli::write::{AttributeValue, Dwarf, LineProgram, Unit};
+        let encoding = gimli::Encoding {
+            format: gimli::Format::Dwarf32,
+            version: 4,
+            address_size: 4,
+        };
+        let mut dwarf = Dwarf::new();
+        let unit_id = dwarf.units.add(Unit::new(encoding, LineProgram::none()));
+        let unit = dwarf.units.get_mut(unit_id);
+        let root = unit.get_mut(unit.root());
+        root.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(low_pc)),
+        );
+        root.set(
+            gimli::DW_AT_high_pc,
+            AttributeValue::Address(Address::Constant(high_pc)),
+        );
+        let mut written = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut written).unwrap();
+        let mut sections = Vec::new();
+        written
+            .for_each(|id, data| -> Result<()> {
+                if !data.slice().is_empty() {
+                    sections.push(CustomSection {
+                        name: Cow::Borrowed(id.name()),
+                        data: Cow::Owned(data.slice().to_vec()),
+                    });
+                }
+                Ok(())
+            })
+            .unwrap();
+        sections
+    }
+
+    // `DW_AT_low_pc` and `DW_AT_high_pc` of the first compile unit.
+    fn unit_range(sections: &[CustomSection<'_>]) -> (u64, u64) {
+        let dwarf = gimli::Dwarf::load(|id| -> Result<_, gimli::Error> {
+            let data = sections
+                .iter()
+                .find(|section| section.name == id.name())
+                .map(|section| section.data.as_ref())
+                .unwrap_or(&[]);
+            Ok(EndianSlice::new(data, LittleEndian))
+        })
+        .unwrap();
+        let header = dwarf.units().next().unwrap().unwrap();
+        let unit = dwarf.unit(header).unwrap();
+        let mut entries = unit.entries();
+        let (_, entry) = entries.next_dfs().unwrap().unwrap();
+        let address = |name| match entry.attr_value(name).unwrap() {
+            Some(gimli::AttributeValue::Addr(address)) => address,
+            other => panic!("{other:?}"),
+        };
+        (address(gimli::DW_AT_low_pc), address(gimli::DW_AT_high_pc))
+    }
+
+    #[test]
+    fn test_rewrite_dwarf() {
+        let policy = CustomSectionPolicy::default();
+        // the first body grew by 4 bytes, moving the second one
+        let old = layout(&[(1, 11, 1), (12, 22, 2)]);
+        let new = layout(&[(1, 15, 3), (16, 26, 2)]);
+        let sections = dwarf_sections(12, 22);
+        assert_eq!(unit_range(&sections), (12, 22));
+        let rewritten = apply_policy(&sections, &policy, &old, &new).unwrap();
+        assert_eq!(unit_range(&rewritten), (16, 26));
+        assert_eq!(
+            apply_policy(&sections, &policy, &old, &old).unwrap(),
+            sections
+        );
+
+        let mut sections = sections;
+        sections.push(CustomSection {
+            name: Cow::Borrowed(".debug_aranges"),
+            data: Cow::Borrowed(&[0; 4]),
+        });
+        // the default policy drops what it cannot relocate
+        let rewritten = apply_policy(&sections, &policy, &old, &new).unwrap();
+        assert_eq!(unit_range(&rewritten), (16, 26));
+        assert!(
+            rewritten
+                .iter()
+                .all(|section| section.name != ".debug_aranges")
+        );
+        // a rule of its own asking to rewrite it is an error
+        let policy =
+            CustomSectionPolicy::keep_all().with_rule(".debug_*", CustomSectionAction::Rewrite);
+        let error = apply_policy(&sections, &policy, &old, &new).unwrap_err();
+        assert!(error.to_string().contains(".debug_aranges"), "{error}");
+        assert_eq!(
+            apply_policy(&sections, &policy, &old, &old).unwrap().len(),
+            sections.len()
+        );
+    }
+
+    #[test]
+    fn test_encode_module_with_debug_sections() {
+        use wasm_encoder::{CodeSection, Function, Instruction};
+        let wasm = wat::parse_str(
+            "(module (func (result i32) i32.const 1) (func (result i32) i32.const 2))",
+        )
+        .unwrap();
+        let old = CodeLayout::from_binary(&wasm).unwrap();
+        let mut module = WasmModule::new(&wasm);
+        module.custom_sections = dwarf_sections(old.bodies[1].start, old.bodies[1].end);
+        module.custom_sections.push(CustomSection {
+            name: Cow::Borrowed(".debug_aranges"),
+            data: Cow::Borrowed(&[0; 4]),
+        });
+
+        // the first body grows, the second one moves unchanged
+        let mut code = CodeSection::new();
+        let mut first = Function::new([]);
+        first.instruction(&Instruction::Nop);
+        first.instruction(&Instruction::I32Const(1));
+        first.instruction(&Instruction::End);
+        let mut second = Function::new([]);
+        second.instruction(&Instruction::I32Const(2));
+        second.instruction(&Instruction::End);
+        code.function(&first).function(&second);
+        module.code_section = code;
+
+        let encoded = module
+            .encode_with_policy(&CustomSectionPolicy::default())
+            .unwrap();
+        let new = CodeLayout::from_binary(&encoded).unwrap();
+        assert_eq!(new.bodies[1].start, old.bodies[1].start + 1);
+        let mut sections = Vec::new();
+        for payload in Parser::new(0).parse_all(&encoded) {
+            if let Payload::CustomSection(reader) = payload.unwrap() {
+                sections.push(CustomSection {
+                    name: Cow::Owned(reader.name().to_string()),
+                    data: Cow::Owned(reader.data().to_vec()),
+                });
+            }
+        }
+        assert_eq!(
+            unit_range(&sections),
+            (new.bodies[1].start, new.bodies[1].end)
+        );
+        assert!(
+            sections
+                .iter()
+                .all(|section| section.name != ".debug_aranges")
+        );
+    }
+}
@@ -0,0 +1,525 @@
+use std::fmt;
+
+use anyhow::Result;
+use serde::Serialize;
+use similar::{Algorithm, DiffOp, capture_diff_slices};
+use wasmparser::{
+    CompositeInnerType, DataKind, ElementItems, ElementKind, Operator, Parser, Payload, TableInit,
+    TypeRef,
+};
+
+use crate::module::WasmModule;
+
+/// Differences between two modules, grouped per section.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ModuleDiff {
+    pub sections: Vec<SectionDiff>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SectionDiff {
+    pub section: &'static str,
+    pub changes: Vec<ItemChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ItemChange {
+    Added {
+        index: u32,
+        item: String,
+    },
+    Removed {
+        index: u32,
+        item: String,
+    },
+    Changed {
+        index: u32,
+        before: String,
+        after: String,
+        /// Instruction-level diff, only filled in for function bodies.
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        body: Vec<InstrChange>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum InstrChange {
+    Delete { old_index: usize, instr: String },
+    Insert { new_index: usize, instr: String },
+}
+
+impl ModuleDiff {
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+// Every section item rendered as text, function bodies additionally as one
+// line per instruction.
+#[derive(Default)]
+struct ModuleSummary {
+    types: Vec<String>,
+    imports: Vec<String>,
+    // functions are indexed after the imported ones
+    imported_functions: usize,
+    functions: Vec<String>,
+    bodies: Vec<Vec<String>>,
+    tables: Vec<String>,
+    memories: Vec<String>,
+    tags: Vec<String>,
+    globals: Vec<String>,
+    exports: Vec<String>,
+    start: Vec<String>,
+    elements: Vec<String>,
+    data: Vec<String>,
+    custom: Vec<String>,
+}
+
+pub fn diff(a: &WasmModule, b: &WasmModule) -> Result<ModuleDiff> {
+    diff_binaries(&a.encode(), &b.encode())
+}
+
+pub fn diff_binaries(a: &[u8], b: &[u8]) -> Result<ModuleDiff> {
+    let a = ModuleSummary::from_binary(a)?;
+    let b = ModuleSummary::from_binary(b)?;
+
+    let mut module_diff = ModuleDiff::default();
+    let mut push = |section, changes: Vec<ItemChange>| {
+        if !changes.is_empty() {
+            module_diff.sections.push(SectionDiff { section, changes });
+        }
+    };
+    push("type", diff_items(&a.types, &b.types));
+    push("import", diff_items(&a.imports, &b.imports));
+    push("function", diff_functions(&a, &b));
+    push("table", diff_items(&a.tables, &b.tables));
+    push("memory", diff_items(&a.memories, &b.memories));
+    push("tag", diff_items(&a.tags, &b.tags));
+    push("global", diff_items(&a.globals, &b.globals));
+    push("export", diff_items(&a.exports, &b.exports));
+    push("start", diff_items(&a.start, &b.start));
+    push("element", diff_items(&a.elements, &b.elements));
+    push("data", diff_items(&a.data, &b.data));
+    push("custom", diff_items(&a.custom, &b.custom));
+    Ok(module_diff)
+}
+
+fn diff_items(a: &[String], b: &[String]) -> Vec<ItemChange> {
+    let mut changes = Vec::new();
+    for index in 0..a.len().max(b.len()) {
+        match (a.get(index), b.get(index)) {
+            (Some(before), Some(after)) if before != after => changes.push(ItemChange::Changed {
+                index: index as u32,
+                before: before.clone(),
+                after: after.clone(),
+                body: Vec::new(),
+            }),
+            (Some(item), None) => changes.push(ItemChange::Removed {
+                index: index as u32,
+                item: item.clone(),
+            }),
+            (None, Some(item)) => changes.push(ItemChange::Added {
+                index: index as u32,
+                item: item.clone(),
+            }),
+            _ => {}
+        }
+    }
+    changes
+}
+
+// Functions are compared by their index in the function index space, so a
+// function that became an import or the other way round is removed or added.
+fn diff_functions(a: &ModuleSummary, b: &ModuleSummary) -> Vec<ItemChange> {
+    let mut changes = Vec::new();
+    let first = a.imported_functions.min(b.imported_functions);
+    let end =
+        (a.imported_functions + a.functions.len()).max(b.imported_functions + b.functions.len());
+    for index in first..end {
+        match (a.function(index), b.function(index)) {
+            (Some((before, old_body)), Some((after, new_body))) => {
+                if before == after && old_body == new_body {
+                    continue;
+                }
+                changes.push(ItemChange::Changed {
+                    index: index as u32,
+                    before: before.clone(),
+                    after: after.clone(),
+                    body: diff_instructions(old_body, new_body),
+                });
+            }
+            (Some((item, _)), None) => changes.push(ItemChange::Removed {
+                index: index as u32,
+                item: item.clone(),
+            }),
+            (None, Some((item, _))) => changes.push(ItemChange::Added {
+                index: index as u32,
+                item: item.clone(),
+            }),
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+fn diff_instructions(old: &[String], new: &[String]) -> Vec<InstrChange> {
+    let mut changes = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, old, new) {
+        let (old_range, new_range) = match op {
+            DiffOp::Equal { .. } => continue,
+            DiffOp::Delete {
+                old_index, old_len, ..
+            } => (old_index..old_index + old_len, 0..0),
+            DiffOp::Insert {
+                new_index, new_len, ..
+            } => (0..0, new_index..new_index + new_len),
+            DiffOp::Replace {
+                old_index,
+                old_len,
+                new_index,
+                new_len,
+            } => (
+                old_index..old_index + old_len,
+                new_index..new_index + new_len,
+            ),
+        };
+        for old_index in old_range {
+            changes.push(InstrChange::Delete {
+                old_index,
+                instr: old[old_index].clone(),
+            });
+        }
+        for new_index in new_range {
+            changes.push(InstrChange::Insert {
+                new_index,
+                instr: new[new_index].clone(),
+            });
+        }
+    }
+    changes
+}
+
+impl ModuleSummary {
+    // The defined function at `index` of the function index space, with its
+    // body.
+    fn function(&self, index: usize) -> Option<(&String, &[String])> {
+        let defined = index.checked_sub(self.imported_functions)?;
+        let function = self.functions.get(defined)?;
+        Some((
+            function,
+            self.bodies.get(defined).map(Vec::as_slice).unwrap_or(&[]),
+        ))
+    }
+
+    fn from_binary(wasm_binary: &[u8]) -> Result<Self> {
+        let mut summary = ModuleSummary::default();
+        for payload in Parser::new(0).parse_all(wasm_binary) {
+            match payload? {
+                Payload::TypeSection(reader) => {
+                    for rec_group in reader {
+                        for sub_type in rec_group?.into_types() {
+                            summary.types.push(match sub_type.composite_type.inner {
+                                CompositeInnerType::Func(func_type) => format!(
+                                    "func {:?} -> {:?}",
+                                    func_type.params(),
+                                    func_type.results()
+                                ),
+                                other => format!("{other:?}"),
+                            });
+                        }
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        let import = import?;
+                        if let TypeRef::Func(_) = import.ty {
+                            summary.imported_functions += 1;
+                        }
+                        summary.imports.push(format!(
+                            "{}.{}: {:?}",
+                            import.module, import.name, import.ty
+                        ));
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for type_index in reader {
+                        summary.functions.push(format!("type {}", type_index?));
+                    }
+                }
+                Payload::TableSection(reader) => {
+                    for table in reader {
+                        let table = table?;
+                        summary.tables.push(match table.init {
+                            TableInit::RefNull => format!("{:?}", table.ty),
+                            TableInit::Expr(expr) => format!(
+                                "{:?} = {}",
+                                table.ty,
+                                operators(expr.get_operators_reader())?.join(" ")
+                            ),
+                        });
+                    }
+                }
+                Payload::MemorySection(reader) => {
+                    for memory in reader {
+                        summary.memories.push(format!("{:?}", memory?));
+                    }
+                }
+                Payload::TagSection(reader) => {
+                    for tag in reader {
+                        summary.tags.push(format!("{:?}", tag?));
+                    }
+                }
+                Payload::GlobalSection(reader) => {
+                    for global in reader {
+                        let global = global?;
+                        summary.globals.push(format!(
+                            "{:?} = {}",
+                            global.ty,
+                            operators(global.init_expr.get_operators_reader())?.join(" ")
+                        ));
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for export in reader {
+                        let export = export?;
+                        summary.exports.push(format!(
+                            "{}: {:?} {}",
+                            export.name, export.kind, export.index
+                        ));
+                    }
+                }
+                Payload::StartSection { func, .. } => {
+                    summary.start.push(format!("func {func}"));
+                }
+                Payload::ElementSection(reader) => {
+                    for element in reader {
+                        let element = element?;
+                        let kind = match element.kind {
+                            ElementKind::Passive => "passive".to_string(),
+                            ElementKind::Declared => "declared".to_string(),
+                            ElementKind::Active {
+                                table_index,
+                                offset_expr,
+                            } => format!(
+                                "active table {} offset {}",
+                                table_index.unwrap_or(0),
+                                operators(offset_expr.get_operators_reader())?.join(" ")
+                            ),
+                        };
+                        let items = match element.items {
+                            ElementItems::Functions(reader) => {
+                                let funcs = reader.into_iter().collect::<Result<Vec<_>, _>>()?;
+                                format!("funcs {funcs:?}")
+                            }
+                            ElementItems::Expressions(ty, reader) => {
+                                let mut exprs = Vec::new();
+                                for expr in reader {
+                                    exprs.push(operators(expr?.get_operators_reader())?.join(" "));
+                                }
+                                format!("{ty:?} {exprs:?}")
+                            }
+                        };
+                        summary.elements.push(format!("{kind}: {items}"));
+                    }
+                }
+                Payload::DataSection(reader) => {
+                    for data in reader {
+                        let data = data?;
+                        let kind = match data.kind {
+                            DataKind::Passive => "passive".to_string(),
+                            DataKind::Active {
+                                memory_index,
+                                offset_expr,
+                            } => format!(
+                                "active memory {} offset {}",
+                                memory_index,
+                                operators(offset_expr.get_operators_reader())?.join(" ")
+                            ),
+                        };
+                        summary.data.push(format!(
+                            "{kind}: {} bytes {}",
+                            data.data.len(),
+                            hex(data.data)
+                        ));
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let index = summary.bodies.len();
+                    let mut locals = Vec::new();
+                    for local in body.get_locals_reader()? {
+                        let (count, ty) = local?;
+                        locals.push(format!("{count} x {ty:?}"));
+                    }
+                    if let Some(function) = summary.functions.get_mut(index) {
+                        function.push_str(&format!(", locals [{}]", locals.join(", ")));
+                    }
+                    summary
+                        .bodies
+                        .push(operators(body.get_operators_reader()?)?);
+                }
+                Payload::CustomSection(reader) => {
+                    summary.custom.push(format!(
+                        "{}: {} bytes",
+                        reader.name(),
+                        reader.data().len()
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(summary)
+    }
+}
+
+fn operators(mut reader: wasmparser::OperatorsReader<'_>) -> Result<Vec<String>> {
+    let mut ops = Vec::new();
+    while !reader.eof() {
+        let op = reader.read()?;
+        if !matches!(op, Operator::End) || !reader.eof() {
+            ops.push(format!("{op:?}"));
+        }
+    }
+    Ok(ops)
+}
+
+// Long segments are cut, the length is printed separately anyway.
+fn hex(bytes: &[u8]) -> String {
+    const MAX: usize = 32;
+    let mut text: String = bytes.iter().take(MAX).map(|b| format!("{b:02x}")).collect();
+    if bytes.len() > MAX {
+        text.push_str("...");
+    }
+    text
+}
+
+impl fmt::Display for ModuleDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sections.is_empty() {
+            return writeln!(f, "modules are identical");
+        }
+        for section in &self.sections {
+            writeln!(f, "{} section:", section.section)?;
+            for change in &section.changes {
+                match change {
+                    ItemChange::Added { index, item } => writeln!(f, "  + [{index}] {item}")?,
+                    ItemChange::Removed { index, item } => writeln!(f, "  - [{index}] {item}")?,
+                    ItemChange::Changed {
+                        index,
+                        before,
+                        after,
+                        body,
+                    } => {
+                        if before == after {
+                            writeln!(f, "  ~ [{index}] {after}")?;
+                        } else {
+                            writeln!(f, "  ~ [{index}] {before}")?;
+                            writeln!(f, "        => {after}")?;
+                        }
+                        for instr in body {
+                            match instr {
+                                InstrChange::Delete { old_index, instr } => {
+                                    writeln!(f, "      - {old_index:>5}: {instr}")?
+                                }
+                                InstrChange::Insert { new_index, instr } => {
+                                    writeln!(f, "      + {new_index:>5}: {instr}")?
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_binaries() {
+        let a = wat::parse_str(
+            r#"
+            (module
+              (import "env" "log" (func (param i32)))
+              (func (export "f") (result i32) i32.const 1)
+              (func (result i32) i32.const 2 i32.const 3 i32.add))
+        "#,
+        )
+        .unwrap();
+        assert!(diff_binaries(&a, &a).unwrap().is_empty());
+
+        let b = wat::parse_str(
+            r#"
+            (module
+              (import "env" "log" (func (param i32)))
+              (func (export "f") (result i32) i32.const 1)
+              (func (result i32) i32.const 2 i32.const 4 i32.add)
+              (global i32 (i32.const 0)))
+        "#,
+        )
+        .unwrap();
+        let module_diff = diff_binaries(&a, &b).unwrap();
+        let sections: Vec<&str> = module_diff
+            .sections
+            .iter()
+            .map(|section| section.section)
+            .collect();
+        assert_eq!(sections, vec!["function", "global"]);
+        // the second defined function is function 2, after the import
+        let [ItemChange::Changed { index, body, .. }] = module_diff.sections[0].changes.as_slice()
+        else {
+            panic!("{:?}", module_diff.sections[0]);
+        };
+        assert_eq!(*index, 2);
+        assert_eq!(
+            body,
+            &vec![
+                InstrChange::Delete {
+                    old_index: 1,
+                    instr: "I32Const { value: 3 }".to_string()
+                },
+                InstrChange::Insert {
+                    new_index: 1,
+                    instr: "I32Const { value: 4 }".to_string()
+                },
+            ]
+        );
+        assert!(matches!(
+            module_diff.sections[1].changes[0],
+            ItemChange::Added { index: 0, .. }
+        ));
+        assert!(module_diff.to_string().contains("~ [2] type 1"));
+
+        // importing the first function shifts the others: nothing else changed
+        let c = wat::parse_str(
+            r#"
+            (module
+              (import "env" "log" (func (param i32)))
+              (import "env" "f" (func (result i32)))
+              (func (result i32) i32.const 2 i32.const 3 i32.add)
+              (export "f" (func 1)))
+        "#,
+        )
+        .unwrap();
+        let module_diff = diff_binaries(&a, &c).unwrap();
+        let functions = &module_diff
+            .sections
+            .iter()
+            .find(|section| section.section == "function")
+            .unwrap()
+            .changes;
+        assert!(
+            matches!(functions.as_slice(), [ItemChange::Removed { index: 1, .. }]),
+            "{:?}",
+            functions
+        );
+    }
+}
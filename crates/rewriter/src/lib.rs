@@ -1,100 +1,7 @@
-use anyhow::Result;
-use std::io::Read;
-use wasmparser::{Chunk, Parser, Payload::*};
-
+pub mod callgraph;
 mod convert;
-mod module;
-
-fn parse(mut reader: impl Read) -> Result<()> {
-    let mut buf = Vec::new();
-    let mut cur = Parser::new(0);
-    let mut eof = false;
-    let mut stack = Vec::new();
-
-    loop {
-        let (payload, consumed) = match cur.parse(&buf, eof)? {
-            Chunk::NeedMoreData(hint) => {
-                assert!(!eof); // otherwise an error would be returned
-
-                // Use the hint to preallocate more space, then read
-                // some more data into our buffer.
-                //
-                // Note that the buffer management here is not ideal,
-                // but it's compact enough to fit in an example!
-                let len = buf.len();
-                buf.extend((0..hint).map(|_| 0u8));
-                let n = reader.read(&mut buf[len..])?;
-                buf.truncate(len + n);
-                eof = n == 0;
-                continue;
-            }
-
-            Chunk::Parsed { consumed, payload } => (payload, consumed),
-        };
-
-        match payload {
-            // Sections for WebAssembly modules
-            Version { .. } => { /* ... */ }
-            TypeSection(_) => { /* ... */ }
-            ImportSection(_) => { /* ... */ }
-            FunctionSection(_) => { /* ... */ }
-            TableSection(_) => { /* ... */ }
-            MemorySection(_) => { /* ... */ }
-            TagSection(_) => { /* ... */ }
-            GlobalSection(_) => { /* ... */ }
-            ExportSection(_) => { /* ... */ }
-            StartSection { .. } => { /* ... */ }
-            ElementSection(_) => { /* ... */ }
-            DataCountSection { .. } => { /* ... */ }
-            DataSection(_) => { /* ... */ }
-
-            // Here we know how many functions we'll be receiving as
-            // `CodeSectionEntry`, so we can prepare for that, and
-            // afterwards we can parse and handle each function
-            // individually.
-            CodeSectionStart { .. } => { /* ... */ }
-            CodeSectionEntry(body) => {
-                // here we can iterate over `body` to parse the function
-                // and its locals
-            }
-
-            // Sections for WebAssembly components
-            InstanceSection(_) => { /* ... */ }
-            CoreTypeSection(_) => { /* ... */ }
-            ComponentInstanceSection(_) => { /* ... */ }
-            ComponentAliasSection(_) => { /* ... */ }
-            ComponentTypeSection(_) => { /* ... */ }
-            ComponentCanonicalSection(_) => { /* ... */ }
-            ComponentStartSection { .. } => { /* ... */ }
-            ComponentImportSection(_) => { /* ... */ }
-            ComponentExportSection(_) => { /* ... */ }
-
-            ModuleSection { parser, .. } | ComponentSection { parser, .. } => {
-                stack.push(cur.clone());
-                cur = parser.clone();
-            }
-
-            CustomSection(_) => { /* ... */ }
-
-            // Once we've reached the end of a parser we either resume
-            // at the parent parser or we break out of the loop because
-            // we're done.
-            End(_) => {
-                if let Some(parent_parser) = stack.pop() {
-                    cur = parent_parser;
-                } else {
-                    break;
-                }
-            }
-
-            // most likely you'd return an error here
-            _ => { /* ... */ }
-        }
-
-        // once we're done processing the payload we can forget the
-        // original.
-        buf.drain(..consumed);
-    }
-
-    Ok(())
-}
+pub mod custom_sections;
+pub mod diff;
+pub mod module;
+pub mod printer;
+pub mod text;
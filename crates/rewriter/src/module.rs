@@ -1,17 +1,21 @@
 use core::panic;
 use std::borrow::Cow;
 
+use anyhow::{Result, bail};
+
 use wasm_encoder::{
-    CodeSection, CompositeInnerType, CustomSection, DataCountSection, DataSection, ElementSection,
-    ExportSection, FuncType, FunctionSection, GlobalSection, ImportSection, MemorySection, Module,
-    SectionId, StartSection, TableSection, TagSection, TypeSection,
+    CodeSection, CustomSection, DataCountSection, DataSection, ElementSection, ExportSection,
+    FuncType, FunctionSection, GlobalSection, ImportSection, MemorySection, Module, StartSection,
+    TableSection, TagSection, TypeSection,
 };
-use wasmparser::{Parser, Payload, TypeRef};
+use wasmparser::{Parser, Payload, TableInit, TypeRef};
 
 use crate::convert::{
-    ParserToEncoderGlobalType, ParserToEncoderMemoryType, ParserToEncoderTableType,
-    ParserToEncoderTagType, ParserToEncoderValType,
+    ParserToEncoderConstExpr, ParserToEncoderExportKind, ParserToEncoderGlobalType,
+    ParserToEncoderMemoryType, ParserToEncoderTableType, ParserToEncoderTagType,
+    ParserToEncoderValType,
 };
+use crate::custom_sections::{CodeLayout, CustomSectionPolicy, apply_policy};
 
 #[derive(Clone, Debug)] // I did not add Default here, it may be used later
 pub struct WasmModule<'a> {
@@ -23,11 +27,11 @@ pub struct WasmModule<'a> {
     pub memory_section: MemorySection,
     pub global_section: GlobalSection,
     pub export_section: ExportSection,
-    pub start_section: StartSection,
+    pub start_section: Option<StartSection>,
     pub element_section: ElementSection,
     pub code_section: CodeSection,
     pub data_section: DataSection,
-    pub data_count_section: DataCountSection,
+    pub data_count_section: Option<DataCountSection>,
     pub tag_section: TagSection,
 
     imported_functions_count: u32,
@@ -35,10 +39,22 @@ pub struct WasmModule<'a> {
     imported_memories_count: u32,
     imported_tables_count: u32,
     imported_tags_count: u32,
+
+    // where the function bodies were in the input binary, needed to relocate
+    // debug info once the code section changes
+    original_code_layout: CodeLayout,
 }
 
 impl<'a> WasmModule<'a> {
+    /// Parse `input_wasm_binary`. Panics where `try_new` fails.
     pub fn new(input_wasm_binary: &[u8]) -> Self {
+        Self::try_new(input_wasm_binary).unwrap_or_else(|e| panic!("{e:#}"))
+    }
+
+    /// Parse `input_wasm_binary`, failing on malformed input and on types
+    /// the rewriter cannot re-encode: GC types and references other than
+    /// `funcref`, `externref` and `exnref` in value types.
+    pub fn try_new(input_wasm_binary: &[u8]) -> Result<Self> {
         let mut parser = Parser::new(0);
         let mut wasm_module = WasmModule {
             custom_sections: Vec::new(),
@@ -49,11 +65,11 @@ impl<'a> WasmModule<'a> {
             memory_section: MemorySection::new(),
             global_section: GlobalSection::new(),
             export_section: ExportSection::new(),
-            start_section: StartSection { function_index: 0 },
+            start_section: None,
             element_section: ElementSection::new(),
             code_section: CodeSection::new(),
             data_section: DataSection::new(),
-            data_count_section: DataCountSection { count: 0 },
+            data_count_section: None,
             tag_section: TagSection::new(),
 
             imported_functions_count: 0,
@@ -61,15 +77,20 @@ impl<'a> WasmModule<'a> {
             imported_memories_count: 0,
             imported_tables_count: 0,
             imported_tags_count: 0,
+
+            original_code_layout: CodeLayout::default(),
         };
 
+        let mut offset = 0;
+        let mut code_section_start = 0;
         loop {
-            let (payload, consumed) = match parser.parse(input_wasm_binary, true).unwrap() {
+            let (payload, consumed) = match parser.parse(&input_wasm_binary[offset..], true)? {
                 wasmparser::Chunk::NeedMoreData(hint) => {
-                    panic!("Invalid wasm binary: {hint:?}");
+                    bail!("invalid wasm binary: {hint:?} more bytes needed");
                 }
                 wasmparser::Chunk::Parsed { payload, consumed } => (payload, consumed),
             };
+            offset += consumed;
 
             match payload {
                 Payload::CustomSection(reader) => {
@@ -82,11 +103,16 @@ impl<'a> WasmModule<'a> {
                 Payload::TypeSection(reader) => {
                     let mut type_section = TypeSection::new();
                     for ty_group_iter in reader.into_iter() {
-                        let ty_iter = ty_group_iter.unwrap().into_types();
+                        let ty_iter = ty_group_iter?.into_types();
                         for ty in ty_iter {
                             let composite_type = ty.composite_type.inner;
                             match composite_type {
                                 wasmparser::CompositeInnerType::Func(func_ty) => {
+                                    func_ty
+                                        .params()
+                                        .iter()
+                                        .chain(func_ty.results())
+                                        .try_for_each(check_val_type)?;
                                     let func_params: Vec<wasm_encoder::ValType> = func_ty
                                         .params()
                                         .iter()
@@ -100,13 +126,8 @@ impl<'a> WasmModule<'a> {
                                     let func_type = FuncType::new(func_params, func_results);
                                     type_section.ty().func_type(&func_type);
                                 }
-                                // The following types are not supported yet.
-                                wasmparser::CompositeInnerType::Array(array_type) => {}
-                                wasmparser::CompositeInnerType::Struct(struct_type) => {}
-                                wasmparser::CompositeInnerType::Cont(cont_type) => {}
-                                _ => {
-                                    panic!("Unsupported type: {:?}", composite_type);
-                                }
+                                // dropping them would shift the indices of the types after them
+                                _ => bail!("unsupported type: {:?}", composite_type),
                             }
                         }
                     }
@@ -115,7 +136,7 @@ impl<'a> WasmModule<'a> {
                 Payload::ImportSection(reader) => {
                     let mut import_section = ImportSection::new();
                     for import_item in reader {
-                        let import_item = import_item.unwrap();
+                        let import_item = import_item?;
                         let module = import_item.module;
                         let name = import_item.name;
                         match import_item.ty {
@@ -125,6 +146,7 @@ impl<'a> WasmModule<'a> {
                                 import_section.import(module, name, entity_type);
                             }
                             TypeRef::Global(globaltype) => {
+                                check_val_type(&globaltype.content_type)?;
                                 wasm_module.imported_globals_count += 1;
                                 let entity_type =
                                     wasm_encoder::EntityType::Global(globaltype.to_encoder_type());
@@ -137,6 +159,7 @@ impl<'a> WasmModule<'a> {
                                 import_section.import(module, name, entity_type);
                             }
                             TypeRef::Table(tabletype) => {
+                                check_ref_type(&tabletype.element_type)?;
                                 wasm_module.imported_tables_count += 1;
                                 let entity_type =
                                     wasm_encoder::EntityType::Table(tabletype.to_encoder_type());
@@ -155,47 +178,240 @@ impl<'a> WasmModule<'a> {
                 Payload::FunctionSection(reader) => {
                     let mut function_section = FunctionSection::new();
                     for func in reader {
-                        let func = func.unwrap();
+                        let func = func?;
                         function_section.function(func);
                     }
                     wasm_module.function_section = function_section;
                 }
-                // Payload::TableSection(reader) => {
-                //     let mut table_section = TableSection::new();
-                //     for table in reader {
-                //         let table = table.unwrap();
-                //         let tabletype = table.ty.to_encoder_type();
-                //         let init_expr = table.init;
-                //         table_section.table_with_init(tabletype, table.init);
-                //     }
-                //     wasm_module.table_section = table_section;
-                // }
-                // Payload::MemorySection(reader) => {
-                //     let mut memory_section = MemorySection::new();
-                //     for memory in reader {
-                //         let memory = memory.unwrap();
-                //         memory_section.memory(memory);
-                //     }
-                //     wasm_module.memory_section = memory_section;
-                // }
-                // Payload::GlobalSection(reader) => {
-                //     let mut global_section = GlobalSection::new();
-                //     for global in reader {
-                //         let global = global.unwrap();
-                //         global_section.global(
-                //             global.ty,
-                //             global
-                //                 .init_expr
-                //                 .get_operators_reader()
-                //                 .into_iter()
-                //                 .map(|op| op.unwrap()),
-                //         );
-                //     }
-                //     wasm_module.global_section = global_section;
-                // }
+                Payload::TableSection(reader) => {
+                    let mut table_section = TableSection::new();
+                    for table in reader {
+                        let table = table?;
+                        check_ref_type(&table.ty.element_type)?;
+                        let tabletype = table.ty.to_encoder_type();
+                        match table.init {
+                            TableInit::RefNull => {
+                                table_section.table(tabletype);
+                            }
+                            TableInit::Expr(init_expr) => {
+                                table_section
+                                    .table_with_init(tabletype, &init_expr.to_encoder_type());
+                            }
+                        }
+                    }
+                    wasm_module.table_section = table_section;
+                }
+                Payload::MemorySection(reader) => {
+                    let mut memory_section = MemorySection::new();
+                    for memory in reader {
+                        let memory = memory?;
+                        memory_section.memory(memory.to_encoder_type());
+                    }
+                    wasm_module.memory_section = memory_section;
+                }
+                Payload::TagSection(reader) => {
+                    let mut tag_section = TagSection::new();
+                    for tag in reader {
+                        let tag = tag?;
+                        tag_section.tag(tag.to_encoder_type());
+                    }
+                    wasm_module.tag_section = tag_section;
+                }
+                Payload::GlobalSection(reader) => {
+                    let mut global_section = GlobalSection::new();
+                    for global in reader {
+                        let global = global?;
+                        check_val_type(&global.ty.content_type)?;
+                        global_section.global(
+                            global.ty.to_encoder_type(),
+                            &global.init_expr.to_encoder_type(),
+                        );
+                    }
+                    wasm_module.global_section = global_section;
+                }
+                Payload::ExportSection(reader) => {
+                    let mut export_section = ExportSection::new();
+                    for export in reader {
+                        let export = export?;
+                        export_section.export(
+                            export.name,
+                            export.kind.to_encoder_type(),
+                            export.index,
+                        );
+                    }
+                    wasm_module.export_section = export_section;
+                }
+                Payload::StartSection { func, .. } => {
+                    wasm_module.start_section = Some(StartSection {
+                        function_index: func,
+                    });
+                }
+                // Element and data segments are copied as raw entries, their
+                // encoding does not depend on anything we rewrite.
+                Payload::ElementSection(reader) => {
+                    let mut element_section = ElementSection::new();
+                    for element in reader {
+                        let element = element?;
+                        element_section.raw(&input_wasm_binary[element.range]);
+                    }
+                    wasm_module.element_section = element_section;
+                }
+                Payload::DataCountSection { count, .. } => {
+                    wasm_module.data_count_section = Some(DataCountSection { count });
+                }
+                Payload::DataSection(reader) => {
+                    let mut data_section = DataSection::new();
+                    for data in reader {
+                        let data = data?;
+                        data_section.raw(&input_wasm_binary[data.range]);
+                    }
+                    wasm_module.data_section = data_section;
+                }
+                Payload::CodeSectionStart { range, .. } => {
+                    code_section_start = range.start;
+                }
+                Payload::CodeSectionEntry(body) => {
+                    wasm_module.original_code_layout.push(
+                        code_section_start,
+                        body.range(),
+                        &input_wasm_binary[body.range()],
+                    );
+                    wasm_module
+                        .code_section
+                        .raw(&input_wasm_binary[body.range()]);
+                }
+                Payload::End(_) => break,
                 _ => {}
             }
         }
-        wasm_module
+        Ok(wasm_module)
+    }
+
+    pub fn imported_functions_count(&self) -> u32 {
+        self.imported_functions_count
+    }
+
+    pub fn imported_globals_count(&self) -> u32 {
+        self.imported_globals_count
+    }
+
+    pub fn imported_memories_count(&self) -> u32 {
+        self.imported_memories_count
+    }
+
+    pub fn imported_tables_count(&self) -> u32 {
+        self.imported_tables_count
+    }
+
+    pub fn imported_tags_count(&self) -> u32 {
+        self.imported_tags_count
+    }
+
+    /// Encode the module back into a binary. Sections are emitted in the
+    /// order required by the spec, empty ones are skipped and custom sections
+    /// are appended verbatim at the end.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut module = self.encode_known_sections();
+        for custom_section in &self.custom_sections {
+            module.section(custom_section);
+        }
+        module.finish()
+    }
+
+    /// Like `encode`, but custom sections go through `policy` first so that
+    /// debug info matches the current code section.
+    pub fn encode_with_policy(&self, policy: &CustomSectionPolicy) -> Result<Vec<u8>> {
+        let mut module = self.encode_known_sections();
+        let new_code_layout = CodeLayout::from_binary(module.as_slice())?;
+        let custom_sections = apply_policy(
+            &self.custom_sections,
+            policy,
+            &self.original_code_layout,
+            &new_code_layout,
+        )?;
+        for custom_section in &custom_sections {
+            module.section(custom_section);
+        }
+        Ok(module.finish())
+    }
+
+    fn encode_known_sections(&self) -> Module {
+        let mut module = Module::new();
+        if !self.type_section.is_empty() {
+            module.section(&self.type_section);
+        }
+        if !self.import_section.is_empty() {
+            module.section(&self.import_section);
+        }
+        if !self.function_section.is_empty() {
+            module.section(&self.function_section);
+        }
+        if !self.table_section.is_empty() {
+            module.section(&self.table_section);
+        }
+        if !self.memory_section.is_empty() {
+            module.section(&self.memory_section);
+        }
+        if !self.tag_section.is_empty() {
+            module.section(&self.tag_section);
+        }
+        if !self.global_section.is_empty() {
+            module.section(&self.global_section);
+        }
+        if !self.export_section.is_empty() {
+            module.section(&self.export_section);
+        }
+        if let Some(start_section) = &self.start_section {
+            module.section(start_section);
+        }
+        if !self.element_section.is_empty() {
+            module.section(&self.element_section);
+        }
+        if let Some(data_count_section) = &self.data_count_section {
+            module.section(data_count_section);
+        }
+        if !self.code_section.is_empty() {
+            module.section(&self.code_section);
+        }
+        if !self.data_section.is_empty() {
+            module.section(&self.data_section);
+        }
+        module
+    }
+}
+
+// What `ParserToEncoderValType` can convert without panicking.
+fn check_val_type(ty: &wasmparser::ValType) -> Result<()> {
+    match ty {
+        wasmparser::ValType::Ref(ref_ty)
+            if ![
+                wasmparser::RefType::FUNCREF,
+                wasmparser::RefType::EXTERNREF,
+                wasmparser::RefType::EXNREF,
+            ]
+            .contains(ref_ty) =>
+        {
+            bail!("unsupported value type {}", ref_ty)
+        }
+        _ => Ok(()),
+    }
+}
+
+// What `ParserToEncoderRefType` can convert without panicking.
+fn check_ref_type(ty: &wasmparser::RefType) -> Result<()> {
+    use wasmparser::RefType as R;
+    if ![
+        R::ANYREF,
+        R::EQREF,
+        R::FUNCREF,
+        R::EXTERNREF,
+        R::I31REF,
+        R::ARRAYREF,
+        R::EXNREF,
+    ]
+    .contains(ty)
+    {
+        bail!("unsupported reference type {}", ty);
     }
+    Ok(())
 }
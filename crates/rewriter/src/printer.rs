@@ -0,0 +1,18 @@
+use anyhow::Result;
+use common::printing::InstrStyle;
+use wasmprinter::{Config, PrintFmtWrite};
+
+use crate::module::WasmModule;
+
+/// Render a module in WebAssembly text format.
+pub fn print_module(module: &WasmModule, style: InstrStyle) -> Result<String> {
+    print_binary(&module.encode(), style)
+}
+
+pub fn print_binary(wasm_binary: &[u8], style: InstrStyle) -> Result<String> {
+    let mut wat = String::new();
+    Config::new()
+        .fold_instructions(style == InstrStyle::Folded)
+        .print(wasm_binary, &mut PrintFmtWrite(&mut wat))?;
+    Ok(wat)
+}
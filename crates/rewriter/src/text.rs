@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use wast::parser::{self, ParseBuffer};
+use wast::{QuoteWat, Wast, WastDirective, Wat};
+
+use crate::module::WasmModule;
+
+/// Parse a module written in WebAssembly text format.
+pub fn parse_wat(wat_text: &str) -> Result<WasmModule<'static>> {
+    let binary = wat::parse_str(wat_text)?;
+    WasmModule::try_new(&binary)
+}
+
+/// Parse a `.wat` file, or a binary `.wasm` file which is passed through.
+pub fn parse_wat_file(path: impl AsRef<Path>) -> Result<WasmModule<'static>> {
+    let binary = wat::parse_file(path)?;
+    WasmModule::try_new(&binary)
+}
+
+/// Every core module defined in a `.wast` script, in order. Assertions and
+/// components are ignored.
+pub fn parse_wast_modules(wast_text: &str) -> Result<Vec<WasmModule<'static>>> {
+    let buffer = ParseBuffer::new(wast_text)?;
+    let wast: Wast = parser::parse(&buffer)?;
+    let mut modules = Vec::new();
+    for directive in wast.directives {
+        let mut quote_wat = match directive {
+            WastDirective::Module(quote_wat) | WastDirective::ModuleDefinition(quote_wat) => {
+                quote_wat
+            }
+            _ => continue,
+        };
+        if !matches!(
+            quote_wat,
+            QuoteWat::Wat(Wat::Module(_)) | QuoteWat::QuoteModule(..)
+        ) {
+            continue;
+        }
+        let binary = quote_wat
+            .encode()
+            .with_context(|| format!("failed to encode module #{}", modules.len()))?;
+        modules.push(
+            WasmModule::try_new(&binary)
+                .with_context(|| format!("cannot read module #{}", modules.len()))?,
+        );
+    }
+    Ok(modules)
+}
+
+pub fn parse_wast_file(path: impl AsRef<Path>) -> Result<Vec<WasmModule<'static>>> {
+    let path = path.as_ref();
+    let wast_text =
+        std::fs::read_to_string(path).with_context(|| format!("failed to read {:?}", path))?;
+    parse_wast_modules(&wast_text)
+}
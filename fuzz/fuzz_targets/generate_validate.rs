@@ -0,0 +1,56 @@
+//! Generate a program from the fuzzer input, check that it validates and,
+//! when `WASMAKER_FUZZ_RUNTIME` is set, run its exports in that runtime.
+//!
+//! `WASMAKER_FUZZ_RUNTIME` is a command line as `run --runtime` takes it,
+//! without the name, e.g. `wasmtime run --invoke {export} {module} {args}`.
+//! Every export is called once with zero arguments and killed after
+//! `TIMEOUT`. Traps and timeouts are normal outcomes; the runtime being
+//! killed by a signal counts as a crash.
+
+#![no_main]
+
+use std::path::Path;
+use std::time::Duration;
+
+use generator::fuzzing::ArbitraryProgram;
+use generator::module_generator::encode_module;
+use generator::printer::print_node;
+use common::printing::InstrStyle;
+use harness::command::CommandRuntime;
+use harness::differential::{callable_exports, zero_arguments};
+use harness::engine::{Call, Engine, Outcome};
+use libfuzzer_sys::fuzz_target;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fuzz_target!(|program: ArbitraryProgram| {
+    let wasm = match encode_module(&program.composition.body) {
+        Ok(wasm) => wasm,
+        Err(e) => panic!("cannot encode generated program: {e}"),
+    };
+    if let Err(e) = wasmparser::validate(&wasm) {
+        let text = print_node(&program.composition.body, InstrStyle::Folded).unwrap_or_else(|e| e.to_string());
+        panic!("generated module is invalid: {e}\n{text}");
+    }
+    if let Ok(command) = std::env::var("WASMAKER_FUZZ_RUNTIME") {
+        run(&command, &wasm);
+    }
+});
+
+fn run(command: &str, wasm: &[u8]) {
+    let runtime = CommandRuntime::new("fuzz", command, TIMEOUT).expect("WASMAKER_FUZZ_RUNTIME is empty");
+    let path = std::env::temp_dir().join(format!("wasmaker-fuzz-{}.wasm", std::process::id()));
+    std::fs::write(&path, wasm).expect("cannot write module");
+    for export in callable_exports(wasm).expect("generated modules have no imports") {
+        for args in zero_arguments(&export) {
+            let call = Call { path: Path::new(&path), wasm, export: &export.name, args: &args, results: &export.results };
+            match runtime.invoke(&call) {
+                Ok(Outcome::Crashed { signal, stderr }) => {
+                    panic!("{command} crashed in `{}` (signal {signal:?}): {stderr}", export.name)
+                }
+                Ok(_) => {}
+                Err(e) => panic!("cannot run {command}: {e:#}"),
+            }
+        }
+    }
+}
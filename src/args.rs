@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use anyhow::{Result, anyhow, bail};
+
+
+/// A command line split into positional arguments and `--name value`,
+/// `--name=value` or `--flag` options, which may appear anywhere. Commands
+/// take out what they understand and `finish` rejects whatever is left.
+#[derive(Debug, Default)]
+pub struct Args {
+    positional: Vec<String>,
+    // flags have no values
+    options: HashMap<String, Vec<String>>,
+}
+
+impl Args {
+    /// `flags` are the options that never take a value. Everything after
+    /// `--` is positional.
+    pub fn parse(args: impl IntoIterator<Item = String>, flags: &[&str]) -> Result<Self> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.positional.extend(args.by_ref());
+                break;
+            }
+            let Some(option) = arg.strip_prefix("--").or_else(|| (arg == "-h").then_some("help")) else {
+                parsed.positional.push(arg);
+                continue;
+            };
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.to_string(), Some(value.to_string())),
+                None => (option.to_string(), None),
+            };
+            let values = parsed.options.entry(name.clone()).or_default();
+            if flags.contains(&name.as_str()) {
+                if value.is_some() {
+                    bail!("--{} does not take a value", name);
+                }
+                continue;
+            }
+            match value.or_else(|| args.next()) {
+                Some(value) => values.push(value),
+                None => bail!("--{} needs a value", name),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// The first positional argument, normally the command.
+    pub fn next_positional(&mut self) -> Option<String> {
+        (!self.positional.is_empty()).then(|| self.positional.remove(0))
+    }
+
+    pub fn take_positional(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.positional)
+    }
+
+    pub fn flag(&mut self, name: &str) -> bool {
+        self.options.remove(name).is_some()
+    }
+
+    /// Every value of an option that may be repeated.
+    pub fn values(&mut self, name: &str) -> Vec<String> {
+        self.options.remove(name).unwrap_or_default()
+    }
+
+    pub fn value(&mut self, name: &str) -> Result<Option<String>> {
+        let mut values = self.values(name);
+        if values.len() > 1 {
+            bail!("--{} is given {} times", name, values.len());
+        }
+        Ok(values.pop())
+    }
+
+    pub fn parse_value<T>(&mut self, name: &str) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.value(name)?
+            .map(|value| value.parse().map_err(|e| anyhow!("--{} {}: {}", name, value, e)))
+            .transpose()
+    }
+
+    /// Fail on options nobody asked for and on positional arguments left over.
+    pub fn finish(self, command: &str) -> Result<()> {
+        let mut unknown: Vec<&String> = self.options.keys().collect();
+        unknown.sort();
+        if let Some(name) = unknown.first() {
+            bail!("unknown option --{} for `{}`", name, command);
+        }
+        if let Some(arg) = self.positional.first() {
+            bail!("unexpected argument `{}` for `{}`", arg, command);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Args> {
+        Args::parse(line.split_whitespace().map(String::from), &["folded", "help"])
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let mut args = parse("--seed 7 print a.wasm --folded --out=dir -- --b.wasm").unwrap();
+        assert_eq!(args.parse_value::<u64>("seed").unwrap(), Some(7));
+        assert_eq!(args.next_positional().as_deref(), Some("print"));
+        assert!(args.flag("folded"));
+        assert!(!args.flag("folded"));
+        assert_eq!(args.value("out").unwrap().as_deref(), Some("dir"));
+        assert_eq!(args.take_positional(), vec!["a.wasm", "--b.wasm"]);
+        args.finish("print").unwrap();
+
+        let mut args = parse("-h --count x --count 2 --unknown 1").unwrap();
+        assert!(args.flag("help"));
+        assert!(args.value("count").is_err());
+        assert!(args.finish("run").unwrap_err().to_string().contains("--unknown"));
+
+        assert!(parse("--seed").is_err());
+        assert!(parse("--folded=yes").is_err());
+        let mut args = parse("--count ten").unwrap();
+        assert!(args.parse_value::<usize>("count").is_err());
+    }
+}
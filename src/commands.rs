@@ -0,0 +1,463 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+use anyhow::{Context, Result, anyhow, bail};
+use common::printing::InstrStyle;
+use generator::AST::Node;
+use generator::ast_loader::{AstQuery, open_source};
+use generator::composer::{Composer, ComposerConfig};
+use generator::harvester::{HarvestConfig, Harvester};
+use generator::instruction::{Instruction, InstructionArgs, ValType};
+use generator::module_generator::encode_module;
+use generator::mutator::mutate_binary;
+use generator::random_generator::{RandomConfig, RandomGenerator};
+use generator::resolver::resolve;
+use harness::command::CommandRuntime;
+use harness::differential::{Harness, Report};
+use harness::inputs::InputGenerator;
+use harness::interpreter::Interpreter;
+use harness::triage::Triage;
+use rewriter::module::WasmModule;
+use rewriter::printer::print_binary;
+use crate::args::Args;
+use crate::profile::Profile;
+use crate::{Options, read_module, stem};
+
+
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+const DEFAULT_INPUTS: usize = 8;
+
+pub fn generate(options: &Options, mut args: Args) -> Result<ExitCode> {
+    let count: u64 = args.parse_value("count")?.unwrap_or(1);
+    let mut programs = Programs::new(options, &mut args)?;
+    args.finish("generate")?;
+    for seed in options.seeds(count)? {
+        let wasm = programs.generate(seed)?;
+        options.validate(&wasm).with_context(|| format!("module of seed {} is invalid", seed))?;
+        println!("{}", options.write(&format!("module-{}.wasm", seed), &wasm)?.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn rewrite(options: &Options, mut args: Args) -> Result<ExitCode> {
+    let inputs = inputs(&mut args)?;
+    args.finish("rewrite")?;
+    for input in &inputs {
+        let wasm = read_module(input)?;
+        options.validate(&wasm).with_context(|| format!("{} is invalid", input))?;
+        let module = WasmModule::try_new(&wasm).with_context(|| format!("cannot rewrite {}", input))?;
+        let rewritten = module.encode();
+        println!("{}", options.write(&format!("{}.wasm", stem(input)), &rewritten)?.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn mutate(options: &Options, mut args: Args) -> Result<ExitCode> {
+    let count: u64 = args.parse_value("count")?.unwrap_or(1);
+    let inputs = inputs(&mut args)?;
+    args.finish("mutate")?;
+    let [input] = inputs.as_slice() else {
+        bail!("`mutate` takes one input");
+    };
+    let wasm = read_module(input)?;
+    options.validate(&wasm).with_context(|| format!("{} is invalid", input))?;
+    for seed in options.seeds(count)? {
+        let config = RandomConfig { seed, weights: profile_weights(options.profile), ..RandomConfig::default() };
+        let mutation = mutate_binary(&wasm, &config)?;
+        options.validate(&mutation.wasm).with_context(|| format!("mutant of seed {} is invalid", seed))?;
+        let path = options.write(&format!("{}-mutant-{}.wasm", stem(input), seed), &mutation.wasm)?;
+        println!("{} (function {})", path.display(), mutation.function);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn harvest(options: &Options, mut args: Args) -> Result<ExitCode> {
+    let collection = args.value("collection")?.unwrap_or_else(|| "harvested".to_string());
+    let mut config = HarvestConfig::default();
+    if let Some(max_size) = args.parse_value("max-size")? {
+        config.max_size = max_size;
+    }
+    let inputs = inputs(&mut args)?;
+    args.finish("harvest")?;
+    let Some(corpus) = &options.corpus else {
+        bail!("`harvest` needs --corpus to store the snippets in");
+    };
+
+    let mut harvester = Harvester::new(config);
+    for input in &inputs {
+        let wasm = read_module(input)?;
+        options.validate(&wasm).with_context(|| format!("{} is invalid", input))?;
+        let stats = harvester.harvest_binary(&wasm).with_context(|| format!("cannot harvest {}", input))?;
+        println!(
+            "{}: {} functions, {} skipped, {} new snippets",
+            input, stats.functions, stats.skipped_functions, stats.new_snippets,
+        );
+    }
+    harvester.store(open_source(corpus).as_ref(), &collection)?;
+    println!("{} snippets stored in `{}`", harvester.snippets().len(), collection);
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn validate(options: &Options, mut args: Args) -> Result<ExitCode> {
+    let inputs = inputs(&mut args)?;
+    args.finish("validate")?;
+    let mut invalid = 0;
+    for input in &inputs {
+        match read_module(input).and_then(|wasm| options.validate(&wasm)) {
+            Ok(()) => println!("{}: ok", input),
+            Err(e) => {
+                println!("{}: {:#}", input, e);
+                invalid += 1;
+            }
+        }
+    }
+    Ok(if invalid == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+pub fn print(_options: &Options, mut args: Args) -> Result<ExitCode> {
+    let style = if args.flag("folded") { InstrStyle::Folded } else { InstrStyle::Flat };
+    let inputs = inputs(&mut args)?;
+    args.finish("print")?;
+    for input in &inputs {
+        print!("{}", print_binary(&read_module(input)?, style)?);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Generate and validate `--count` modules. Seeds that give no module or an
+/// invalid one are a bug of the generator; they are reported and the invalid
+/// modules are written to the output directory.
+///
+/// With `--runtime`s every valid module is run on all of them, and with
+/// `--oracle` on the reference interpreter first, with `--inputs` argument
+/// vectors per export. Divergences are appended to `divergences.jsonl` and
+/// counted in the buckets of `buckets.json`; only the modules that open a
+/// bucket are kept. Calls the interpreter does not support, e.g. SIMD, are
+/// counted in the summary.
+pub fn run(options: &Options, mut args: Args) -> Result<ExitCode> {
+    let count: u64 = args.parse_value("count")?.unwrap_or(100);
+    let timeout = Duration::from_millis(args.parse_value("timeout")?.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let inputs: usize = args.parse_value("inputs")?.unwrap_or(DEFAULT_INPUTS);
+    let mut harness = Harness::new();
+    let oracle = args.flag("oracle");
+    if oracle {
+        harness = harness.with_engine(Box::new(Interpreter::default()));
+    }
+    for spec in args.values("runtime") {
+        harness = harness.with_engine(Box::new(CommandRuntime::from_spec(&spec, timeout)?));
+    }
+    let mut programs = Programs::new(options, &mut args)?;
+    args.finish("run")?;
+    let differential = harness.engines().next().is_some();
+    let mut triage = Triage::open(&options.out.join("buckets.json"))?;
+    let buckets = triage.buckets().len();
+    let mut failed = 0;
+    let mut invalid = 0;
+    let mut divergences = 0;
+    let mut calls = 0;
+    let mut unsupported = 0;
+    for seed in options.seeds(count)? {
+        let wasm = match programs.generate(seed) {
+            Ok(wasm) => wasm,
+            Err(e) => {
+                println!("seed {}: {:#}", seed, e);
+                failed += 1;
+                continue;
+            }
+        };
+        if let Err(e) = options.validate(&wasm) {
+            let path = options.write(&format!("invalid-{}.wasm", seed), &wasm)?;
+            println!("{}: {:#}", path.display(), e);
+            invalid += 1;
+            continue;
+        }
+        if differential {
+            let report = run_differential(options, &harness, &mut triage, seed, &wasm, inputs)?;
+            divergences += report.divergences.len();
+            calls += report.calls;
+            unsupported += report.unsupported;
+        }
+    }
+    print!("{} seeds, {} failed to generate, {} invalid", count, failed, invalid);
+    if differential {
+        print!(", {} divergences, {} new buckets", divergences, triage.buckets().len() - buckets);
+    }
+    if oracle {
+        print!(", {} of {} calls unsupported by the interpreter", unsupported, calls);
+    }
+    println!();
+    Ok(if failed + invalid + divergences == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
+
+// The calls made on the module of `seed` and the divergences found.
+fn run_differential(
+    options: &Options,
+    harness: &Harness,
+    triage: &mut Triage,
+    seed: u64,
+    wasm: &[u8],
+    inputs: usize,
+) -> Result<Report> {
+    let path = options.write(&format!("module-{}.wasm", seed), wasm)?;
+    let mut inputs = InputGenerator::new(wasm, seed)?.with_count(inputs);
+    let report = harness.run(&path, |export| inputs.arguments(export))?;
+    // only modules that open a bucket are kept
+    let mut opened = false;
+    if !report.divergences.is_empty() {
+        let log = options.out.join("divergences.jsonl");
+        let mut file = OpenOptions::new().create(true).append(true).open(&log)
+            .with_context(|| format!("cannot open {}", log.display()))?;
+        for divergence in &report.divergences {
+            writeln!(file, "{}", divergence.to_json())?;
+            let Some((bucket, new)) = triage.add(divergence) else { continue };
+            println!(
+                "{}: {} in `{}` on {}, bucket {}{}",
+                path.display(),
+                bucket.classification.class.name(),
+                divergence.export,
+                bucket.classification.runtime,
+                bucket.id,
+                if new { " (new)" } else { "" },
+            );
+            opened |= new;
+        }
+        triage.save()?;
+    }
+    if !opened {
+        std::fs::remove_file(&path)?;
+    }
+    Ok(report)
+}
+
+fn inputs(args: &mut Args) -> Result<Vec<String>> {
+    let inputs = args.take_positional();
+    if inputs.is_empty() {
+        bail!("no input given");
+    }
+    Ok(inputs)
+}
+
+/// Where generated functions come from: the corpus when there is one, the
+/// random generator otherwise. Either way only opcodes of the feature
+/// profile are used.
+struct Programs {
+    source: ProgramSource,
+    params: Vec<ValType>,
+    results: Vec<ValType>,
+}
+
+enum ProgramSource {
+    Corpus(Composer),
+    Random(RandomConfig),
+}
+
+impl Programs {
+    fn new(options: &Options, args: &mut Args) -> Result<Self> {
+        let params = val_types(args.value("params")?.as_deref().unwrap_or(""))?;
+        let results = val_types(args.value("results")?.as_deref().unwrap_or("i32"))?;
+        let max_size: Option<usize> = args.parse_value("max-size")?;
+        let max_depth: Option<usize> = args.parse_value("max-depth")?;
+
+        let source = match &options.corpus {
+            Some(corpus) => {
+                let snippets: Vec<Node> = open_source(corpus)
+                    .stream_all(&AstQuery::new())?
+                    .filter(|snippet| snippet.as_ref().map_or(true, |node| node_enabled(node, options.profile)))
+                    .collect::<Result<_>>()?;
+                if snippets.is_empty() {
+                    bail!("{} has no snippets for the `{:?}` profile", corpus, options.profile);
+                }
+                let default = ComposerConfig::default();
+                let config = ComposerConfig {
+                    max_depth: max_depth.unwrap_or(default.max_depth),
+                    max_size: max_size.unwrap_or(default.max_size),
+                    ..default
+                };
+                ProgramSource::Corpus(Composer::new(snippets, config))
+            }
+            None => {
+                let default = RandomConfig::default();
+                ProgramSource::Random(RandomConfig {
+                    max_depth: max_depth.unwrap_or(default.max_depth),
+                    max_size: max_size.unwrap_or(default.max_size),
+                    weights: profile_weights(options.profile),
+                    ..default
+                })
+            }
+        };
+        Ok(Programs { source, params, results })
+    }
+
+    fn generate(&mut self, seed: u64) -> Result<Vec<u8>> {
+        let body = match &mut self.source {
+            ProgramSource::Corpus(composer) => {
+                composer.set_seed(seed);
+                let mut composition = composer.compose(&self.params, &self.results)?;
+                resolve(&mut composition.body, &composition.params, &composition.locals)?;
+                composition.body
+            }
+            ProgramSource::Random(config) => {
+                config.seed = seed;
+                RandomGenerator::new(config.clone()).generate(&self.params, &self.results)?.body
+            }
+        };
+        encode_module(&body).with_context(|| format!("cannot encode the module of seed {}", seed))
+    }
+}
+
+/// The default weights of the random generator without the opcodes the
+/// profile does not enable.
+fn profile_weights(profile: Profile) -> std::collections::HashMap<common::opcodes::OpCode, u32> {
+    let mut weights = RandomConfig::default().weights;
+    weights.retain(|opcode, _| profile.enables_opcode(*opcode));
+    weights
+}
+
+fn val_types(list: &str) -> Result<Vec<ValType>> {
+    list.split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| ValType::from_name(name).ok_or_else(|| anyhow!("unknown value type `{}`", name)))
+        .collect()
+}
+
+fn node_enabled(node: &Node, profile: Profile) -> bool {
+    instr_enabled(&node.instr, profile) && node.sub_instrs.iter().all(|sub| node_enabled(sub, profile))
+}
+
+fn instr_enabled(instr: &Instruction, profile: Profile) -> bool {
+    profile.enables_opcode(instr.opcode)
+        && match &instr.args {
+            InstructionArgs::If(if_args) => {
+                if_args.instrs1.iter().chain(&if_args.instrs2).all(|instr| instr_enabled(instr, profile))
+            }
+            _ => true,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use super::*;
+
+    const ADD: &str = r#"(module (func (export "add") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))"#;
+
+    // Run a command line with `{out}` standing for an output directory of
+    // its own, which is returned for inspection.
+    fn run_command(name: &str, line: &str) -> (Result<ExitCode>, PathBuf) {
+        let out = std::env::temp_dir().join(format!("wasmaker-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&out);
+        run_command_in(&out, line)
+    }
+
+    // Run a command line again in an output directory that is kept.
+    fn run_command_in(out: &Path, line: &str) -> (Result<ExitCode>, PathBuf) {
+        let dir = out.display().to_string();
+        let mut args = vec!["--out".to_string(), dir.clone()];
+        args.extend(line.replace("{out}", &dir).split_whitespace().map(String::from));
+        (crate::run(args), out.to_path_buf())
+    }
+
+    // Write `wat` as an input file outside of any output directory.
+    fn input(name: &str, wat: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("wasmaker-inputs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, wat).unwrap();
+        path.display().to_string()
+    }
+
+    #[test]
+    fn test_generate() {
+        let (result, out) = run_command("generate", "--seed 3 generate --count 2");
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        assert!(out.join("module-3.wasm").is_file() && out.join("module-4.wasm").is_file());
+        std::fs::remove_dir_all(&out).unwrap();
+
+        let (result, _) = run_command("generate-overflow", "--seed 18446744073709551615 generate --count 2");
+        assert!(result.unwrap_err().to_string().contains("largest seed"));
+    }
+
+    #[test]
+    fn test_rewrite() {
+        let add = input("rewrite-add.wat", ADD);
+        let (result, out) = run_command("rewrite", &format!("rewrite {}", add));
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        assert_eq!(std::fs::read(out.join("rewrite-add.wasm")).unwrap(), read_module(&add).unwrap());
+        std::fs::remove_dir_all(&out).unwrap();
+
+        // GC types are valid under `all` but cannot be re-encoded
+        let gc = input("rewrite-gc.wat", "(module (type (struct (field i32))) (func))");
+        let (result, _) = run_command("rewrite-gc", &format!("--features all rewrite {}", gc));
+        assert!(format!("{:#}", result.unwrap_err()).contains("cannot rewrite"));
+    }
+
+    #[test]
+    fn test_mutate() {
+        let add = input("mutate-add.wat", ADD);
+        let (result, out) = run_command("mutate", &format!("--seed 5 mutate --count 2 {}", add));
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        assert!(out.join("mutate-add-mutant-5.wasm").is_file() && out.join("mutate-add-mutant-6.wasm").is_file());
+        std::fs::remove_dir_all(&out).unwrap();
+
+        let (result, _) = run_command("mutate-two", &format!("mutate {} {}", add, add));
+        assert!(result.unwrap_err().to_string().contains("one input"));
+    }
+
+    #[test]
+    fn test_harvest() {
+        let add = input("harvest-add.wat", ADD);
+        let (result, out) = run_command("harvest", &format!("--corpus {{out}}/corpus harvest --collection add {}", add));
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        let stored = std::fs::read_to_string(out.join("corpus/add.jsonl")).unwrap();
+        assert!(stored.lines().count() > 0);
+        // harvesting the same module again only counts its snippets twice
+        let (result, _) = run_command_in(&out, &format!("--corpus {{out}}/corpus harvest --collection add {}", add));
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        let merged = std::fs::read_to_string(out.join("corpus/add.jsonl")).unwrap();
+        assert_eq!(merged.lines().count(), stored.lines().count());
+        assert_eq!(merged, stored.replace(r#""count":2}"#, r#""count":4}"#).replace(r#""count":1}"#, r#""count":2}"#));
+        std::fs::remove_dir_all(&out).unwrap();
+
+        let (result, _) = run_command("harvest-no-corpus", &format!("harvest {}", add));
+        assert!(result.unwrap_err().to_string().contains("--corpus"));
+    }
+
+    #[test]
+    fn test_validate() {
+        let add = input("validate-add.wat", ADD);
+        let simd = input("validate-simd.wat", "(module (func (result v128) v128.const i64x2 0 0))");
+        let (result, _) = run_command("validate", &format!("validate {} {}", add, simd));
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        let (result, _) = run_command("validate-mvp", &format!("--features mvp validate {} {}", add, simd));
+        assert_eq!(result.unwrap(), ExitCode::FAILURE);
+        let (result, _) = run_command("validate-none", "validate");
+        assert!(result.unwrap_err().to_string().contains("no input"));
+    }
+
+    #[test]
+    fn test_print() {
+        let add = input("print-add.wat", ADD);
+        let (result, _) = run_command("print", &format!("print --folded {}", add));
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        let (result, _) = run_command("print-missing", "print /nonexistent.wasm");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        // nothing is written when every module is valid
+        let (result, out) = run_command("run", "--seed 11 run --count 3");
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        assert!(!out.exists());
+
+        // the interpreter alone has nothing to disagree with
+        let (result, out) = run_command("run-oracle", "--seed 11 run --count 3 --oracle --inputs 2");
+        assert_eq!(result.unwrap(), ExitCode::SUCCESS);
+        assert!(!out.join("divergences.jsonl").exists());
+        std::fs::remove_dir_all(&out).unwrap();
+
+        let (result, _) = run_command("run-unknown", "run --count 3 --bogus 1");
+        assert!(result.unwrap_err().to_string().contains("--bogus"));
+    }
+}
@@ -1,3 +1,135 @@
-fn main() {
-    println!("Hello, world!");
+mod args;
+mod commands;
+mod profile;
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use anyhow::{Context, Result, bail};
+use crate::args::Args;
+use crate::profile::Profile;
+
+
+const USAGE: &str = "\
+usage: wasmaker-rust [options] <command> [arguments]
+
+commands:
+  generate [--count N] [--params TYPES] [--results TYPES] [--max-size N] [--max-depth N]
+                     write N generated modules to the output directory
+  rewrite <input>... re-encode modules through the rewriter
+  mutate <input> [--count N]
+                     replace one function body of the input with generated code
+  harvest <input>... [--collection NAME] [--max-size N]
+                     add the snippets of real modules to the corpus
+  validate <input>...
+                     check modules against the feature profile
+  print <input> [--folded]
+                     print a module as text
+  run [--count N] [--oracle] [--runtime NAME=COMMAND]... [--timeout MS] [--inputs N]
+      [generate options]
+                     generate and validate modules, keeping the invalid ones; with
+                     runtimes, run every export on each and record divergences,
+                     bucketed by kind and stack signature in `buckets.json`;
+                     `--oracle` adds the reference interpreter as the first runtime
+                     and counts the calls it does not support, e.g. SIMD;
+                     each export gets N argument vectors (default 8) of boundary,
+                     random and module constant values
+
+options:
+  --seed N           seed of the first module, the next ones count up (default 0)
+  --out DIR          output directory (default `out`)
+  --features NAME    feature profile: mvp, wasm2, wasm3, all (default wasm2)
+  --corpus SPEC      corpus directory or mongodb:// URI; without one, `generate`
+                     and `run` use the random generator
+
+TYPES is a comma separated list such as `i32,f64`. Inputs may be binary or text.
+A runtime COMMAND is split on spaces; `{module}`, `{export}` and `{args}` are
+replaced by the module path, the export name and the arguments, for example
+`wasmtime=wasmtime run --invoke {export} {module} {args}`.";
+
+/// The options every command understands.
+pub struct Options {
+    pub seed: u64,
+    pub out: PathBuf,
+    pub profile: Profile,
+    pub corpus: Option<String>,
+}
+
+impl Options {
+    fn take(args: &mut Args) -> Result<Self> {
+        Ok(Options {
+            seed: args.parse_value("seed")?.unwrap_or(0),
+            out: args.value("out")?.unwrap_or_else(|| "out".to_string()).into(),
+            profile: args.parse_value("features")?.unwrap_or(Profile::Wasm2),
+            corpus: args.value("corpus")?,
+        })
+    }
+
+    /// Write `wasm` as `name` in the output directory, which is created on
+    /// first use.
+    pub fn write(&self, name: &str, wasm: &[u8]) -> Result<PathBuf> {
+        std::fs::create_dir_all(&self.out).with_context(|| format!("cannot create {}", self.out.display()))?;
+        let path = self.out.join(name);
+        std::fs::write(&path, wasm).with_context(|| format!("cannot write {}", path.display()))?;
+        Ok(path)
+    }
+
+    /// The seeds of `count` modules, counting up from `--seed`.
+    pub fn seeds(&self, count: u64) -> Result<Range<u64>> {
+        match self.seed.checked_add(count) {
+            Some(end) => Ok(self.seed..end),
+            None => bail!("{} seeds from {} go past the largest seed", count, self.seed),
+        }
+    }
+
+    /// Fail unless `wasm` is valid under the feature profile.
+    pub fn validate(&self, wasm: &[u8]) -> Result<()> {
+        self.profile.validator().validate_all(wasm)?;
+        Ok(())
+    }
+}
+
+/// A `.wasm` or `.wat` file as a binary.
+pub fn read_module(path: &str) -> Result<Vec<u8>> {
+    wat::parse_file(path).with_context(|| format!("cannot read {}", path))
+}
+
+/// File name of `path` without its extension, to name derived outputs.
+pub fn stem(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "module".to_string())
+}
+
+fn run(args: impl IntoIterator<Item = String>) -> Result<ExitCode> {
+    let mut args = Args::parse(args, &["folded", "help", "oracle"])?;
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let options = Options::take(&mut args)?;
+    let Some(command) = args.next_positional() else {
+        bail!("no command given\n\n{}", USAGE);
+    };
+    match command.as_str() {
+        "generate" => commands::generate(&options, args),
+        "rewrite"  => commands::rewrite(&options, args),
+        "mutate"   => commands::mutate(&options, args),
+        "harvest"  => commands::harvest(&options, args),
+        "validate" => commands::validate(&options, args),
+        "print"    => commands::print(&options, args),
+        "run"      => commands::run(&options, args),
+        _ => bail!("unknown command `{}`\n\n{}", command, USAGE),
+    }
+}
+
+fn main() -> ExitCode {
+    match run(std::env::args().skip(1)) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::from(2)
+        }
+    }
 }
@@ -0,0 +1,76 @@
+use std::str::FromStr;
+use anyhow::{Error, bail};
+use wasmparser::{Validator, WasmFeatures};
+use common::metadata::Proposal;
+use common::opcodes::OpCode;
+
+
+/// The feature set generated modules may use and input modules are
+/// validated against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Mvp,
+    Wasm2,
+    Wasm3,
+    All,
+}
+
+impl Profile {
+    pub const NAMES: &str = "mvp, wasm2, wasm3, all";
+
+    pub fn features(self) -> WasmFeatures {
+        match self {
+            Profile::Mvp   => WasmFeatures::WASM1,
+            Profile::Wasm2 => WasmFeatures::WASM2,
+            Profile::Wasm3 => WasmFeatures::WASM3,
+            Profile::All   => WasmFeatures::all(),
+        }
+    }
+
+    pub fn validator(self) -> Validator {
+        Validator::new_with_features(self.features())
+    }
+
+    pub fn enables(self, proposal: Proposal) -> bool {
+        match proposal.feature() {
+            None => true,
+            Some(name) => WasmFeatures::from_name(&name.to_uppercase())
+                .is_some_and(|flag| self.features().contains(flag)),
+        }
+    }
+
+    /// Unknown opcodes are never enabled.
+    pub fn enables_opcode(self, opcode: OpCode) -> bool {
+        opcode.info().is_some_and(|info| self.enables(info.proposal))
+    }
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        Ok(match name {
+            "mvp"   => Profile::Mvp,
+            "wasm2" => Profile::Wasm2,
+            "wasm3" => Profile::Wasm3,
+            "all"   => Profile::All,
+            _ => bail!("unknown feature profile, expected one of {}", Profile::NAMES),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common::metadata::Proposal;
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        assert_eq!("wasm2".parse::<Profile>().unwrap(), Profile::Wasm2);
+        assert!("wasm4".parse::<Profile>().unwrap_err().to_string().contains(Profile::NAMES));
+        assert!(Profile::Mvp.enables(Proposal::Mvp));
+        assert!(!Profile::Mvp.enables(Proposal::Simd) && Profile::Wasm2.enables(Proposal::Simd));
+        assert!(Profile::Mvp.enables_opcode(OpCode::I32Add) && !Profile::Mvp.enables_opcode(OpCode::I64Extend8S));
+        assert!(Profile::All.features().contains(WasmFeatures::GC) && !Profile::Wasm2.features().contains(WasmFeatures::GC));
+    }
+}